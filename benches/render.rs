@@ -0,0 +1,57 @@
+//! Times rendering the same scenes in full precision and with each faster way of evaluating the field, over
+//! a range of resolutions and metaball counts, so a change that slows one of them down shows up. Run with
+//! `cargo bench`, which builds with optimizations.
+
+use metaballs::{naive_impl, Generation, MetaballData, Precision, RenderOpts, BASE_METABALL_SIZE};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::time::Instant;
+
+const RESOLUTIONS: [u32; 4] = [128, 256, 512, 1024];
+const COUNTS: [u32; 3] = [4, 16, 64];
+const PRECISIONS: [Precision; 3] = [Precision::F64, Precision::F32, Precision::Lut];
+
+/// How many times each scene is rendered in each precision, after one render to warm up
+const ITERATIONS: u32 = 5;
+
+/// The same random looking scene every time, with the shapes as big relative to the image at any resolution
+fn scene(resolution: u32, count: u32) -> MetaballData {
+    let goo = 1.6;
+    let generation = Generation {
+        count: Some(count),
+        base_size: BASE_METABALL_SIZE * (resolution as f64 / 256.0).powf(goo),
+        ..Generation::default()
+    };
+    MetaballData::from_rng(goo, 0.5, resolution, resolution, &generation, &mut StdRng::seed_from_u64(0x6d65_7461))
+}
+
+/// The mean milliseconds a render takes
+fn time(metadata: &MetaballData, precision: Precision) -> f64 {
+    let opts = RenderOpts { precision, ..RenderOpts::default() };
+    naive_impl(metadata.width, metadata.height, 1, metadata, &opts);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        naive_impl(metadata.width, metadata.height, 1, metadata, &opts);
+    }
+    start.elapsed().as_secs_f64() * 1000.0 / ITERATIONS as f64
+}
+
+fn main() {
+    println!("{:>10} {:>6} {:>10} {:>10} {:>10} {:>9} {:>9}", "resolution", "balls", "f64 ms", "f32 ms", "lut ms", "f32 x", "lut x");
+    for resolution in RESOLUTIONS {
+        for count in COUNTS {
+            let metadata = scene(resolution, count);
+            let [full, single, table] = PRECISIONS.map(|precision| time(&metadata, precision));
+            println!(
+                "{:>10} {:>6} {:>10.2} {:>10.2} {:>10.2} {:>9.2} {:>9.2}",
+                resolution,
+                count,
+                full,
+                single,
+                table,
+                full / single,
+                full / table
+            );
+        }
+    }
+}
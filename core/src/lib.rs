@@ -0,0 +1,62 @@
+//! The metaball field and a rasterizer for it, without std, so embedded projects can draw metaballs onto
+//! small LED matrices or e-ink displays. It's the additive field of round metaballs with the inverse power
+//! falloff, the same as the `metaballs` crate renders by default, and nothing here allocates.
+
+#![no_std]
+
+mod math;
+
+pub use math::{exp, ln, powf, sqrt};
+
+/// A round metaball, at a point in pixels from the top left
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Ball {
+    pub x: f64,
+    pub y: f64,
+    /// How big the metaball is, reaching `size^(1 / goo)` pixels before adding 1 to the field, and taking
+    /// away from the field instead when it's negative
+    pub size: f64,
+}
+
+/// The field at a point, the sum of `size / distance^goo` over the balls, which is infinite on a center
+pub fn field(x: f64, y: f64, balls: &[Ball], goo: f64) -> f64 {
+    balls.iter().fold(0.0, |field, ball| {
+        let (dx, dy) = (ball.x - x, ball.y - y);
+        field + ball.size / powf(sqrt(dx * dx + dy * dy), goo)
+    })
+}
+
+/// Whether a point is inside the shape, where the field is above the threshold
+pub fn inside(x: f64, y: f64, balls: &[Ball], goo: f64, threshold: f64) -> bool {
+    field(x, y, balls, goo) > threshold
+}
+
+/// Rasterize the shape onto a `width × height` display a pixel at a time, row by row from the top left,
+/// calling `set` with each pixel and whether it's inside the shape. Each pixel samples the field at its
+/// own coordinates, the same points the `metaballs` crate samples.
+pub fn rasterize(width: u32, height: u32, balls: &[Ball], goo: f64, threshold: f64, mut set: impl FnMut(u32, u32, bool)) {
+    for y in 0..height {
+        for x in 0..width {
+            set(x, y, inside(x as f64, y as f64, balls, goo, threshold));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rasterizing_lights_the_pixels_inside_the_edge() {
+        // reaching the threshold 10 pixels out
+        let balls = [Ball { x: 16.0, y: 8.0, size: 100.0 }];
+        let mut lit = [[false; 32]; 16];
+        rasterize(32, 16, &balls, 2.0, 1.0, |x, y, on| lit[y as usize][x as usize] = on);
+        for (y, row) in lit.iter().enumerate() {
+            for (x, &on) in row.iter().enumerate() {
+                let (dx, dy) = (x as f64 - 16.0, y as f64 - 8.0);
+                assert_eq!(on, dx * dx + dy * dy < 100.0, "pixel {}, {}", x, y);
+            }
+        }
+    }
+}
@@ -0,0 +1,47 @@
+/* The C interface to the metaballs renderer, built with
+ *
+ *     cargo rustc --release --lib --crate-type cdylib --features cdylib
+ *
+ * Every function returning an int returns METABALLS_OK or one of the error codes below. Images are RGBA,
+ * four bytes to a pixel, row by row from the top left, so the buffers hold width * height * 4 bytes.
+ */
+#ifndef METABALLS_H
+#define METABALLS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define METABALLS_OK 0
+/* a pointer was null, a size was 0 or a goo or threshold wasn't positive */
+#define METABALLS_INVALID -1
+/* there's no preset by that name */
+#define METABALLS_UNKNOWN_PRESET -2
+/* rendering panicked, which is a bug */
+#define METABALLS_PANICKED -3
+
+/* a round metaball, negative sizes taking away from the field */
+typedef struct MetaballsBall {
+    double x;
+    double y;
+    double size;
+} MetaballsBall;
+
+/* render count metaballs as red on black, balls being allowed to be NULL if there are none */
+int metaballs_render(uint32_t width, uint32_t height, const MetaballsBall *balls, size_t count, double goo, double threshold,
+                     uint8_t *out_buf);
+
+/* render one of the preset scenes, such as "ring", laid out for the size */
+int metaballs_render_preset(const char *name, uint32_t width, uint32_t height, uint8_t *out_buf);
+
+/* the field of count metaballs at a point, NaN if the parameters are out of range */
+double metaballs_field_at(double x, double y, const MetaballsBall *balls, size_t count, double goo);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
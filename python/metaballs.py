@@ -0,0 +1,115 @@
+"""Python bindings to the metaballs renderer, for scripting scenes and plotting them in Jupyter notebooks.
+
+They load the shared library with the C interface declared in include/metaballs.h, built with
+
+    cargo rustc --release --lib --crate-type cdylib --features python
+
+from target/release, or from the path in the METABALLS_LIB environment variable. Images come back as numpy
+arrays of RGBA pixels, `height` rows of `width` pixels of 4 bytes each, ready for `matplotlib.pyplot.imshow`.
+"""
+
+import ctypes
+import os
+import sys
+from ctypes import POINTER, c_char_p, c_double, c_int, c_size_t, c_uint8, c_uint32
+
+_ERRORS = {
+    -1: "a size was 0, or the goo or threshold wasn't positive",
+    -2: "there's no preset by that name",
+    -3: "rendering panicked, which is a bug",
+}
+
+
+class _Ball(ctypes.Structure):
+    _fields_ = [("x", c_double), ("y", c_double), ("size", c_double)]
+
+
+def _library_path():
+    if "METABALLS_LIB" in os.environ:
+        return os.environ["METABALLS_LIB"]
+    name = {"win32": "metaballs.dll", "darwin": "libmetaballs.dylib"}.get(sys.platform, "libmetaballs.so")
+    return os.path.join(os.path.dirname(os.path.abspath(__file__)), "..", "target", "release", name)
+
+
+def _load():
+    library = ctypes.CDLL(_library_path())
+    library.metaballs_render.argtypes = [c_uint32, c_uint32, POINTER(_Ball), c_size_t, c_double, c_double, POINTER(c_uint8)]
+    library.metaballs_render.restype = c_int
+    library.metaballs_render_preset.argtypes = [c_char_p, c_uint32, c_uint32, POINTER(c_uint8)]
+    library.metaballs_render_preset.restype = c_int
+    library.metaballs_field_at.argtypes = [c_double, c_double, POINTER(_Ball), c_size_t, c_double]
+    library.metaballs_field_at.restype = c_double
+    return library
+
+
+_library = None
+
+
+def _lib():
+    global _library
+    if _library is None:
+        _library = _load()
+    return _library
+
+
+def _image(pixels, width, height):
+    """The RGBA pixels as a numpy array of height × width × 4 bytes"""
+    import numpy
+
+    return numpy.frombuffer(pixels, dtype=numpy.uint8).reshape((height, width, 4))
+
+
+def _check(code):
+    if code != 0:
+        raise ValueError(_ERRORS.get(code, "unknown error {}".format(code)))
+
+
+class MetaballData:
+    """A scene of round metaballs, each an (x, y, size) in pixels from the top left, with a negative size
+    taking away from the field"""
+
+    def __init__(self, goo=1.6, threshold=0.5, width=256, height=256, metaballs=()):
+        self.goo = goo
+        self.threshold = threshold
+        self.width = width
+        self.height = height
+        self.metaballs = [tuple(metaball) for metaball in metaballs]
+
+    def __repr__(self):
+        return "MetaballData(goo={!r}, threshold={!r}, width={!r}, height={!r}, metaballs={!r})".format(
+            self.goo, self.threshold, self.width, self.height, self.metaballs
+        )
+
+    def _balls(self):
+        return (_Ball * len(self.metaballs))(*(_Ball(x, y, size) for x, y, size in self.metaballs))
+
+    def render_bytes(self, width=None, height=None):
+        """The scene rendered as red metaballs on black into bytes of RGBA pixels, at its own size unless
+        another is given"""
+        width, height = width or self.width, height or self.height
+        pixels = (c_uint8 * (width * height * 4))()
+        _check(_lib().metaballs_render(width, height, self._balls(), len(self.metaballs), self.goo, self.threshold, pixels))
+        return bytes(pixels)
+
+    def render(self, width=None, height=None):
+        """The scene rendered as a numpy array of RGBA pixels, see render_bytes"""
+        width, height = width or self.width, height or self.height
+        return _image(self.render_bytes(width, height), width, height)
+
+    def field_at(self, x, y):
+        """The field at a point, which is inside the shape where it's above the threshold"""
+        return _lib().metaballs_field_at(x, y, self._balls(), len(self.metaballs), self.goo)
+
+
+def render(width, height, scene=None):
+    """Render a scene, by default a few metaballs in the middle, as a numpy array of RGBA pixels"""
+    if scene is None:
+        scene = MetaballData(width=width, height=height, metaballs=[(width * 0.4, height * 0.5, 30.0), (width * 0.6, height * 0.5, 20.0)])
+    return scene.render(width, height)
+
+
+def preset(name, width, height):
+    """One of the preset scenes, such as "ring", laid out for the size and rendered as a numpy array"""
+    pixels = (c_uint8 * (width * height * 4))()
+    _check(_lib().metaballs_render_preset(name.encode(), width, height, pixels))
+    return _image(bytes(pixels), width, height)
@@ -0,0 +1,199 @@
+//! Audio analysis for reacting to music: the energy in the bass, mids and treble of the latest samples,
+//! and a scene pumped by it
+
+use crate::MetaballData;
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+/// How many samples each analysis looks at, a power of two for the FFT. About 40ms at the usual rates.
+pub const FFT_SIZE: usize = 2048;
+
+/// The frequencies in hertz each band runs from and to
+const BASS: (f64, f64) = (20.0, 250.0);
+const MID: (f64, f64) = (250.0, 4000.0);
+const TREBLE: (f64, f64) = (4000.0, 16000.0);
+
+/// How much of the loudest a band has been lately is kept each analysis, so the levels adjust to how loud
+/// the music is over a few seconds
+const PEAK_DECAY: f64 = 0.995;
+
+/// The quietest a band's peak goes, so silence doesn't get turned up into noise
+const NOISE_FLOOR: f64 = 1e-6;
+
+/// How much of the last level is kept when a band gets quieter, so the shapes fall back smoothly instead of
+/// flickering. Getting louder shows straight away.
+const RELEASE: f64 = 0.85;
+
+/// How much bigger the metaballs get with the bass at its loudest
+const BASS_SIZE: f64 = 0.6;
+
+/// How much of the goo the mids take away at their loudest, melting the metaballs together
+const MID_GOO: f64 = 0.3;
+
+/// How much the treble raises the threshold at its loudest, sharpening the shapes into each other
+const TREBLE_THRESHOLD: f64 = 0.5;
+
+/// How loud each band is, from 0 for silent to 1 for the loudest it's been lately
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Levels {
+    pub bass: f64,
+    pub mid: f64,
+    pub treble: f64,
+}
+
+/// Keeps the latest samples of a mono signal and turns them into [Levels]
+#[derive(Clone, Debug)]
+pub struct Analyzer {
+    sample_rate: u32,
+    /// Up to [FFT_SIZE] of the latest samples, oldest first
+    samples: VecDeque<f32>,
+    /// The loudest each band has been lately, as energy
+    peaks: [f64; 3],
+    levels: Levels,
+}
+
+impl Analyzer {
+    pub fn new(sample_rate: u32) -> Analyzer {
+        Analyzer { sample_rate, samples: VecDeque::with_capacity(FFT_SIZE), peaks: [NOISE_FLOOR; 3], levels: Levels::default() }
+    }
+
+    /// Add the samples that came in since last time, dropping ones too old to be analyzed
+    pub fn push(&mut self, samples: impl IntoIterator<Item = f32>) {
+        for sample in samples {
+            if self.samples.len() == FFT_SIZE {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(sample);
+        }
+    }
+
+    /// Analyze the latest samples, returning how loud each band is now
+    pub fn update(&mut self) -> Levels {
+        let energies = band_energies(&self.samples, self.sample_rate);
+        let mut levels = [0.0; 3];
+        for ((level, peak), energy) in levels.iter_mut().zip(&mut self.peaks).zip(&energies) {
+            *peak = energy.max(*peak * PEAK_DECAY).max(NOISE_FLOOR);
+            *level = energy / *peak;
+        }
+        let smooth = |last: f64, level: f64| if level >= last { level } else { last * RELEASE + level * (1.0 - RELEASE) };
+        self.levels = Levels {
+            bass: smooth(self.levels.bass, levels[0]),
+            mid: smooth(self.levels.mid, levels[1]),
+            treble: smooth(self.levels.treble, levels[2]),
+        };
+        self.levels
+    }
+}
+
+/// The scene pumped by the audio: the bass swells the metaballs, the mids melt them together and the treble
+/// sharpens them. It's the same scene when everything's silent.
+pub fn react(metaball_data: &MetaballData, levels: Levels) -> MetaballData {
+    let mut reacting = metaball_data.clone();
+    let goo = 1.0 - MID_GOO * levels.mid;
+    for metaball in &mut reacting.metaballs {
+        metaball.size *= 1.0 + BASS_SIZE * levels.bass;
+        metaball.goo = metaball.goo.map(|own| own * goo);
+    }
+    reacting.goo *= goo;
+    reacting.threshold *= 1.0 + TREBLE_THRESHOLD * levels.treble;
+    reacting
+}
+
+/// The mean energy per sample of the bass, mids and treble in a window of samples, which is padded with
+/// silence up to [FFT_SIZE]
+fn band_energies(samples: &VecDeque<f32>, sample_rate: u32) -> [f64; 3] {
+    // a Hann window, so the edges of the window don't spread energy into every band
+    let mut bins: Vec<(f64, f64)> = (0..FFT_SIZE)
+        .map(|i| {
+            let sample = samples.get(i).map_or(0.0, |&sample| sample as f64);
+            let hann = 0.5 - 0.5 * (2.0 * PI * i as f64 / FFT_SIZE as f64).cos();
+            (sample * hann, 0.0)
+        })
+        .collect();
+    fft(&mut bins);
+    let hertz_per_bin = sample_rate as f64 / FFT_SIZE as f64;
+    [BASS, MID, TREBLE].map(|(from, to)| {
+        let bins = &bins[..FFT_SIZE / 2];
+        let (from, to) = ((from / hertz_per_bin).ceil() as usize, ((to / hertz_per_bin).ceil() as usize).min(bins.len()));
+        bins.get(from..to).map_or(0.0, |band| band.iter().map(|&(re, im)| re * re + im * im).sum::<f64>()) / (FFT_SIZE * FFT_SIZE) as f64
+    })
+}
+
+/// An in-place radix-2 FFT of complex numbers as (real, imaginary), whose length is a power of two
+fn fft(values: &mut [(f64, f64)]) {
+    let count = values.len();
+    debug_assert!(count.is_power_of_two());
+    // put the values in bit-reversed order, so each pass combines neighbors
+    let mut j = 0;
+    for i in 1..count {
+        let mut bit = count >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+    let mut length = 2;
+    while length <= count {
+        let angle = -2.0 * PI / length as f64;
+        for start in (0..count).step_by(length) {
+            for k in 0..length / 2 {
+                let (sin, cos) = (angle * k as f64).sin_cos();
+                let (re, im) = values[start + k + length / 2];
+                let twiddled = (re * cos - im * sin, re * sin + im * cos);
+                let even = values[start + k];
+                values[start + k] = (even.0 + twiddled.0, even.1 + twiddled.1);
+                values[start + k + length / 2] = (even.0 - twiddled.0, even.1 - twiddled.1);
+            }
+        }
+        length *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metaball, Pointf};
+
+    fn tone(hertz: f64, sample_rate: u32) -> impl Iterator<Item = f32> {
+        (0..FFT_SIZE).map(move |i| (2.0 * PI * hertz * i as f64 / sample_rate as f64).sin() as f32)
+    }
+
+    #[test]
+    fn a_sine_lands_in_its_frequency_bin() {
+        let mut values: Vec<(f64, f64)> = (0..64).map(|i| ((2.0 * PI * 5.0 * i as f64 / 64.0).cos(), 0.0)).collect();
+        fft(&mut values);
+        let loudest = (0..32).max_by(|&a, &b| values[a].0.hypot(values[a].1).total_cmp(&values[b].0.hypot(values[b].1)));
+        assert_eq!(loudest, Some(5));
+        assert!((values[5].0 - 32.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn tones_light_up_their_bands() {
+        let mut analyzer = Analyzer::new(44100);
+        analyzer.push(tone(100.0, 44100));
+        let levels = analyzer.update();
+        assert_eq!(levels.bass, 1.0);
+        assert!(levels.mid < 0.1 && levels.treble < 0.1, "{:?}", levels);
+
+        // the bass falls away gradually once the music moves on to the treble
+        analyzer.push(tone(8000.0, 44100));
+        let levels = analyzer.update();
+        assert_eq!(levels.treble, 1.0);
+        assert!(levels.bass > 0.5 && levels.bass < 1.0, "{:?}", levels);
+    }
+
+    #[test]
+    fn silence_leaves_the_scene_alone() {
+        let metadata = MetaballData::new(1.6, 0.5, 64, 64, vec![Metaball::new(Pointf { x: 32.0, y: 32.0 }, 60.0)]);
+        let mut analyzer = Analyzer::new(48000);
+        analyzer.push(vec![0.0; FFT_SIZE]);
+        assert_eq!(react(&metadata, analyzer.update()), metadata);
+
+        let loud = react(&metadata, Levels { bass: 1.0, mid: 1.0, treble: 1.0 });
+        assert!(loud.metaballs[0].size > 60.0 && loud.goo < 1.6 && loud.threshold > 0.5);
+    }
+}
@@ -0,0 +1,121 @@
+//! Scripted behaviors: a program of the user's own, in any language, that's sent the scene every animation
+//! tick and answers with how it changes, for custom animations and generative scenes without rebuilding.
+//!
+//! Each tick the script is written a line of JSON, `{"t": 1.5, "tick": 90, "scene": {...}}`, with the
+//! seconds and ticks since it started and the scene the way the state command prints it. It answers with a
+//! line of JSON with any of `goo`, `threshold` and `metaballs`, a list taking the place of the metaballs with
+//! an object for each. The object for a metaball that's already there moves or resizes it with any of its
+//! `x`, `y` and `size`, one past the end adds a metaball, which needs an `x` and `y`, and a shorter list
+//! removes the ones after it. `{}` leaves the scene as it is.
+
+use crate::dataset::scene_json;
+use crate::json::{self, Json};
+use crate::{Metaball, MetaballData, Pointf, ANIMATION_TICK, BASE_METABALL_SIZE};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{ChildStdin, ChildStdout, Command, Stdio};
+
+/// A running script, see the [module](self)
+pub struct Behavior {
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    tick: u64,
+}
+
+impl Behavior {
+    /// Start the script, which exits when it reads the end of its input
+    pub fn spawn(program: &Path) -> io::Result<Behavior> {
+        let mut child = Command::new(program).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn()?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+        Ok(Behavior { stdin, stdout, tick: 0 })
+    }
+
+    /// Send the script the scene for the next tick and apply its answer, returning whether the scene changed
+    pub fn update(&mut self, metaball_data: &mut MetaballData) -> Result<bool, String> {
+        let scene: String = scene_json(metaball_data).lines().map(str::trim).collect();
+        let t = self.tick as f64 * ANIMATION_TICK.as_secs_f64();
+        writeln!(self.stdin, "{{\"t\": {}, \"tick\": {}, \"scene\": {}}}", t, self.tick, scene)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|err| format!("unable to write to the script: {}", err))?;
+        self.tick += 1;
+        let mut line = String::new();
+        match self.stdout.read_line(&mut line) {
+            Ok(0) => Err("the script exited".to_string()),
+            Ok(_) => apply(&json::parse(&line)?, metaball_data),
+            Err(err) => Err(format!("unable to read from the script: {}", err)),
+        }
+    }
+}
+
+/// Apply a script's answer to the scene, returning whether it changed anything
+pub fn apply(answer: &Json, metaball_data: &mut MetaballData) -> Result<bool, String> {
+    let number = |object: &Json, key: &str| match object.get(key) {
+        None => Ok(None),
+        Some(value) => value.as_f64().filter(|number| number.is_finite()).map(Some).ok_or(format!("{} needs to be a number", key)),
+    };
+    if !matches!(answer, Json::Object(_)) {
+        return Err("the answer needs to be an object".to_string());
+    }
+    let mut changed = false;
+    if let Some(goo) = number(answer, "goo")? {
+        changed |= goo != metaball_data.goo;
+        metaball_data.set_goo(goo)?;
+    }
+    if let Some(threshold) = number(answer, "threshold")? {
+        changed |= threshold != metaball_data.threshold;
+        metaball_data.set_threshold(threshold)?;
+    }
+    let metaballs = match answer.get("metaballs") {
+        None => return Ok(changed),
+        Some(Json::Array(metaballs)) => metaballs,
+        Some(_) => return Err("metaballs needs to be a list".to_string()),
+    };
+    for (index, object) in metaballs.iter().enumerate() {
+        let (x, y, size) = (number(object, "x")?, number(object, "y")?, number(object, "size")?);
+        match metaball_data.metaballs.get_mut(index) {
+            Some(metaball) => {
+                let location = Pointf { x: x.unwrap_or(metaball.location.x), y: y.unwrap_or(metaball.location.y) };
+                if location != metaball.location {
+                    metaball.move_to(location);
+                    changed = true;
+                }
+                if let Some(size) = size.filter(|&size| size != metaball.size) {
+                    metaball.size = size;
+                    changed = true;
+                }
+            }
+            None => {
+                let (x, y) = x.zip(y).ok_or(format!("the new metaball {} needs an x and y", index))?;
+                metaball_data.metaballs.push(Metaball::new(Pointf { x, y }, size.unwrap_or(BASE_METABALL_SIZE)));
+                changed = true;
+            }
+        }
+    }
+    if metaballs.len() < metaball_data.metaballs.len() {
+        metaball_data.metaballs.truncate(metaballs.len());
+        changed = true;
+    }
+    Ok(changed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn answers_move_add_and_remove_metaballs() {
+        let ball = |x: f64| Metaball::new(Pointf { x, y: 10.0 }, 40.0);
+        let mut metadata = MetaballData::new(1.6, 0.5, 100, 100, vec![ball(10.0), ball(20.0), ball(30.0)]);
+        let answer = json::parse(r#"{"threshold": 0.8, "metaballs": [{}, {"x": 25, "size": 60}, {"x": 50, "y": 60}, {"x": 1, "y": 2}]}"#).unwrap();
+        assert_eq!(apply(&answer, &mut metadata), Ok(true));
+        assert_eq!(metadata.threshold, 0.8);
+        let locations: Vec<_> = metadata.metaballs.iter().map(|metaball| (metaball.location.x, metaball.location.y, metaball.size)).collect();
+        assert_eq!(locations, vec![(10.0, 10.0, 40.0), (25.0, 10.0, 60.0), (50.0, 60.0, 40.0), (1.0, 2.0, BASE_METABALL_SIZE)]);
+
+        assert_eq!(apply(&json::parse(r#"{"metaballs": [{"x": 10}]}"#).unwrap(), &mut metadata), Ok(true));
+        assert_eq!(metadata.metaballs.len(), 1);
+        assert_eq!(apply(&json::parse("{}").unwrap(), &mut metadata), Ok(false));
+        assert!(apply(&json::parse(r#"{"metaballs": [{}, {"x": 1}]}"#).unwrap(), &mut metadata).is_err());
+    }
+}
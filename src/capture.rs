@@ -0,0 +1,50 @@
+//! Listening to the default audio input with cpal, for `--audio`
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{Sample, SampleFormat, Stream, StreamConfig};
+use metaballs::audio::{Analyzer, Levels};
+use std::sync::{Arc, Mutex};
+
+/// The default input device being captured, mixed down to mono and kept for analyzing
+pub struct AudioInput {
+    analyzer: Arc<Mutex<Analyzer>>,
+    /// Captures as long as it's kept around
+    _stream: Stream,
+}
+
+impl AudioInput {
+    /// Start capturing the default input device. Listening to what's playing instead of a microphone takes
+    /// making a loopback or monitor device the default input.
+    pub fn open() -> Result<AudioInput, String> {
+        let device = cpal::default_host().default_input_device().ok_or("there's no audio input device")?;
+        let config = device.default_input_config().map_err(|err| err.to_string())?;
+        let analyzer = Arc::new(Mutex::new(Analyzer::new(config.sample_rate().0)));
+        let stream = match config.sample_format() {
+            SampleFormat::F32 => capture::<f32>(&device, &config.into(), analyzer.clone()),
+            SampleFormat::I16 => capture::<i16>(&device, &config.into(), analyzer.clone()),
+            SampleFormat::U16 => capture::<u16>(&device, &config.into(), analyzer.clone()),
+        }?;
+        stream.play().map_err(|err| err.to_string())?;
+        Ok(AudioInput { analyzer, _stream: stream })
+    }
+
+    /// How loud each band of the latest audio is
+    pub fn levels(&self) -> Levels {
+        self.analyzer.lock().unwrap().update()
+    }
+}
+
+/// Build a stream feeding the analyzer samples of one format
+fn capture<T: Sample>(device: &cpal::Device, config: &StreamConfig, analyzer: Arc<Mutex<Analyzer>>) -> Result<Stream, String> {
+    let channels = config.channels as usize;
+    device
+        .build_input_stream(
+            config,
+            move |data: &[T], _: &cpal::InputCallbackInfo| {
+                let mono = data.chunks(channels).map(|frame| frame.iter().map(|sample| sample.to_f32()).sum::<f32>() / channels as f32);
+                analyzer.lock().unwrap().push(mono);
+            },
+            |err| log::error!("Audio input failed: {}", err),
+        )
+        .map_err(|err| err.to_string())
+}
@@ -0,0 +1,190 @@
+//! The colormaps the heatmap can be drawn in, and the range of field values it's spread across. The
+//! perceptually uniform viridis and magma, and the rainbow-like turbo, are the ones figures are usually
+//! made with, where equal steps in the field read as equal steps in the color.
+
+use crate::{heatmap_color, lerp_color};
+use image::Rgba;
+use std::fmt;
+use std::str::FromStr;
+
+/// Nine evenly spaced colors of matplotlib's viridis, interpolated between
+const VIRIDIS: [[u8; 3]; 9] = [
+    [0x44, 0x01, 0x54],
+    [0x47, 0x2d, 0x7b],
+    [0x3b, 0x52, 0x8b],
+    [0x2c, 0x72, 0x8e],
+    [0x21, 0x91, 0x8c],
+    [0x28, 0xae, 0x80],
+    [0x5e, 0xc9, 0x62],
+    [0xad, 0xdc, 0x30],
+    [0xfd, 0xe7, 0x25],
+];
+
+/// Nine evenly spaced colors of matplotlib's magma
+const MAGMA: [[u8; 3]; 9] = [
+    [0x00, 0x00, 0x04],
+    [0x1d, 0x11, 0x47],
+    [0x51, 0x12, 0x7c],
+    [0x82, 0x26, 0x81],
+    [0xb6, 0x36, 0x79],
+    [0xe6, 0x51, 0x64],
+    [0xfb, 0x88, 0x61],
+    [0xfe, 0xc2, 0x87],
+    [0xfc, 0xfd, 0xbf],
+];
+
+/// Eleven evenly spaced colors of turbo, sampled from the polynomial approximation published with it
+const TURBO: [[u8; 3]; 11] = [
+    [35, 23, 27],
+    [73, 88, 221],
+    [47, 158, 245],
+    [39, 215, 195],
+    [78, 249, 131],
+    [150, 250, 80],
+    [223, 220, 50],
+    [255, 163, 35],
+    [244, 92, 23],
+    [184, 32, 8],
+    [144, 13, 0],
+];
+
+/// The colors of the heatmap from cold to hot
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum Colormap {
+    /// Black through blue, cyan and yellow to red, see [HEATMAP_STOPS](crate::HEATMAP_STOPS)
+    #[default]
+    Classic,
+    Viridis,
+    Magma,
+    Turbo,
+}
+
+impl Colormap {
+    /// The color some way from cold at 0 to hot at 1
+    pub fn color(&self, heat: f64) -> Rgba<u8> {
+        let stops: &[[u8; 3]] = match self {
+            Colormap::Classic => return heatmap_color(heat),
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Magma => &MAGMA,
+            Colormap::Turbo => &TURBO,
+        };
+        let along = heat.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+        let below = (along as usize).min(stops.len() - 2);
+        let color = |[r, g, b]: [u8; 3]| Rgba([r, g, b, 255]);
+        lerp_color(color(stops[below]), color(stops[below + 1]), along - below as f64)
+    }
+}
+
+impl FromStr for Colormap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "classic" => Ok(Colormap::Classic),
+            "viridis" => Ok(Colormap::Viridis),
+            "magma" => Ok(Colormap::Magma),
+            "turbo" => Ok(Colormap::Turbo),
+            _ => Err(format!("Unknown colormap \"{}\", expected classic, viridis, magma or turbo", s)),
+        }
+    }
+}
+
+impl fmt::Display for Colormap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Colormap::Classic => "classic",
+            Colormap::Viridis => "viridis",
+            Colormap::Magma => "magma",
+            Colormap::Turbo => "turbo",
+        })
+    }
+}
+
+/// Which field values the cold and hot ends of the heatmap are
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum HeatScale {
+    /// `sum / (sum + threshold)`, which puts the shape's edge halfway along whatever the scene, though the
+    /// colors don't go up evenly with the field
+    #[default]
+    Threshold,
+    /// From the lowest to the highest finite field value in each frame, so the whole colormap is used
+    Frame,
+    /// From a fixed `min` to `max`, the same in every frame and image so they can be compared
+    Fixed { min: f64, max: f64 },
+}
+
+impl HeatScale {
+    /// How far from cold at 0 to hot at 1 each field value of a frame is. The field is infinite at the
+    /// centers of the metaballs, which are as hot as it gets.
+    pub fn normalizer(&self, field: &[f64], threshold: f64) -> impl Fn(f64) -> f64 {
+        let range = match *self {
+            HeatScale::Threshold => None,
+            HeatScale::Frame => {
+                let finite = field.iter().copied().filter(|sum| sum.is_finite());
+                Some(finite.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), sum| (min.min(sum), max.max(sum))))
+            }
+            HeatScale::Fixed { min, max } => Some((min, max)),
+        };
+        move |sum: f64| match range {
+            _ if sum == f64::INFINITY => 1.0,
+            // the smooth union's field goes negative far from the shape, which is as cold as it gets
+            None => sum.max(0.0) / (sum.max(0.0) + threshold),
+            Some((min, max)) if max > min => ((sum - min) / (max - min)).clamp(0.0, 1.0),
+            Some(_) => 0.0,
+        }
+    }
+}
+
+impl FromStr for HeatScale {
+    type Err = String;
+
+    /// `threshold`, `frame`, or a fixed range as `min:max`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "threshold" => Ok(HeatScale::Threshold),
+            "frame" => Ok(HeatScale::Frame),
+            _ => {
+                let error = || format!("Expected threshold, frame or a range like 0:2, got \"{}\"", s);
+                let (min, max) = s.split_once(':').ok_or_else(error)?;
+                let (min, max): (f64, f64) = (min.trim().parse().map_err(|_| error())?, max.trim().parse().map_err(|_| error())?);
+                if !min.is_finite() || !max.is_finite() || min >= max {
+                    return Err(format!("The heat scale's range needs a finite min below its max, got \"{}\"", s));
+                }
+                Ok(HeatScale::Fixed { min, max })
+            }
+        }
+    }
+}
+
+impl fmt::Display for HeatScale {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HeatScale::Threshold => f.write_str("threshold"),
+            HeatScale::Frame => f.write_str("frame"),
+            HeatScale::Fixed { min, max } => write!(f, "{}:{}", min, max),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::HEATMAP_STOPS;
+
+    #[test]
+    fn colormaps_run_from_end_to_end_over_the_scaled_field() {
+        assert_eq!(Colormap::Viridis.color(0.0), Rgba([0x44, 0x01, 0x54, 255]));
+        assert_eq!(Colormap::Magma.color(1.0), Rgba([0xfc, 0xfd, 0xbf, 255]));
+        assert_eq!(Colormap::Viridis.color(0.5), Rgba([0x21, 0x91, 0x8c, 255]));
+        assert_eq!(Colormap::Classic.color(0.5), HEATMAP_STOPS[2].1);
+        assert_eq!("turbo".parse::<Colormap>().map(|colormap| colormap.to_string()), Ok("turbo".to_string()));
+
+        let field = [-1.0, 1.0, 3.0, f64::INFINITY];
+        let frame = HeatScale::Frame.normalizer(&field, 0.5);
+        assert_eq!(field.map(frame), [0.0, 0.5, 1.0, 1.0]);
+        let fixed = "0:2".parse::<HeatScale>().unwrap().normalizer(&field, 0.5);
+        assert_eq!(field.map(fixed), [0.0, 0.5, 1.0, 1.0]);
+        assert_eq!(HeatScale::Threshold.normalizer(&field, 1.0)(1.0), 0.5);
+        assert!("2:1".parse::<HeatScale>().is_err() && "hot".parse::<HeatScale>().is_err());
+    }
+}
@@ -0,0 +1,90 @@
+//! Rendering the same metaballs with two sets of parameters side by side, for trying out a goo, threshold or
+//! falloff against another. The left pane is the scene as it is, and the right one the same metaballs with
+//! the comparison's parameters, each showing the whole view at half the size.
+
+use crate::falloff::Falloff;
+use crate::{render_image, MetaballData, RenderOpts, View};
+use image::{GenericImage, ImageBuffer, Rgba};
+use std::fmt;
+
+/// The parameters the right pane is rendered with
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Comparison {
+    pub goo: f64,
+    pub threshold: f64,
+    pub falloff: Falloff,
+}
+
+impl Comparison {
+    /// The parameters a scene has now, to compare the changes made to it after against
+    pub fn of(metaball_data: &MetaballData) -> Comparison {
+        Comparison { goo: metaball_data.goo, threshold: metaball_data.threshold, falloff: metaball_data.falloff }
+    }
+
+    /// The scene with these parameters in place of its own
+    pub fn applied(&self, metaball_data: &MetaballData) -> MetaballData {
+        MetaballData { goo: self.goo, threshold: self.threshold, falloff: self.falloff, ..metaball_data.clone() }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "goo {}, threshold {} and the {} falloff", self.goo, self.threshold, self.falloff)
+    }
+}
+
+/// The view of each pane of a `height` pixels tall image, zoomed out by half around the middle so the pane
+/// half as wide shows all of what the view does, with as much again above and below it
+pub fn pane_view(view: &View, height: u32) -> View {
+    View { x: view.x, y: view.y - height as f64 / 2.0 / view.zoom, zoom: view.zoom / 2.0 }
+}
+
+/// How wide the left pane of a `width` pixels wide image is, the right one being the rest
+pub fn pane_width(width: u32) -> u32 {
+    width / 2
+}
+
+/// The field point under a pixel of the side by side image, in whichever pane it's over
+pub fn field_point(x: f64, y: f64, width: u32, height: u32, view: &View) -> (f64, f64) {
+    let left = pane_width(width) as f64;
+    pane_view(view, height).field_point(if x < left { x } else { x - left }, y)
+}
+
+/// Render the scene in the left pane and the scene with the comparison's parameters in the right one, with a
+/// line in the indicator color between them
+pub fn side_by_side(width: u32, height: u32, metaball_data: &MetaballData, comparison: &Comparison, opts: &RenderOpts) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let opts = RenderOpts { compare: None, view: pane_view(&opts.view, height), ..opts.clone() };
+    let left = pane_width(width);
+    let mut image = ImageBuffer::new(width, height);
+    if left > 0 {
+        image.copy_from(&render_image(left, height, metaball_data, &opts), 0, 0).expect("the left pane fits");
+    }
+    image.copy_from(&render_image(width - left, height, &comparison.applied(metaball_data), &opts), left, 0).expect("the right pane fits");
+    if left > 0 {
+        for y in 0..height {
+            image.put_pixel(left, y, opts.cross_color);
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metaball, Pointf};
+
+    #[test]
+    fn each_pane_shows_the_whole_scene_with_its_own_parameters() {
+        let metadata = MetaballData::new(1.6, 0.5, 80, 40, vec![Metaball::new(Pointf { x: 40.0, y: 20.0 }, 60.0)]);
+        let comparison = Comparison { threshold: 2.0, ..Comparison::of(&metadata) };
+        let opts = RenderOpts { compare: Some(comparison), ..RenderOpts::default() };
+        let image = render_image(80, 40, &metadata, &opts);
+        // the center of the scene is in the middle of each pane
+        assert_eq!(field_point(20.0, 20.0, 80, 40, &opts.view), (40.0, 20.0));
+        assert_eq!(field_point(60.0, 20.0, 80, 40, &opts.view), (40.0, 20.0));
+        // the higher threshold draws the metaball in, so less of the right pane is inside
+        let inside = |pane: std::ops::Range<u32>| pane.filter(|&x| *image.get_pixel(x, 20) == opts.on_color).count();
+        assert!(inside(41..80) < inside(0..40) && inside(41..80) > 0);
+        assert_eq!(*image.get_pixel(40, 0), opts.cross_color);
+    }
+}
@@ -0,0 +1,116 @@
+//! Marching squares over the metaball field, turning the edge of the shape into polylines
+
+use crate::{field_grid, MetaballData, Pointf, Precision, View};
+use std::collections::{HashMap, HashSet};
+
+/// The outline of a scene's shape at its threshold, sampling the field at every pixel
+pub fn outline(metaball_data: &MetaballData) -> Vec<Vec<Pointf>> {
+    let (width, height) = (metaball_data.width, metaball_data.height);
+    let field = field_grid(width, height, 1, &View::default(), metaball_data, Precision::F64);
+    trace(&field, width, height, metaball_data.threshold)
+}
+
+/// An edge between two neighboring field points, to the right of the point if horizontal and below it
+/// otherwise. A contour crosses an edge at most once, so edges identify the points along contours.
+type GridEdge = (u32, u32, bool);
+
+/// Trace where a grid of field values crosses a level with marching squares, joining the crossings into
+/// polylines of field points. Polylines that loop around end with their first point again.
+pub fn trace(field: &[f64], width: u32, height: u32, level: f64) -> Vec<Vec<Pointf>> {
+    let value = |x: u32, y: u32| field[(y * width + x) as usize];
+    let inside = |x: u32, y: u32| value(x, y) > level;
+    // where along an edge the field crosses the level
+    let crossing = |(x, y, horizontal): GridEdge| {
+        let (to_x, to_y) = if horizontal { (x + 1, y) } else { (x, y + 1) };
+        let (from, to) = (value(x, y), value(to_x, to_y));
+        // the field is infinite right on a metaball's center, which the level is right next to
+        let t = if from.is_infinite() {
+            1.0
+        } else if to.is_infinite() {
+            0.0
+        } else {
+            (level - from) / (to - from)
+        };
+        Pointf { x: x as f64 + (to_x - x) as f64 * t, y: y as f64 + (to_y - y) as f64 * t }
+    };
+
+    // connect the crossings on the edges of each cell of four field points
+    let mut neighbors: HashMap<GridEdge, Vec<GridEdge>> = HashMap::new();
+    for y in 0..height.saturating_sub(1) {
+        for x in 0..width.saturating_sub(1) {
+            // top, right, bottom and left
+            let edges = [(x, y, true), (x + 1, y, false), (x, y + 1, true), (x, y, false)];
+            let case = inside(x, y) as u8
+                | (inside(x + 1, y) as u8) << 1
+                | (inside(x + 1, y + 1) as u8) << 2
+                | (inside(x, y + 1) as u8) << 3;
+            // when only diagonal corners are inside, the center decides whether they're joined
+            let center = (value(x, y) + value(x + 1, y) + value(x + 1, y + 1) + value(x, y + 1)) / 4.0 > level;
+            let segments: &[(usize, usize)] = match case {
+                0 | 15 => &[],
+                1 | 14 => &[(3, 0)],
+                2 | 13 => &[(0, 1)],
+                3 | 12 => &[(3, 1)],
+                4 | 11 => &[(1, 2)],
+                6 | 9 => &[(0, 2)],
+                7 | 8 => &[(3, 2)],
+                5 if center => &[(0, 1), (2, 3)],
+                5 => &[(3, 0), (1, 2)],
+                10 if center => &[(3, 0), (1, 2)],
+                _ => &[(0, 1), (2, 3)],
+            };
+            for (from, to) in segments {
+                neighbors.entry(edges[*from]).or_default().push(edges[*to]);
+                neighbors.entry(edges[*to]).or_default().push(edges[*from]);
+            }
+        }
+    }
+
+    // follow the crossings from one to the next, starting with the ends of paths cut off by the edge of the
+    // field and then the loops, in a fixed order so the output is the same every time
+    let mut ends: Vec<GridEdge> = neighbors.iter().filter(|(_, next)| next.len() == 1).map(|(edge, _)| *edge).collect();
+    let mut rest: Vec<GridEdge> = neighbors.keys().copied().collect();
+    ends.sort_unstable();
+    rest.sort_unstable();
+    let mut visited = HashSet::new();
+    let mut paths = vec![];
+    for start in ends.into_iter().chain(rest) {
+        if !visited.insert(start) {
+            continue;
+        }
+        let mut path = vec![crossing(start)];
+        let mut current = start;
+        while let Some(next) = neighbors[&current].iter().copied().find(|edge| !visited.contains(edge)) {
+            visited.insert(next);
+            path.push(crossing(next));
+            current = next;
+        }
+        if path.len() > 2 && neighbors[&current].contains(&start) {
+            path.push(path[0]);
+        }
+        paths.push(path);
+    }
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Metaball;
+
+    #[test]
+    fn a_lone_metaball_traces_a_closed_circle() {
+        let metadata = MetaballData::new(1.6, 0.5, 64, 64, vec![Metaball::new(Pointf { x: 32.0, y: 32.0 }, 60.0)]);
+        let contours = outline(&metadata);
+        assert_eq!(contours.len(), 1);
+
+        // a lone metaball's edge is where size / distance^goo is the threshold
+        let radius = (60.0f64 / 0.5).powf(1.0 / 1.6);
+        let contour = &contours[0];
+        assert_eq!(contour.first(), contour.last());
+        for point in contour {
+            let distance = Pointf { x: 32.0, y: 32.0 }.distance_to(point.x, point.y, 1.0);
+            assert!((distance - radius).abs() < 0.5, "{:?} is {} from the center", point, distance);
+        }
+    }
+}
@@ -0,0 +1,61 @@
+//! Labels for datasets of rendered scenes, written next to each image by the `generate` command so what's in
+//! it can be learned from the pixels
+
+use crate::{hex_color, MetaballData, Primitive};
+
+/// The parameters of a scene and every metaball in it as JSON, one metaball to a line. Each metaball has
+/// all of its fields with its own goo filled in from the scene's, null for the radius of one that reaches
+/// forever and the end of one that isn't a metaline.
+pub fn scene_json(metaball_data: &MetaballData) -> String {
+    let metaballs: Vec<String> = metaball_data
+        .metaballs
+        .iter()
+        .map(|metaball| {
+            let end = match metaball.primitive {
+                Primitive::Segment { end } => format!("[{}, {}]", end.x, end.y),
+                _ => "null".to_string(),
+            };
+            format!(
+                "    {{\"x\": {}, \"y\": {}, \"size\": {}, \"radius\": {}, \"goo\": {}, \"color\": \"{}\", \"axes\": [{}, {}], \"angle\": {}, \"end\": {}, \"layer\": {}}}",
+                metaball.location.x,
+                metaball.location.y,
+                metaball.size,
+                metaball.radius.map_or("null".to_string(), |radius| radius.to_string()),
+                metaball.goo_or(metaball_data.goo),
+                hex_color(metaball.color),
+                metaball.axes.0,
+                metaball.axes.1,
+                metaball.angle,
+                end,
+                metaball.layer,
+            )
+        })
+        .collect();
+    format!(
+        "{{\n  \"width\": {},\n  \"height\": {},\n  \"goo\": {},\n  \"threshold\": {},\n  \"metaballs\": [\n{}\n  ]\n}}\n",
+        metaball_data.width,
+        metaball_data.height,
+        metaball_data.goo,
+        metaball_data.threshold,
+        metaballs.join(",\n")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metaball, Pointf};
+
+    #[test]
+    fn every_metaball_is_labelled() {
+        let mut line = Metaball::new(Pointf { x: 10.0, y: 20.5 }, 40.0);
+        line.primitive = Primitive::Segment { end: Pointf { x: 30.0, y: 20.5 } };
+        let metadata = MetaballData::new(1.6, 0.5, 64, 48, vec![line, Metaball { radius: Some(12.0), ..Metaball::new(Pointf { x: 5.0, y: 6.0 }, -20.0) }]);
+        let json = scene_json(&metadata);
+        assert!(json.starts_with("{\n  \"width\": 64,\n  \"height\": 48,\n  \"goo\": 1.6,\n  \"threshold\": 0.5,\n"));
+        assert!(json.contains("{\"x\": 10, \"y\": 20.5, \"size\": 40, \"radius\": null, \"goo\": 1.6, \"color\": \"ff0000\""));
+        assert!(json.contains("\"end\": [30, 20.5], \"layer\": 0},\n"));
+        assert!(json.contains("\"size\": -20, \"radius\": 12,"));
+        assert!(json.ends_with("\"end\": null, \"layer\": 0}\n  ]\n}\n"));
+    }
+}
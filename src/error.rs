@@ -0,0 +1,23 @@
+//! What can go wrong running the window or the terminal and reading their commands, as readable messages rather than panics
+
+use std::io;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum MetaballError {
+    /// The window couldn't be made, such as without a display to open it on
+    #[error("Unable to open the window: {0}")]
+    Window(#[from] winit::error::OsError),
+    /// The pixel buffer couldn't be bound to the window or drawn to it, such as without a GPU surface for it
+    #[error("Unable to draw to the window: {0}")]
+    Pixels(#[from] pixels::Error),
+    /// Commands couldn't be read from a script, STDIN or a connection
+    #[error("Unable to read commands: {0}")]
+    Read(#[from] io::Error),
+    /// The terminal couldn't be taken over or drawn to for the --tui
+    #[error("Unable to use the terminal: {0}")]
+    Terminal(io::Error),
+    /// Nothing's taking the commands any more, because the window or batch run has finished
+    #[error("There's nothing left to send commands to")]
+    Closed,
+}
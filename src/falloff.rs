@@ -0,0 +1,152 @@
+//! The shapes of the field around each metaball, how what a metaball adds falls off with distance
+
+use std::fmt;
+use std::str::FromStr;
+
+/// How much a metaball adds to the field at a distance from its center. A metaball of `size` with a `goo`
+/// reaches `|size|^(1 / goo)` pixels before adding 1, which every falloff shares so sizes look alike
+/// whichever is used, and negative sizes take the same amount away.
+pub trait FalloffFn {
+    /// What a metaball adds to the field at a distance from its center
+    fn value(&self, size: f64, distance: f64, goo: f64) -> f64;
+
+    /// How far from its center a metaball on its own reaches the threshold, which is 0 if it never does
+    fn edge(&self, size: f64, threshold: f64, goo: f64) -> f64;
+}
+
+/// How far a metaball reaches before adding 1 to the field
+fn reach(size: f64, goo: f64) -> f64 {
+    size.abs().powf(1.0 / goo)
+}
+
+/// `size / distance^goo`, infinite at the center and reaching forever
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct InversePower;
+
+impl FalloffFn for InversePower {
+    fn value(&self, size: f64, distance: f64, goo: f64) -> f64 {
+        size / distance.powf(goo)
+    }
+
+    fn edge(&self, size: f64, threshold: f64, goo: f64) -> f64 {
+        (size.abs() / threshold).powf(1.0 / goo)
+    }
+}
+
+/// A bell curve peaking at 2 on the center, so neighbors merge smoothly without any point of the field
+/// being infinite
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Gaussian;
+
+impl FalloffFn for Gaussian {
+    fn value(&self, size: f64, distance: f64, goo: f64) -> f64 {
+        let scaled = distance / reach(size, goo);
+        2f64.powf(1.0 - scaled * scaled).copysign(size)
+    }
+
+    fn edge(&self, size: f64, threshold: f64, goo: f64) -> f64 {
+        reach(size, goo) * (1.0 - threshold.log2()).max(0.0).sqrt()
+    }
+}
+
+/// The Wyvills' soft object polynomial, peaking at 2 on the center and falling to nothing at twice the
+/// reach. Metaballs further apart than that never merge, which gives tighter, rounder blobs.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Wyvill;
+
+impl Wyvill {
+    /// The polynomial from 1 at the center to 0 at the edge of its support, a fraction of the way there
+    fn curve(along: f64) -> f64 {
+        if along >= 1.0 {
+            return 0.0;
+        }
+        let squared = along * along;
+        1.0 + squared * (-22.0 / 9.0 + squared * (17.0 / 9.0 - squared * 4.0 / 9.0))
+    }
+}
+
+impl FalloffFn for Wyvill {
+    fn value(&self, size: f64, distance: f64, goo: f64) -> f64 {
+        2.0 * Wyvill::curve(distance / (2.0 * reach(size, goo))).copysign(size)
+    }
+
+    fn edge(&self, size: f64, threshold: f64, goo: f64) -> f64 {
+        let target = threshold / 2.0;
+        if target >= 1.0 {
+            return 0.0;
+        }
+        // the polynomial falls all the way from the center to the edge of its support, so halving
+        // the range it's in converges on the only place it crosses the threshold
+        let (mut inside, mut outside) = (0.0, 1.0);
+        for _ in 0..32 {
+            let middle = (inside + outside) / 2.0;
+            if Wyvill::curve(middle) > target {
+                inside = middle;
+            } else {
+                outside = middle;
+            }
+        }
+        2.0 * reach(size, goo) * (inside + outside) / 2.0
+    }
+}
+
+/// Which falloff a scene's field uses, see [FalloffFn]
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum Falloff {
+    #[default]
+    InversePower,
+    Gaussian,
+    Wyvill,
+}
+
+impl Falloff {
+    /// The falloff function itself
+    pub fn function(self) -> &'static dyn FalloffFn {
+        match self {
+            Falloff::InversePower => &InversePower,
+            Falloff::Gaussian => &Gaussian,
+            Falloff::Wyvill => &Wyvill,
+        }
+    }
+}
+
+impl FromStr for Falloff {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "inverse-power" => Ok(Falloff::InversePower),
+            "gaussian" => Ok(Falloff::Gaussian),
+            "wyvill" => Ok(Falloff::Wyvill),
+            _ => Err(format!("Unknown falloff \"{}\", expected inverse-power, gaussian or wyvill", s)),
+        }
+    }
+}
+
+impl fmt::Display for Falloff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Falloff::InversePower => "inverse-power",
+            Falloff::Gaussian => "gaussian",
+            Falloff::Wyvill => "wyvill",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_falloff_reaches_the_threshold_at_its_edge() {
+        for falloff in [Falloff::InversePower, Falloff::Gaussian, Falloff::Wyvill] {
+            let function = falloff.function();
+            for &(size, threshold, goo) in &[(90.0, 0.5, 1.6), (-40.0, 1.2, 2.0), (30.0, 0.1, 1.0)] {
+                let edge = function.edge(size, threshold, goo);
+                let value = function.value(size, edge, goo).abs();
+                assert!((value - threshold).abs() < 1e-6, "{} should be {} at {}, got {}", falloff, threshold, edge, value);
+                assert_eq!(function.value(size, edge * 0.5, goo) > 0.0, size > 0.0);
+            }
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! The summed field in single precision for `--precision f32`, comparing squared distances so no pixel
+//! takes a square root, and working out each metaball's powers up front so most falloffs need no `powf`
+//! per pixel either. A row of [LANES] pixels is evaluated at once in SIMD lanes.
+
+use crate::falloff::Falloff;
+use crate::spatial::CELL_SIZE;
+use crate::{BlendMode, Metaball, MetaballData, Primitive};
+use wide::{f32x8, CmpGe, CmpLe};
+
+/// How many pixels are evaluated at once. The runs of pixels start at multiples of it, so they never
+/// straddle two cells of the metaball grid.
+pub(crate) const LANES: usize = 8;
+const _: () = assert!((CELL_SIZE as usize).is_multiple_of(LANES));
+
+/// A metaball with what each pixel needs from it worked out
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct FastMetaball {
+    x: f32,
+    y: f32,
+    size: f32,
+    /// The square of the radius of influence, infinite when it reaches forever
+    radius_squared: f32,
+    shape: Shape,
+}
+
+/// The falloff in terms of the squared distance
+#[derive(Copy, Clone, Debug)]
+enum Shape {
+    /// `size / d²^n` for a goo of `2n`
+    WholePower(i32),
+    /// `size / d²^(goo / 2)`
+    Power(f32),
+    /// The Gaussian, with the square of how far the metaball reaches before adding 1 divided into 1
+    Gaussian(f32),
+    /// The Wyvill polynomial, with the square of its support divided into 1
+    Wyvill(f32),
+}
+
+/// Whether single precision can evaluate a scene's field, which it only does for the sum of round points on
+/// one layer.
+/// The smooth union needs the distances themselves, so it's always evaluated in full.
+pub(crate) fn supports(metaball_data: &MetaballData) -> bool {
+    metaball_data.blend == BlendMode::Additive
+        && !metaball_data.is_layered()
+        && metaball_data.metaballs.iter().all(|metaball| metaball.is_round() && metaball.primitive == Primitive::Point)
+}
+
+/// Get metaballs ready to be evaluated for a scene
+pub(crate) fn prepare(metaballs: &[Metaball], metaball_data: &MetaballData) -> Vec<FastMetaball> {
+    metaballs
+        .iter()
+        .map(|metaball| {
+            let goo = metaball.goo_or(metaball_data.goo);
+            // how far the metaball reaches before adding 1, which the falloffs other than the power scale by
+            let reach_squared = metaball.size.abs().powf(2.0 / goo);
+            let shape = match metaball_data.falloff {
+                Falloff::InversePower if goo / 2.0 == (goo / 2.0).round() && goo.abs() < 64.0 => Shape::WholePower((goo / 2.0) as i32),
+                Falloff::InversePower => Shape::Power((goo / 2.0) as f32),
+                Falloff::Gaussian => Shape::Gaussian((1.0 / reach_squared) as f32),
+                Falloff::Wyvill => Shape::Wyvill((1.0 / (4.0 * reach_squared)) as f32),
+            };
+            FastMetaball {
+                x: metaball.location.x as f32,
+                y: metaball.location.y as f32,
+                size: metaball.size as f32,
+                radius_squared: metaball.radius.map_or(f32::INFINITY, |radius| (radius * radius) as f32),
+                shape,
+            }
+        })
+        .collect()
+}
+
+/// The summed field at [LANES] points along a row, like [field_at](crate::field_at) but in single precision
+pub(crate) fn field_at(xs: [f32; LANES], y: f32, metaballs: &[FastMetaball], metaball_data: &MetaballData) -> [f32; LANES] {
+    let xs = f32x8::new(xs);
+    let aspect = metaball_data.pixel_aspect as f32;
+    let (width, height) = (metaball_data.width as f32, metaball_data.height as f32);
+    let mut sum = f32x8::ZERO;
+    for metaball in metaballs {
+        let squared = |offset_x: f32, offset_y: f32| {
+            let dx = (f32x8::splat(metaball.x) - xs - f32x8::splat(offset_x)) * f32x8::splat(aspect);
+            let dy = metaball.y - y - offset_y;
+            dx * dx + f32x8::splat(dy * dy)
+        };
+        let distance_squared = if metaball_data.tile {
+            // the closest of the metaball and its copies in the eight surrounding tiles
+            let mut closest = f32x8::splat(f32::INFINITY);
+            for i in &[-1.0, 0.0, 1.0] {
+                for j in &[-1.0, 0.0, 1.0] {
+                    closest = closest.min(squared(i * width, j * height));
+                }
+            }
+            closest
+        } else {
+            squared(0.0, 0.0)
+        };
+        let size = f32x8::splat(metaball.size);
+        let value = match metaball.shape {
+            Shape::WholePower(power) => size / (1..power).fold(distance_squared, |product, _| product * distance_squared),
+            Shape::Power(power) => size / distance_squared.powf(power),
+            Shape::Gaussian(inverse) => {
+                let exponent = f32x8::ONE - distance_squared * f32x8::splat(inverse);
+                (exponent * f32x8::LN_2).exp() * f32x8::splat(1f32.copysign(metaball.size))
+            }
+            Shape::Wyvill(inverse) => {
+                let squared = distance_squared * f32x8::splat(inverse);
+                let curve = f32x8::ONE
+                    + squared * (f32x8::splat(-22.0 / 9.0) + squared * (f32x8::splat(17.0 / 9.0) - squared * f32x8::splat(4.0 / 9.0)));
+                let curve = squared.cmp_ge(f32x8::ONE).blend(f32x8::ZERO, curve);
+                curve * f32x8::splat(2f32.copysign(metaball.size))
+            }
+        };
+        // nothing past the radius of influence
+        sum += distance_squared.cmp_le(f32x8::splat(metaball.radius_squared)).blend(value, f32x8::ZERO);
+    }
+    sum.to_array()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{field_at as full_field_at, field_grid, Pointf, Precision, View};
+
+    /// Single precision has to stay within a part in ten thousand of the full field, and be infinite
+    /// exactly where it is, at the centers
+    fn assert_close(expected: f64, actual: f64, context: &str) {
+        if expected.is_infinite() {
+            assert_eq!(expected, actual, "{}", context);
+        } else {
+            assert!((expected - actual).abs() <= expected.abs() * 1e-4 + 1e-6, "{}: {} vs {}", context, expected, actual);
+        }
+    }
+
+    #[test]
+    fn single_precision_matches_the_full_field() {
+        let mut metaballs = vec![Metaball::new(Pointf { x: 20.0, y: 30.0 }, 40.0), Metaball::new(Pointf { x: 44.0, y: 36.0 }, -12.0)];
+        metaballs[1].radius = Some(20.0);
+        metaballs[0].goo = Some(2.0);
+        for &falloff in &[Falloff::InversePower, Falloff::Gaussian, Falloff::Wyvill] {
+            let mut metadata = MetaballData::new(1.6, 0.5, 64, 64, metaballs.clone());
+            metadata.falloff = falloff;
+            metadata.tile = falloff == Falloff::Gaussian;
+            let prepared = prepare(&metadata.metaballs, &metadata);
+            for &y in &[0.0, 30.0, 40.0] {
+                let xs = [0.0, 20.0, 21.5, 30.0, 40.0, 44.0, 50.0, 63.0];
+                let fields = field_at(xs, y, &prepared, &metadata);
+                for (&x, &actual) in xs.iter().zip(&fields) {
+                    let (expected, actual) = (full_field_at(x as f64, y as f64, &metadata), actual as f64);
+                    assert_close(expected, actual, &format!("{:?} at ({}, {})", falloff, x, y));
+                }
+            }
+            // a width that isn't a multiple of the lanes leaves a short run at the end of each row
+            let (width, height) = (61, 48);
+            let mut metadata = MetaballData::new(1.6, 0.5, width, height, metadata.metaballs.clone());
+            metadata.falloff = falloff;
+            assert!(supports(&metadata));
+            let field = field_grid(width, height, 1, &View::default(), &metadata, Precision::F32);
+            for (i, &actual) in field.iter().enumerate() {
+                let (x, y) = (i as u32 % width, i as u32 / width);
+                assert_close(full_field_at(x as f64, y as f64, &metadata), actual, &format!("{:?} at ({}, {}) of {}", falloff, x, y, width));
+            }
+        }
+    }
+}
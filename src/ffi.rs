@@ -0,0 +1,131 @@
+//! A C interface to the renderer, with the `cdylib` feature, so C and C++ programs can rasterize metaballs
+//! with the same math. The functions return [METABALLS_OK] or one of the error codes, and never unwind a
+//! panic into the caller. `include/metaballs.h` declares them for C.
+
+use crate::{field_at, preset, render_metaballs, MetaballData, RenderOpts};
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::slice;
+
+/// Rendered into the buffer
+pub const METABALLS_OK: c_int = 0;
+/// A pointer was null, a size was 0 or a goo or threshold wasn't positive
+pub const METABALLS_INVALID: c_int = -1;
+/// There's no preset by that name
+pub const METABALLS_UNKNOWN_PRESET: c_int = -2;
+/// Rendering panicked, which is a bug
+pub const METABALLS_PANICKED: c_int = -3;
+
+/// A round metaball for the C interface
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct MetaballsBall {
+    pub x: f64,
+    pub y: f64,
+    /// See [Metaball::size], negative to take away from the field
+    pub size: f64,
+}
+
+/// A scene of the balls with the default blend and falloff, `None` if the parameters are out of range
+unsafe fn scene(width: u32, height: u32, balls: *const MetaballsBall, count: usize, goo: f64, threshold: f64) -> Option<MetaballData> {
+    if balls.is_null() && count > 0 {
+        return None;
+    }
+    let balls = if count == 0 { &[] } else { slice::from_raw_parts(balls, count) };
+    let builder = MetaballData::builder().size(width, height).goo(goo).threshold(threshold);
+    balls.iter().fold(builder, |builder, ball| builder.ball(ball.x, ball.y, ball.size)).build().ok()
+}
+
+/// Render a scene with the default render options, red metaballs on black, into a buffer of RGBA pixels,
+/// which has to hold every pixel of the scene
+unsafe fn render_into(out_buf: *mut u8, metadata: &MetaballData) -> c_int {
+    let (width, height) = (metadata.width, metadata.height);
+    let frame = slice::from_raw_parts_mut(out_buf, width as usize * height as usize * 4);
+    render_metaballs(frame, width, height, metadata, &RenderOpts::default());
+    METABALLS_OK
+}
+
+/// The body of one of the functions, with a panic anywhere in it caught before it reaches the caller
+fn guarded<T>(panicked: T, body: impl FnOnce() -> T) -> T {
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or(panicked)
+}
+
+/// Render `count` metaballs into `out_buf` as `width × height` RGBA pixels, row by row from the top left.
+///
+/// # Safety
+///
+/// `balls` has to point to `count` metaballs, or may be null if there are none, and `out_buf` has to point to
+/// `width * height * 4` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn metaballs_render(width: u32, height: u32, balls: *const MetaballsBall, count: usize, goo: f64, threshold: f64, out_buf: *mut u8) -> c_int {
+    guarded(METABALLS_PANICKED, || match scene(width, height, balls, count, goo, threshold) {
+        Some(metadata) if !out_buf.is_null() => render_into(out_buf, &metadata),
+        _ => METABALLS_INVALID,
+    })
+}
+
+/// Render one of the preset scenes laid out for the size, like `--preset`, into `out_buf` the same as
+/// [metaballs_render]
+///
+/// # Safety
+///
+/// `name` has to be a nul terminated string, and `out_buf` has to point to `width * height * 4` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn metaballs_render_preset(name: *const c_char, width: u32, height: u32, out_buf: *mut u8) -> c_int {
+    if name.is_null() || out_buf.is_null() || width == 0 || height == 0 {
+        return METABALLS_INVALID;
+    }
+    guarded(METABALLS_PANICKED, || {
+        let Ok(name) = CStr::from_ptr(name).to_str() else {
+            return METABALLS_UNKNOWN_PRESET;
+        };
+        match preset(name, width, height) {
+            Ok(metadata) => render_into(out_buf, &metadata),
+            Err(_) => METABALLS_UNKNOWN_PRESET,
+        }
+    })
+}
+
+/// The field of `count` metaballs at a point, which is inside the shape where it's above the threshold.
+/// NaN if the parameters are out of range, or if evaluating it panicked.
+///
+/// # Safety
+///
+/// `balls` has to point to `count` metaballs, or may be null if there are none.
+#[no_mangle]
+pub unsafe extern "C" fn metaballs_field_at(x: f64, y: f64, balls: *const MetaballsBall, count: usize, goo: f64) -> f64 {
+    // the threshold doesn't change the field, and the size of the image only matters to tiled scenes
+    guarded(f64::NAN, || match scene(1, 1, balls, count, goo, 1.0) {
+        Some(metadata) => field_at(x, y, &metadata),
+        None => f64::NAN,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{render_to_image, Metaball, Pointf};
+    use std::ffi::CString;
+
+    #[test]
+    fn the_c_interface_renders_like_the_crate() {
+        let balls = [MetaballsBall { x: 20.0, y: 20.0, size: 30.0 }, MetaballsBall { x: 45.0, y: 30.0, size: 20.0 }];
+        let mut buffer = vec![0; 64 * 48 * 4];
+        assert_eq!(unsafe { metaballs_render(64, 48, balls.as_ptr(), balls.len(), 1.6, 0.5, buffer.as_mut_ptr()) }, METABALLS_OK);
+        let metadata = MetaballData::new(1.6, 0.5, 64, 48, vec![
+            Metaball::new(Pointf { x: 20.0, y: 20.0 }, 30.0),
+            Metaball::new(Pointf { x: 45.0, y: 30.0 }, 20.0),
+        ]);
+        assert_eq!(buffer, *render_to_image(&metadata).as_raw());
+        assert_eq!(unsafe { metaballs_field_at(20.0, 30.0, balls.as_ptr(), balls.len(), 1.6) }, field_at(20.0, 30.0, &metadata));
+        assert_eq!(unsafe { metaballs_render(64, 48, balls.as_ptr(), balls.len(), 0.0, 0.5, buffer.as_mut_ptr()) }, METABALLS_INVALID);
+
+        let ring = CString::new("ring").unwrap();
+        assert_eq!(unsafe { metaballs_render_preset(ring.as_ptr(), 64, 48, buffer.as_mut_ptr()) }, METABALLS_OK);
+        assert_eq!(buffer, *render_to_image(&preset("ring", 64, 48).unwrap()).as_raw());
+        let unknown = CString::new("nothing").unwrap();
+        assert_eq!(unsafe { metaballs_render_preset(unknown.as_ptr(), 64, 48, buffer.as_mut_ptr()) }, METABALLS_UNKNOWN_PRESET);
+        assert_eq!(guarded(METABALLS_PANICKED, || panic!("a bug")), METABALLS_PANICKED);
+    }
+}
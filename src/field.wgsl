@@ -0,0 +1,118 @@
+// Evaluates the metaball field for every pixel of an image, the same way `field_at` does on the CPU
+
+[[block]] struct Params {
+    // the inside and outside colors packed as RGBA bytes, and the width and height of the image
+    image: vec4<u32>;
+    // the field point at the top left pixel, the zoom and the pixel aspect
+    view: vec4<f32>;
+    // the threshold, the smooth union k which is 0 when summing the metaballs, and the number of metaballs
+    field: vec4<f32>;
+    // the width and height the field repeats at, and whether it tiles
+    tile: vec4<f32>;
+};
+
+struct Ball {
+    // the x, y, size and radius of influence, the size being negative for carving out of the shape and the
+    // radius 0 for reaching forever
+    shape: vec4<f32>;
+    // the goo, which is the scene's unless the metaball overrides it
+    falloff: vec4<f32>;
+};
+
+[[block]] struct Balls {
+    balls: [[stride(32)]] array<Ball>;
+};
+
+[[block]] struct Pixels {
+    pixels: [[stride(4)]] array<u32>;
+};
+
+[[group(0), binding(0)]] var<uniform> r_params: Params;
+[[group(0), binding(1)]] var<storage> r_balls: [[access(read)]] Balls;
+[[group(0), binding(2)]] var<storage> r_pixels: [[access(read_write)]] Pixels;
+
+// The distance from a metaball's center to a point, as the field measures it
+fn field_distance(ball: vec2<f32>, point: vec2<f32>) -> f32 {
+    var closest: f32 = 1.0e30;
+    var i: i32 = -1;
+    loop {
+        if (i > 1) { break; }
+        var j: i32 = -1;
+        loop {
+            if (j > 1) { break; }
+            // only the metaball itself counts unless the field tiles
+            if (r_params.tile.z > 0.5 || (i == 0 && j == 0)) {
+                let copy = point + vec2<f32>(f32(i), f32(j)) * r_params.tile.xy;
+                let offset = (ball - copy) * vec2<f32>(r_params.view.w, 1.0);
+                closest = min(closest, length(offset));
+            }
+            continuing {
+                j = j + 1;
+            }
+        }
+        continuing {
+            i = i + 1;
+        }
+    }
+    return closest;
+}
+
+// Polynomial smooth minimum, the same as `smooth_min`
+fn smooth_min(a: f32, b: f32, k: f32) -> f32 {
+    if (k <= 0.0) {
+        return min(a, b);
+    }
+    let h = max(k - abs(a - b), 0.0) / k;
+    return min(a, b) - h * h * k * 0.25;
+}
+
+fn field_at(point: vec2<f32>) -> f32 {
+    let threshold = r_params.field.x;
+    let k = r_params.field.y;
+    let count = u32(r_params.field.z);
+    var sum: f32 = 0.0;
+    var edge: f32 = 1.0e30;
+    // the negative metaballs' circles, carved out of the rest once they're all merged
+    var carved: f32 = 1.0e30;
+    var index: u32 = 0u;
+    loop {
+        if (index >= count) { break; }
+        let ball = r_balls.balls[index];
+        let goo = ball.falloff.x;
+        let ball_distance = field_distance(ball.shape.xy, point);
+        if (ball.shape.w > 0.0 && ball_distance > ball.shape.w) {
+            // past the metaball's radius of influence
+        } elseif (k > 0.0) {
+            let radius = pow(abs(ball.shape.z) / threshold, 1.0 / goo);
+            if (ball.shape.z < 0.0) {
+                carved = smooth_min(carved, ball_distance - radius, k);
+            } else {
+                edge = smooth_min(edge, ball_distance - radius, k);
+            }
+        } else {
+            sum = sum + ball.shape.z / pow(ball_distance, goo);
+        }
+        continuing {
+            index = index + 1u;
+        }
+    }
+    if (k > 0.0) {
+        return threshold + smooth_min(-edge, carved, k);
+    }
+    return sum;
+}
+
+[[stage(compute), workgroup_size(8, 8)]]
+fn cs_main([[builtin(global_invocation_id)]] id: vec3<u32>) {
+    // the workgroups hang off the right and bottom edges of images that aren't a multiple of their size
+    let width = r_params.image.z;
+    if (id.x >= width || id.y >= r_params.image.w) {
+        return;
+    }
+    let point = r_params.view.xy + vec2<f32>(f32(id.x), f32(id.y)) / r_params.view.z;
+    var color: u32 = r_params.image.y;
+    if (field_at(point) > r_params.field.x) {
+        color = r_params.image.x;
+    }
+    r_pixels.pixels[id.y * width + id.x] = color;
+}
@@ -0,0 +1,166 @@
+//! Driving the scene from a gamepad read through the Linux joystick interface, `/dev/input/js*`. The left
+//! stick moves the selected metaball, the triggers soften and sharpen the goo, or lower and raise the
+//! threshold while X is held, A randomizes the scene and the bumpers select the previous or next metaball.
+//! The buttons and axes are the ones the xpad driver gives Xbox style pads.
+
+/// How many bytes each event from the joystick interface is
+pub const EVENT_SIZE: usize = 8;
+
+/// How far in pixels the selected metaball moves each tick with the stick all the way over
+const STICK_SPEED: f64 = 4.0;
+
+/// How far the stick has to be pushed before it moves anything, ignoring the drift of worn sticks
+const DEAD_ZONE: f64 = 0.15;
+
+/// How much the goo or threshold is scaled each tick with a trigger pulled all the way
+const TRIGGER_RATE: f64 = 0.005;
+
+// The xpad driver's buttons and axes
+const BUTTON_A: u8 = 0;
+const BUTTON_X: u8 = 2;
+const BUTTON_LB: u8 = 4;
+const BUTTON_RB: u8 = 5;
+const AXIS_LEFT_X: u8 = 0;
+const AXIS_LEFT_Y: u8 = 1;
+const AXIS_LEFT_TRIGGER: u8 = 2;
+const AXIS_RIGHT_TRIGGER: u8 = 5;
+
+/// A button or axis changing, with the axes from -1 to 1
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Event {
+    Button { number: u8, pressed: bool },
+    Axis { number: u8, value: f64 },
+}
+
+/// Decode an event from the joystick interface, a timestamp, a signed value, a type and the button or axis
+/// number. Events describing the state the pad starts in come first, decoded the same as changes to it.
+pub fn decode(bytes: [u8; EVENT_SIZE]) -> Option<Event> {
+    let value = i16::from_ne_bytes([bytes[4], bytes[5]]);
+    let number = bytes[7];
+    match bytes[6] & !0x80 {
+        0x01 => Some(Event::Button { number, pressed: value != 0 }),
+        0x02 => Some(Event::Axis { number, value: (value as f64 / i16::MAX as f64).max(-1.0) }),
+        _ => None,
+    }
+}
+
+/// What the pad does to the scene. The selection counts up and down from 0 without knowing how many
+/// metaballs there are, wrapping around them with [selected](Action::selected).
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Action {
+    /// Move the selected metaball by an offset in pixels
+    Nudge { selection: i64, by: (f64, f64) },
+    /// Select a metaball, to be moved by the stick
+    Select(i64),
+    /// Multiply the goo by a factor
+    ScaleGoo(f64),
+    /// Multiply the threshold by a factor
+    ScaleThreshold(f64),
+    Randomize,
+}
+
+impl Action {
+    /// The index a selection is of among some metaballs, if there are any
+    pub fn selected(selection: i64, count: usize) -> Option<usize> {
+        (count > 0).then(|| selection.rem_euclid(count as i64) as usize)
+    }
+}
+
+/// The state of the pad, turned into actions as its buttons are pressed and on each tick while a stick or
+/// trigger is held
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Gamepad {
+    stick: (f64, f64),
+    /// How far each trigger is pulled, from 0 to 1
+    triggers: (f64, f64),
+    threshold_held: bool,
+    selection: i64,
+}
+
+impl Gamepad {
+    /// Take in an event, the action for it if it's a button that does something as it's pressed
+    pub fn event(&mut self, event: Event) -> Option<Action> {
+        match event {
+            Event::Button { number: BUTTON_A, pressed: true } => Some(Action::Randomize),
+            Event::Button { number: BUTTON_LB, pressed: true } => {
+                self.selection -= 1;
+                Some(Action::Select(self.selection))
+            }
+            Event::Button { number: BUTTON_RB, pressed: true } => {
+                self.selection += 1;
+                Some(Action::Select(self.selection))
+            }
+            Event::Button { number: BUTTON_X, pressed } => {
+                self.threshold_held = pressed;
+                None
+            }
+            Event::Axis { number: AXIS_LEFT_X, value } => {
+                self.stick.0 = value;
+                None
+            }
+            Event::Axis { number: AXIS_LEFT_Y, value } => {
+                self.stick.1 = value;
+                None
+            }
+            // a trigger is at -1 let go and 1 pulled all the way
+            Event::Axis { number: AXIS_LEFT_TRIGGER, value } => {
+                self.triggers.0 = (value + 1.0) / 2.0;
+                None
+            }
+            Event::Axis { number: AXIS_RIGHT_TRIGGER, value } => {
+                self.triggers.1 = (value + 1.0) / 2.0;
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// The actions for the stick and triggers as they're held, once per tick
+    pub fn tick(&self) -> Vec<Action> {
+        let mut actions = vec![];
+        let past_dead_zone = |value: f64| if value.abs() > DEAD_ZONE { value } else { 0.0 };
+        let by = (past_dead_zone(self.stick.0) * STICK_SPEED, past_dead_zone(self.stick.1) * STICK_SPEED);
+        if by != (0.0, 0.0) {
+            actions.push(Action::Nudge { selection: self.selection, by });
+        }
+        // the right trigger sharpens the edges by raising the goo, or raises the threshold
+        let pulled = past_dead_zone(self.triggers.1) - past_dead_zone(self.triggers.0);
+        if pulled != 0.0 {
+            let factor = 1.0 + pulled * TRIGGER_RATE;
+            actions.push(if self.threshold_held { Action::ScaleThreshold(factor) } else { Action::ScaleGoo(factor) });
+        }
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw(value: i16, kind: u8, number: u8) -> [u8; EVENT_SIZE] {
+        let value = value.to_ne_bytes();
+        [0, 0, 0, 0, value[0], value[1], kind, number]
+    }
+
+    #[test]
+    fn the_stick_and_triggers_act_while_held() {
+        let mut pad = Gamepad::default();
+        // the starting state, with the triggers let go
+        for event in [raw(-32767, 0x82, AXIS_LEFT_TRIGGER), raw(-32767, 0x82, AXIS_RIGHT_TRIGGER), raw(1000, 0x82, AXIS_LEFT_X)] {
+            assert_eq!(pad.event(decode(event).unwrap()), None);
+        }
+        assert_eq!(pad.tick(), vec![]);
+
+        assert_eq!(pad.event(decode(raw(1, 0x01, BUTTON_RB)).unwrap()), Some(Action::Select(1)));
+        pad.event(decode(raw(i16::MIN, 0x02, AXIS_LEFT_Y)).unwrap());
+        pad.event(decode(raw(i16::MAX, 0x02, AXIS_RIGHT_TRIGGER)).unwrap());
+        assert_eq!(pad.tick(), vec![Action::Nudge { selection: 1, by: (0.0, -STICK_SPEED) }, Action::ScaleGoo(1.0 + TRIGGER_RATE)]);
+        pad.event(decode(raw(1, 0x01, BUTTON_X)).unwrap());
+        assert_eq!(pad.tick()[1], Action::ScaleThreshold(1.0 + TRIGGER_RATE));
+
+        assert_eq!(pad.event(decode(raw(1, 0x01, BUTTON_LB)).unwrap()), Some(Action::Select(0)));
+        assert_eq!(pad.event(decode(raw(1, 0x01, BUTTON_LB)).unwrap()), Some(Action::Select(-1)));
+        assert_eq!(Action::selected(-1, 3), Some(2));
+        assert_eq!(Action::selected(4, 0), None);
+    }
+}
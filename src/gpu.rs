@@ -0,0 +1,219 @@
+//! Evaluates the metaball field in a compute shader, on a device of its own so it works with or without
+//! a window, reading the image back for the CPU to show or save
+
+use image::{ImageBuffer, Rgba};
+use metaballs::falloff::Falloff;
+use metaballs::{blend_over, BlendMode, MetaballData, Primitive, RenderMode, RenderOpts};
+use pixels::wgpu;
+use pixels::wgpu::util::DeviceExt;
+use std::borrow::Cow;
+use std::time::Instant;
+
+/// How many pixels wide and tall each workgroup of the shader is, matching its `workgroup_size`
+const WORKGROUP_SIZE: u32 = 8;
+
+/// The number of floats in the shader's `Params`, four vectors of settings
+const PARAMS_LEN: usize = 4 * 4;
+
+/// The number of floats in each of the shader's `Ball`s
+const BALL_LEN: usize = 8;
+
+/// The compute pipeline evaluating the metaball field, and the buffers the image is written to and read back from
+pub struct GpuRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    params: wgpu::Buffer,
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::ComputePipeline,
+    /// The image buffers for the last image size rendered, which are reused until the size changes
+    pixels: Option<PixelBuffers>,
+}
+
+/// The buffer the shader writes the pixels to and the one they're copied to for reading on the CPU
+struct PixelBuffers {
+    size: (u32, u32),
+    output: wgpu::Buffer,
+    readback: wgpu::Buffer,
+}
+
+impl GpuRenderer {
+    /// Set up the shader on the first adapter found, or `None` if there isn't one to render with
+    pub fn new() -> Option<GpuRenderer> {
+        let instance = wgpu::Instance::new(wgpu::BackendBit::PRIMARY);
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: None,
+        }))?;
+        let (device, queue) = pollster::block_on(adapter.request_device(&wgpu::DeviceDescriptor {
+            label: Some("metaballs_field_device"),
+            features: wgpu::Features::empty(),
+            limits: wgpu::Limits::default(),
+        }, None)).ok()?;
+
+        let module = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("metaballs_field_shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(include_str!("field.wgsl"))),
+            flags: wgpu::ShaderFlags::VALIDATION,
+        });
+        let params = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("metaballs_field_params"),
+            size: (PARAMS_LEN * std::mem::size_of::<f32>()) as u64,
+            usage: wgpu::BufferUsage::UNIFORM | wgpu::BufferUsage::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStage::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("metaballs_field_bind_group_layout"),
+            entries: &[
+                entry(0, wgpu::BufferBindingType::Uniform),
+                entry(1, wgpu::BufferBindingType::Storage { read_only: true }),
+                entry(2, wgpu::BufferBindingType::Storage { read_only: false }),
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("metaballs_field_pipeline_layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("metaballs_field_pipeline"),
+            layout: Some(&pipeline_layout),
+            module: &module,
+            entry_point: "cs_main",
+        });
+        Some(GpuRenderer { device, queue, params, bind_group_layout, pipeline, pixels: None })
+    }
+
+    /// Whether the shader can draw everything the render options ask for. It only draws the flat colored
+    /// shape of the inverse power falloff, anything drawn on top of it or colored pixel by pixel is left to the CPU.
+    pub fn supports(metaballs: &MetaballData, opts: &RenderOpts) -> bool {
+        metaballs.falloff == Falloff::InversePower
+            && metaballs.noise.is_none()
+            && !metaballs.is_layered()
+            && metaballs.metaballs.iter().all(|metaball| metaball.is_round() && metaball.primitive == Primitive::Point)
+            && opts.mode == RenderMode::Naive
+            && !opts.crosses
+            && !opts.gradient
+            && !opts.ball_colors
+            && opts.isolines.is_empty()
+            && opts.bands.is_empty()
+            && opts.background.is_none()
+            && opts.ssaa == 1
+            && opts.compare.is_none()
+            && !opts.histogram
+    }
+
+    /// Render a metaball image on the GPU, which should only be asked for when it [supports](GpuRenderer::supports)
+    /// the render options
+    pub fn render_image(&mut self, width: u32, height: u32, metaballs: &MetaballData, opts: &RenderOpts) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        let start = Instant::now();
+        let k = match metaballs.blend {
+            BlendMode::Additive => 0.0,
+            BlendMode::SmoothUnion { k } => k as f32,
+        };
+        let on_color = u32::from_le_bytes(blend_over(opts.fill_color(), opts.off_color).0);
+        let off_color = u32::from_le_bytes(opts.off_color.0);
+
+        // the colors and image size are integers, so every param goes in as its bytes
+        let mut params: Vec<u8> = [on_color, off_color, width, height].iter().flat_map(|param| param.to_ne_bytes()).collect();
+        let floats = [
+            opts.view.x as f32, opts.view.y as f32, opts.view.zoom as f32, metaballs.pixel_aspect as f32,
+            metaballs.threshold as f32, k, metaballs.metaballs.len() as f32, 0.0,
+            metaballs.width as f32, metaballs.height as f32, if metaballs.tile { 1.0 } else { 0.0 }, 0.0,
+        ];
+        params.extend(floats.iter().flat_map(|param| param.to_ne_bytes()));
+        self.queue.write_buffer(&self.params, 0, &params);
+
+        let mut balls = Vec::with_capacity(metaballs.metaballs.len().max(1) * BALL_LEN);
+        for metaball in &metaballs.metaballs {
+            // a radius of influence of 0 reaches forever
+            let radius = metaball.radius.unwrap_or(0.0) as f32;
+            balls.extend_from_slice(&[metaball.location.x as f32, metaball.location.y as f32, metaball.size as f32, radius]);
+            balls.extend_from_slice(&[metaball.goo_or(metaballs.goo) as f32, 0.0, 0.0, 0.0]);
+        }
+        // bindings can't be empty, so a scene without metaballs still gets room for one
+        balls.resize(balls.len().max(BALL_LEN), 0.0);
+        let bytes: Vec<u8> = balls.iter().flat_map(|ball| ball.to_ne_bytes()).collect();
+        let balls = self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("metaballs_field_balls"),
+            contents: &bytes,
+            usage: wgpu::BufferUsage::STORAGE,
+        });
+
+        self.fit_pixel_buffers((width, height));
+        let pixels = self.pixels.as_ref().expect("the pixel buffers were just made");
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("metaballs_field_bind_group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: self.params.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: balls.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 2, resource: pixels.output.as_entire_binding() },
+            ],
+        });
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("metaballs_field_encoder"),
+        });
+        {
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("metaballs_field_compute_pass"),
+            });
+            cpass.set_pipeline(&self.pipeline);
+            cpass.set_bind_group(0, &bind_group, &[]);
+            cpass.dispatch(width.div_ceil(WORKGROUP_SIZE), height.div_ceil(WORKGROUP_SIZE), 1);
+        }
+        encoder.copy_buffer_to_buffer(&pixels.output, 0, &pixels.readback, 0, pixel_bytes((width, height)));
+        self.queue.submit(Some(encoder.finish()));
+
+        // wait for the shader to finish and copy the pixels out of the mapped buffer
+        let slice = pixels.readback.slice(..);
+        let mapped = slice.map_async(wgpu::MapMode::Read);
+        self.device.poll(wgpu::Maintain::Wait);
+        pollster::block_on(mapped).expect("the pixels should be readable once the device is done with them");
+        let mut raw = slice.get_mapped_range().to_vec();
+        pixels.readback.unmap();
+        raw.truncate(width as usize * height as usize * 4);
+        log::debug!(
+            "GPU render of {} metaballs at {}x{} took {:.2}ms",
+            metaballs.metaballs.len(),
+            width,
+            height,
+            start.elapsed().as_secs_f64() * 1000.0
+        );
+        ImageBuffer::from_raw(width, height, raw).expect("the shader should write a pixel for every pixel of the image")
+    }
+
+    /// Make new pixel buffers for an image size, unless the last image was the same size
+    fn fit_pixel_buffers(&mut self, size: (u32, u32)) {
+        if self.pixels.as_ref().is_none_or(|pixels| pixels.size != size) {
+            let buffer = |label, usage| {
+                self.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(label),
+                    size: pixel_bytes(size),
+                    usage,
+                    mapped_at_creation: false,
+                })
+            };
+            self.pixels = Some(PixelBuffers {
+                size,
+                output: buffer("metaballs_field_output", wgpu::BufferUsage::STORAGE | wgpu::BufferUsage::COPY_SRC),
+                readback: buffer("metaballs_field_readback", wgpu::BufferUsage::MAP_READ | wgpu::BufferUsage::COPY_DST),
+            });
+        }
+    }
+}
+
+/// How many bytes an image of a size takes, which is at least one pixel since buffers can't be empty
+fn pixel_bytes(size: (u32, u32)) -> u64 {
+    (size.0 as u64 * size.1 as u64).max(1) * 4
+}
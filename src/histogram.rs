@@ -0,0 +1,120 @@
+//! A histogram of the field values across the frame, drawn over its bottom left corner with the threshold
+//! marked. It shows at a glance how much of the frame is near the threshold, since moving the threshold past
+//! a tall bar takes that much of the frame into or out of the shape at once.
+
+use crate::{field_grid, lerp_color, MetaballData, RenderOpts};
+use image::{ImageBuffer, Rgba};
+use std::ops::DerefMut;
+
+/// How many bins the field values are counted into
+pub const BINS: usize = 64;
+
+/// How far the plot is drawn in from the corner of the frame, in pixels
+const MARGIN: u32 = 4;
+
+/// The color of the bars for field values outside the shape
+const OUTSIDE_BAR: Rgba<u8> = Rgba([160, 160, 160, 255]);
+
+/// How the field values of a frame are spread out
+#[derive(Clone, PartialEq, Debug)]
+pub struct Histogram {
+    /// The field value at the start of the first bin, which also counts everything below it
+    pub low: f64,
+    /// The field value at the end of the last bin, which also counts everything above it
+    pub high: f64,
+    pub counts: [u32; BINS],
+}
+
+impl Histogram {
+    /// Count the field values from the lowest, or 0, up to the 99th percentile, so the few huge values near the
+    /// centers don't squash the rest into the first bin. The range reaches past the threshold either way.
+    pub fn new(field: &[f64], threshold: f64) -> Histogram {
+        let mut finite: Vec<f64> = field.iter().copied().filter(|value| value.is_finite()).collect();
+        let low = finite.iter().copied().fold(0.0, f64::min);
+        let high = match finite.len() {
+            0 => threshold,
+            len => *finite.select_nth_unstable_by(len * 99 / 100, |a, b| a.partial_cmp(b).unwrap()).1,
+        };
+        let mut histogram = Histogram { low, high: high.max(threshold * 1.5), counts: [0; BINS] };
+        for &value in field.iter().filter(|value| !value.is_nan()) {
+            histogram.counts[histogram.bin(value)] += 1;
+        }
+        histogram
+    }
+
+    /// How far along the range a field value is, from 0 at the low end to 1 at the high end
+    pub fn fraction(&self, value: f64) -> f64 {
+        ((value - self.low) / (self.high - self.low)).clamp(0.0, 1.0)
+    }
+
+    /// The bin a field value is counted in
+    pub fn bin(&self, value: f64) -> usize {
+        ((self.fraction(value) * BINS as f64) as usize).min(BINS - 1)
+    }
+}
+
+/// Draw the histogram of the field across an image of it over its bottom left corner, dimming what's under
+/// it. The bars are on a log scale so the pixels near the threshold still show beside the many far from every
+/// metaball. The bars of field values inside the shape are in its inside color, and the threshold's marked
+/// by a line in the indicator color.
+pub fn draw_histogram<C: DerefMut<Target = [u8]>>(image: &mut ImageBuffer<Rgba<u8>, C>, metaball_data: &MetaballData, opts: &RenderOpts) {
+    let (width, height) = image.dimensions();
+    let (plot_width, plot_height) = ((width / 3).min(BINS as u32 * 3), (height / 4).min(96));
+    if plot_width == 0 || plot_height == 0 {
+        return;
+    }
+    let field = field_grid(width, height, 1, &opts.view, metaball_data, opts.precision);
+    let threshold = metaball_data.threshold;
+    let histogram = Histogram::new(&field, threshold);
+    let (left, top) = (MARGIN.min(width - plot_width), height - plot_height - MARGIN.min(height - plot_height));
+    // a plot narrower than the bins adds up several of them in each column
+    let bins = |column: u32| {
+        let start = column as usize * BINS / plot_width as usize;
+        start..((column as usize + 1) * BINS / plot_width as usize).max(start + 1)
+    };
+    let counts: Vec<u32> = (0..plot_width).map(|column| histogram.counts[bins(column)].iter().sum()).collect();
+    let tallest = (*counts.iter().max().unwrap_or(&0) as f64).ln_1p();
+    let marker = ((histogram.fraction(threshold) * plot_width as f64) as u32).min(plot_width - 1);
+    let inside = opts.fill_color();
+    for column in 0..plot_width {
+        let bar = if tallest > 0.0 { (counts[column as usize] as f64).ln_1p() / tallest * plot_height as f64 } else { 0.0 };
+        let bins = bins(column);
+        let middle = histogram.low + (bins.start + bins.end) as f64 / 2.0 / BINS as f64 * (histogram.high - histogram.low);
+        let bar_color = if middle > threshold { inside } else { OUTSIDE_BAR };
+        for row in 0..plot_height {
+            let pixel = image.get_pixel_mut(left + column, top + row);
+            *pixel = if column == marker {
+                opts.cross_color
+            } else if (plot_height - row) as f64 <= bar.round() {
+                bar_color
+            } else {
+                lerp_color(*pixel, Rgba([0, 0, 0, 255]), 0.6)
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{render_image, Metaball, Pointf, OFF_PIXEL};
+
+    #[test]
+    fn field_values_are_counted_with_the_threshold_marked() {
+        let field: Vec<f64> = (0..100).map(|value| value as f64 / 100.0).chain([f64::INFINITY, f64::NAN]).collect();
+        let histogram = Histogram::new(&field, 0.5);
+        assert_eq!((histogram.low, histogram.high), (0.0, 0.99));
+        assert_eq!(histogram.counts.iter().sum::<u32>(), 101);
+        assert_eq!(histogram.counts[BINS - 1], 3);
+        assert_eq!(Histogram::new(&[0.1, 0.2], 2.0).high, 3.0);
+
+        let metadata = MetaballData::new(1.6, 0.5, 96, 96, vec![Metaball::new(Pointf { x: 48.0, y: 20.0 }, 30.0)]);
+        let opts = RenderOpts { histogram: true, ..RenderOpts::default() };
+        let image = render_image(96, 96, &metadata, &opts);
+        let marker = MARGIN + (Histogram::new(&field_grid(96, 96, 1, &opts.view, &metadata, opts.precision), 0.5).fraction(0.5) * 32.0) as u32;
+        assert_eq!(*image.get_pixel(marker, 96 - MARGIN - 1), opts.cross_color);
+        // most of the frame is outside the metaball, so the tallest bar is below the threshold
+        assert!((MARGIN..marker).any(|x| *image.get_pixel(x, 96 - MARGIN - 24) == OUTSIDE_BAR));
+        assert_eq!(*image.get_pixel(90, 90), OFF_PIXEL);
+    }
+}
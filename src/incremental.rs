@@ -0,0 +1,122 @@
+//! Rendering a scene again after only some of its metaballs have changed, as when one is being dragged, by
+//! drawing only the cells of pixels that the changed metaballs reach now or reached before. Every other cell
+//! counts the same metaballs as it did, so the frame comes out just as a full render would have drawn it.
+
+use crate::spatial::{self, DirtyCells};
+use crate::{draw_indicators, milliseconds_since, naive_cells, render_metaballs, Metaball, MetaballData, RenderMode, RenderOpts};
+use image::ImageBuffer;
+use std::time::{Duration, Instant};
+
+/// Keeps what the last frame was rendered from, to redraw only what's changed since
+#[derive(Default)]
+pub struct IncrementalRender {
+    last: Option<Rendered>,
+}
+
+/// What a frame was rendered from
+struct Rendered {
+    size: (u32, u32),
+    /// The scene's parameters, without its metaballs
+    scene: MetaballData,
+    metaballs: Vec<Metaball>,
+    opts: RenderOpts,
+}
+
+impl IncrementalRender {
+    /// Render metaballs into an RGBA buffer like [render_metaballs], which has to still hold the last frame
+    /// rendered through here. Only the cells that changed are drawn when the last frame was of the same scene
+    /// with the same options and as many metaballs, rendered per pixel without supersampling, isolines, the
+    /// histogram or the metaballs' colors, which take in every metaball everywhere. Anything else is rendered
+    /// in full.
+    /// Returns how long rendering took.
+    pub fn render(&mut self, frame: &mut [u8], width: u32, height: u32, metaballs: &MetaballData, opts: &RenderOpts) -> Duration {
+        let start = Instant::now();
+        let scene = MetaballData { metaballs: Vec::new(), ..metaballs.clone() };
+        let incremental = opts.mode == RenderMode::Naive
+            && opts.ssaa == 1
+            && opts.compare.is_none()
+            && opts.isolines.is_empty()
+            && !opts.ball_colors
+            && !opts.histogram
+            && spatial::bucketed(metaballs);
+        let dirty = self
+            .last
+            .as_ref()
+            .filter(|last| incremental && last.size == (width, height) && last.scene == scene && last.opts == *opts)
+            .and_then(|last| dirty_cells(last, width, height, metaballs, opts));
+        match dirty {
+            Some(dirty) => {
+                let count = dirty.count();
+                if count > 0 {
+                    naive_cells(frame, width, height, 1, metaballs, opts, Some(&dirty));
+                    let mut image = ImageBuffer::from_raw(width, height, &mut *frame).expect("the frame should hold every pixel");
+                    draw_indicators(&mut image, metaballs, opts);
+                }
+                let balls = metaballs.metaballs.len();
+                log::debug!("Redrawing {} cells of {} metaballs at {}x{} took {:.2}ms", count, balls, width, height, milliseconds_since(start));
+            }
+            None => {
+                render_metaballs(frame, width, height, metaballs, opts);
+            }
+        }
+        self.last = incremental.then(|| Rendered { size: (width, height), scene, metaballs: metaballs.metaballs.clone(), opts: opts.clone() });
+        start.elapsed()
+    }
+
+    /// Forget the last frame, after something else has been drawn into the buffer
+    pub fn invalidate(&mut self) {
+        self.last = None;
+    }
+}
+
+/// The cells reached by each metaball that's changed since the last frame, before and after, along with the
+/// pixels under their indicators. `None` if metaballs have been added or removed.
+fn dirty_cells(last: &Rendered, width: u32, height: u32, metaballs: &MetaballData, opts: &RenderOpts) -> Option<DirtyCells> {
+    if last.metaballs.len() != metaballs.metaballs.len() {
+        return None;
+    }
+    let mut dirty = DirtyCells::new(width, height);
+    let radius = opts.cross_radius as i64;
+    for (before, after) in last.metaballs.iter().zip(&metaballs.metaballs).filter(|(before, after)| before != after) {
+        for metaball in [before, after] {
+            spatial::cells_reached(metaball, width, height, 1, &opts.view, metaballs, |cell| dirty.mark(cell));
+            if opts.crosses {
+                let (x, y) = opts.view.screen_point(metaball.location.x, metaball.location.y);
+                let (x, y) = (x.floor() as i64, y.floor() as i64);
+                dirty.mark_pixels(x - radius, y - radius, x + radius, y + radius);
+            }
+        }
+    }
+    Some(dirty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Pointf;
+
+    #[test]
+    fn redrawing_a_moved_metaball_matches_a_full_render() {
+        let mut metadata = MetaballData::new(1.6, 0.5, 90, 70, vec![
+            Metaball { radius: Some(12.0), ..Metaball::new(Pointf { x: 20.0, y: 20.0 }, 30.0) },
+            Metaball { radius: Some(15.0), ..Metaball::new(Pointf { x: 60.0, y: 40.0 }, 20.0) },
+        ]);
+        let opts = RenderOpts { crosses: true, cross_radius: 3, gradient: true, ..RenderOpts::default() };
+        let mut incremental = IncrementalRender::default();
+        let mut frame = vec![0; 90 * 70 * 4];
+        incremental.render(&mut frame, 90, 70, &metadata, &opts);
+        metadata.metaballs[0].location = Pointf { x: 35.0, y: 50.0 };
+        incremental.render(&mut frame, 90, 70, &metadata, &opts);
+        let mut full = vec![0; 90 * 70 * 4];
+        render_metaballs(&mut full, 90, 70, &metadata, &opts);
+        assert_eq!(frame, full);
+        // the far corner is never touched again, so scribbling on it shows what was redrawn
+        frame[(69 * 90 + 89) * 4] = 7;
+        metadata.metaballs[0].location = Pointf { x: 20.0, y: 20.0 };
+        incremental.render(&mut frame, 90, 70, &metadata, &opts);
+        assert_eq!(frame[(69 * 90 + 89) * 4], 7);
+        frame[(69 * 90 + 89) * 4] = full[(69 * 90 + 89) * 4];
+        render_metaballs(&mut full, 90, 70, &metadata, &opts);
+        assert_eq!(frame, full);
+    }
+}
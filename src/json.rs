@@ -0,0 +1,207 @@
+//! Just enough JSON for the line protocol on STDIN: parsing one value, such as a command object, and quoting
+//! strings to write back in acknowledgments
+
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::str::Chars;
+
+/// How deeply arrays and objects can be nested in a value, far more than any command needs, so a line of
+/// brackets can't parse deep enough to overflow the stack
+pub const MAX_DEPTH: usize = 64;
+
+/// A JSON value
+#[derive(Clone, PartialEq, Debug)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(BTreeMap<String, Json>),
+}
+
+impl Json {
+    /// The value of a key, if this is an object that has it
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.get(key),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Json::Number(number) => Some(number),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(string) => Some(string),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a JSON value, with nothing but whitespace after it
+pub fn parse(text: &str) -> Result<Json, String> {
+    let mut chars = text.chars().peekable();
+    let value = parse_value(&mut chars, 0)?;
+    skip_whitespace(&mut chars);
+    match chars.next() {
+        None => Ok(value),
+        Some(c) => Err(format!("unexpected '{}' after the value", c)),
+    }
+}
+
+/// A string as a JSON string literal, quoted and escaped
+pub fn quote(string: &str) -> String {
+    let mut quoted = String::with_capacity(string.len() + 2);
+    quoted.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+        chars.next();
+    }
+}
+
+/// Take the next characters if they're a word, such as `true`
+fn expect_word(chars: &mut Peekable<Chars>, word: &str) -> Result<(), String> {
+    for expected in word.chars() {
+        if chars.next() != Some(expected) {
+            return Err(format!("expected {}", word));
+        }
+    }
+    Ok(())
+}
+
+/// Parse a value inside as many arrays and objects as the depth
+fn parse_value(chars: &mut Peekable<Chars>, depth: usize) -> Result<Json, String> {
+    skip_whitespace(chars);
+    if depth == MAX_DEPTH && matches!(chars.peek(), Some('{' | '[')) {
+        return Err(format!("arrays and objects can only be nested {} deep", MAX_DEPTH));
+    }
+    match chars.peek().copied() {
+        Some('{') => {
+            chars.next();
+            let mut members = BTreeMap::new();
+            skip_whitespace(chars);
+            if chars.peek() == Some(&'}') {
+                chars.next();
+                return Ok(Json::Object(members));
+            }
+            loop {
+                skip_whitespace(chars);
+                let key = match parse_value(chars, depth + 1)? {
+                    Json::String(key) => key,
+                    _ => return Err("expected a string key".to_string()),
+                };
+                skip_whitespace(chars);
+                if chars.next() != Some(':') {
+                    return Err(format!("expected ':' after \"{}\"", key));
+                }
+                members.insert(key, parse_value(chars, depth + 1)?);
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some('}') => return Ok(Json::Object(members)),
+                    _ => return Err("expected ',' or '}' in an object".to_string()),
+                }
+            }
+        }
+        Some('[') => {
+            chars.next();
+            let mut elements = vec![];
+            skip_whitespace(chars);
+            if chars.peek() == Some(&']') {
+                chars.next();
+                return Ok(Json::Array(elements));
+            }
+            loop {
+                elements.push(parse_value(chars, depth + 1)?);
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => continue,
+                    Some(']') => return Ok(Json::Array(elements)),
+                    _ => return Err("expected ',' or ']' in an array".to_string()),
+                }
+            }
+        }
+        Some('"') => {
+            chars.next();
+            parse_string(chars).map(Json::String)
+        }
+        Some('t') => expect_word(chars, "true").map(|_| Json::Bool(true)),
+        Some('f') => expect_word(chars, "false").map(|_| Json::Bool(false)),
+        Some('n') => expect_word(chars, "null").map(|_| Json::Null),
+        Some(c) if c == '-' || c.is_ascii_digit() => {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+                number.push(c);
+                chars.next();
+            }
+            number.parse().map(Json::Number).map_err(|_| format!("unable to parse the number {}", number))
+        }
+        Some(c) => Err(format!("unexpected '{}'", c)),
+        None => Err("expected a value".to_string()),
+    }
+}
+
+/// The rest of a string after its opening quote
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    let mut string = String::new();
+    loop {
+        match chars.next().ok_or("unterminated string")? {
+            '"' => return Ok(string),
+            '\\' => match chars.next().ok_or("unterminated string")? {
+                'n' => string.push('\n'),
+                'r' => string.push('\r'),
+                't' => string.push('\t'),
+                'b' => string.push('\u{8}'),
+                'f' => string.push('\u{c}'),
+                'u' => {
+                    let code: String = chars.by_ref().take(4).collect();
+                    let code = u32::from_str_radix(&code, 16).map_err(|_| format!("bad escape \\u{}", code))?;
+                    // surrogate pairs aren't put back together, they're only in characters no command needs
+                    string.push(char::from_u32(code).unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                c => string.push(c),
+            },
+            c => string.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_parse_and_strings_quote_back() {
+        let command = parse(r#" {"cmd": "set", "goo": 1.8, "balls": [1, -2.5e1, true, null], "name": "a \"b\"\n"} "#).unwrap();
+        assert_eq!(command.get("cmd").and_then(Json::as_str), Some("set"));
+        assert_eq!(command.get("goo").and_then(Json::as_f64), Some(1.8));
+        assert_eq!(command.get("balls"), Some(&Json::Array(vec![Json::Number(1.0), Json::Number(-25.0), Json::Bool(true), Json::Null])));
+        let name = command.get("name").and_then(Json::as_str).unwrap();
+        assert_eq!(parse(&quote(name)), Ok(Json::String(name.to_string())));
+        assert!(parse("{\"cmd\": }").is_err() && parse("{} x").is_err() && parse("[1, 2").is_err());
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert!(parse(&nested(MAX_DEPTH + 1)).is_err() && parse(&"[".repeat(300_000)).is_err());
+    }
+}
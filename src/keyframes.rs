@@ -0,0 +1,213 @@
+//! Authored animations: scenes at points in time, played by interpolating between them
+
+use crate::{default_goo, default_threshold, interpolate, MetaballConfig, MetaballData, SceneConfig, ANIMATION_TICK};
+use serde::Deserialize;
+use std::path::Path;
+use std::time::Duration;
+
+/// A file of keyframes, in TOML such as
+///
+/// ```toml
+/// loop = true
+///
+/// [[keyframes]]
+/// time = 0.0
+/// goo = 1.6
+///
+/// [[keyframes.metaballs]]
+/// x = 64
+/// y = 128
+/// size = 40.0
+///
+/// [[keyframes]]
+/// time = 1.5
+///
+/// [[keyframes.metaballs]]
+/// x = 192
+/// y = 128
+/// size = 60.0
+/// ```
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct KeyframesConfig {
+    /// Start over from the first keyframe after the last one
+    #[serde(default, rename = "loop")]
+    looping: bool,
+    keyframes: Vec<KeyframeConfig>,
+}
+
+/// A keyframe in a [KeyframesConfig]. The goo and threshold carry on from the keyframe before when left out.
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct KeyframeConfig {
+    /// Seconds from the start of the animation
+    time: f64,
+    goo: Option<f64>,
+    threshold: Option<f64>,
+    #[serde(default)]
+    metaballs: Vec<MetaballConfig>,
+}
+
+/// Scenes at points in time, with the metaballs matched up by index between them like a
+/// [Tween](crate::Tween)'s
+#[derive(Clone, PartialEq, Debug)]
+pub struct Keyframes {
+    /// The seconds from the start of each scene, in order
+    keyframes: Vec<(f64, MetaballData)>,
+    looping: bool,
+}
+
+impl Keyframes {
+    /// Read and parse a TOML keyframes file for an image
+    pub fn load(path: &Path, width: u32, height: u32) -> Result<Keyframes, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        Keyframes::parse(&text, width, height)
+    }
+
+    /// Parse TOML keyframes for an image, checking that they're in order and every metaball is on it
+    pub fn parse(text: &str, width: u32, height: u32) -> Result<Keyframes, String> {
+        let config: KeyframesConfig = toml::from_str(text).map_err(|err| err.to_string())?;
+        if config.keyframes.is_empty() {
+            return Err("there needs to be at least one keyframe".to_string());
+        }
+        let (mut goo, mut threshold) = (default_goo(), default_threshold());
+        let mut keyframes: Vec<(f64, MetaballData)> = vec![];
+        for (index, keyframe) in config.keyframes.into_iter().enumerate() {
+            let in_order = keyframes.last().is_none_or(|(time, _)| keyframe.time > *time);
+            if !keyframe.time.is_finite() || keyframe.time < 0.0 || !in_order {
+                return Err(format!("keyframe {} at {}s needs to come after the one before it", index, keyframe.time));
+            }
+            goo = keyframe.goo.unwrap_or(goo);
+            threshold = keyframe.threshold.unwrap_or(threshold);
+            let scene = SceneConfig { goo, threshold, layers: vec![], metaballs: keyframe.metaballs }
+                .into_data(width, height)
+                .map_err(|err| format!("keyframe {}: {}", index, err))?;
+            keyframes.push((keyframe.time, scene));
+        }
+        Ok(Keyframes { keyframes, looping: config.looping })
+    }
+
+    /// The seconds from the start to the last keyframe
+    pub fn duration(&self) -> f64 {
+        self.keyframes.last().map_or(0.0, |(time, _)| *time)
+    }
+
+    /// The width and height of the image the metaballs are laid out on
+    pub fn size(&self) -> (u32, u32) {
+        let scene = &self.keyframes[0].1;
+        (scene.width, scene.height)
+    }
+
+    /// Whether the animation starts over after the last keyframe
+    pub fn looping(&self) -> bool {
+        self.looping
+    }
+
+    /// The scene some seconds from the start. Before the first keyframe it's the first one, and after the
+    /// last it's the last one unless the animation loops.
+    pub fn scene_at(&self, seconds: f64) -> MetaballData {
+        let duration = self.duration();
+        let seconds = if self.looping && duration > 0.0 { seconds % duration } else { seconds };
+        let next = self.keyframes.iter().position(|(time, _)| *time > seconds);
+        match next {
+            Some(0) => self.keyframes[0].1.clone(),
+            Some(next) => {
+                let ((from_time, from), (to_time, to)) = (&self.keyframes[next - 1], &self.keyframes[next]);
+                interpolate(from, to, (seconds - from_time) / (to_time - from_time))
+            }
+            None => self.keyframes[self.keyframes.len() - 1].1.clone(),
+        }
+    }
+
+    /// How many frames this far apart play the animation once. A looping one leaves out the last keyframe,
+    /// since the first frame comes straight after it again.
+    pub fn frames(&self, delay: Duration) -> u32 {
+        let frames = (self.duration() / delay.as_secs_f64()).ceil() as u32;
+        if self.looping && frames > 0 {
+            frames
+        } else {
+            frames + 1
+        }
+    }
+}
+
+/// Keyframes being played an animation tick at a time
+pub struct Playback {
+    keyframes: Keyframes,
+    frame: u32,
+}
+
+impl Playback {
+    pub fn new(keyframes: Keyframes) -> Playback {
+        Playback { keyframes, frame: 0 }
+    }
+
+    /// Advance a tick, returning the scene at that point of the animation
+    pub fn step(&mut self) -> MetaballData {
+        self.frame = self.frame.wrapping_add(1);
+        self.keyframes.scene_at(self.seconds())
+    }
+
+    /// Whether the animation has reached its last keyframe, which a looping one never does
+    pub fn finished(&self) -> bool {
+        !self.keyframes.looping && self.seconds() >= self.keyframes.duration()
+    }
+
+    fn seconds(&self) -> f64 {
+        self.frame as f64 * ANIMATION_TICK.as_secs_f64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEYFRAMES: &str = "
+        [[keyframes]]
+        time = 0.0
+        goo = 1.6
+        [[keyframes.metaballs]]
+        x = 10
+        y = 20
+        size = 30.0
+
+        [[keyframes]]
+        time = 2.0
+        threshold = 0.9
+        [[keyframes.metaballs]]
+        x = 50
+        y = 20
+        size = 10.0
+        [[keyframes.metaballs]]
+        x = 30
+        y = 30
+        size = 20.0
+    ";
+
+    #[test]
+    fn scenes_between_keyframes_are_interpolated() {
+        let keyframes = Keyframes::parse(KEYFRAMES, 64, 64).unwrap();
+        let halfway = keyframes.scene_at(1.0);
+        assert_eq!(halfway.goo, 1.6);
+        assert_eq!(halfway.threshold, 0.7);
+        assert_eq!((halfway.metaballs[0].location.x, halfway.metaballs[0].size), (30.0, 20.0));
+        // the metaball only in the second keyframe grows from nothing in place
+        assert_eq!((halfway.metaballs[1].location.x, halfway.metaballs[1].size), (30.0, 10.0));
+        assert_eq!(keyframes.scene_at(5.0), keyframes.keyframes[1].1);
+        assert_eq!(keyframes.frames(Duration::from_millis(500)), 5);
+    }
+
+    #[test]
+    fn looping_keyframes_start_over_after_the_last_one() {
+        let keyframes = Keyframes::parse(&format!("loop = true\n{}", KEYFRAMES), 64, 64).unwrap();
+        assert_eq!(keyframes.scene_at(2.5), keyframes.scene_at(0.5));
+        assert_eq!(keyframes.frames(Duration::from_millis(500)), 4);
+    }
+
+    #[test]
+    fn keyframes_have_to_be_in_order() {
+        let backwards = KEYFRAMES.replace("time = 2.0", "time = 0.0");
+        assert!(Keyframes::parse(&backwards, 64, 64).is_err());
+        assert!(Keyframes::parse("keyframes = []", 64, 64).is_err());
+    }
+}
@@ -0,0 +1,144 @@
+//! Layers of metaballs, each blended into a field of its own and then combined with the layers under it,
+//! for compositions like a solid blob with an overlay that's cut out of it or only kept where they overlap.
+//! Every metaball starts out on the bottom layer, which is the plain field of the whole scene.
+
+use crate::MetaballData;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// The highest layer a metaball can be on, which keeps the list of layer blends short
+pub const MAX_LAYER: usize = 255;
+
+/// How a layer's field is combined with the field of the layers under it
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LayerBlend {
+    /// Added, as if the metaballs were all on one layer
+    #[default]
+    Sum,
+    /// Whichever is higher, so the layers are drawn over each other without merging
+    Max,
+    /// Whichever is lower, keeping only where the layers overlap
+    Min,
+    /// Multiplied, so the layer scales the field under it
+    Multiply,
+}
+
+impl LayerBlend {
+    /// The field with a layer's combined with the field under it
+    pub fn combine(&self, below: f64, layer: f64) -> f64 {
+        match self {
+            LayerBlend::Sum => below + layer,
+            LayerBlend::Max => below.max(layer),
+            LayerBlend::Min => below.min(layer),
+            LayerBlend::Multiply => below * layer,
+        }
+    }
+}
+
+impl FromStr for LayerBlend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sum" => Ok(LayerBlend::Sum),
+            "max" => Ok(LayerBlend::Max),
+            "min" => Ok(LayerBlend::Min),
+            "multiply" => Ok(LayerBlend::Multiply),
+            _ => Err(format!("Unknown layer blend \"{}\", expected sum, max, min or multiply", s)),
+        }
+    }
+}
+
+impl fmt::Display for LayerBlend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            LayerBlend::Sum => "sum",
+            LayerBlend::Max => "max",
+            LayerBlend::Min => "min",
+            LayerBlend::Multiply => "multiply",
+        })
+    }
+}
+
+impl MetaballData {
+    /// Whether any metaball is above the bottom layer, so the layers are blended separately
+    pub fn is_layered(&self) -> bool {
+        self.metaballs.iter().any(|metaball| metaball.layer > 0)
+    }
+
+    /// How a layer is combined with the ones under it, summed unless it's been set
+    pub fn layer_blend(&self, layer: usize) -> LayerBlend {
+        layer.checked_sub(1).and_then(|index| self.layers.get(index)).copied().unwrap_or_default()
+    }
+
+    /// Set how a layer above the bottom one is combined with the ones under it
+    pub fn set_layer_blend(&mut self, layer: usize, blend: LayerBlend) -> Result<(), String> {
+        if layer == 0 {
+            return Err("the bottom layer has nothing under it to blend with".to_string());
+        }
+        if layer > MAX_LAYER {
+            return Err(format!("there are only layers up to {}", MAX_LAYER));
+        }
+        if self.layers.len() < layer {
+            self.layers.resize(layer, LayerBlend::Sum);
+        }
+        self.layers[layer - 1] = blend;
+        // no need to keep the layers past the last one that isn't summed
+        while self.layers.last() == Some(&LayerBlend::Sum) {
+            self.layers.pop();
+        }
+        Ok(())
+    }
+
+    /// The layers there are metaballs on from the bottom up, worked out once for the whole frame rather
+    /// than at every pixel
+    pub(crate) fn layers_in_use(&self) -> Vec<usize> {
+        let mut layers: Vec<usize> = self.metaballs.iter().map(|metaball| metaball.layer).collect();
+        layers.sort_unstable();
+        layers.dedup();
+        layers
+    }
+
+    /// The field at a point from the field of each of the [layers in use](MetaballData::layers_in_use),
+    /// combined from the bottom up
+    pub(crate) fn layered_field(&self, layers: &[usize], field_of: impl Fn(usize) -> f64) -> f64 {
+        layers
+            .iter()
+            .map(|&layer| (layer, field_of(layer)))
+            .reduce(|(_, below), (layer, field)| (layer, self.layer_blend(layer).combine(below, field)))
+            .map_or(0.0, |(_, field)| field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{field_at, Metaball, Pointf};
+
+    #[test]
+    fn layers_are_blended_separately() {
+        // a big blob with a smaller one overlapping its right edge
+        let base = Metaball::new(Pointf { x: 40.0, y: 40.0 }, 20.0);
+        let overlay = Metaball { layer: 1, ..Metaball::new(Pointf { x: 60.0, y: 40.0 }, 10.0) };
+        let mut metadata = MetaballData::new(1.0, 1.0, 100, 80, vec![base, overlay]);
+        let inside = |metadata: &MetaballData, x: f64| field_at(x, 40.0, metadata) > metadata.threshold;
+        // summed it's just like one layer, and the two merge in between
+        assert!(inside(&metadata, 40.0) && inside(&metadata, 60.0) && inside(&metadata, 72.0));
+        metadata.set_layer_blend(1, LayerBlend::Max).unwrap();
+        assert!(inside(&metadata, 40.0) && inside(&metadata, 60.0) && !inside(&metadata, 72.0));
+        // only where they overlap
+        metadata.set_layer_blend(1, LayerBlend::Min).unwrap();
+        assert!(!inside(&metadata, 40.0) && inside(&metadata, 55.0) && !inside(&metadata, 75.0));
+        metadata.set_layer_blend(1, LayerBlend::Sum).unwrap();
+        assert!(metadata.layers.is_empty());
+        assert!(metadata.set_layer_blend(0, LayerBlend::Max).is_err());
+        assert!(metadata.set_layer_blend(MAX_LAYER + 1, LayerBlend::Max).is_err());
+        // far apart layers are just as quick to blend as neighboring ones
+        metadata.metaballs[1].layer = 1_000_000;
+        assert_eq!(metadata.layers_in_use(), vec![0, 1_000_000]);
+        assert!(inside(&metadata, 40.0) && inside(&metadata, 72.0));
+        assert_eq!(LayerBlend::from_str(&LayerBlend::Multiply.to_string()), Ok(LayerBlend::Multiply));
+    }
+}
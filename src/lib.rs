@@ -0,0 +1,3070 @@
+//! The metaball field and the renderers turning it into images, along with the scenes they draw.
+//! This is everything but the window, so the math can be used without winit or pixels by turning off the
+//! default `app` feature, which only the binary needs.
+
+use image::imageops::FilterType;
+use image::{ImageBuffer, ImageResult, Luma, Rgba};
+use rand::Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::DerefMut;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use falloff::Falloff;
+use layer::{LayerBlend, MAX_LAYER};
+use motion::{Motion, Pulse};
+use noise::Noise;
+use symmetry::Symmetry;
+
+pub mod audio;
+pub mod behavior;
+pub mod colormap;
+pub mod compare;
+pub mod contours;
+pub mod dataset;
+pub mod falloff;
+#[cfg(feature = "cdylib")]
+pub mod ffi;
+mod fast;
+pub mod gamepad;
+pub mod histogram;
+pub mod incremental;
+pub mod json;
+pub mod keyframes;
+pub mod layer;
+pub mod lit;
+mod lut;
+pub mod mesh;
+pub mod midi;
+pub mod motion;
+pub mod noise;
+pub mod osc;
+pub mod quadtree;
+pub mod raymarch;
+pub mod recording;
+pub mod sdf;
+pub mod slots;
+mod spatial;
+pub mod symmetry;
+pub mod theme;
+pub mod touch;
+
+/// The summed field of round metaballs without std, from the `metaballs-core` crate in `core/`, for
+/// embedded projects that only need to rasterize onto a small display
+pub use metaballs_core as core;
+
+/// How many rows of pixels are in each band of the frame rendered in parallel
+const BAND_ROWS: u32 = 16;
+
+/// The fraction of the threshold a metaball has to add to the field somewhere in a cell of pixels for
+/// [field_grid] to count it there. Leaving out the tail of each metaball's falloff only moves the edge of
+/// the shape by a hair, while sparing each pixel the metaballs that are too small or too far away to matter.
+pub const FIELD_EPSILON: f64 = 1e-4;
+
+/// The default base metaball size for the provided generation function
+pub const BASE_METABALL_SIZE: f64 = 90.0;
+
+/// The default minimum metaball count for the provided generation function
+pub const MIN_METABALL_COUNT: u32 = 3;
+
+/// The most metaballs the provided generation function makes, however many the count factor would give
+pub const MAX_METABALL_COUNT: u32 = 1000;
+
+/// The lowest count factor for the provided generation function, which gives scenes of about this many
+/// more metaballs than the minimum
+pub const MIN_COUNT_FACTOR: f64 = 0.01;
+
+/// The largest per-tick speed, in pixels, of a randomly generated metaball
+const MAX_METABALL_SPEED: i64 = 2;
+
+/// The interval between animation ticks
+pub const ANIMATION_TICK: Duration = Duration::from_millis(16);
+
+/// The fastest a metaball can move in the physics mode, in pixels per tick
+const MAX_PHYSICS_SPEED: f64 = 6.0;
+
+/// The default pixel color to draw for being inside the shape
+pub const ON_PIXEL: Rgba<u8> = Rgba([255u8, 0, 0, 255]);
+
+/// The default background pixel
+pub const OFF_PIXEL: Rgba<u8> = Rgba([0u8, 0, 0, 255]);
+
+/// The pixel color for the weakest part of the shape when drawing the intensity gradient, fading to the foreground
+pub const GRADIENT_LOW_PIXEL: Rgba<u8> = Rgba([0u8, 0, 255, 255]);
+
+/// The default color of the center point indicators
+pub const CROSS_PIXEL: Rgba<u8> = Rgba([0u8, 0, 255, 255]);
+
+/// The default color of the isolines
+pub const ISOLINE_PIXEL: Rgba<u8> = Rgba([255u8, 255, 255, 255]);
+
+/// The colors of the classic heatmap from cold to hot, and how far along it each one is. The threshold is
+/// halfway with the default heat scale, see [heatmap_impl].
+pub const HEATMAP_STOPS: [(f64, Rgba<u8>); 5] = [
+    (0.0, Rgba([0u8, 0, 0, 255])),
+    (0.25, Rgba([0u8, 0, 255, 255])),
+    (0.5, Rgba([0u8, 255, 255, 255])),
+    (0.75, Rgba([255u8, 255, 0, 255])),
+    (1.0, Rgba([255u8, 0, 0, 255])),
+];
+
+/// Format a color the way [parse_color] reads it, leaving the alpha channel off when it's opaque. There's no
+/// leading `#`, which would start a comment in a command.
+pub fn hex_color(color: Rgba<u8>) -> String {
+    let [r, g, b, a] = color.0;
+    if a == 255 {
+        format!("{:02x}{:02x}{:02x}", r, g, b)
+    } else {
+        format!("{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+    }
+}
+
+/// Field levels and the colors of the bands above them, see [RenderOpts::bands]
+pub type Bands = Vec<(f64, Rgba<u8>)>;
+
+/// Parse a hex color such as `#ff0000` or `ff0000aa`, the leading `#` and the alpha channel being optional
+pub fn parse_color(hex: &str) -> Result<Rgba<u8>, String> {
+    let digits = hex.strip_prefix('#').unwrap_or(hex);
+    if !(digits.len() == 6 || digits.len() == 8) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("\"{}\" is not a color, expected hex RRGGBB or RRGGBBAA", hex));
+    }
+    let mut color = Rgba([0u8, 0, 0, 255]);
+    for (i, channel) in color.0.iter_mut().take(digits.len() / 2).enumerate() {
+        *channel = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).unwrap();
+    }
+    Ok(color)
+}
+
+/// The value of the metaball field at a point, which is inside the shape when greater than the threshold
+pub fn field_at(x: f64, y: f64, metaball_data: &MetaballData) -> f64 {
+    let field = field_of(x, y, &metaball_data.metaballs, &metaball_data.layers_in_use(), metaball_data);
+    metaball_data.noise.map_or(field, |noise| field * noise.factor(x, y))
+}
+
+/// The field at a point counting only some of the scene's metaballs, see [field_at]. With more than one of
+/// the scene's [layers in use](MetaballData::layers_in_use), each one is blended separately and they're
+/// combined afterwards.
+fn field_of(x: f64, y: f64, metaballs: &[Metaball], layers: &[usize], metaball_data: &MetaballData) -> f64 {
+    if layers.len() > 1 {
+        return metaball_data.layered_field(layers, |layer| layer_field(x, y, metaballs.iter().filter(|metaball| metaball.layer == layer), metaball_data));
+    }
+    layer_field(x, y, metaballs.iter(), metaball_data)
+}
+
+/// The field at a point of some metaballs blended together, see [field_of]
+fn layer_field<'a>(x: f64, y: f64, metaballs: impl Iterator<Item = &'a Metaball>, metaball_data: &MetaballData) -> f64 {
+    blend_field(
+        metaball_data,
+        metaballs.filter_map(|metaball| {
+            // the distance of the metaball, which adds nothing past its radius of influence
+            let distance = field_distance(metaball, x, y, metaball_data);
+            Some((metaball.size, metaball.goo_or(metaball_data.goo), distance)).filter(|_| metaball.reaches(distance))
+        }),
+    )
+}
+
+/// The field at a point from the size, goo and distance of each metaball reaching it, blended the way the
+/// scene blends them
+fn blend_field(metaball_data: &MetaballData, metaballs: impl Iterator<Item = (f64, f64, f64)>) -> f64 {
+    let falloff = metaball_data.falloff.function();
+    match metaball_data.blend {
+        BlendMode::Additive => {
+            // sum the metaball values
+            metaballs.fold(0f64, |acc, (size, goo, distance)| acc + falloff.value(size, distance, goo))
+        }
+        BlendMode::SmoothUnion { k } => {
+            // each metaball is a circle as big as it would be on its own in the additive field. The negative
+            // ones are merged separately and carved out of the rest afterwards, so their order doesn't matter.
+            let (union, carved) = metaballs.fold((f64::INFINITY, f64::INFINITY), |(union, carved), (size, goo, distance)| {
+                let radius = falloff.edge(size, metaball_data.threshold, goo);
+                if size < 0.0 {
+                    (union, smooth_min(carved, distance - radius, k))
+                } else {
+                    (smooth_min(union, distance - radius, k), carved)
+                }
+            });
+            // outside of the carved circles is the negative of the distance into them, and the shape is
+            // only where it's inside both
+            let distance = -smooth_min(-union, carved, k);
+            // inside the shape the distance is negative, so this is still above the threshold there
+            metaball_data.threshold - distance
+        }
+    }
+}
+
+/// The distance from a metaball's center to a point, as the field measures it
+fn field_distance(metaball: &Metaball, x: f64, y: f64, metaball_data: &MetaballData) -> f64 {
+    let aspect = metaball_data.pixel_aspect;
+    if metaball_data.tile {
+        // the closest of the metaball and its copies in the eight surrounding tiles
+        let (width, height) = (metaball_data.width as f64, metaball_data.height as f64);
+        let offsets = [-1.0, 0.0, 1.0];
+        offsets
+            .iter()
+            .flat_map(|i| offsets.iter().map(move |j| (i, j)))
+            .map(|(i, j)| metaball.distance_to(x + i * width, y + j * height, aspect))
+            .fold(f64::INFINITY, f64::min)
+    } else {
+        metaball.distance_to(x, y, aspect)
+    }
+}
+
+/// Polynomial smooth minimum, which blends `a` and `b` together when they are within `k` of each other
+fn smooth_min(a: f64, b: f64, k: f64) -> f64 {
+    if k <= 0.0 {
+        return a.min(b);
+    }
+    let h = (k - (a - b).abs()).max(0.0) / k;
+    a.min(b) - h * h * k * 0.25
+}
+
+/// Evaluate the field at every pixel, row by row, looking at the field through a view. With a scale above 1
+/// each point on the screen is covered by `scale × scale` pixels, with the pixels sampling the field at their centers.
+/// Bands of rows are evaluated in parallel, and each pixel only counts the metaballs that reach its cell of a
+/// uniform grid over the image, see [FIELD_EPSILON]. Single precision and the lookup table only speed up
+/// the summed field, see [Precision]. The noise is applied afterwards, whichever way the field was evaluated.
+pub fn field_grid(width: u32, height: u32, scale: u32, view: &View, metaball_data: &MetaballData, precision: Precision) -> Vec<f64> {
+    field_cells(width, height, scale, view, metaball_data, precision, None)
+}
+
+/// Evaluate the field like [field_grid], only in the dirty cells if there are any, leaving the rest 0
+fn field_cells(width: u32, height: u32, scale: u32, view: &View, metaball_data: &MetaballData, precision: Precision, dirty: Option<&spatial::DirtyCells>) -> Vec<f64> {
+    let skipped = |x: u32, y: u32| dirty.is_some_and(|dirty| !dirty.contains(x, y));
+    let mut field = vec![0.0; width as usize * height as usize];
+    if width == 0 {
+        return field;
+    }
+    let start = Instant::now();
+    let grid = spatial::MetaballGrid::new(width, height, scale, view, metaball_data);
+    let cells = if grid.is_some() { "bucketed into cells" } else { "all counted everywhere" };
+    let metaballs_at = |x: u32, y: u32| grid.as_ref().map_or(&metaball_data.metaballs[..], |grid| grid.metaballs_at(x, y));
+    if precision == Precision::F32 && fast::supports(metaball_data) {
+        field.par_chunks_mut((width * BAND_ROWS) as usize).enumerate().for_each(|(band, values)| {
+            // the metaballs of the cell the last run of pixels was in, ready for single precision
+            let mut prepared: Option<(&[Metaball], Vec<fast::FastMetaball>)> = None;
+            for (row, values) in values.chunks_mut(width as usize).enumerate() {
+                let y = band as u32 * BAND_ROWS + row as u32;
+                for (run, values) in values.chunks_mut(fast::LANES).enumerate() {
+                    let x = (run * fast::LANES) as u32;
+                    // the runs line up with the cells, so a run is in one cell
+                    if skipped(x, y) {
+                        continue;
+                    }
+                    let metaballs = metaballs_at(x, y);
+                    if !prepared.as_ref().is_some_and(|(cell, _)| std::ptr::eq(*cell, metaballs)) {
+                        prepared = Some((metaballs, fast::prepare(metaballs, metaball_data)));
+                    }
+                    // the lanes past the end of the row are evaluated too, and left out
+                    let xs = [0, 1, 2, 3, 4, 5, 6, 7].map(|lane| field_point(x + lane, y, scale, view).0 as f32);
+                    let fields = fast::field_at(xs, field_point(x, y, scale, view).1 as f32, &prepared.as_ref().unwrap().1, metaball_data);
+                    for (value, field) in values.iter_mut().zip(&fields) {
+                        *value = *field as f64;
+                    }
+                }
+            }
+        });
+        add_noise(&mut field, width, scale, view, metaball_data, dirty);
+        log::trace!("The single precision field at {}x{}, with the metaballs {}, took {:.2}ms", width, height, cells, milliseconds_since(start));
+        return field;
+    }
+    let table = if precision == Precision::Lut && lut::supports(metaball_data) { Some(lut::table(metaball_data)) } else { None };
+    let layers = metaball_data.layers_in_use();
+    field.par_chunks_mut((width * BAND_ROWS) as usize).enumerate().for_each(|(band, values)| {
+        for (i, value) in values.iter_mut().enumerate() {
+            let (x, y) = (i as u32 % width, band as u32 * BAND_ROWS + i as u32 / width);
+            if skipped(x, y) {
+                continue;
+            }
+            let (px, py) = field_point(x, y, scale, view);
+            *value = match &table {
+                Some(table) => lut::field_of(px, py, metaballs_at(x, y), metaball_data, table),
+                None => field_of(px, py, metaballs_at(x, y), &layers, metaball_data),
+            };
+        }
+    });
+    add_noise(&mut field, width, scale, view, metaball_data, dirty);
+    let precision = if table.is_some() { "looked up" } else { "full precision" };
+    log::trace!("The {} field at {}x{}, with the metaballs {}, took {:.2}ms", precision, width, height, cells, milliseconds_since(start));
+    field
+}
+
+/// Perturb a field grid with the scene's noise, if it has any, see [field_grid]
+fn add_noise(field: &mut [f64], width: u32, scale: u32, view: &View, metaball_data: &MetaballData, dirty: Option<&spatial::DirtyCells>) {
+    if let Some(noise) = metaball_data.noise {
+        field.par_chunks_mut(width as usize).enumerate().for_each(|(y, row)| {
+            for (x, value) in row.iter_mut().enumerate() {
+                if dirty.is_some_and(|dirty| !dirty.contains(x as u32, y as u32)) {
+                    continue;
+                }
+                let (px, py) = field_point(x as u32, y as u32, scale, view);
+                *value *= noise.factor(px, py);
+            }
+        });
+    }
+}
+
+/// The field point that a pixel samples, see [field_grid]
+pub(crate) fn field_point(x: u32, y: u32, scale: u32, view: &View) -> (f64, f64) {
+    let to_screen = |pixel: u32| (pixel as f64 + 0.5) / scale as f64 - 0.5;
+    view.field_point(to_screen(x), to_screen(y))
+}
+
+/// The metaballs' colors mixed by how much each metaball adds to the field at a point
+fn ball_color_at(x: f64, y: f64, metaball_data: &MetaballData) -> Rgba<u8> {
+    let mut total = 0.0;
+    let mut sums = [0.0; 4];
+    for metaball in &metaball_data.metaballs {
+        // negative metaballs only take away from the shape, they have no color of their own
+        if metaball.size < 0.0 {
+            continue;
+        }
+        let weight = metaball.contribution(Pointf { x, y }, metaball_data);
+        if weight.is_infinite() {
+            // right on the center of the metaball
+            return metaball.color;
+        }
+        total += weight;
+        for (sum, channel) in sums.iter_mut().zip(metaball.color.0.iter()) {
+            *sum += *channel as f64 * weight;
+        }
+    }
+    let mut color = Rgba([0u8; 4]);
+    for (channel, sum) in color.0.iter_mut().zip(sums.iter()) {
+        *channel = (sum / total).round().clamp(0.0, 255.0) as u8;
+    }
+    color
+}
+
+/// Write the field values of every pixel to a file. A `.csv` file gets one line of comma separated values
+/// per row, `.f32` and `.f64` files get the raw little endian floats row by row, and a `.png` file gets
+/// 16-bit grayscale from black at 0 to white at twice the threshold, so the edge is half brightness.
+pub fn export_field(path: &Path, metaball_data: &MetaballData) -> io::Result<()> {
+    let field = field_grid(metaball_data.width, metaball_data.height, 1, &View::default(), metaball_data, Precision::F64);
+    if path.extension().is_some_and(|ext| ext == "png") {
+        let brightness = field.iter().map(|sum| sum / (2.0 * metaball_data.threshold));
+        return save_gray16(path, metaball_data.width, metaball_data.height, brightness);
+    }
+    let mut out = BufWriter::new(File::create(path)?);
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("csv") => {
+            for row in field.chunks(metaball_data.width as usize) {
+                let line: Vec<String> = row.iter().map(f64::to_string).collect();
+                writeln!(out, "{}", line.join(","))?;
+            }
+        }
+        Some("f32") => {
+            for value in field {
+                out.write_all(&(value as f32).to_le_bytes())?;
+            }
+        }
+        Some("f64") => {
+            for value in field {
+                out.write_all(&value.to_le_bytes())?;
+            }
+        }
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "expected a .csv, .f32, .f64 or .png file")),
+    }
+    out.flush()
+}
+
+/// How many pixels either side of the edge an SDF PNG reaches before it's white or black, unless asked otherwise
+pub const SDF_SPREAD: f64 = 16.0;
+
+/// Write an approximate signed distance field of the shape to a file, see [sdf::signed_distances]. A `.png`
+/// file gets 16-bit grayscale with the edge at half brightness, lighter inside, reaching white and black
+/// `spread` pixels either side of it. A `.f32` file gets the distances in pixels, negative inside, as raw
+/// little endian floats row by row.
+pub fn export_sdf(path: &Path, metaball_data: &MetaballData, spread: f64) -> io::Result<()> {
+    let distances = sdf::signed_distances(metaball_data);
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("png") => {
+            let brightness = distances.iter().map(|distance| 0.5 - distance / (2.0 * spread));
+            save_gray16(path, metaball_data.width, metaball_data.height, brightness)
+        }
+        Some("f32") => {
+            let mut out = BufWriter::new(File::create(path)?);
+            for distance in distances {
+                out.write_all(&(distance as f32).to_le_bytes())?;
+            }
+            out.flush()
+        }
+        _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "expected a .png or .f32 file")),
+    }
+}
+
+/// Save a 16-bit grayscale PNG of brightnesses from 0 to 1, row by row
+fn save_gray16(path: &Path, width: u32, height: u32, brightness: impl Iterator<Item = f64>) -> io::Result<()> {
+    let pixels = brightness.map(|value| (value.clamp(0.0, 1.0) * u16::MAX as f64).round() as u16).collect();
+    let image: ImageBuffer<Luma<u16>, Vec<u16>> = ImageBuffer::from_raw(width, height, pixels).expect("there should be a brightness for every pixel");
+    image.save(path).map_err(io::Error::other)
+}
+
+/// Write the outline of the shape at the current threshold to an SVG file, as paths in field coordinates
+pub fn export_svg(path: &Path, metaball_data: &MetaballData, color: Rgba<u8>) -> io::Result<()> {
+    let (width, height) = (metaball_data.width, metaball_data.height);
+    let mut out = BufWriter::new(File::create(path)?);
+    // field points are at the centers of the pixels they're rendered to, so the view box is shifted by half a pixel
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{1}" viewBox="-0.5 -0.5 {0} {1}">"#,
+        width, height
+    )?;
+    let stroke = format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2]);
+    for contour in contours::outline(metaball_data) {
+        let points: Vec<String> = contour.iter().map(|point| format!("{:.3},{:.3}", point.x, point.y)).collect();
+        writeln!(out, r#"  <polyline points="{}" fill="none" stroke="{}"/>"#, points.join(" "), stroke)?;
+    }
+    writeln!(out, "</svg>")?;
+    out.flush()
+}
+
+/// How many pixels wide the cubes are that the field is sampled in for meshes, unless asked otherwise
+pub const MESH_CELL: f64 = 2.0;
+
+/// Write the surface of the shape in 3D to a mesh file, OBJ or binary STL by the path's extension, with the
+/// field sampled in cubes `cell` pixels wide, see [mesh::surface]
+pub fn export_mesh(path: &Path, metaball_data: &MetaballData, cell: f64) -> io::Result<()> {
+    let mesh = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("obj") | Some("stl") => mesh::surface(metaball_data, cell),
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "the path needs to end in .obj or .stl")),
+    };
+    let out = BufWriter::new(File::create(path)?);
+    if path.extension().is_some_and(|ext| ext == "stl") {
+        mesh.write_stl(out)
+    } else {
+        mesh.write_obj(out)
+    }
+}
+
+/// A naive implementation to render metaballs. This is slow, but works.
+/// The scale is how many pixels wide each point of the metaball data is, see [field_grid].
+pub fn naive_impl(width: u32, height: u32, scale: u32, metaball_data: &MetaballData, opts: &RenderOpts) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let mut image = ImageBuffer::new(width, height);
+    naive_into(&mut image, width, height, scale, metaball_data, opts);
+    image
+}
+
+/// Render metaballs the same way as [naive_impl], straight into an RGBA buffer of the given size.
+/// The frame is split into bands of rows which are filled in parallel.
+pub fn naive_into(frame: &mut [u8], width: u32, height: u32, scale: u32, metaball_data: &MetaballData, opts: &RenderOpts) {
+    naive_cells(frame, width, height, scale, metaball_data, opts, None)
+}
+
+/// Render metaballs like [naive_into], only into the dirty cells if there are any
+fn naive_cells(frame: &mut [u8], width: u32, height: u32, scale: u32, metaball_data: &MetaballData, opts: &RenderOpts, dirty: Option<&spatial::DirtyCells>) {
+    // Evaluate the field once per pixel, so neighbors can be compared when drawing isolines
+    let field = field_cells(width, height, scale, &opts.view, metaball_data, opts.precision, dirty);
+    let mut image = ImageBuffer::from_raw(width, height, frame).expect("the frame should hold every pixel");
+    shade_field(&mut image, &field, scale, metaball_data, opts, dirty);
+}
+
+/// Color each pixel of an image by the field there, into the dirty cells if there are any, and draw the
+/// isolines over them
+fn shade_field<C: DerefMut<Target = [u8]>>(image: &mut ImageBuffer<Rgba<u8>, C>, field: &[f64], scale: u32, metaball_data: &MetaballData, opts: &RenderOpts, dirty: Option<&spatial::DirtyCells>) {
+    let (width, height) = image.dimensions();
+    if width == 0 {
+        return;
+    }
+    // Use the field to determine whether each individual pixel should be on or off
+    image.par_chunks_mut((width * BAND_ROWS * 4) as usize).enumerate().for_each(|(band, pixels)| {
+        for (i, pixel) in pixels.chunks_exact_mut(4).enumerate() {
+            let (x, y) = (i as u32 % width, band as u32 * BAND_ROWS + i as u32 / width);
+            if dirty.is_some_and(|dirty| !dirty.contains(x, y)) {
+                continue;
+            }
+            let sum = field[(y * width + x) as usize];
+            let background = background_at(x, y, width, height, opts);
+            let color = if !opts.bands.is_empty() {
+                band_color(sum, &opts.bands).map_or(background, |color| blend_over(color, background))
+            } else if sum > metaball_data.threshold {
+                // if the sum if greater than the threshold then draw a pixel
+                blend_over(inside_color(x, y, sum, scale, metaball_data, opts), background)
+            } else {
+                background
+            };
+            pixel.copy_from_slice(&color.0);
+        }
+    });
+    if !opts.isolines.is_empty() {
+        draw_isolines(image, field, &opts.isolines, opts.isoline_color);
+    }
+}
+
+/// Render metaballs by evaluating the field only at the corners of square cells `cell` pixels wide and
+/// interpolating it across each cell, which puts the edge where marching squares would put it on the
+/// cell edges. Pixels along the edge are partly filled by how far they are from it, so the boundary is
+/// smoother than thresholding each pixel even though the field is evaluated far less often.
+pub fn marching_squares_impl(width: u32, height: u32, scale: u32, cell: u32, metaball_data: &MetaballData, opts: &RenderOpts) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let threshold = metaball_data.threshold;
+    // the summed field falls off with a power of the distance, so its logarithm is much closer to linear
+    // across a cell and interpolates far better. The smooth union's field is a distance already. The summed
+    // field is infinite right on a metaball's center though, which would spread to the whole cell, so it's
+    // kept to a range around the threshold.
+    let logarithmic = metaball_data.blend == BlendMode::Additive;
+    let to_level = |value: f64| if logarithmic { value.clamp(threshold * 1e-3, threshold * 1e3).ln() } else { value };
+    let from_level = |level: f64| if logarithmic { level.exp() } else { level };
+    let threshold_level = to_level(threshold);
+
+    // the corners of enough cells to cover the image
+    let (columns, rows) = (width / cell + 2, height / cell + 2);
+    let corners: Vec<f64> = (0..rows)
+        .flat_map(|row| (0..columns).map(move |column| (column * cell, row * cell)))
+        .map(|(x, y)| {
+            let (x, y) = field_point(x, y, scale, &opts.view);
+            to_level(field_at(x, y, metaball_data))
+        })
+        .collect();
+    let corner = |column: u32, row: u32| corners[(row * columns + column) as usize];
+
+    // interpolate the field, and how steeply it changes, at each pixel
+    let mut field = Vec::with_capacity(width as usize * height as usize);
+    let mut slopes = Vec::with_capacity(width as usize * height as usize);
+    for y in 0..height {
+        for x in 0..width {
+            let (column, row) = (x / cell, y / cell);
+            let (tx, ty) = ((x % cell) as f64 / cell as f64, (y % cell) as f64 / cell as f64);
+            let (top_left, top_right) = (corner(column, row), corner(column + 1, row));
+            let (bottom_left, bottom_right) = (corner(column, row + 1), corner(column + 1, row + 1));
+            let top = top_left + (top_right - top_left) * tx;
+            let bottom = bottom_left + (bottom_right - bottom_left) * tx;
+            field.push(from_level(top + (bottom - top) * ty));
+            let dx = ((top_right - top_left) * (1.0 - ty) + (bottom_right - bottom_left) * ty) / cell as f64;
+            let dy = ((bottom_left - top_left) * (1.0 - tx) + (bottom_right - top_right) * tx) / cell as f64;
+            slopes.push(dx.hypot(dy));
+        }
+    }
+
+    let mut image = ImageBuffer::from_fn(width, height, |x, y| {
+        let index = (y * width + x) as usize;
+        let (sum, slope) = (field[index], slopes[index]);
+        // how much of the pixel is inside, from how many pixels away from the edge its center is
+        let coverage = if slope > 0.0 {
+            (0.5 + (to_level(sum) - threshold_level) / slope).clamp(0.0, 1.0)
+        } else if sum > threshold {
+            1.0
+        } else {
+            0.0
+        };
+        let background = background_at(x, y, width, height, opts);
+        if coverage > 0.0 {
+            let inside = blend_over(inside_color(x, y, sum.max(threshold), scale, metaball_data, opts), background);
+            lerp_color(background, inside, coverage)
+        } else {
+            background
+        }
+    });
+    if !opts.isolines.is_empty() {
+        draw_isolines(&mut image, &field, &opts.isolines, opts.isoline_color);
+    }
+    image
+}
+
+/// Render the field itself rather than the shape, coloring every pixel by its field value from cold to hot
+/// in the colormap so the falloff around the metaballs shows. The field is unbounded towards the centers, so
+/// by default it's mapped by `sum / (sum + threshold)`, which puts the shape's edge halfway along the colormap
+/// whatever the scene, see [HeatScale](colormap::HeatScale) for the others.
+pub fn heatmap_impl(width: u32, height: u32, scale: u32, metaball_data: &MetaballData, opts: &RenderOpts) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let field = field_grid(width, height, scale, &opts.view, metaball_data, opts.precision);
+    let heat = opts.heat_scale.normalizer(&field, metaball_data.threshold);
+    let mut image = ImageBuffer::from_fn(width, height, |x, y| opts.colormap.color(heat(field[(y * width + x) as usize])));
+    if !opts.isolines.is_empty() {
+        draw_isolines(&mut image, &field, &opts.isolines, opts.isoline_color);
+    }
+    image
+}
+
+/// Render only the edge of the shape, the pixels where the field crosses the threshold between one and the
+/// next, in the outline color over the background
+pub fn outline_impl(width: u32, height: u32, scale: u32, metaball_data: &MetaballData, opts: &RenderOpts) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let field = field_grid(width, height, scale, &opts.view, metaball_data, opts.precision);
+    let mut image = ImageBuffer::from_fn(width, height, |x, y| background_at(x, y, width, height, opts));
+    draw_isolines(&mut image, &field, &[metaball_data.threshold], opts.outline_color.unwrap_or_else(|| opts.fill_color()));
+    if !opts.isolines.is_empty() {
+        draw_isolines(&mut image, &field, &opts.isolines, opts.isoline_color);
+    }
+    image
+}
+
+/// The 4×4 Bayer matrix, the order its pixels light up in as a patch of the dithered image gets brighter
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// How sharply the dithered brightness rises across the edge of the shape, see [dither_impl]
+const DITHER_CONTRAST: i32 = 4;
+
+/// Render the field in two colors, the inside color and the background, ordered dithered through the
+/// [BAYER] matrix for a 1-bit look. The brightness is `r^n / (r^n + 1)` for `r = sum / threshold` and a
+/// [DITHER_CONTRAST] of `n`, so the edge of the shape is half lit and the pattern fades out soon after it.
+/// It's never supersampled, which would blur the pattern into more colors.
+pub fn dither_impl(width: u32, height: u32, scale: u32, metaball_data: &MetaballData, opts: &RenderOpts) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let field = field_grid(width, height, scale, &opts.view, metaball_data, opts.precision);
+    let threshold = metaball_data.threshold;
+    let mut image = ImageBuffer::from_fn(width, height, |x, y| {
+        let ratio = (field[(y * width + x) as usize].max(0.0) / threshold).powi(DITHER_CONTRAST);
+        let brightness = if ratio.is_infinite() { 1.0 } else { ratio / (ratio + 1.0) };
+        let order = BAYER[(y % 4) as usize][(x % 4) as usize];
+        let background = background_at(x, y, width, height, opts);
+        if brightness > (order as f64 + 0.5) / 16.0 { blend_over(opts.fill_color(), background) } else { background }
+    });
+    if !opts.isolines.is_empty() {
+        draw_isolines(&mut image, &field, &opts.isolines, opts.isoline_color);
+    }
+    image
+}
+
+/// Render the shape split into the regions where each metaball adds the most to the field, each in a color
+/// of its own, to show how the metaballs merge. The colors are the metaballs' own with `ball_colors`, and
+/// hues spread around the color wheel by index otherwise.
+pub fn regions_impl(width: u32, height: u32, scale: u32, metaball_data: &MetaballData, opts: &RenderOpts) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let field = field_grid(width, height, scale, &opts.view, metaball_data, opts.precision);
+    let mut image = ImageBuffer::from_fn(width, height, |x, y| {
+        let background = background_at(x, y, width, height, opts);
+        if field[(y * width + x) as usize] <= metaball_data.threshold {
+            return background;
+        }
+        let (field_x, field_y) = field_point(x, y, scale, &opts.view);
+        match dominant_metaball(Pointf { x: field_x, y: field_y }, metaball_data) {
+            Some(index) if opts.ball_colors => blend_over(metaball_data.metaballs[index].color, background),
+            Some(index) => blend_over(region_color(index), background),
+            None => background,
+        }
+    });
+    if !opts.isolines.is_empty() {
+        draw_isolines(&mut image, &field, &opts.isolines, opts.isoline_color);
+    }
+    image
+}
+
+/// The index of the metaball adding the most to the field at a point, if any add to it. Negative metaballs
+/// only take away from the shape, so they're never the one.
+pub fn dominant_metaball(point: Pointf, metaball_data: &MetaballData) -> Option<usize> {
+    metaball_data
+        .metaballs
+        .iter()
+        .enumerate()
+        .filter(|(_, metaball)| metaball.size > 0.0)
+        .map(|(index, metaball)| (index, metaball.contribution(point, metaball_data)))
+        .filter(|&(_, contribution)| contribution > 0.0)
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(index, _)| index)
+}
+
+/// The color of a metaball's region in [regions_impl], stepping around the color wheel by the golden angle
+/// so neighboring indices get far apart hues
+fn region_color(index: usize) -> Rgba<u8> {
+    hsv_to_rgb((index as f64 * 137.508).rem_euclid(360.0), 0.7, 1.0)
+}
+
+/// The color of the classic heatmap some way from cold at 0 to hot at 1
+pub(crate) fn heatmap_color(heat: f64) -> Rgba<u8> {
+    let above = HEATMAP_STOPS.iter().position(|(stop, _)| *stop >= heat).unwrap_or(HEATMAP_STOPS.len() - 1).max(1);
+    let ((from, low), (to, high)) = (HEATMAP_STOPS[above - 1], HEATMAP_STOPS[above]);
+    lerp_color(low, high, (heat - from) / (to - from))
+}
+
+/// The color of the highest band the field is above, if it's above any
+fn band_color(sum: f64, bands: &[(f64, Rgba<u8>)]) -> Option<Rgba<u8>> {
+    bands.iter()
+        .filter(|(level, _)| sum > *level)
+        .max_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap())
+        .map(|(_, color)| *color)
+}
+
+/// The color behind the metaballs at a pixel
+fn background_at(x: u32, y: u32, width: u32, height: u32, opts: &RenderOpts) -> Rgba<u8> {
+    match &opts.background {
+        Some(background) => *background.get_pixel(x * background.width() / width, y * background.height() / height),
+        None => opts.off_color,
+    }
+}
+
+/// The color of a pixel inside of the shape with the given field value
+fn inside_color(x: u32, y: u32, sum: f64, scale: u32, metaball_data: &MetaballData, opts: &RenderOpts) -> Rgba<u8> {
+    if opts.ball_colors {
+        let (field_x, field_y) = field_point(x, y, scale, &opts.view);
+        ball_color_at(field_x, field_y, metaball_data)
+    } else if opts.gradient {
+        // threshold / sum falls from 1 at the edge towards 0 at the centers regardless of the scene,
+        // so the colors don't jump around when the strongest point of the field changes
+        lerp_color(opts.fill_color(), opts.gradient_color, metaball_data.threshold / sum)
+    } else {
+        opts.fill_color()
+    }
+}
+
+/// Draw a line wherever the field crosses one of the levels, which shows up as the field
+/// being above a level on one pixel but below it on the pixel to the right or below.
+fn draw_isolines<C: DerefMut<Target = [u8]>>(image: &mut ImageBuffer<Rgba<u8>, C>, field: &[f64], levels: &[f64], color: Rgba<u8>) {
+    let (width, height) = image.dimensions();
+    let crosses = |a: f64, b: f64| levels.iter().any(|level| (a > *level) != (b > *level));
+    for y in 0..height {
+        for x in 0..width {
+            let value = field[(y * width + x) as usize];
+            let right = x + 1 < width && crosses(value, field[(y * width + x + 1) as usize]);
+            let down = y + 1 < height && crosses(value, field[((y + 1) * width + x) as usize]);
+            if right || down {
+                image.put_pixel(x, y, color);
+            }
+        }
+    }
+}
+
+/// Shrink an image by averaging each `factor × factor` block of pixels into one pixel
+fn downsample(image: &ImageBuffer<Rgba<u8>, Vec<u8>>, factor: u32) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    ImageBuffer::from_fn(image.width() / factor, image.height() / factor, |x, y| {
+        let mut sums = [0u32; 4];
+        for dy in 0..factor {
+            for dx in 0..factor {
+                let pixel = image.get_pixel(x * factor + dx, y * factor + dy);
+                for (sum, channel) in sums.iter_mut().zip(pixel.0.iter()) {
+                    *sum += *channel as u32;
+                }
+            }
+        }
+        let samples = factor * factor;
+        let mut color = Rgba([0u8; 4]);
+        for (channel, sum) in color.0.iter_mut().zip(sums.iter()) {
+            *channel = ((sum + samples / 2) / samples) as u8;
+        }
+        color
+    })
+}
+
+/// Composite a color over another by its alpha
+pub fn blend_over(top: Rgba<u8>, bottom: Rgba<u8>) -> Rgba<u8> {
+    let alpha = top[3] as f64 / 255.0;
+    let mut color = lerp_color(bottom, top, alpha);
+    color[3] = (top[3] as f64 + bottom[3] as f64 * (1.0 - alpha)).round() as u8;
+    color
+}
+
+/// Load an image to draw the metaballs over, stretched to the given size
+pub fn load_background(path: &Path, width: u32, height: u32) -> ImageResult<ImageBuffer<Rgba<u8>, Vec<u8>>> {
+    Ok(image::open(path)?.resize_exact(width, height, FilterType::Triangle).to_rgba8())
+}
+
+/// Convert a hue in degrees, saturation and value within \[0, 1\] to an opaque color
+pub fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> Rgba<u8> {
+    let chroma = value * saturation;
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let second = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, second, 0.0),
+        1 => (second, chroma, 0.0),
+        2 => (0.0, chroma, second),
+        3 => (0.0, second, chroma),
+        4 => (second, 0.0, chroma),
+        _ => (chroma, 0.0, second),
+    };
+    let to_channel = |c: f64| ((c + value - chroma) * 255.0).round() as u8;
+    Rgba([to_channel(r), to_channel(g), to_channel(b), 255])
+}
+
+/// Linearly interpolate between two colors, where `t` of 0 is `from` and 1 is `to`
+pub(crate) fn lerp_color(from: Rgba<u8>, to: Rgba<u8>, t: f64) -> Rgba<u8> {
+    let t = t.clamp(0.0, 1.0);
+    let mut color = from;
+    for (channel, target) in color.0.iter_mut().zip(to.0.iter()) {
+        *channel = (*channel as f64 + (*target as f64 - *channel as f64) * t).round() as u8;
+    }
+    color
+}
+
+#[derive(Clone, PartialEq)]
+pub struct RenderOpts {
+    pub crosses: bool,
+    /// Color the inside of the shape by the field intensity instead of a flat color
+    pub gradient: bool,
+    /// The color the intensity gradient fades to towards the centers
+    pub gradient_color: Rgba<u8>,
+    pub on_color: Rgba<u8>,
+    pub off_color: Rgba<u8>,
+    pub cross_color: Rgba<u8>,
+    /// The shape of the center point indicators
+    pub cross_style: IndicatorStyle,
+    /// How many pixels the center point indicators reach out from the center
+    pub cross_radius: u32,
+    /// Field levels to draw contour lines at, none to draw no lines
+    pub isolines: Vec<f64>,
+    pub isoline_color: Rgba<u8>,
+    /// The color of the edge in the outline mode, the inside color if there isn't one
+    pub outline_color: Option<Rgba<u8>>,
+    /// How the field is turned into pixels
+    pub mode: RenderMode,
+    /// How many pixels wide the cells are when rendering with marching squares
+    pub cell_size: u32,
+    /// Supersampling factor for anti-aliasing, 1 renders each pixel once
+    pub ssaa: u32,
+    /// The part of the field that is visible
+    pub view: View,
+    /// An image to draw instead of the background color
+    pub background: Option<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+    /// Color the inside of the shape by mixing the colors of the metaballs
+    pub ball_colors: bool,
+    /// Cycling the inside color through the hues while animating, in place of the on color
+    pub hue_cycle: HueCycle,
+    /// The colors of the heatmap mode
+    pub colormap: colormap::Colormap,
+    /// Which field values the heatmap's colormap runs between
+    pub heat_scale: colormap::HeatScale,
+    /// Field levels and the colors to draw where the field is above them, in place of the threshold
+    /// and inside color. None draws the shape as usual.
+    pub bands: Bands,
+    /// The spacing of the grid that added metaballs are snapped to, if they're snapped
+    pub snap: Option<u32>,
+    /// Where the raymarched 3D view is seen from
+    pub camera: raymarch::Camera,
+    /// How precisely the field is evaluated at each pixel
+    pub precision: Precision,
+    /// Parameters to render the metaballs with again beside the scene, for comparing them
+    pub compare: Option<compare::Comparison>,
+    /// Make the background fully transparent in exported images, whatever its color, so the metaballs
+    /// composite cleanly onto other artwork
+    pub transparent_export: bool,
+    /// Draw a histogram of the field values across the frame over its corner, with the threshold marked
+    pub histogram: bool,
+}
+
+impl RenderOpts {
+    /// Snap a point to the nearest grid point within a `width` by `height` image, if snapping
+    pub fn snapped(&self, point: Pointf, width: u32, height: u32) -> Pointf {
+        let spacing = match self.snap {
+            Some(spacing) => spacing as f64,
+            None => return point,
+        };
+        let snap = |value: f64, extent: u32| {
+            let snapped = (value / spacing).round() * spacing;
+            // rounding up can land just past the edge
+            if snapped >= extent as f64 { (snapped - spacing).max(0.0) } else { snapped }
+        };
+        Pointf { x: snap(point.x, width), y: snap(point.y, height) }
+    }
+
+    /// The field point under a pixel of a `width × height` image, through the view or the pane of the
+    /// comparison the pixel is in
+    pub fn field_point(&self, x: f64, y: f64, width: u32, height: u32) -> (f64, f64) {
+        match self.compare {
+            Some(_) => compare::field_point(x, y, width, height, &self.view),
+            None => self.view.field_point(x, y),
+        }
+    }
+}
+
+impl RenderOpts {
+    /// The options images are exported with, the background color made fully transparent if asked for
+    pub fn export_opts(&self) -> RenderOpts {
+        let mut opts = self.clone();
+        if self.transparent_export {
+            opts.off_color[3] = 0;
+        }
+        opts
+    }
+
+    /// The flat color of the inside of the shape, which the gradient starts from
+    pub fn fill_color(&self) -> Rgba<u8> {
+        if self.hue_cycle.enabled {
+            let mut color = hsv_to_rgb(self.hue_cycle.hue, 1.0, 1.0);
+            color[3] = self.on_color[3];
+            color
+        } else {
+            self.on_color
+        }
+    }
+
+    /// Bands at several thresholds as concentric contours, shaded evenly from the background toward the
+    /// inside color so the highest level is drawn in it
+    pub fn threshold_bands(&self, levels: &[f64]) -> Bands {
+        let mut levels = levels.to_vec();
+        levels.sort_by(f64::total_cmp);
+        let count = levels.len() as f64;
+        levels.iter().enumerate().map(|(i, &level)| (level, lerp_color(self.off_color, self.fill_color(), (i + 1) as f64 / count))).collect()
+    }
+}
+
+/// The state of cycling a color through the hues
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct HueCycle {
+    pub enabled: bool,
+    /// The current hue in degrees
+    pub hue: f64,
+    /// How many degrees the hue moves each animation tick
+    pub speed: f64,
+}
+
+impl HueCycle {
+    /// Move on to the next hue
+    pub fn advance(&mut self) {
+        self.hue = (self.hue + self.speed).rem_euclid(360.0);
+    }
+}
+
+impl Default for RenderOpts {
+    fn default() -> Self {
+        RenderOpts {
+            crosses: false,
+            gradient: false,
+            gradient_color: GRADIENT_LOW_PIXEL,
+            on_color: ON_PIXEL,
+            off_color: OFF_PIXEL,
+            cross_color: CROSS_PIXEL,
+            cross_style: IndicatorStyle::Cross,
+            cross_radius: 1,
+            isolines: vec![],
+            isoline_color: ISOLINE_PIXEL,
+            outline_color: None,
+            mode: RenderMode::Naive,
+            cell_size: 4,
+            ssaa: 1,
+            view: View::default(),
+            background: None,
+            ball_colors: false,
+            hue_cycle: HueCycle { enabled: false, hue: 0.0, speed: 1.0 },
+            colormap: colormap::Colormap::Classic,
+            heat_scale: colormap::HeatScale::Threshold,
+            bands: vec![],
+            snap: None,
+            camera: raymarch::Camera::default(),
+            precision: Precision::F64,
+            compare: None,
+            transparent_export: false,
+            histogram: false,
+        }
+    }
+}
+
+/// The ways of turning the field into pixels
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum RenderMode {
+    /// Threshold the field at every pixel, see [naive_impl]
+    Naive,
+    /// Interpolate the field across cells, see [marching_squares_impl]
+    MarchingSquares,
+    /// Color every pixel by the field value, see [heatmap_impl]
+    Heatmap,
+    /// Raymarch the metaballs as balls in 3D, see [raymarch_impl](raymarch::raymarch_impl)
+    Raymarch,
+    /// Shade the shape as if it were glossy and lit, see [lit_impl](lit::lit_impl)
+    Lit,
+    /// Draw only the edge of the shape, see [outline_impl]
+    Outline,
+    /// Dither the field in two colors, see [dither_impl]
+    Dither,
+    /// Color the shape by which metaball adds the most to the field, see [regions_impl]
+    Regions,
+    /// Threshold the field like the naive mode, evaluating it only where the edge might be, see
+    /// [quadtree_impl](quadtree::quadtree_impl)
+    Quadtree,
+}
+
+impl FromStr for RenderMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "naive" => Ok(RenderMode::Naive),
+            "marching-squares" => Ok(RenderMode::MarchingSquares),
+            "heatmap" => Ok(RenderMode::Heatmap),
+            "raymarch" => Ok(RenderMode::Raymarch),
+            "lit" => Ok(RenderMode::Lit),
+            "outline" => Ok(RenderMode::Outline),
+            "dither" => Ok(RenderMode::Dither),
+            "regions" => Ok(RenderMode::Regions),
+            "quadtree" => Ok(RenderMode::Quadtree),
+            _ => Err(format!("Unknown render mode \"{}\", expected naive, marching-squares, heatmap, raymarch, lit, outline, dither, regions or quadtree", s)),
+        }
+    }
+}
+
+/// How precisely the field is evaluated when rendering
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Precision {
+    F64,
+    /// Single precision, comparing squared distances instead of taking square roots. It's often several
+    /// times faster for the summed field with no visible difference, and the smooth union is evaluated in
+    /// full either way.
+    F32,
+    /// The inverse power falloff looked up in a table of distances worked out whenever the goo changes,
+    /// instead of a `powf` per pixel. It differs from f64 by less than a thousandth, and the other falloffs
+    /// and the smooth union are evaluated in full.
+    Lut,
+}
+
+impl FromStr for Precision {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "f64" => Ok(Precision::F64),
+            "f32" => Ok(Precision::F32),
+            "lut" => Ok(Precision::Lut),
+            _ => Err(format!("Unknown precision \"{}\", expected f64, f32 or lut", s)),
+        }
+    }
+}
+
+/// The shape drawn at the center of each metaball
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum IndicatorStyle {
+    Cross,
+    Dot,
+    Ring,
+}
+
+impl IndicatorStyle {
+    /// The points relative to the center that make up the indicator
+    pub fn offsets(&self, radius: u32) -> Vec<RelPoint> {
+        let radius = radius as i64;
+        // pixels count as on a circle when they're within half a pixel of it
+        let outer = (radius as f64 + 0.5).powi(2);
+        let inner = (radius as f64 - 0.5).max(0.0).powi(2);
+        let mut offsets = vec![];
+        for y in -radius..=radius {
+            for x in -radius..=radius {
+                let squared = (x * x + y * y) as f64;
+                let on = match self {
+                    IndicatorStyle::Cross => x == 0 || y == 0,
+                    IndicatorStyle::Dot => squared <= outer,
+                    IndicatorStyle::Ring => squared <= outer && squared > inner,
+                };
+                if on {
+                    offsets.push(RelPoint { x, y });
+                }
+            }
+        }
+        offsets
+    }
+}
+
+impl FromStr for IndicatorStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cross" => Ok(IndicatorStyle::Cross),
+            "dot" => Ok(IndicatorStyle::Dot),
+            "ring" => Ok(IndicatorStyle::Ring),
+            _ => Err(format!("Unknown indicator style \"{}\", expected cross, dot or ring", s)),
+        }
+    }
+}
+
+/// Maps points on the screen to points in the metaball field, for panning and zooming
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct View {
+    /// The field point at the top left corner of the screen
+    pub x: f64,
+    pub y: f64,
+    /// How many screen pixels wide a unit of the field is
+    pub zoom: f64,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        View { x: 0.0, y: 0.0, zoom: 1.0 }
+    }
+}
+
+impl View {
+    /// The field point under a screen point
+    pub fn field_point(&self, x: f64, y: f64) -> (f64, f64) {
+        (self.x + x / self.zoom, self.y + y / self.zoom)
+    }
+
+    /// The screen point over a field point
+    pub fn screen_point(&self, x: f64, y: f64) -> (f64, f64) {
+        ((x - self.x) * self.zoom, (y - self.y) * self.zoom)
+    }
+
+    /// Zoom in by a factor, keeping the field point under the screen point in place
+    pub fn zoom_at(&mut self, factor: f64, x: f64, y: f64) {
+        let (field_x, field_y) = self.field_point(x, y);
+        self.zoom *= factor;
+        self.x = field_x - x / self.zoom;
+        self.y = field_y - y / self.zoom;
+    }
+
+    /// Move the view so the field follows a drag across the screen
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        self.x -= dx / self.zoom;
+        self.y -= dy / self.zoom;
+    }
+}
+
+/// Use the metaball formula to detect which pixels should be highlighted to create a metaball image,
+/// rendering it at the size of the RGBA screen buffer. Returns how long rendering took.
+pub fn render_metaballs(screenbuffer: &mut [u8], width: u32, height: u32, metaballs: &MetaballData, opts: &RenderOpts) -> Duration {
+    assert_eq!(
+        screenbuffer.len(),
+        width as usize * height as usize * 4,
+        "the screen buffer should hold {}x{} RGBA pixels",
+        width,
+        height
+    );
+    let start = Instant::now();
+    if opts.mode == RenderMode::Naive && opts.ssaa == 1 && opts.compare.is_none() {
+        // without supersampling there's nothing to shrink, so the pixels go straight into the buffer
+        naive_into(screenbuffer, width, height, 1, metaballs, opts);
+        log::debug!("Naive render of {} metaballs at {}x{} took {:.2}ms", metaballs.metaballs.len(), width, height, milliseconds_since(start));
+        let mut image = ImageBuffer::from_raw(width, height, screenbuffer).expect("the screen buffer was checked");
+        draw_indicators(&mut image, metaballs, opts);
+        if opts.histogram {
+            histogram::draw_histogram(&mut image, metaballs, opts);
+        }
+    } else {
+        let meta = render_image(width, height, metaballs, opts);
+
+        // copy to buffer
+        screenbuffer.copy_from_slice(meta.as_raw().as_slice());
+    }
+    start.elapsed()
+}
+
+/// Render a metaball image with everything the render options ask for on top of it
+pub fn render_image(width: u32, height: u32, metaballs: &MetaballData, opts: &RenderOpts) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    if let Some(comparison) = &opts.compare {
+        return compare::side_by_side(width, height, metaballs, comparison, opts);
+    }
+    // draw base metaballs, supersampling and shrinking back down if anti-aliasing
+    let factor = if opts.mode == RenderMode::Dither { 1 } else { opts.ssaa };
+    let start = Instant::now();
+    let mut meta = match opts.mode {
+        RenderMode::Naive => naive_impl(width * factor, height * factor, factor, metaballs, opts),
+        RenderMode::MarchingSquares => marching_squares_impl(width * factor, height * factor, factor, opts.cell_size * factor, metaballs, opts),
+        RenderMode::Heatmap => heatmap_impl(width * factor, height * factor, factor, metaballs, opts),
+        RenderMode::Raymarch => raymarch::raymarch_impl(width * factor, height * factor, metaballs, opts),
+        RenderMode::Lit => lit::lit_impl(width * factor, height * factor, factor, metaballs, opts),
+        RenderMode::Outline => outline_impl(width * factor, height * factor, factor, metaballs, opts),
+        RenderMode::Dither => dither_impl(width * factor, height * factor, factor, metaballs, opts),
+        RenderMode::Regions => regions_impl(width * factor, height * factor, factor, metaballs, opts),
+        RenderMode::Quadtree => quadtree::quadtree_impl(width * factor, height * factor, factor, metaballs, opts),
+    };
+    log::debug!(
+        "{:?} render of {} metaballs at {}x{} took {:.2}ms",
+        opts.mode,
+        metaballs.metaballs.len(),
+        width * factor,
+        height * factor,
+        milliseconds_since(start)
+    );
+    if factor > 1 {
+        let start = Instant::now();
+        meta = downsample(&meta, factor);
+        log::debug!("Shrinking it {} times took {:.2}ms", factor, milliseconds_since(start));
+    }
+
+    draw_indicators(&mut meta, metaballs, opts);
+    if opts.histogram {
+        histogram::draw_histogram(&mut meta, metaballs, opts);
+    }
+    meta
+}
+
+/// How long it's been since an instant, in milliseconds, for logging timings
+fn milliseconds_since(start: Instant) -> f64 {
+    start.elapsed().as_secs_f64() * 1000.0
+}
+
+/// Draw center point indicators over the metaballs, if the render options ask for them
+fn draw_indicators<C: DerefMut<Target = [u8]>>(meta: &mut ImageBuffer<Rgba<u8>, C>, metaballs: &MetaballData, opts: &RenderOpts) {
+    let (width, height) = meta.dimensions();
+    // the centers are only where they're drawn in the 2D views
+    if opts.crosses && opts.mode != RenderMode::Raymarch {
+        let offsets = opts.cross_style.offsets(opts.cross_radius);
+        for ball in &metaballs.metaballs {
+            let (x, y) = opts.view.screen_point(ball.location.x, ball.location.y);
+            // skip metaballs that are panned or zoomed off of the screen
+            if x < 0.0 || y < 0.0 || x >= width as f64 || y >= height as f64 {
+                continue;
+            }
+            // the indicators are drawn around the pixel the center is on
+            let center = Point { x: x as u32, y: y as u32 };
+            for modifier in &offsets {
+                // skip the parts of the indicator hanging off the edge
+                let (x, y) = (center.x as i64 + modifier.x, center.y as i64 + modifier.y);
+                if x >= 0 && y >= 0 && x < width as i64 && y < height as i64 {
+                    *meta.get_pixel_mut(x as u32, y as u32) = opts.cross_color;
+                }
+            }
+        }
+    }
+}
+
+/// Render a scene at its own size with the default render options, red metaballs on black
+///
+/// ```
+/// let scene = metaballs::preset("ring", 64, 64).unwrap();
+/// let image = metaballs::render_to_image(&scene);
+/// assert_eq!(image.dimensions(), (64, 64));
+/// ```
+pub fn render_to_image(metaballs: &MetaballData) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    render_image(metaballs.width, metaballs.height, metaballs, &RenderOpts::default())
+}
+
+/// How the metaballs are combined into one shape
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum BlendMode {
+    /// Sum `size / distance^goo` over the metaballs, the original metaball formula.
+    /// Metaballs reach out to each other with thin bridges well before they touch.
+    Additive,
+
+    /// Smoothly union the distances to each metaball's circle, so metaballs only merge once their
+    /// edges are within `k` pixels of each other, filling in the joint with a rounded fillet.
+    SmoothUnion { k: f64 },
+}
+
+/// Defines factors/exponents and positions for rendering a set of metaballs
+#[derive(Clone, PartialEq, Debug)]
+pub struct MetaballData {
+    pub goo: f64,
+    pub threshold: f64,
+    pub width: u32,
+    pub height: u32,
+    pub metaballs: Vec<Metaball>,
+    pub blend: BlendMode,
+    /// How what each metaball adds to the field falls off with distance
+    pub falloff: Falloff,
+    /// How many times wider than tall a pixel is displayed, distances are measured on the displayed
+    /// shape so metaballs stay round on non-square pixels
+    pub pixel_aspect: f64,
+    /// Wrap the field around the edges of the image, so the image tiles seamlessly
+    pub tile: bool,
+    /// How each metaball is mirrored or repeated around the middle of the image, see [set_symmetry](MetaballData::set_symmetry)
+    pub symmetry: Symmetry,
+    /// Noise that the field is multiplied by, wobbling the edges of the shape
+    pub noise: Option<Noise>,
+    /// How the metaballs move when animating in the physics mode
+    pub physics: Physics,
+    /// How each layer above the bottom one is combined with the layers under it, starting from layer 1,
+    /// see [layer_blend](MetaballData::layer_blend)
+    pub layers: Vec<LayerBlend>,
+}
+
+impl MetaballData {
+    /// Metaball data with the additive blend, the inverse power falloff, square pixels, no tiling, no
+    /// symmetry, no noise, the physics mode off and every layer summed
+    pub fn new(goo: f64, threshold: f64, width: u32, height: u32, metaballs: Vec<Metaball>) -> MetaballData {
+        MetaballData {
+            goo,
+            threshold,
+            width,
+            height,
+            metaballs,
+            blend: BlendMode::Additive,
+            falloff: Falloff::default(),
+            pixel_aspect: 1.0,
+            tile: false,
+            symmetry: Symmetry::None,
+            noise: None,
+            physics: Physics::default(),
+            layers: vec![],
+        }
+    }
+
+    /// A builder for putting a scene together a piece at a time, with the goo at 1.6 and the threshold at
+    /// 0.5 unless they're set, and the rest as [new](MetaballData::new) leaves it
+    pub fn builder() -> MetaballDataBuilder {
+        MetaballDataBuilder::default()
+    }
+
+    /// Generate a bunch of metaballs randomly, drawing from `rng`.
+    pub fn from_rng(goo: f64, threshold: f64, width: u32, height: u32, generation: &Generation, rng: &mut impl Rng) -> MetaballData {
+        let count = random_count_metaballs(generation, rng);
+        let mut metaballs = vec![];
+        for _ in 0..count {
+            metaballs.push(random_metaball(width, height, generation, rng))
+        }
+        MetaballData::new(goo, threshold, width, height, metaballs)
+    }
+
+    /// Set how quickly each metaball's field falls off with distance. It has to be positive, with no
+    /// fall off every pixel gets the same field from a metaball and the whole image is on or off.
+    pub fn set_goo(&mut self, goo: f64) -> Result<(), String> {
+        if !goo.is_finite() || goo <= 0.0 {
+            return Err("the goo has to be a positive number".to_string());
+        }
+        self.goo = goo;
+        Ok(())
+    }
+
+    /// Set the field value the shape's edge is at. It has to be positive, since the field is positive
+    /// everywhere and a threshold at or below 0 would make the whole image the inside of the shape.
+    pub fn set_threshold(&mut self, threshold: f64) -> Result<(), String> {
+        if !threshold.is_finite() || threshold <= 0.0 {
+            return Err("the threshold has to be a positive number".to_string());
+        }
+        self.threshold = threshold;
+        Ok(())
+    }
+
+    /// The field at a point of the scene, see [field_at]
+    pub fn field_at(&self, x: f64, y: f64) -> f64 {
+        field_at(x, y, self)
+    }
+
+    /// The field at every pixel of a `width × height` image of the scene, a row at a time from the top,
+    /// for shading or analysing the raw values without rendering an image. Each row is evaluated as it's
+    /// taken, the same as [field_grid] evaluates it in full precision without a view.
+    pub fn rows(&self, width: u32, height: u32) -> impl Iterator<Item = Vec<f64>> + '_ {
+        let view = View::default();
+        let grid = spatial::MetaballGrid::new(width, height, 1, &view, self);
+        let layers = self.layers_in_use();
+        (0..height).map(move |y| {
+            (0..width)
+                .map(|x| {
+                    let metaballs = grid.as_ref().map_or(&self.metaballs[..], |grid| grid.metaballs_at(x, y));
+                    let (px, py) = field_point(x, y, 1, &view);
+                    let field = field_of(px, py, metaballs, &layers, self);
+                    self.noise.map_or(field, |noise| field * noise.factor(px, py))
+                })
+                .collect()
+        })
+    }
+
+    /// Fit the scene to a new image size, moving the metaballs to the same place relative to the image and
+    /// scaling their sizes with the shorter side so they stay as big relative to the image, like the presets
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if (width, height) == (self.width, self.height) {
+            return;
+        }
+        let (x_scale, y_scale) = (width as f64 / self.width as f64, height as f64 / self.height as f64);
+        let size_scale = width.min(height) as f64 / self.width.min(self.height) as f64;
+        for metaball in &mut self.metaballs {
+            metaball.location.x = (metaball.location.x * x_scale).min((width - 1) as f64);
+            metaball.location.y = (metaball.location.y * y_scale).min((height - 1) as f64);
+            match &mut metaball.primitive {
+                Primitive::Point => {}
+                Primitive::Segment { end } => *end = Pointf { x: end.x * x_scale, y: end.y * y_scale },
+                Primitive::RoundedBox { half, corner } => {
+                    *half = (half.0 * x_scale, half.1 * y_scale);
+                    *corner *= size_scale;
+                }
+                Primitive::Ring { radius } => *radius *= size_scale,
+            }
+            metaball.size *= size_scale.powf(metaball.goo_or(self.goo));
+            metaball.radius = metaball.radius.map(|radius| radius * size_scale);
+        }
+        self.width = width;
+        self.height = height;
+        self.symmetrize();
+    }
+
+    /// Move each metaball to a random place, keeping everything else about them
+    pub fn randomize_locations(&mut self, generation: &Generation, rng: &mut impl Rng) {
+        for metaball in &mut self.metaballs {
+            metaball.move_to(random_location(self.width, self.height, generation, rng));
+        }
+        self.symmetrize();
+    }
+
+    /// Give each metaball a random size, keeping everything else about them, negative metaballs included
+    pub fn randomize_sizes(&mut self, generation: &Generation, rng: &mut impl Rng) {
+        for metaball in &mut self.metaballs {
+            // the random sizes are for the scene's goo, so one with its own goo is sized to reach as far
+            let size = random_size(generation, rng).powf(metaball.goo_or(self.goo) / self.goo);
+            metaball.size = size.copysign(metaball.size);
+        }
+        self.symmetrize();
+    }
+
+    /// Replace the metaballs with random ones, keeping the rest of the parameters
+    pub fn randomize(&mut self, generation: &Generation) {
+        self.randomize_with(generation, &mut rand::thread_rng());
+    }
+
+    /// Replace the metaballs with random ones drawn from `rng`, keeping the rest of the parameters. With a
+    /// symmetry, the random count is of all the copies.
+    pub fn randomize_with(&mut self, generation: &Generation, rng: &mut impl Rng) {
+        let metaballs = MetaballData::from_rng(self.goo, self.threshold, self.width, self.height, generation, rng).metaballs;
+        let copies = self.symmetry.copies();
+        self.metaballs = metaballs[..metaballs.len().div_ceil(copies)].iter().flat_map(|metaball| std::iter::repeat_n(*metaball, copies)).collect();
+        self.symmetrize();
+    }
+
+    /// Switch to the goo, threshold, metaballs, symmetry and layer blends of another scene, such as a preset
+    /// or a loaded one without a symmetry, keeping how the field is measured and blended
+    pub fn set_scene(&mut self, scene: MetaballData) {
+        self.goo = scene.goo;
+        self.threshold = scene.threshold;
+        self.metaballs = scene.metaballs;
+        self.symmetry = scene.symmetry;
+        self.layers = scene.layers;
+    }
+
+    /// The stdin commands that would recreate these parameters and metaballs, with each metaball's index
+    /// as a comment after it
+    pub fn commands(&self) -> Vec<String> {
+        let mut commands = vec![format!("g{}", self.goo), format!("t{}", self.threshold)];
+        if self.falloff != Falloff::default() {
+            commands.push(format!("falloff {}", self.falloff));
+        }
+        if let Some(noise) = self.noise {
+            commands.push(format!("noise {}", noise));
+        }
+        commands.extend(self.layer_commands(&[]));
+        commands.extend(self.metaball_commands());
+        commands
+    }
+
+    /// The stdin commands that blend each layer the way it's blended here rather than how it was before
+    fn layer_commands(&self, before: &[LayerBlend]) -> Vec<String> {
+        (1..=self.layers.len().max(before.len()))
+            .filter(|&layer| self.layer_blend(layer) != before.get(layer - 1).copied().unwrap_or_default())
+            .map(|layer| format!("layer-blend {} {}", layer, self.layer_blend(layer)))
+            .collect()
+    }
+
+    /// The stdin commands that add the metaballs to an empty scene, which with a symmetry sets it and adds
+    /// only the originals, since adding them adds the copies
+    fn metaball_commands(&self) -> Vec<String> {
+        let mut commands = vec![];
+        if self.symmetry != Symmetry::None {
+            commands.push(format!("symmetry {}", self.symmetry));
+        }
+        let copies = self.symmetry.copies();
+        let whole = self.metaballs.len() / copies * copies;
+        for (index, metaball) in self.metaballs.iter().enumerate().filter(|(index, _)| index % copies == 0 || *index >= whole) {
+            commands.extend(metaball.commands(index));
+        }
+        commands
+    }
+
+    /// The stdin commands that turn an earlier scene into this one, moving, adding or deleting a metaball
+    /// when that's all that changed and clearing them all away to add them again otherwise
+    pub fn commands_from(&self, before: &MetaballData) -> Vec<String> {
+        let mut commands = vec![];
+        if self.goo != before.goo {
+            commands.push(format!("g{}", self.goo));
+        }
+        if self.threshold != before.threshold {
+            commands.push(format!("t{}", self.threshold));
+        }
+        if self.falloff != before.falloff {
+            commands.push(format!("falloff {}", self.falloff));
+        }
+        if self.noise != before.noise {
+            commands.push(self.noise.map_or("noise off".to_string(), |noise| format!("noise {}", noise)));
+        }
+        commands.extend(self.layer_commands(&before.layers));
+        let (now, then) = (&self.metaballs, &before.metaballs);
+        let only_moved = now.len() == then.len() && now.iter().zip(then).all(|(now, then)| Metaball { location: then.location, ..*now } == *then);
+        let removed = (0..then.len()).find(|&index| now.len() + 1 == then.len() && now[..index] == then[..index] && now[index..] == then[index + 1..]);
+        if self.symmetry != before.symmetry {
+            commands.push("clear".to_string());
+            commands.extend(self.metaball_commands());
+        } else if only_moved {
+            for (index, (now, _)) in now.iter().zip(then).enumerate().filter(|(_, (now, then))| now.location != then.location) {
+                commands.push(format!("m {} {} {}", index, now.location.x, now.location.y));
+            }
+        } else if now.len() == then.len() + 1 && now[..then.len()] == then[..] {
+            commands.extend(now[then.len()].commands(then.len()));
+        } else if let Some(index) = removed {
+            commands.push(format!("d {}", index));
+        } else {
+            commands.push("clear".to_string());
+            commands.extend(self.metaball_commands());
+        }
+        commands
+    }
+
+    /// The index of the metaball whose center is closest to a point, if any are within `radius` of it
+    pub fn metaball_near(&self, point: Pointf, radius: f64) -> Option<usize> {
+        self.metaballs
+            .iter()
+            .map(|metaball| metaball.location.distance_to(point.x, point.y, 1.0))
+            .enumerate()
+            .filter(|(_, distance)| *distance <= radius)
+            .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+            .map(|(index, _)| index)
+    }
+
+    /// Move each metaball along its path or by its velocity, bouncing off the edges of the image or carried
+    /// over to the other side when the field wraps around, or by the physics when it's enabled, and swing
+    /// the sizes of the pulsing ones. Returns whether any metaball actually changed position or size.
+    pub fn step(&mut self) -> bool {
+        let mut moved = false;
+        for metaball in &mut self.metaballs {
+            if let Some(pulse) = &mut metaball.pulse {
+                metaball.size *= pulse.advance();
+                moved |= pulse.amplitude > 0.0;
+            }
+        }
+        if self.physics.enabled {
+            moved |= self.physics_step();
+            self.symmetrize();
+            return moved;
+        }
+        let tile = self.tile;
+        let travel = |pos: f64, velocity: &mut i64, bound: u32| {
+            if tile {
+                (pos + *velocity as f64).rem_euclid(bound as f64)
+            } else {
+                bounce(pos, velocity, bound)
+            }
+        };
+        for metaball in &mut self.metaballs {
+            if let Some(motion) = &mut metaball.motion {
+                motion.advance();
+                let location = motion.location();
+                moved |= location != metaball.location;
+                metaball.shift_to(location);
+            } else if let Some(velocity) = &mut metaball.velocity {
+                let location = Pointf {
+                    x: travel(metaball.location.x, &mut velocity.x, self.width),
+                    y: travel(metaball.location.y, &mut velocity.y, self.height),
+                };
+                moved |= location != metaball.location;
+                metaball.shift_to(location);
+            }
+        }
+        self.symmetrize();
+        moved
+    }
+
+    /// Accelerate each metaball by gravity and the pull of the others, then move it, bouncing off the
+    /// edges of the image
+    fn physics_step(&mut self) -> bool {
+        let MetaballData { goo, threshold, width, height, metaballs, physics, .. } = self;
+        // pick up the metaballs that were added or removed since the last tick
+        physics.bodies.truncate(metaballs.len());
+        for metaball in &metaballs[physics.bodies.len()..] {
+            physics.bodies.push(Body::at(metaball));
+        }
+
+        // the pull of each other metaball is the field it adds, which stops growing once inside its edge, and
+        // the push of a collision how far inside its edge the edge of this one is
+        let edges: Vec<f64> = metaballs.iter().map(|metaball| (metaball.size.abs() / *threshold).powf(1.0 / metaball.goo_or(*goo))).collect();
+        let accelerations: Vec<(f64, f64)> = metaballs.iter().enumerate()
+            .map(|(index, metaball)| {
+                let mut acceleration = (0.0, physics.gravity);
+                for (other_index, other) in metaballs.iter().enumerate() {
+                    if other_index == index {
+                        continue;
+                    }
+                    let (dx, dy) = (other.location.x - metaball.location.x, other.location.y - metaball.location.y);
+                    let distance = (dx * dx + dy * dy).sqrt();
+                    if distance == 0.0 {
+                        continue;
+                    }
+                    let (size, goo) = (other.size, other.goo_or(*goo));
+                    let radius = (size / *threshold).powf(1.0 / goo);
+                    let overlap = (edges[index] + edges[other_index] - distance).max(0.0);
+                    let pull = physics.attraction * size / distance.max(radius).powf(goo) - physics.collision * overlap;
+                    acceleration.0 += pull * dx / distance;
+                    acceleration.1 += pull * dy / distance;
+                }
+                acceleration
+            })
+            .collect();
+
+        let mut moved = false;
+        for ((body, metaball), (ax, ay)) in physics.bodies.iter_mut().zip(metaballs.iter_mut()).zip(accelerations) {
+            body.vx = (body.vx + ax) * physics.damping;
+            body.vy = (body.vy + ay) * physics.damping;
+            let speed = (body.vx * body.vx + body.vy * body.vy).sqrt();
+            if speed > MAX_PHYSICS_SPEED {
+                body.vx *= MAX_PHYSICS_SPEED / speed;
+                body.vy *= MAX_PHYSICS_SPEED / speed;
+            }
+            let location = Pointf {
+                x: collide(metaball.location.x + body.vx, &mut body.vx, *width, physics.damping),
+                y: collide(metaball.location.y + body.vy, &mut body.vy, *height, physics.damping),
+            };
+            moved |= location != metaball.location;
+            metaball.shift_to(location);
+        }
+        moved
+    }
+}
+
+/// Puts a [MetaballData] together, see [builder](MetaballData::builder). The size has to be set, and
+/// [build](MetaballDataBuilder::build) checks it along with the goo and the threshold.
+#[derive(Clone, Debug)]
+pub struct MetaballDataBuilder {
+    goo: f64,
+    threshold: f64,
+    width: u32,
+    height: u32,
+    metaballs: Vec<Metaball>,
+    blend: BlendMode,
+    falloff: Falloff,
+    tile: bool,
+}
+
+impl Default for MetaballDataBuilder {
+    fn default() -> MetaballDataBuilder {
+        MetaballDataBuilder {
+            goo: 1.6,
+            threshold: 0.5,
+            width: 0,
+            height: 0,
+            metaballs: vec![],
+            blend: BlendMode::Additive,
+            falloff: Falloff::default(),
+            tile: false,
+        }
+    }
+}
+
+impl MetaballDataBuilder {
+    /// The size of the image the scene is laid out on, in pixels
+    pub fn size(mut self, width: u32, height: u32) -> MetaballDataBuilder {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    pub fn goo(mut self, goo: f64) -> MetaballDataBuilder {
+        self.goo = goo;
+        self
+    }
+
+    pub fn threshold(mut self, threshold: f64) -> MetaballDataBuilder {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Add a round metaball at a point, see [Metaball::new]
+    pub fn ball(self, x: f64, y: f64, size: f64) -> MetaballDataBuilder {
+        self.metaball(Metaball::new(Pointf { x, y }, size))
+    }
+
+    /// Add a metaball set up any way
+    pub fn metaball(mut self, metaball: Metaball) -> MetaballDataBuilder {
+        self.metaballs.push(metaball);
+        self
+    }
+
+    pub fn blend(mut self, blend: BlendMode) -> MetaballDataBuilder {
+        self.blend = blend;
+        self
+    }
+
+    pub fn falloff(mut self, falloff: Falloff) -> MetaballDataBuilder {
+        self.falloff = falloff;
+        self
+    }
+
+    pub fn tile(mut self, tile: bool) -> MetaballDataBuilder {
+        self.tile = tile;
+        self
+    }
+
+    /// The scene, or what's wrong with it if the size is 0 or the goo or the threshold isn't positive
+    pub fn build(self) -> Result<MetaballData, String> {
+        if self.width == 0 || self.height == 0 {
+            return Err(format!("the size has to be at least 1x1, not {}x{}", self.width, self.height));
+        }
+        let mut metaball_data = MetaballData::new(1.0, 1.0, self.width, self.height, self.metaballs);
+        metaball_data.set_goo(self.goo)?;
+        metaball_data.set_threshold(self.threshold)?;
+        metaball_data.blend = self.blend;
+        metaball_data.falloff = self.falloff;
+        metaball_data.tile = self.tile;
+        Ok(metaball_data)
+    }
+}
+
+/// Settings for the physics mode, where the metaballs fall, bounce off the edges of the image and pull on
+/// each other so they settle and merge. Each force is off at 0.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Physics {
+    pub enabled: bool,
+    /// How many pixels per tick the metaballs speed up downwards by each tick, upwards when negative
+    pub gravity: f64,
+    /// The fraction of their speed the metaballs keep each tick and off each bounce, within [0, 1]
+    pub damping: f64,
+    /// How strongly the metaballs pull on each other, scaling the field each adds at the others' centers.
+    /// They push each other away when it's negative.
+    pub attraction: f64,
+    /// How stiffly the metaballs push apart where they overlap, speeding up by this fraction of how far
+    /// their edges overlap each tick, like springs between them. At 0 they pass through each other and merge.
+    pub collision: f64,
+    /// The velocities of the metaballs, which unlike their own velocities can be fractions of a pixel
+    bodies: Vec<Body>,
+}
+
+impl Default for Physics {
+    fn default() -> Physics {
+        Physics { enabled: false, gravity: 0.2, damping: 0.98, attraction: 0.5, collision: 0.0, bodies: Vec::new() }
+    }
+}
+
+/// A metaball's velocity in the physics mode
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct Body {
+    vx: f64,
+    vy: f64,
+}
+
+impl Body {
+    /// A body moving at the metaball's velocity, if it has one
+    pub fn at(metaball: &Metaball) -> Body {
+        let velocity = metaball.velocity.unwrap_or(RelPoint { x: 0, y: 0 });
+        Body { vx: velocity.x as f64, vy: velocity.y as f64 }
+    }
+}
+
+/// Keeps a coordinate within \[0, bound - 1\] by reflecting it and its velocity off either edge, losing
+/// speed by `damping` on the bounce
+fn collide(pos: f64, velocity: &mut f64, bound: u32, damping: f64) -> f64 {
+    let max = (bound - 1) as f64;
+    if pos < 0.0 {
+        *velocity = -*velocity * damping;
+        (-pos).min(max)
+    } else if pos > max {
+        *velocity = -*velocity * damping;
+        (2.0 * max - pos).max(0.0)
+    } else {
+        pos
+    }
+}
+
+/// A transition from one scene to another over a number of animation ticks. Metaballs are matched up by
+/// index, the ones without a partner in the other scene growing from or shrinking to nothing in place.
+pub struct Tween {
+    from: MetaballData,
+    to: MetaballData,
+    frame: u32,
+    frames: u32,
+}
+
+impl Tween {
+    pub fn new(from: MetaballData, to: MetaballData, frames: u32) -> Tween {
+        Tween { from, to, frame: 0, frames }
+    }
+
+    /// Advance a frame, returning the scene in between. The last frame is exactly the target scene.
+    pub fn step(&mut self) -> MetaballData {
+        self.frame += 1;
+        if self.finished() {
+            return self.to.clone();
+        }
+        interpolate(&self.from, &self.to, self.frame as f64 / self.frames as f64)
+    }
+
+    /// Whether the tween has reached the target scene
+    pub fn finished(&self) -> bool {
+        self.frame >= self.frames
+    }
+}
+
+/// Eases the goo and threshold a scene is shown with towards the scene's own whenever they change, instead
+/// of snapping to them, slowing down towards the end over a set time. Changing them again on the way starts
+/// over from wherever they've got to, and since the easing starts off fast, a slider being dragged or an
+/// animation changing them every frame isn't held back by much.
+#[derive(Clone, Debug)]
+pub struct Easing {
+    duration: Duration,
+    /// The goo and threshold being eased from, when the change started
+    from: (f64, f64),
+    changed: Instant,
+    /// The goo and threshold being eased to, unless none have been shown yet
+    to: Option<(f64, f64)>,
+    /// The goo and threshold that were shown last
+    shown: (f64, f64),
+}
+
+impl Easing {
+    pub fn new(duration: Duration) -> Easing {
+        Easing { duration, from: (0.0, 0.0), changed: Instant::now(), to: None, shown: (0.0, 0.0) }
+    }
+
+    /// The goo and threshold to show a scene with now
+    pub fn shown(&mut self, metaball_data: &MetaballData) -> (f64, f64) {
+        self.shown_at(metaball_data, Instant::now())
+    }
+
+    fn shown_at(&mut self, metaball_data: &MetaballData, now: Instant) -> (f64, f64) {
+        let target = (metaball_data.goo, metaball_data.threshold);
+        if self.to.is_none() {
+            self.shown = target;
+        }
+        if self.to != Some(target) {
+            self.from = self.shown;
+            self.changed = now;
+            self.to = Some(target);
+        }
+        let t = if self.duration.is_zero() { 1.0 } else { (now.duration_since(self.changed).as_secs_f64() / self.duration.as_secs_f64()).min(1.0) };
+        let eased = 1.0 - (1.0 - t).powi(3);
+        self.shown = if t < 1.0 {
+            (self.from.0 + (target.0 - self.from.0) * eased, self.from.1 + (target.1 - self.from.1) * eased)
+        } else {
+            target
+        };
+        self.shown
+    }
+
+    /// Whether the goo and threshold shown last aren't the scene's yet, so there's more to ease
+    pub fn easing(&self) -> bool {
+        self.to.is_some_and(|to| to != self.shown)
+    }
+}
+
+/// The scene a fraction `t` of the way from one scene to another, with the metaballs matched up the same
+/// way as a [Tween]'s. Everything but the goo, threshold and metaballs is the target's.
+pub fn interpolate(from: &MetaballData, to: &MetaballData, t: f64) -> MetaballData {
+    let lerp = |from: f64, to: f64| from + (to - from) * t;
+    let count = from.metaballs.len().max(to.metaballs.len());
+    let metaballs = (0..count)
+        .map(|index| {
+            let (from, to) = match (from.metaballs.get(index), to.metaballs.get(index)) {
+                (Some(from), Some(to)) => (*from, *to),
+                (Some(from), None) => (*from, Metaball { size: 0.0, ..*from }),
+                (None, Some(to)) => (Metaball { size: 0.0, ..*to }, *to),
+                (None, None) => unreachable!("index is within the longer scene"),
+            };
+            Metaball {
+                location: Pointf {
+                    x: lerp(from.location.x, to.location.x),
+                    y: lerp(from.location.y, to.location.y),
+                },
+                size: lerp(from.size, to.size),
+                axes: (lerp(from.axes.0, to.axes.0), lerp(from.axes.1, to.axes.1)),
+                angle: lerp(from.angle, to.angle),
+                // a point grows into a metaline, a box or a ring or shrinks back from one, as if it were one of
+                // no size, and the other shapes turn into each other at the end
+                primitive: match (from.primitive, to.primitive) {
+                    (Primitive::Point, Primitive::Point) => Primitive::Point,
+                    (Primitive::RoundedBox { .. } | Primitive::Point, Primitive::RoundedBox { .. } | Primitive::Point) => {
+                        let box_of = |primitive| match primitive {
+                            Primitive::RoundedBox { half, corner } => (half, corner),
+                            _ => ((0.0, 0.0), 0.0),
+                        };
+                        let ((from_half, from_corner), (to_half, to_corner)) = (box_of(from.primitive), box_of(to.primitive));
+                        Primitive::RoundedBox { half: (lerp(from_half.0, to_half.0), lerp(from_half.1, to_half.1)), corner: lerp(from_corner, to_corner) }
+                    }
+                    (Primitive::Ring { .. } | Primitive::Point, Primitive::Ring { .. } | Primitive::Point) => {
+                        let radius_of = |primitive| if let Primitive::Ring { radius } = primitive { radius } else { 0.0 };
+                        Primitive::Ring { radius: lerp(radius_of(from.primitive), radius_of(to.primitive)) }
+                    }
+                    (Primitive::Segment { .. } | Primitive::Point, Primitive::Segment { .. } | Primitive::Point) => {
+                        Primitive::Segment { end: Pointf { x: lerp(from.end().x, to.end().x), y: lerp(from.end().y, to.end().y) } }
+                    }
+                    (_, primitive) => primitive,
+                },
+                ..to
+            }
+        })
+        .collect();
+    MetaballData {
+        goo: lerp(from.goo, to.goo),
+        threshold: lerp(from.threshold, to.threshold),
+        metaballs,
+        ..to.clone()
+    }
+}
+
+/// Moves a coordinate by a velocity, keeping it within \[0, bound) by reflecting the velocity off either edge.
+fn bounce(pos: f64, velocity: &mut i64, bound: u32) -> f64 {
+    let next = pos + *velocity as f64;
+    if next < 0.0 || next >= bound as f64 {
+        *velocity = -*velocity;
+        (pos + *velocity as f64).clamp(0.0, (bound - 1) as f64)
+    } else {
+        next
+    }
+}
+
+/// A hand written or saved scene, in TOML such as
+///
+/// ```toml
+/// goo = 1.6
+/// threshold = 0.5
+///
+/// [[metaballs]]
+/// x = 100
+/// y = 128
+/// size = 40.0
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SceneConfig {
+    #[serde(default = "default_goo")]
+    pub goo: f64,
+    #[serde(default = "default_threshold")]
+    pub threshold: f64,
+    /// How each layer above the bottom one is combined with the layers under it, starting from layer 1
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<LayerBlend>,
+    #[serde(default)]
+    pub metaballs: Vec<MetaballConfig>,
+}
+
+/// A metaball in a [SceneConfig]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct MetaballConfig {
+    pub x: f64,
+    pub y: f64,
+    pub size: f64,
+    /// How far the metaball reaches, it adds nothing to the field any further away
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub radius: Option<f64>,
+    /// The goo of this metaball, in place of the scene's
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goo: Option<f64>,
+    /// Hex color for when each metaball is colored separately
+    #[serde(default, deserialize_with = "deserialize_color", serialize_with = "serialize_color", skip_serializing_if = "Option::is_none")]
+    pub color: Option<Rgba<u8>>,
+    /// How many times further the metaball reaches along its own x and y axes, stretching it into an ellipse
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub axes: Option<(f64, f64)>,
+    /// How far the metaball's axes are turned clockwise, in degrees
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub angle: Option<f64>,
+    /// The other end of a metaline, measuring the field from the line to it rather than just the center
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<(f64, f64)>,
+    /// How far a rounded box reaches along the metaball's axes either way and the radius of its corners,
+    /// measuring the field from the box rather than the center
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rounded_box: Option<(f64, f64, f64)>,
+    /// The radius of a ring around the center, measuring the field from the ring
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ring: Option<f64>,
+    /// Which layer the metaball is blended on, the bottom one if not given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layer: Option<usize>,
+    /// The amplitude, frequency and phase of the metaball's size swinging around the resting size when
+    /// animating, see [Pulse]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pulse: Option<(f64, f64, f64)>,
+}
+
+/// Deserialize an optional hex color, see [parse_color]
+fn deserialize_color<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Rgba<u8>>, D::Error> {
+    let hex = String::deserialize(deserializer)?;
+    parse_color(&hex).map(Some).map_err(serde::de::Error::custom)
+}
+
+/// Serialize an optional color as hex, only called for the colors there are
+fn serialize_color<S: serde::Serializer>(color: &Option<Rgba<u8>>, serializer: S) -> Result<S::Ok, S::Error> {
+    match color {
+        Some(color) => serializer.serialize_str(&hex_color(*color)),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn default_goo() -> f64 {
+    1.6
+}
+
+fn default_threshold() -> f64 {
+    0.5
+}
+
+impl SceneConfig {
+    /// Read and parse a TOML scene
+    pub fn load(path: &Path) -> Result<SceneConfig, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        toml::from_str(&text).map_err(|err| err.to_string())
+    }
+
+    /// Write the scene to a TOML file
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = toml::to_string(self).map_err(|err| err.to_string())?;
+        std::fs::write(path, text).map_err(|err| err.to_string())
+    }
+
+    /// The scene of some metaball data, for saving it to load again later
+    pub fn from_data(data: &MetaballData) -> SceneConfig {
+        SceneConfig {
+            goo: data.goo,
+            threshold: data.threshold,
+            layers: data.layers.clone(),
+            metaballs: data
+                .metaballs
+                .iter()
+                .map(|metaball| MetaballConfig {
+                    x: metaball.location.x,
+                    y: metaball.location.y,
+                    size: metaball.rest_size(),
+                    radius: metaball.radius,
+                    goo: metaball.goo,
+                    color: Some(metaball.color).filter(|&color| color != ON_PIXEL),
+                    axes: Some(metaball.axes).filter(|_| !metaball.is_round()),
+                    angle: Some(metaball.angle).filter(|&angle| angle != 0.0),
+                    end: match metaball.primitive {
+                        Primitive::Segment { end } => Some((end.x, end.y)),
+                        _ => None,
+                    },
+                    rounded_box: match metaball.primitive {
+                        Primitive::RoundedBox { half, corner } => Some((half.0, half.1, corner)),
+                        _ => None,
+                    },
+                    ring: match metaball.primitive {
+                        Primitive::Ring { radius } => Some(radius),
+                        _ => None,
+                    },
+                    layer: Some(metaball.layer).filter(|&layer| layer != 0),
+                    pulse: metaball.pulse.map(|pulse| (pulse.amplitude, pulse.frequency, pulse.phase)),
+                })
+                .collect(),
+        }
+    }
+
+    /// Turn the scene into metaball data for an image, checking that every metaball is on it
+    pub fn into_data(self, width: u32, height: u32) -> Result<MetaballData, String> {
+        let mut metaballs = vec![];
+        for (index, metaball) in self.metaballs.into_iter().enumerate() {
+            if !(0.0..width as f64).contains(&metaball.x) || !(0.0..height as f64).contains(&metaball.y) {
+                return Err(format!(
+                    "metaball {} at ({}, {}) is outside of the {}x{} image",
+                    index, metaball.x, metaball.y, width, height
+                ));
+            }
+            if metaball.radius.is_some_and(|radius| radius.is_nan() || radius <= 0.0) {
+                return Err(format!("metaball {} needs a positive radius", index));
+            }
+            if metaball.goo.is_some_and(|goo| !goo.is_finite() || goo <= 0.0) {
+                return Err(format!("metaball {} needs a positive goo", index));
+            }
+            if metaball.layer.is_some_and(|layer| layer > MAX_LAYER) {
+                return Err(format!("metaball {} needs to be on a layer no higher than {}", index, MAX_LAYER));
+            }
+            if metaball.axes.is_some_and(|(x, y)| !(x.is_finite() && y.is_finite() && x > 0.0 && y > 0.0)) {
+                return Err(format!("metaball {} needs positive axes", index));
+            }
+            if [metaball.end.is_some(), metaball.rounded_box.is_some(), metaball.ring.is_some()].iter().filter(|&&shape| shape).count() > 1 {
+                return Err(format!("metaball {} can only be one of a metaline, a rounded box or a ring", index));
+            }
+            if metaball.rounded_box.is_some_and(|(x, y, corner)| !(x.is_finite() && y.is_finite() && x > 0.0 && y > 0.0 && (0.0..=x.min(y)).contains(&corner))) {
+                return Err(format!("metaball {} needs a rounded box of positive half sizes with a corner radius no bigger than them", index));
+            }
+            if metaball.ring.is_some_and(|radius| !radius.is_finite() || radius <= 0.0) {
+                return Err(format!("metaball {} needs a ring of positive radius", index));
+            }
+            if metaball.pulse.is_some_and(|pulse| Pulse::check(pulse.0, pulse.1, pulse.2).is_err()) {
+                return Err(format!("metaball {} needs a pulse with an amplitude within [0, 1), a positive frequency and a phase", index));
+            }
+            let primitive = match (metaball.end, metaball.rounded_box, metaball.ring) {
+                (Some((x, y)), ..) => Primitive::Segment { end: Pointf { x, y } },
+                (_, Some((x, y, corner)), _) => Primitive::RoundedBox { half: (x, y), corner },
+                (.., Some(radius)) => Primitive::Ring { radius },
+                _ => Primitive::Point,
+            };
+            let mut added = Metaball {
+                color: metaball.color.unwrap_or(ON_PIXEL),
+                radius: metaball.radius,
+                goo: metaball.goo,
+                axes: metaball.axes.unwrap_or((1.0, 1.0)),
+                angle: metaball.angle.unwrap_or(0.0),
+                primitive,
+                layer: metaball.layer.unwrap_or(0),
+                ..Metaball::new(Pointf { x: metaball.x, y: metaball.y }, metaball.size)
+            };
+            added.set_pulse(metaball.pulse.map(|(amplitude, frequency, phase)| Pulse::new(amplitude, frequency, phase)));
+            metaballs.push(added);
+        }
+        if self.layers.len() > MAX_LAYER {
+            return Err(format!("there are only layers up to {} to blend", MAX_LAYER));
+        }
+        let mut data = MetaballData::new(1.6, 0.5, width, height, metaballs);
+        data.layers = self.layers;
+        let (goo, threshold) = (self.goo, self.threshold);
+        data.set_goo(goo).map_err(|err| format!("goo {}: {}", goo, err))?;
+        data.set_threshold(threshold).map_err(|err| format!("threshold {}: {}", threshold, err))?;
+        Ok(data)
+    }
+}
+
+/// Lays metaballs out the same way every time for an image of a given width and height
+pub type Preset = fn(u32, u32) -> MetaballData;
+
+/// The named scenes
+pub const PRESETS: &[(&str, Preset)] = &[
+    ("binary", binary_preset),
+    ("ring", ring_preset),
+    ("grid", grid_preset),
+    ("line", line_preset),
+];
+
+/// The preset scene with a name, or an error listing the presets there are
+pub fn preset(name: &str, width: u32, height: u32) -> Result<MetaballData, String> {
+    match PRESETS.iter().find(|(preset, _)| *preset == name) {
+        Some((_, scene)) => Ok(scene(width, height)),
+        None => {
+            let names: Vec<&str> = PRESETS.iter().map(|(preset, _)| *preset).collect();
+            Err(format!("Unknown preset \"{}\", the presets are {}", name, names.join(", ")))
+        }
+    }
+}
+
+/// Metaball data for a preset from field points relative to the image size and sizes relative to a
+/// 256x256 image, with the metaballs' colors spread around the color wheel
+fn preset_data(width: u32, height: u32, metaballs: &[(f64, f64, f64)]) -> MetaballData {
+    let goo = 1.6;
+    // the field falls off with distance to the goo, so this keeps the shapes the same relative size
+    let size_scale = (width.min(height) as f64 / 256.0).powf(goo);
+    let metaballs = metaballs
+        .iter()
+        .enumerate()
+        .map(|(index, (x, y, size))| Metaball {
+            color: hsv_to_rgb(index as f64 * 360.0 / metaballs.len() as f64, 0.8, 1.0),
+            ..Metaball::new(Pointf { x: x * width as f64, y: y * height as f64 }, size * size_scale)
+        })
+        .collect();
+    MetaballData::new(goo, 0.5, width, height, metaballs)
+}
+
+/// Two metaballs side by side, just far enough apart to be joined by a neck
+fn binary_preset(width: u32, height: u32) -> MetaballData {
+    preset_data(width, height, &[(0.38, 0.5, 75.0), (0.62, 0.5, 75.0)])
+}
+
+/// Eight metaballs in a circle around the center
+fn ring_preset(width: u32, height: u32) -> MetaballData {
+    let metaballs: Vec<_> = (0..8)
+        .map(|i| {
+            let angle = i as f64 * std::f64::consts::TAU / 8.0;
+            (0.5 + 0.3 * angle.cos(), 0.5 + 0.3 * angle.sin(), 30.0)
+        })
+        .collect();
+    preset_data(width, height, &metaballs)
+}
+
+/// Nine metaballs in a three by three grid
+fn grid_preset(width: u32, height: u32) -> MetaballData {
+    let metaballs: Vec<_> = (1..=3)
+        .flat_map(|y| (1..=3).map(move |x| (x as f64 / 4.0, y as f64 / 4.0, 25.0)))
+        .collect();
+    preset_data(width, height, &metaballs)
+}
+
+/// Five metaballs in a row across the middle, merged into one long shape
+fn line_preset(width: u32, height: u32) -> MetaballData {
+    let metaballs: Vec<_> = (1..=5).map(|x| (x as f64 / 6.0, 0.5, 40.0)).collect();
+    preset_data(width, height, &metaballs)
+}
+
+/// How random scenes are generated
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Generation {
+    /// The centers are kept inside of the image inset by this times the width and height on each side
+    pub margin: f64,
+    pub base_size: f64,
+    pub min_count: u32,
+    /// The rate of the exponential distribution of how many metaballs there are past the minimum
+    pub count_factor: f64,
+    /// An exact number of metaballs for every scene, instead of a random number past the minimum
+    pub count: Option<u32>,
+    /// The `inner` of the [centered_random] the metaballs' sizes are scaled by
+    pub size_spread: f64,
+    /// The chance of each metaball being negative, within \[0, 1\]
+    pub negative_fraction: f64,
+}
+
+impl Default for Generation {
+    fn default() -> Generation {
+        Generation {
+            margin: 0.0,
+            base_size: BASE_METABALL_SIZE,
+            min_count: MIN_METABALL_COUNT,
+            count_factor: 0.5,
+            count: None,
+            size_spread: 0.5,
+            negative_fraction: 0.0,
+        }
+    }
+}
+
+/// Calculates the number of metaballs using RNG
+fn random_count_metaballs(generation: &Generation, rng: &mut impl Rng) -> u32 {
+    if let Some(count) = generation.count {
+        return count;
+    }
+    let extra = random_exponential_distribution(generation.count_factor, rng).floor() as u32;
+    extra.saturating_add(generation.min_count).min(MAX_METABALL_COUNT)
+}
+
+/// Generates a random metaball for an image, the way [MetaballData::from_rng] does
+pub fn random_metaball(width: u32, height: u32, generation: &Generation, rng: &mut impl Rng) -> Metaball {
+    let location = random_location(width, height, generation, rng);
+    let mut size = random_size(generation, rng);
+    // scenes without negative metaballs don't roll for them, so they come out the same as they always have
+    if generation.negative_fraction > 0.0 && rng.gen_bool(generation.negative_fraction) {
+        size = -size;
+    }
+    Metaball {
+        size,
+        location,
+        velocity: Some(random_velocity(rng)),
+        color: hsv_to_rgb(rng.gen::<f64>() * 360.0, 0.8, 1.0),
+        radius: None,
+        goo: None,
+        motion: None,
+        pulse: None,
+        axes: (1.0, 1.0),
+        angle: 0.0,
+        primitive: Primitive::Point,
+        layer: 0,
+    }
+}
+
+/// Generates a random metaball center, kept inside of the image inset by the margin on each side
+fn random_location(width: u32, height: u32, generation: &Generation, rng: &mut impl Rng) -> Pointf {
+    let margin = generation.margin;
+    let mut inset = |extent: u32| extent as f64 * margin + extent as f64 * (1.0 - 2.0 * margin) * centered_random(0.5, rng);
+    Pointf {
+        x: inset(width),
+        y: inset(height),
+    }
+}
+
+/// Generates a random metaball size
+fn random_size(generation: &Generation, rng: &mut impl Rng) -> f64 {
+    centered_random(generation.size_spread, rng) * generation.base_size
+}
+
+/// Generates a random velocity with each component within \[-MAX_METABALL_SPEED, MAX_METABALL_SPEED\]
+fn random_velocity(rng: &mut impl Rng) -> RelPoint {
+    RelPoint {
+        x: rng.gen_range(-MAX_METABALL_SPEED..=MAX_METABALL_SPEED),
+        y: rng.gen_range(-MAX_METABALL_SPEED..=MAX_METABALL_SPEED),
+    }
+}
+
+/// Generates a random number following an exponential distribution.
+/// This would be like the number of coin flips if on heads flip again, if tails halt.
+fn random_exponential_distribution(factor: f64, rng: &mut impl Rng) -> f64 {
+    let random = rng.gen::<f64>();
+    f64::ln(1f64 - random) / (-factor)
+}
+
+/// Generates a random number that will be within \[inner / 2, inner * 1.5\]
+///
+/// Example:
+/// ```ignore
+/// let mut rng = rand::thread_rng();
+/// for _ in 0..1000 {
+///     let num = centered_random(0.5, &mut rng);
+///     assert!(num >= 0.25 && num <= 0.75);
+/// }
+/// ```
+fn centered_random(inner: f64, rng: &mut impl Rng) -> f64 {
+    assert!(inner < 1.0 && inner > 0.0, "Inner should be within (0, 1)");
+    let random = rng.gen::<f64>();
+    random * inner + (inner / 2.0)
+}
+
+/// Represents a metaball position and size.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Metaball {
+    pub location: Pointf,
+    /// How much the metaball adds to the field. A negative metaball takes away from it instead, carving a
+    /// hole out of the shape.
+    pub size: f64,
+    /// Pixels moved per animation tick, if the metaball moves at all
+    pub velocity: Option<RelPoint>,
+    /// The color of the metaball when coloring each metaball separately
+    pub color: Rgba<u8>,
+    /// How far from its center the metaball adds to the field, if it doesn't reach forever. Metaballs
+    /// further apart than this never merge.
+    pub radius: Option<f64>,
+    /// How quickly this metaball's field falls off, in place of the scene's goo. Higher is a sharper edge.
+    pub goo: Option<f64>,
+    /// The path the metaball loops around when animating, in place of its velocity. The physics mode
+    /// ignores it.
+    pub motion: Option<Motion>,
+    /// How the metaball's size swings around its resting size when animating, if it does
+    pub pulse: Option<Pulse>,
+    /// How many times further the metaball reaches along its own x and y axes than a round one would,
+    /// stretching it into an ellipse. The 3D views leave it round.
+    pub axes: (f64, f64),
+    /// How far the metaball's axes are turned clockwise from the image's, in degrees
+    pub angle: f64,
+    /// What the field is measured from, the center or a line from it
+    pub primitive: Primitive,
+    /// Which layer the metaball is blended on, 0 being the bottom one, see [layer](crate::layer). The 3D
+    /// views sum all the layers.
+    pub layer: usize,
+}
+
+/// The shape a metaball's field is measured from, so a scene can mix round metaballs with elongated ones
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum Primitive {
+    /// The metaball's location
+    #[default]
+    Point,
+    /// A "metaline", the line segment from the metaball's location to an end, which makes a capsule
+    Segment { end: Pointf },
+    /// A rectangle around the metaball's location reaching `half` as far along its own axes either way,
+    /// turned by its angle, with its corners rounded off by a radius
+    RoundedBox { half: (f64, f64), corner: f64 },
+    /// A circle around the metaball's location, which makes a ring of goo with a hole in the middle
+    Ring { radius: f64 },
+}
+
+/// Anything adding to the field, which a scene sums or blends with everything else under the same
+/// threshold. Every shape of a scene is a [Metaball], mixing round ones with the other implicit shapes by
+/// what its [Primitive] measures the field from.
+pub trait FieldSource {
+    /// What the source adds to the field of a scene at a point, nothing past its radius of influence
+    fn contribution(&self, point: Pointf, metaball_data: &MetaballData) -> f64;
+}
+
+impl FieldSource for Metaball {
+    fn contribution(&self, point: Pointf, metaball_data: &MetaballData) -> f64 {
+        let distance = field_distance(self, point.x, point.y, metaball_data);
+        if !self.reaches(distance) {
+            return 0.0;
+        }
+        metaball_data.falloff.function().value(self.size, distance, self.goo_or(metaball_data.goo))
+    }
+}
+
+impl Metaball {
+    /// A still metaball in the default foreground color, reaching forever
+    pub fn new(location: Pointf, size: f64) -> Metaball {
+        Metaball { location, size, velocity: None, color: ON_PIXEL, radius: None, goo: None, motion: None, pulse: None, axes: (1.0, 1.0), angle: 0.0, primitive: Primitive::Point, layer: 0 }
+    }
+
+    /// Whether the metaball is a circle rather than stretched into an ellipse
+    pub fn is_round(&self) -> bool {
+        self.axes == (1.0, 1.0)
+    }
+
+    /// The distance from the center to a point as the field measures it, on pixels `aspect` times wider than
+    /// they are tall. It's the quadratic form of the ellipse, so a stretched metaball reaches as far along
+    /// each of its axes as a round one would times that axis.
+    pub fn distance_to(&self, x: f64, y: f64, aspect: f64) -> f64 {
+        let nearest = self.nearest_to(x, y, aspect);
+        if self.is_round() {
+            return nearest.distance_to(x, y, aspect);
+        }
+        let (dx, dy) = ((x - nearest.x) * aspect, y - nearest.y);
+        let (sin, cos) = self.angle.to_radians().sin_cos();
+        let (along, across) = (dx * cos + dy * sin, dy * cos - dx * sin);
+        (along / self.axes.0).hypot(across / self.axes.1)
+    }
+
+    /// The point of the metaball's primitive closest to a point, on pixels `aspect` times wider than they
+    /// are tall
+    fn nearest_to(&self, x: f64, y: f64, aspect: f64) -> Pointf {
+        let (dx, dy) = ((x - self.location.x) * aspect, y - self.location.y);
+        let end = match self.primitive {
+            Primitive::Point => return self.location,
+            Primitive::Segment { end } => end,
+            Primitive::Ring { radius } => {
+                let distance = dx.hypot(dy);
+                // every point of the ring is as close to its center
+                let (dx, dy) = if distance == 0.0 { (radius, 0.0) } else { (dx / distance * radius, dy / distance * radius) };
+                return Pointf { x: self.location.x + dx / aspect, y: self.location.y + dy };
+            }
+            Primitive::RoundedBox { half, corner } => {
+                // in the box's own axes, the closest point of the box shrunk by the corner radius, then
+                // out towards the point by that radius unless the point is inside the rounded box
+                let (sin, cos) = self.angle.to_radians().sin_cos();
+                let (along, across) = (dx * cos + dy * sin, dy * cos - dx * sin);
+                let inner = ((half.0 - corner).max(0.0), (half.1 - corner).max(0.0));
+                let clamped = (along.clamp(-inner.0, inner.0), across.clamp(-inner.1, inner.1));
+                let outside = (along - clamped.0, across - clamped.1);
+                let distance = outside.0.hypot(outside.1);
+                if distance <= corner {
+                    return Pointf { x, y };
+                }
+                let (along, across) = (clamped.0 + outside.0 / distance * corner, clamped.1 + outside.1 / distance * corner);
+                let (dx, dy) = (along * cos - across * sin, along * sin + across * cos);
+                return Pointf { x: self.location.x + dx / aspect, y: self.location.y + dy };
+            }
+        };
+        let (start, along) = (self.location, ((end.x - self.location.x) * aspect, end.y - self.location.y));
+        let length_squared = along.0 * along.0 + along.1 * along.1;
+        if length_squared == 0.0 {
+            return start;
+        }
+        let t = ((((x - start.x) * aspect) * along.0 + (y - start.y) * along.1) / length_squared).clamp(0.0, 1.0);
+        Pointf { x: start.x + (end.x - start.x) * t, y: start.y + (end.y - start.y) * t }
+    }
+
+    /// The far end of the metaball's primitive, which is its location for anything but a metaline
+    pub fn end(&self) -> Pointf {
+        match self.primitive {
+            Primitive::Segment { end } => end,
+            _ => self.location,
+        }
+    }
+
+    /// How much further than its location the metaball's primitive goes, in pixels
+    pub fn extent(&self) -> f64 {
+        match self.primitive {
+            Primitive::Point => 0.0,
+            Primitive::Segment { end } => self.location.distance_to(end.x, end.y, 1.0),
+            Primitive::RoundedBox { half, .. } => half.0.hypot(half.1),
+            Primitive::Ring { radius } => radius,
+        }
+    }
+
+    /// Put the metaball somewhere else, taking its path along if it has one
+    pub fn move_to(&mut self, location: Pointf) {
+        if let Some(motion) = &mut self.motion {
+            motion.center = Pointf {
+                x: motion.center.x + location.x - self.location.x,
+                y: motion.center.y + location.y - self.location.y,
+            };
+        }
+        self.shift_to(location);
+    }
+
+    /// Move the metaball's location, and the rest of its primitive along with it
+    fn shift_to(&mut self, location: Pointf) {
+        if let Primitive::Segment { end } = &mut self.primitive {
+            *end = Pointf { x: end.x + location.x - self.location.x, y: end.y + location.y - self.location.y };
+        }
+        self.location = location;
+    }
+
+    /// The stdin commands that add this metaball at an index, with the index as a comment after it. A
+    /// metaball on a path is added where the path starts, so the path comes out the same.
+    fn commands(&self, index: usize) -> Vec<String> {
+        let radius = self.radius.map(|radius| format!(" {}", radius)).unwrap_or_default();
+        let location = self.motion.map_or(self.location, |motion| motion.start());
+        let mut commands = vec![format!("a {} {} {}{} # {}", location.x, location.y, self.rest_size(), radius, index)];
+        if self.color != ON_PIXEL {
+            commands.push(format!("color {} {}", index, hex_color(self.color)));
+        }
+        match self.primitive {
+            Primitive::Point => {}
+            Primitive::Segment { end } => commands.push(format!("line {} {} {}", index, end.x, end.y)),
+            Primitive::RoundedBox { half, corner } => commands.push(format!("box {} {} {} {}", index, half.0, half.1, corner)),
+            Primitive::Ring { radius } => commands.push(format!("ring {} {}", index, radius)),
+        }
+        if !self.is_round() || self.angle != 0.0 {
+            commands.push(format!("stretch {} {} {} {}", index, self.axes.0, self.axes.1, self.angle));
+        }
+        if let Some(motion) = self.motion {
+            commands.push(format!("motion {} {}", index, motion));
+        }
+        if let Some(pulse) = self.pulse {
+            commands.push(format!("pulse {} {}", index, pulse));
+        }
+        if self.layer != 0 {
+            commands.push(format!("layer {} {}", index, self.layer));
+        }
+        commands
+    }
+
+    /// The size the metaball's pulse swings around, its size if it doesn't pulse
+    pub fn rest_size(&self) -> f64 {
+        self.pulse.map_or(self.size, |pulse| self.size / pulse.scale())
+    }
+
+    /// Start the metaball pulsing, or stop it, keeping the same resting size
+    pub fn set_pulse(&mut self, pulse: Option<Pulse>) {
+        self.size = self.rest_size() * pulse.map_or(1.0, |pulse| pulse.scale());
+        self.pulse = pulse;
+    }
+
+    /// The goo of the metaball, which is the scene's unless it has its own
+    pub fn goo_or(&self, goo: f64) -> f64 {
+        self.goo.unwrap_or(goo)
+    }
+
+    /// Whether the metaball adds to the field at a distance from its center
+    pub fn reaches(&self, distance: f64) -> bool {
+        self.radius.is_none_or(|radius| distance <= radius)
+    }
+}
+
+/// Represents a point on an image or screen
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
+pub struct Point {
+    pub x: u32,
+    pub y: u32,
+}
+
+/// Like [Point] but between pixels, for where metaballs are
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
+pub struct Pointf {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// Like [Point] but signed integers to allow for negatives. Not used directly for rendering,
+/// but for offsets and velocities
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
+pub struct RelPoint {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Pointf {
+    /// Distance to a point, which may lie between pixels, on pixels `aspect` times wider than they are tall
+    pub fn distance_to(&self, x: f64, y: f64, aspect: f64) -> f64 {
+        f64::sqrt((((self.x - x) * aspect).powf(2f64)) + ((self.y - y).powf(2f64)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn field_at_matches_naive_impl() {
+        let metadata = MetaballData::new(1.6, 0.5, 64, 48, vec![
+            Metaball::new(Pointf { x: 20.0, y: 20.0 }, 30.0),
+            Metaball::new(Pointf { x: 40.0, y: 30.0 }, 20.0),
+        ]);
+        let opts = RenderOpts::default();
+        let image = naive_impl(metadata.width, metadata.height, 1, &metadata, &opts);
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let inside = field_at(x as f64, y as f64, &metadata) > metadata.threshold;
+            assert_eq!(*pixel == opts.on_color, inside, "pixel ({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn smooth_union_merges_later_than_additive() {
+        // two balls whose additive fields bridge in the middle even though their circles are well apart
+        let mut metadata = MetaballData::new(1.6, 0.5, 64, 32, vec![
+            Metaball::new(Pointf { x: 14.0, y: 16.0 }, 30.0),
+            Metaball::new(Pointf { x: 50.0, y: 16.0 }, 30.0),
+        ]);
+        let inside_at_midpoint = |metadata: &MetaballData| field_at(32.0, 16.0, metadata) > metadata.threshold;
+        assert!(inside_at_midpoint(&metadata));
+
+        // the gap between the circles is wider than a small smoothing distance, so they stay apart
+        metadata.blend = BlendMode::SmoothUnion { k: 2.0 };
+        assert!(!inside_at_midpoint(&metadata));
+        // but a large smoothing distance fills it in
+        metadata.blend = BlendMode::SmoothUnion { k: 30.0 };
+        assert!(inside_at_midpoint(&metadata));
+
+        // either way a lone ball's edge is in the same place
+        metadata.metaballs.pop();
+        for blend in [BlendMode::Additive, BlendMode::SmoothUnion { k: 30.0 }] {
+            metadata.blend = blend;
+            assert!(field_at(26.0, 16.0, &metadata) > metadata.threshold);
+            assert!(field_at(28.0, 16.0, &metadata) < metadata.threshold);
+        }
+    }
+
+    #[test]
+    fn tiled_field_wraps_at_the_edges() {
+        let mut metadata = MetaballData::new(1.6, 0.5, 64, 48, vec![
+            Metaball::new(Pointf { x: 3.0, y: 24.0 }, 30.0),
+            Metaball::new(Pointf { x: 40.0, y: 45.0 }, 20.0),
+        ]);
+        metadata.tile = true;
+        // the column and row just past the far edges are the first column and row of the next tile
+        for y in 0..metadata.height {
+            let y = y as f64;
+            assert_eq!(field_at(0.0, y, &metadata), field_at(metadata.width as f64, y, &metadata));
+        }
+        for x in 0..metadata.width {
+            let x = x as f64;
+            assert_eq!(field_at(x, 0.0, &metadata), field_at(x, metadata.height as f64, &metadata));
+        }
+    }
+
+    #[test]
+    fn tiled_metaballs_move_over_the_edges() {
+        let mut metaball = Metaball::new(Pointf { x: 62.0, y: 1.0 }, 30.0);
+        metaball.velocity = Some(RelPoint { x: 5, y: -3 });
+        let mut metadata = MetaballData::new(1.6, 0.5, 64, 48, vec![metaball]);
+        metadata.tile = true;
+        metadata.step();
+        assert_eq!(metadata.metaballs[0].location, Pointf { x: 3.0, y: 46.0 });
+        assert_eq!(metadata.metaballs[0].velocity, Some(RelPoint { x: 5, y: -3 }));
+    }
+
+    #[test]
+    fn thresholds_are_shaded_up_to_the_inside_color() {
+        let opts = RenderOpts { on_color: Rgba([255, 255, 255, 255]), off_color: Rgba([0, 0, 0, 255]), ..RenderOpts::default() };
+        let bands = opts.threshold_bands(&[0.8, 0.3, 0.5]);
+        let levels: Vec<f64> = bands.iter().map(|(level, _)| *level).collect();
+        assert_eq!(levels, vec![0.3, 0.5, 0.8]);
+        assert_eq!(bands[0].1, Rgba([85, 85, 85, 255]));
+        assert_eq!(bands[2].1, opts.on_color);
+        assert_eq!(band_color(0.6, &bands), Some(bands[1].1));
+        assert_eq!(band_color(0.1, &bands), None);
+        // the library's callers could still pass a NaN, which is sorted last rather than panicking
+        assert_eq!(opts.threshold_bands(&[f64::NAN, 0.5])[0].0, 0.5);
+    }
+
+    #[test]
+    fn ball_colors_mix_where_metaballs_meet() {
+        let mut red = Metaball::new(Pointf { x: 20.0, y: 16.0 }, 30.0);
+        red.color = Rgba([255, 0, 0, 255]);
+        let mut blue = Metaball::new(Pointf { x: 44.0, y: 16.0 }, 30.0);
+        blue.color = Rgba([0, 0, 255, 255]);
+        let metadata = MetaballData::new(1.6, 0.5, 64, 32, vec![red, blue]);
+        let opts = RenderOpts { ball_colors: true, ..RenderOpts::default() };
+        let image = naive_impl(metadata.width, metadata.height, 1, &metadata, &opts);
+
+        // each ball's own color dominates at its center
+        assert_eq!(image.get_pixel(20, 16)[0], 255);
+        assert_eq!(image.get_pixel(44, 16)[2], 255);
+        // and they're mixed evenly in the overlap halfway between them
+        assert!(field_at(32.0, 16.0, &metadata) > metadata.threshold);
+        let middle = image.get_pixel(32, 16);
+        assert!(middle[0] > 64 && middle[0] < 192, "{:?}", middle);
+        assert!(middle[2] > 64 && middle[2] < 192, "{:?}", middle);
+    }
+
+    #[test]
+    fn centered_random_stays_within_bounds() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..1000 {
+            let num = centered_random(0.5, &mut rng);
+            assert!((0.25..=0.75).contains(&num), "{}", num);
+        }
+    }
+
+    #[test]
+    fn seeded_scenes_are_reproducible() {
+        let generation = Generation { margin: 0.1, ..Generation::default() };
+        let first = MetaballData::from_rng(1.6, 0.5, 256, 256, &generation, &mut StdRng::seed_from_u64(42));
+        let second = MetaballData::from_rng(1.6, 0.5, 256, 256, &generation, &mut StdRng::seed_from_u64(42));
+        assert_eq!(first.metaballs, second.metaballs);
+        assert!(first.metaballs.len() >= MIN_METABALL_COUNT as usize);
+        // a library caller can still ask for far too many, which is capped rather than overflowing
+        let crowded = Generation { min_count: u32::MAX, count_factor: 1e-300, ..Generation::default() };
+        assert_eq!(random_count_metaballs(&crowded, &mut StdRng::seed_from_u64(42)), MAX_METABALL_COUNT);
+    }
+
+    #[test]
+    fn metaball_near_picks_the_closest_center_in_reach() {
+        let metadata = MetaballData::new(1.6, 0.5, 64, 64, vec![
+            Metaball::new(Pointf { x: 10.0, y: 10.0 }, 30.0),
+            Metaball::new(Pointf { x: 16.0, y: 10.0 }, 30.0),
+        ]);
+        assert_eq!(metadata.metaball_near(Pointf { x: 14.0, y: 11.0 }, 5.0), Some(1));
+        assert_eq!(metadata.metaball_near(Pointf { x: 9.0, y: 9.0 }, 5.0), Some(0));
+        assert_eq!(metadata.metaball_near(Pointf { x: 40.0, y: 40.0 }, 5.0), None);
+    }
+
+    #[test]
+    fn transparent_exports_leave_only_the_shape_opaque() {
+        let metadata = MetaballData::new(1.6, 0.5, 64, 64, vec![Metaball::new(Pointf { x: 32.0, y: 32.0 }, 30.0)]);
+        let opts = RenderOpts { transparent_export: true, ssaa: 2, ..RenderOpts::default() };
+        let image = render_image(64, 64, &metadata, &opts.export_opts());
+        assert_eq!(*image.get_pixel(0, 0), Rgba([0, 0, 0, 0]));
+        assert_eq!(*image.get_pixel(32, 32), ON_PIXEL);
+        // the supersampled edge fades out rather than to an opaque background
+        assert!((0..64).any(|x| (1..255).contains(&image.get_pixel(x, 32)[3])));
+        assert_eq!(render_image(64, 64, &metadata, &opts)[(0, 0)], OFF_PIXEL);
+    }
+
+    #[test]
+    fn heatmaps_are_hot_at_the_centers_and_halfway_at_the_edge() {
+        let metadata = MetaballData::new(1.6, 0.5, 64, 64, vec![Metaball::new(Pointf { x: 32.0, y: 32.0 }, 30.0)]);
+        let opts = RenderOpts { mode: RenderMode::Heatmap, ..RenderOpts::default() };
+        let image = render_image(64, 64, &metadata, &opts);
+        assert_eq!(*image.get_pixel(32, 32), HEATMAP_STOPS[4].1);
+        let corner = image.get_pixel(0, 0);
+        assert!(corner[2] > corner[0] && corner[2] > corner[1], "the far corner should be cold, got {:?}", corner);
+        assert_eq!(heatmap_color(0.5), HEATMAP_STOPS[2].1);
+        assert_eq!(heatmap_color(0.0), HEATMAP_STOPS[0].1);
+    }
+
+    #[test]
+    fn easing_starts_from_where_the_last_change_had_got_to() {
+        let mut metadata = MetaballData::new(1.6, 0.5, 64, 64, vec![]);
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut easing = Easing::new(Duration::from_millis(100));
+        assert_eq!(easing.shown_at(&metadata, at(0)), (1.6, 0.5));
+        assert!(!easing.easing());
+        metadata.threshold = 1.5;
+        assert_eq!(easing.shown_at(&metadata, at(0)), (1.6, 0.5));
+        assert_eq!(easing.shown_at(&metadata, at(50)), (1.6, 1.375));
+        assert!(easing.easing());
+        metadata.threshold = 0.0;
+        assert_eq!(easing.shown_at(&metadata, at(100)), (1.6, 1.375));
+        assert_eq!(easing.shown_at(&metadata, at(200)), (1.6, 0.0));
+        assert!(!easing.easing());
+    }
+
+    #[test]
+    fn outlines_are_only_the_edge() {
+        let metadata = MetaballData::new(1.0, 1.0, 64, 64, vec![Metaball::new(Pointf { x: 32.0, y: 32.0 }, 20.0)]);
+        let stroke = Rgba([0, 255, 0, 255]);
+        let opts = RenderOpts { mode: RenderMode::Outline, outline_color: Some(stroke), ..RenderOpts::default() };
+        let image = render_image(64, 64, &metadata, &opts);
+        // the shape reaches 20 pixels out, so the last pixel inside it on each side is next to the edge
+        assert_eq!(*image.get_pixel(51, 32), stroke);
+        assert_eq!(*image.get_pixel(12, 32), stroke);
+        assert_eq!(*image.get_pixel(32, 32), OFF_PIXEL);
+        assert_eq!(*image.get_pixel(0, 0), OFF_PIXEL);
+    }
+
+    #[test]
+    fn dithering_has_only_two_colors_and_lights_more_towards_the_centers() {
+        let metadata = MetaballData::new(1.6, 0.5, 64, 64, vec![Metaball::new(Pointf { x: 32.0, y: 32.0 }, 30.0)]);
+        let opts = RenderOpts { mode: RenderMode::Dither, ssaa: 2, ..RenderOpts::default() };
+        let image = render_image(64, 64, &metadata, &opts);
+        assert!(image.pixels().all(|pixel| *pixel == ON_PIXEL || *pixel == OFF_PIXEL));
+        // how many of a 4×4 patch's pixels are lit
+        let lit = |x: u32| (0..16).filter(|i| *image.get_pixel(x + i % 4, 30 + i / 4) == ON_PIXEL).count();
+        assert!(lit(30) > lit(40) && lit(40) > lit(48) && lit(48) > lit(60), "{} {} {} {}", lit(30), lit(40), lit(48), lit(60));
+    }
+
+    #[test]
+    fn regions_are_colored_by_the_strongest_metaball() {
+        let metadata = MetaballData::new(1.6, 0.5, 64, 32, vec![
+            Metaball::new(Pointf { x: 20.0, y: 16.0 }, 60.0),
+            Metaball::new(Pointf { x: 44.0, y: 16.0 }, 30.0),
+        ]);
+        let image = render_image(64, 32, &metadata, &RenderOpts { mode: RenderMode::Regions, ..RenderOpts::default() });
+        assert_eq!(*image.get_pixel(20, 16), region_color(0));
+        assert_eq!(*image.get_pixel(44, 16), region_color(1));
+        assert_eq!(*image.get_pixel(0, 0), OFF_PIXEL);
+        // the bigger metaball's region reaches past the middle between them
+        assert_eq!(*image.get_pixel(33, 16), region_color(0));
+        assert_eq!(dominant_metaball(Pointf { x: 33.0, y: 16.0 }, &metadata), Some(0));
+    }
+
+    #[test]
+    fn negative_metaballs_carve_holes_whichever_way_the_field_blends() {
+        for blend in [BlendMode::Additive, BlendMode::SmoothUnion { k: 8.0 }] {
+            let mut metadata = MetaballData::new(1.6, 0.5, 64, 64, vec![
+                Metaball::new(Pointf { x: 32.0, y: 32.0 }, 90.0),
+                Metaball::new(Pointf { x: 40.0, y: 32.0 }, -40.0),
+            ]);
+            metadata.blend = blend;
+            assert!(field_at(40.5, 32.0, &metadata) < metadata.threshold, "the negative metaball should carve a hole with {:?}", blend);
+            assert!(field_at(14.0, 32.0, &metadata) > metadata.threshold, "the far side should be left with {:?}", blend);
+        }
+    }
+
+    #[test]
+    fn the_negative_fraction_of_random_metaballs_are_negative() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let all = Generation { negative_fraction: 1.0, count: Some(6), ..Generation::default() };
+        assert!(MetaballData::from_rng(1.6, 0.5, 64, 64, &all, &mut rng).metaballs.iter().all(|metaball| metaball.size < 0.0));
+        let none = Generation { count: Some(6), ..Generation::default() };
+        assert!(MetaballData::from_rng(1.6, 0.5, 64, 64, &none, &mut rng).metaballs.iter().all(|metaball| metaball.size > 0.0));
+    }
+
+    #[test]
+    fn supersampling_blends_the_colors_along_the_edge() {
+        let metadata = MetaballData::new(1.6, 0.5, 48, 48, vec![Metaball::new(Pointf { x: 23.3, y: 24.1 }, 60.0)]);
+        let is_flat = |pixel: &Rgba<u8>| *pixel == ON_PIXEL || *pixel == OFF_PIXEL;
+        let aliased = render_image(48, 48, &metadata, &RenderOpts::default());
+        assert!(aliased.pixels().all(is_flat));
+        for ssaa in [3, 5] {
+            let smoothed = render_image(48, 48, &metadata, &RenderOpts { ssaa, ..RenderOpts::default() });
+            assert!(smoothed.pixels().any(|pixel| !is_flat(pixel)), "the edge should be blended with {}x{} samples", ssaa, ssaa);
+            assert_eq!(smoothed.get_pixel(23, 24), &ON_PIXEL);
+            assert_eq!(smoothed.get_pixel(0, 0), &OFF_PIXEL);
+        }
+    }
+
+    #[test]
+    fn colors_read_back_the_way_they_are_written() {
+        for color in [ON_PIXEL, Rgba([18u8, 52, 86, 255]), Rgba([1u8, 2, 3, 4])] {
+            assert_eq!(parse_color(&hex_color(color)), Ok(color));
+        }
+    }
+
+    #[test]
+    fn saved_scenes_load_back_the_same() {
+        let mut metaballs = vec![Metaball::new(Pointf { x: 10.5, y: 20.0 }, 30.0), Metaball::new(Pointf { x: 40.0, y: 8.0 }, -12.0)];
+        metaballs[0].color = Rgba([1u8, 2, 3, 4]);
+        metaballs[1].radius = Some(6.0);
+        metaballs[1].goo = Some(2.0);
+        let scene = MetaballData::new(1.8, 0.7, 64, 32, metaballs);
+        let text = toml::to_string(&SceneConfig::from_data(&scene)).unwrap();
+        let config: SceneConfig = toml::from_str(&text).unwrap();
+        assert_eq!(config.into_data(64, 32), Ok(scene));
+    }
+
+    #[test]
+    fn scene_changes_become_the_fewest_commands() {
+        let before = MetaballData::new(1.6, 0.5, 64, 64, vec![
+            Metaball::new(Pointf { x: 10.0, y: 10.0 }, 30.0),
+            Metaball::new(Pointf { x: 20.0, y: 20.0 }, 30.0),
+            Metaball::new(Pointf { x: 30.0, y: 30.0 }, 30.0),
+        ]);
+        assert!(before.commands_from(&before).is_empty());
+
+        let mut moved = before.clone();
+        moved.goo = 2.0;
+        moved.metaballs[1].location = Pointf { x: 22.5, y: 20.0 };
+        assert_eq!(moved.commands_from(&before), ["g2", "m 1 22.5 20"]);
+
+        let mut removed = before.clone();
+        removed.metaballs.remove(1);
+        assert_eq!(removed.commands_from(&before), ["d 1"]);
+
+        let mut added = before.clone();
+        added.metaballs.push(Metaball::new(Pointf { x: 40.0, y: 5.0 }, -12.0));
+        assert_eq!(added.commands_from(&before), ["a 40 5 -12 # 3"]);
+
+        let mut resized = before.clone();
+        resized.metaballs[0].size = 50.0;
+        assert_eq!(resized.commands_from(&before), ["clear", "a 10 10 50 # 0", "a 20 20 30 # 1", "a 30 30 30 # 2"]);
+    }
+
+    #[test]
+    fn leaving_out_far_metaballs_barely_moves_the_field() {
+        let generation = Generation { count: Some(60), base_size: 4.0, ..Generation::default() };
+        let metadata = MetaballData::from_rng(4.0, 0.1, 96, 96, &generation, &mut StdRng::seed_from_u64(9));
+        let field = field_grid(96, 96, 1, &View::default(), &metadata, Precision::F64);
+        // every metaball left out of a pixel adds less than the epsilon there
+        let tolerance = 60.0 * metadata.threshold * FIELD_EPSILON;
+        for (i, value) in field.iter().enumerate() {
+            let (x, y) = ((i % 96) as f64, (i / 96) as f64);
+            let exact = field_at(x, y, &metadata);
+            assert!((value - exact).abs() <= tolerance || value == &exact, "({}, {}) is {} instead of {}", x, y, value, exact);
+        }
+    }
+
+    #[test]
+    fn a_fixed_count_gives_every_scene_that_many_metaballs() {
+        let generation = Generation { count: Some(7), ..Generation::default() };
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..5 {
+            assert_eq!(MetaballData::from_rng(1.6, 0.5, 256, 256, &generation, &mut rng).metaballs.len(), 7);
+        }
+    }
+
+    #[test]
+    fn crosses_at_the_edges_stay_in_bounds() {
+        let metadata = MetaballData::new(1.6, 0.5, 256, 256, vec![
+            Metaball::new(Pointf { x: 0.0, y: 0.0 }, 30.0),
+            Metaball::new(Pointf { x: 255.0, y: 255.0 }, 30.0),
+        ]);
+        for cross_style in [IndicatorStyle::Cross, IndicatorStyle::Dot, IndicatorStyle::Ring] {
+            let opts = RenderOpts { crosses: true, cross_style, cross_radius: 3, ..RenderOpts::default() };
+            let mut buffer = vec![0; 256 * 256 * 4];
+            render_metaballs(&mut buffer, 256, 256, &metadata, &opts);
+            // every style reaches the radius along the edge
+            assert_eq!(buffer[3 * 4..4 * 4], CROSS_PIXEL.0);
+        }
+    }
+
+    #[test]
+    fn rows_are_the_field_grid() {
+        let mut metadata = MetaballData::new(1.6, 0.5, 40, 30, vec![
+            Metaball::new(Pointf { x: 10.0, y: 12.0 }, 30.0),
+            Metaball { radius: Some(9.0), ..Metaball::new(Pointf { x: 30.0, y: 20.0 }, -8.0) },
+        ]);
+        metadata.noise = Some(Noise { amplitude: 0.3, scale: 8.0 });
+        let rows: Vec<Vec<f64>> = metadata.rows(40, 30).collect();
+        assert_eq!(rows.len(), 30);
+        assert_eq!(rows.concat(), field_grid(40, 30, 1, &View::default(), &metadata, Precision::F64));
+        assert_eq!(rows[12][10], metadata.field_at(10.0, 12.0));
+    }
+
+    #[test]
+    fn render_metaballs_matches_render_image() {
+        let metadata = MetaballData::new(1.6, 0.5, 70, 45, vec![
+            Metaball::new(Pointf { x: 20.0, y: 20.0 }, 30.0),
+            Metaball::new(Pointf { x: 50.0, y: 30.0 }, 20.0),
+        ]);
+        // an odd size leaves the last band short
+        let opts = RenderOpts { crosses: true, isolines: vec![0.3, 0.8], gradient: true, ..RenderOpts::default() };
+        let mut buffer = vec![0; 70 * 45 * 4];
+        render_metaballs(&mut buffer, 70, 45, &metadata, &opts);
+        assert_eq!(buffer, *render_image(70, 45, &metadata, &opts).as_raw());
+    }
+
+    #[test]
+    fn marching_squares_fills_a_circle() {
+        let metadata = MetaballData::new(1.6, 0.5, 64, 64, vec![Metaball::new(Pointf { x: 32.0, y: 32.0 }, 60.0)]);
+        let opts = RenderOpts { mode: RenderMode::MarchingSquares, cell_size: 8, ..RenderOpts::default() };
+        let image = marching_squares_impl(metadata.width, metadata.height, 1, opts.cell_size, &metadata, &opts);
+
+        // a lone metaball's edge is where size / distance^goo is the threshold
+        let radius = (60.0f64 / 0.5).powf(1.0 / 1.6);
+        let mut filled = 0.0;
+        for (x, y, pixel) in image.enumerate_pixels() {
+            let distance = Pointf { x: 32.0, y: 32.0 }.distance_to(x as f64, y as f64, 1.0);
+            if distance < radius - 1.5 {
+                assert_eq!(*pixel, ON_PIXEL, "({}, {}) should be inside", x, y);
+            } else if distance > radius + 1.5 {
+                assert_eq!(*pixel, OFF_PIXEL, "({}, {}) should be outside", x, y);
+            }
+            filled += pixel[0] as f64 / 255.0;
+        }
+        let area = std::f64::consts::PI * radius * radius;
+        assert!((filled - area).abs() < area * 0.05, "filled {} of a {} pixel circle", filled, area);
+    }
+
+    /// Where the images the renders are checked against are kept. Running the tests with `UPDATE_GOLDEN`
+    /// set writes the current renders there instead.
+    const GOLDEN_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+    /// The pixels of `actual` with a channel more than `tolerance` off of `expected`
+    fn diff_images(expected: &ImageBuffer<Rgba<u8>, Vec<u8>>, actual: &ImageBuffer<Rgba<u8>, Vec<u8>>, tolerance: u8) -> Vec<(u32, u32)> {
+        assert_eq!(expected.dimensions(), actual.dimensions(), "the images are different sizes");
+        expected.enumerate_pixels()
+            .filter(|(x, y, pixel)| {
+                let other = actual.get_pixel(*x, *y);
+                pixel.0.iter().zip(other.0.iter()).any(|(a, b)| (*a as i16 - *b as i16).abs() > tolerance as i16)
+            })
+            .map(|(x, y, _)| (x, y))
+            .collect()
+    }
+
+    /// How many pixels of a golden image the faster precisions may get wrong, out of each thousand. They
+    /// can only round the field differently right at the edge of the shape.
+    const GOLDEN_EDGE_PIXELS: usize = 5;
+
+    #[test]
+    fn renders_match_golden_images() {
+        let mut scenes: Vec<(String, MetaballData)> = PRESETS.iter()
+            .map(|(name, preset)| (name.to_string(), preset(128, 128)))
+            .collect();
+        scenes.push(("seeded".to_string(), MetaballData::from_rng(1.6, 0.5, 128, 128, &Generation::default(), &mut StdRng::seed_from_u64(42))));
+
+        let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+        let mut failures = vec![];
+        for (name, scene) in scenes {
+            let path = Path::new(GOLDEN_DIR).join(format!("{}.png", name));
+            if update {
+                std::fs::create_dir_all(GOLDEN_DIR).unwrap();
+                naive_impl(scene.width, scene.height, 1, &scene, &RenderOpts::default()).save(&path).unwrap();
+                continue;
+            }
+            let golden = match image::open(&path) {
+                Ok(golden) => golden.to_rgba8(),
+                Err(err) => {
+                    failures.push(format!("{}: unable to open {}: {}", name, path.display(), err));
+                    continue;
+                }
+            };
+            // the golden images are of the full precision field, which the faster ways of evaluating it
+            // have to stay close to
+            for precision in [Precision::F64, Precision::F32, Precision::Lut] {
+                let image = naive_impl(scene.width, scene.height, 1, &scene, &RenderOpts { precision, ..RenderOpts::default() });
+                let differing = diff_images(&golden, &image, 2);
+                let allowed = if precision == Precision::F64 { 0 } else { golden.len() / 4 * GOLDEN_EDGE_PIXELS / 1000 };
+                if differing.len() > allowed {
+                    failures.push(format!(
+                        "{} at {:?}: {} pixels differ, starting with {:?}",
+                        name,
+                        precision,
+                        differing.len(),
+                        &differing[..differing.len().min(8)]
+                    ));
+                }
+            }
+        }
+        assert!(failures.is_empty(), "renders don't match the golden images, rerun with UPDATE_GOLDEN=1 if that's intended:\n{}", failures.join("\n"));
+    }
+
+    #[test]
+    fn colliding_metaballs_push_apart() {
+        // two metaballs reaching 10 pixels out, overlapping by 10 pixels
+        let metaballs = vec![Metaball::new(Pointf { x: 45.0, y: 50.0 }, 5.0), Metaball::new(Pointf { x: 55.0, y: 50.0 }, 5.0)];
+        let mut metadata = MetaballData::new(1.0, 0.5, 100, 100, metaballs);
+        metadata.physics = Physics { enabled: true, gravity: 0.0, attraction: 0.0, ..Physics::default() };
+        let mut merging = metadata.clone();
+        metadata.physics.collision = 0.1;
+        for _ in 0..20 {
+            metadata.step();
+            merging.step();
+        }
+        let gap = |metadata: &MetaballData| metadata.metaballs[1].location.x - metadata.metaballs[0].location.x;
+        assert_eq!(gap(&merging), 10.0);
+        assert!(gap(&metadata) > 19.0, "{}", gap(&metadata));
+    }
+
+    #[test]
+    fn metaball_goo_sharpens_the_edge() {
+        // both metaballs are 20 pixels across at the threshold, but the field around the sharp one falls
+        // off far quicker
+        let mut soft = Metaball::new(Pointf { x: 40.0, y: 40.0 }, 0.5 * 20.0);
+        soft.goo = Some(1.0);
+        let mut sharp = Metaball::new(Pointf { x: 160.0, y: 40.0 }, 0.5 * 20.0f64.powf(3.0));
+        sharp.goo = Some(3.0);
+        let metadata = MetaballData::new(1.6, 0.5, 200, 80, vec![soft, sharp]);
+
+        // how many pixels along the row through the centers are near the threshold
+        let edge_width = |range: std::ops::Range<u32>| {
+            range.filter(|x| (0.25..1.0).contains(&field_at(*x as f64, 40.0, &metadata))).count()
+        };
+        let (soft_edge, sharp_edge) = (edge_width(0..100), edge_width(100..200));
+        assert!(sharp_edge * 2 < soft_edge, "the sharp edge is {} pixels and the soft one {}", sharp_edge, soft_edge);
+
+        // random sizes reach as far with a metaball's own goo as with the scene's
+        let randomized = |goo: Option<f64>| {
+            let mut metaball = Metaball::new(Pointf { x: 40.0, y: 40.0 }, 10.0);
+            metaball.goo = goo;
+            let mut metadata = MetaballData::new(1.6, 0.5, 200, 80, vec![metaball]);
+            metadata.randomize_sizes(&Generation::default(), &mut StdRng::seed_from_u64(3));
+            metadata.metaballs[0].size.powf(1.0 / metadata.metaballs[0].goo_or(1.6))
+        };
+        assert!((randomized(Some(3.0)) - randomized(None)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stretched_metaballs_reach_further_along_their_turned_axis() {
+        // a lone metaball with a goo of 1 reaches the threshold 20 pixels out when round
+        let mut stretched = Metaball::new(Pointf { x: 64.0, y: 64.0 }, 20.0);
+        stretched.axes = (2.0, 0.5);
+        stretched.angle = 90.0;
+        let metadata = MetaballData::new(1.0, 1.0, 128, 128, vec![stretched]);
+        let inside = |x: f64, y: f64| field_at(x, y, &metadata) > metadata.threshold;
+        // turned a quarter of the way clockwise, its long axis runs from top to bottom
+        assert!(inside(64.0, 64.0 + 38.0) && !inside(64.0, 64.0 + 42.0));
+        assert!(inside(64.0 + 9.0, 64.0) && !inside(64.0 + 11.0, 64.0));
+        // the grid of cells still finds it at the far end of its long axis
+        let field = field_grid(128, 128, 1, &View::default(), &metadata, Precision::F32);
+        assert!(field[(64 + 38) * 128 + 64] > metadata.threshold);
+    }
+
+    #[test]
+    fn metalines_are_capsules_mixed_in_with_the_points() {
+        // both reach the threshold 10 pixels out
+        let mut line = Metaball::new(Pointf { x: 20.0, y: 20.0 }, 100.0);
+        line.primitive = Primitive::Segment { end: Pointf { x: 80.0, y: 20.0 } };
+        let point = Metaball::new(Pointf { x: 50.0, y: 70.0 }, 100.0);
+        let metadata = MetaballData::new(2.0, 1.0, 100, 80, vec![line, point]);
+        let inside = |x: f64, y: f64| field_at(x, y, &metadata) > metadata.threshold;
+        // as thick all along the line, with round caps past its ends
+        assert!(inside(50.0, 27.0) && inside(79.0, 27.0) && !inside(50.0, 33.0));
+        assert!(inside(87.0, 20.0) && !inside(92.0, 20.0) && !inside(87.0, 28.0));
+        assert!(inside(50.0, 62.0) && !inside(62.0, 70.0));
+        let mut moved = metadata.clone();
+        moved.metaballs[0].move_to(Pointf { x: 10.0, y: 40.0 });
+        assert_eq!(moved.metaballs[0].end(), Pointf { x: 70.0, y: 40.0 });
+    }
+
+    #[test]
+    fn the_no_std_core_matches_the_field() {
+        let balls = [core::Ball { x: 20.0, y: 30.0, size: 90.0 }, core::Ball { x: 45.0, y: 12.5, size: -20.0 }];
+        let metaballs = balls.iter().map(|ball| Metaball::new(Pointf { x: ball.x, y: ball.y }, ball.size)).collect();
+        let metadata = MetaballData::new(1.6, 0.5, 64, 48, metaballs);
+        for &(x, y) in &[(0.0, 0.0), (21.0, 30.0), (44.0, 13.0), (63.0, 47.0), (30.5, 20.25)] {
+            let (ours, theirs) = (core::field(x, y, &balls, 1.6), field_at(x, y, &metadata));
+            assert!((ours - theirs).abs() < theirs.abs() * 1e-12, "{} at {}, {} should be {}", ours, x, y, theirs);
+        }
+    }
+
+    #[test]
+    fn builders_check_the_scene() {
+        let built = MetaballData::builder().size(64, 32).goo(1.8).threshold(0.7).ball(10.0, 20.0, 30.0).build();
+        assert_eq!(built, Ok(MetaballData::new(1.8, 0.7, 64, 32, vec![Metaball::new(Pointf { x: 10.0, y: 20.0 }, 30.0)])));
+        assert!(MetaballData::builder().ball(10.0, 20.0, 30.0).build().is_err());
+        assert!(MetaballData::builder().size(64, 32).threshold(0.0).build().is_err());
+    }
+
+    #[test]
+    fn boxes_and_rings_are_measured_from_their_outlines() {
+        // both reach the threshold 10 pixels out from their outlines
+        let mut rounded = Metaball::new(Pointf { x: 40.0, y: 40.0 }, 100.0);
+        rounded.primitive = Primitive::RoundedBox { half: (30.0, 15.0), corner: 5.0 };
+        let mut ring = Metaball::new(Pointf { x: 150.0, y: 40.0 }, 100.0);
+        ring.primitive = Primitive::Ring { radius: 25.0 };
+        let metadata = MetaballData::new(2.0, 1.0, 200, 80, vec![rounded, ring]);
+        let inside = |x: f64, y: f64| field_at(x, y, &metadata) > metadata.threshold;
+        // flat along the sides of the box, and through its middle
+        assert!(inside(40.0, 40.0) && inside(79.0, 40.0) && !inside(81.0, 40.0));
+        assert!(inside(15.0, 64.0) && inside(65.0, 64.0) && !inside(40.0, 66.0));
+        // a hole in the middle of the ring, as thick all the way around it
+        assert!(!inside(150.0, 40.0) && inside(150.0, 24.0) && inside(184.0, 40.0) && !inside(186.0, 40.0));
+
+        let text = toml::to_string(&SceneConfig::from_data(&metadata)).unwrap();
+        let config: SceneConfig = toml::from_str(&text).unwrap();
+        assert_eq!(config.into_data(200, 80), Ok(metadata));
+    }
+}
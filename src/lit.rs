@@ -0,0 +1,78 @@
+//! The shape shaded as if it were glossy and lit from the top left, from the gradient of the field at each
+//! pixel, for the classic look of metaballs in 90s demos
+
+use crate::raymarch::Point3;
+use crate::{background_at, blend_over, draw_isolines, field_grid, inside_color, MetaballData, RenderOpts};
+use image::{ImageBuffer, Rgba};
+
+/// How much of the inside color the parts facing away from the light still get
+const AMBIENT: f64 = 0.2;
+
+/// How bright the highlight is at its brightest
+const SPECULAR: f64 = 0.6;
+
+/// How tight the highlight is, higher being glossier
+const SHININESS: i32 = 24;
+
+/// Render the shape lit by a light from the top left with Lambert and Phong shading. The surface is taken
+/// to be a dome that rises from the edge of the shape towards the centers, which is a hemisphere for a
+/// lone metaball with a goo of 1: `threshold / sum` is how far across it a pixel is, and the field falls
+/// off fastest in the direction its surface faces.
+pub fn lit_impl(width: u32, height: u32, scale: u32, metaball_data: &MetaballData, opts: &RenderOpts) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let field = field_grid(width, height, scale, &opts.view, metaball_data, opts.precision);
+    let sum_at = |x: u32, y: u32| field[(y * width + x) as usize];
+    let light = Point3::new(-1.0, -1.0, 1.5).normalized();
+    let mut image = ImageBuffer::from_fn(width, height, |x, y| {
+        let sum = sum_at(x, y);
+        let background = background_at(x, y, width, height, opts);
+        if sum <= metaball_data.threshold {
+            return background;
+        }
+        // the field's gradient from the neighboring pixels, or the pixel itself at the edges of the image
+        let gradient_x = sum_at((x + 1).min(width - 1), y) - sum_at(x.saturating_sub(1), y);
+        let gradient_y = sum_at(x, (y + 1).min(height - 1)) - sum_at(x, y.saturating_sub(1));
+        let normal = surface_normal(metaball_data.threshold / sum, gradient_x, gradient_y);
+        let diffuse = normal.dot(light).max(0.0);
+        // the light reflected off of the surface, and how much of it comes straight out of the image
+        let reflected = normal * (2.0 * normal.dot(light)) - light;
+        let highlight = if diffuse > 0.0 { SPECULAR * reflected.z.max(0.0).powi(SHININESS) } else { 0.0 };
+        let mut lit = inside_color(x, y, sum, scale, metaball_data, opts);
+        for channel in &mut lit.0[..3] {
+            let shaded = *channel as f64 * (AMBIENT + (1.0 - AMBIENT) * diffuse) + 255.0 * highlight;
+            *channel = shaded.round().min(255.0) as u8;
+        }
+        blend_over(lit, background)
+    });
+    if !opts.isolines.is_empty() {
+        draw_isolines(&mut image, &field, &opts.isolines, opts.isoline_color);
+    }
+    image
+}
+
+/// The way out of the dome at a pixel `across` of the way from the centers to the edge, facing down the
+/// field's gradient. It faces straight out of the image where the field is flat.
+fn surface_normal(across: f64, gradient_x: f64, gradient_y: f64) -> Point3 {
+    let across = across.clamp(0.0, 1.0);
+    let steepness = gradient_x.hypot(gradient_y);
+    if steepness == 0.0 || !steepness.is_finite() {
+        return Point3::new(0.0, 0.0, 1.0);
+    }
+    Point3::new(-gradient_x / steepness * across, -gradient_y / steepness * across, (1.0 - across * across).sqrt())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metaball, Pointf};
+
+    #[test]
+    fn the_light_comes_from_the_top_left() {
+        let metadata = MetaballData::new(1.0, 1.0, 64, 64, vec![Metaball::new(Pointf { x: 32.0, y: 32.0 }, 24.0)]);
+        let opts = RenderOpts { on_color: Rgba([200, 200, 200, 255]), ..RenderOpts::default() };
+        let image = lit_impl(64, 64, 1, &metadata, &opts);
+        let brightness = |x: u32, y: u32| image.get_pixel(x, y)[0];
+        assert!(brightness(20, 20) > brightness(32, 32));
+        assert!(brightness(32, 32) > brightness(44, 44));
+        assert_eq!(*image.get_pixel(2, 2), opts.off_color);
+    }
+}
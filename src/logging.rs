@@ -0,0 +1,37 @@
+//! What's going on behind the scenes, such as how long renders take and which way the field is evaluated, logged
+//! to STDERR at the verbosity `-v` and `-q` ask for. STDOUT is left to the replies to commands.
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+struct StderrLogger;
+
+static LOGGER: StderrLogger = StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{}] {}", record.level().as_str().to_lowercase(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Start logging: only errors when quiet, then warnings and notices by default, then the timings of every
+/// render with one `-v` and everything with two
+pub fn init(verbose: u8, quiet: bool) {
+    let level = match (quiet, verbose) {
+        (true, _) => LevelFilter::Error,
+        (false, 0) => LevelFilter::Info,
+        (false, 1) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    };
+    // there's only ever the one logger
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
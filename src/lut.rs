@@ -0,0 +1,112 @@
+//! The inverse power falloff looked up in a table for `--precision lut`, so pixels interpolate between
+//! powers worked out once per goo instead of each taking a `powf` per metaball
+
+use crate::falloff::{Falloff, FalloffFn, InversePower};
+use crate::{field_distance, BlendMode, Metaball, MetaballData};
+use std::sync::{Arc, Mutex};
+
+/// How many entries the table has per unit of distance
+const STEPS_PER_UNIT: f64 = 16.0;
+
+/// How close to the center the power is worked out in full, where it curves too much to interpolate
+const NEAR: f64 = 2.0;
+
+/// The table the last render used, kept until the goo changes or a render needs it to reach further
+static LATEST: Mutex<Option<Arc<PowerTable>>> = Mutex::new(None);
+
+/// `1 / distance^goo` at evenly spaced distances
+#[derive(Clone, Debug)]
+pub(crate) struct PowerTable {
+    goo: f64,
+    values: Vec<f64>,
+}
+
+impl PowerTable {
+    fn new(goo: f64, reach: f64) -> PowerTable {
+        let count = (reach * STEPS_PER_UNIT).ceil() as usize + 2;
+        let values = (0..count).map(|i| (i as f64 / STEPS_PER_UNIT).powf(goo).recip()).collect();
+        PowerTable { goo, values }
+    }
+
+    /// `1 / distance^goo`, interpolated between the nearest entries. Distances [NEAR] the center or past the
+    /// end of the table, which are rare, are worked out in full.
+    fn inverse_power(&self, distance: f64) -> f64 {
+        let along = distance * STEPS_PER_UNIT;
+        let index = along as usize;
+        if distance < NEAR || index + 1 >= self.values.len() {
+            return distance.powf(self.goo).recip();
+        }
+        let fraction = along - index as f64;
+        self.values[index] + (self.values[index + 1] - self.values[index]) * fraction
+    }
+}
+
+/// Whether the table can evaluate a scene's field, which takes the summed inverse power falloff
+pub(crate) fn supports(metaball_data: &MetaballData) -> bool {
+    metaball_data.blend == BlendMode::Additive && metaball_data.falloff == Falloff::InversePower && !metaball_data.is_layered()
+}
+
+/// The table for a scene's goo reaching across its whole image, reusing the last one if it still fits
+pub(crate) fn table(metaball_data: &MetaballData) -> Arc<PowerTable> {
+    // a power of two, so resizing the window a little doesn't need a new table
+    let reach = (metaball_data.width as f64 * metaball_data.pixel_aspect).hypot(metaball_data.height as f64).max(1.0);
+    let reach = 2f64.powf(reach.log2().ceil());
+    let mut latest = LATEST.lock().unwrap();
+    match &*latest {
+        Some(table) if table.goo == metaball_data.goo && table.values.len() as f64 >= reach * STEPS_PER_UNIT => table.clone(),
+        _ => {
+            let table = Arc::new(PowerTable::new(metaball_data.goo, reach));
+            *latest = Some(table.clone());
+            table
+        }
+    }
+}
+
+/// The summed field at a point counting only some of the scene's metaballs, like
+/// [field_of](crate::field_of) but looking up the power. Metaballs with a goo of their own are worked out
+/// in full.
+pub(crate) fn field_of(x: f64, y: f64, metaballs: &[Metaball], metaball_data: &MetaballData, table: &PowerTable) -> f64 {
+    metaballs
+        .iter()
+        .map(|metaball| {
+            let distance = field_distance(metaball, x, y, metaball_data);
+            if !metaball.reaches(distance) {
+                return 0.0;
+            }
+            match metaball.goo {
+                Some(goo) => InversePower.value(metaball.size, distance, goo),
+                None => metaball.size * table.inverse_power(distance),
+            }
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{field_at, Pointf};
+
+    #[test]
+    fn looked_up_powers_are_close_to_the_real_ones() {
+        let table = PowerTable::new(1.6, 128.0);
+        for &distance in &[0.0f64, 0.3, 1.0, 2.03, 7.77, 50.5, 127.9, 200.0] {
+            let expected = distance.powf(1.6).recip();
+            let actual = table.inverse_power(distance);
+            assert!(expected == actual || (expected - actual).abs() <= expected * 1e-3, "at {}: {} vs {}", distance, expected, actual);
+        }
+    }
+
+    #[test]
+    fn the_table_follows_the_goo() {
+        let mut metaballs = vec![Metaball::new(Pointf { x: 20.0, y: 30.0 }, 40.0), Metaball::new(Pointf { x: 44.0, y: 36.0 }, -12.0)];
+        metaballs[1].goo = Some(2.5);
+        for &goo in &[1.6, 2.0, 1.6] {
+            let metadata = MetaballData::new(goo, 0.5, 64, 64, metaballs.clone());
+            let table = table(&metadata);
+            for &(x, y) in &[(0.0, 0.0), (21.5, 30.0), (40.0, 40.0), (63.0, 10.0)] {
+                let (expected, actual) = (field_at(x, y, &metadata), field_of(x, y, &metadata.metaballs, &metadata, &table));
+                assert!((expected - actual).abs() <= expected.abs() * 1e-3, "goo {} at ({}, {}): {} vs {}", goo, x, y, expected, actual);
+            }
+        }
+    }
+}
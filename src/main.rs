@@ -1,331 +1,4489 @@
-use std::path::PathBuf;
-
-use image::{ImageBuffer, Rgba};
-use pixels::SurfaceTexture;
+use image::png::PngEncoder;
+use image::{ColorType, ImageBuffer, Rgba};
+use metaballs::compare::Comparison;
+use metaballs::behavior::Behavior;
+use metaballs::dataset::scene_json;
+use metaballs::falloff::Falloff;
+use metaballs::gamepad::{self, Action, Gamepad};
+use metaballs::midi::{self, Change, MidiMap};
+use metaballs::json::{self, Json};
+use metaballs::incremental::IncrementalRender;
+use metaballs::keyframes::{Keyframes, Playback};
+use metaballs::layer::{LayerBlend, MAX_LAYER};
+use metaballs::motion::{self, Motion, PathKind, Pulse, MOTION_PERIOD};
+use metaballs::noise::{Noise, NOISE_SCALE};
+use metaballs::osc::{self, Arg};
+use metaballs::raymarch::Camera;
+use metaballs::recording::{is_video, record, record_sheet, LiveRecording, Recording, Sequence, VideoWriter, TWEEN_FRAMES};
+use metaballs::slots::{Slots, SLOTS};
+use metaballs::symmetry::Symmetry;
+use metaballs::colormap::{Colormap, HeatScale};
+use metaballs::theme::{Theme, THEMES};
+use metaballs::touch::{self, Gesture, Touches};
+use metaballs::{
+    export_field, export_mesh, export_sdf, export_svg, field_at, hex_color, load_background, naive_impl, parse_color, preset, random_metaball, render_image,
+    Bands, BlendMode, Easing, Generation, HueCycle, IndicatorStyle, Metaball, MetaballData, Pointf, Precision, Primitive,
+    RenderMode, RenderOpts, SceneConfig, Tween, View, ANIMATION_TICK, BASE_METABALL_SIZE, MAX_METABALL_COUNT, MESH_CELL, MIN_COUNT_FACTOR, SDF_SPREAD,
+};
+use pixels::{wgpu, SurfaceTexture};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use structopt::StructOpt;
 use winit::{
-    event::{Event, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
-    window::WindowBuilder,
+    event::{Event, TouchPhase, WindowEvent},
+    event_loop::{ControlFlow, EventLoop, EventLoopProxy},
+    window::{Fullscreen, Window, WindowBuilder},
 };
-use winit::dpi::LogicalSize;
-use std::io::{stdin, BufRead};
-use std::sync::mpsc::{Sender, TryRecvError};
+use winit::dpi::{LogicalSize, PhysicalSize};
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, stdin, BufRead, BufReader, IsTerminal, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, UdpSocket};
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
+use std::path::{Path, PathBuf};
+use std::process::{self, Stdio};
+use std::sync::mpsc::Sender;
 use std::str::FromStr;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use lazy_static::lazy_static;
 use winit_input_helper::WinitInputHelper;
+
+#[cfg(feature = "audio")]
+mod capture;
+mod error;
+#[cfg(feature = "gpu")]
+mod gpu;
+mod logging;
+mod panel;
+mod session;
+mod tui;
+use error::MetaballError;
+use panel::{Panel, PanelFrame};
+use tui::{Glyphs, Graphics, Key, Terminal};
 use winit::event::VirtualKeyCode;
 
-/// The base metaball size for the provided generation function
-const BASE_METABALL_SIZE: f64 = 90.0;
+/// The seed for the scenes rendered by the bench command
+const BENCH_SEED: u64 = 0x6d65_7461;
+
+/// How often --watch checks whether the scene file has changed
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The slowest frame rate anything's drawn or recorded at, a frame every thousand seconds, so the time
+/// between frames fits in a [Duration]
+const MIN_FPS: f64 = 0.001;
+
+/// How many times smaller each way --progressive renders while the scene's being edited
+const PREVIEW_SCALE: u32 = 4;
+
+/// How long after the last edit --progressive waits to render the scene in full again
+const SETTLE: Duration = Duration::from_millis(150);
+
+/// How many buffer pixels from a metaball's center a click can be to pick it up
+const PICK_RADIUS: f64 = 8.0;
+
+/// How many pixels from the cursor a lagging metaball following it is taken to have caught up
+const FOLLOW_CLOSE_ENOUGH: f64 = 0.05;
+
+/// How much of the usual threshold and goo steps the arrow keys take with Shift held, for fine tuning
+const FINE_STEP: f64 = 0.1;
+
+/// How much one step of the mouse wheel zooms in by
+const ZOOM_STEP: f64 = 1.1;
+
+/// How many radians the arrow keys orbit the raymarched 3D view's camera by
+const ORBIT_STEP: f64 = 0.1;
+
+/// How much the [ and ] keys change the hue cycling speed by, in degrees per tick
+const HUE_SPEED_STEP: f64 = 0.5;
+
+/// The most samples each way per pixel that supersampling takes
+const MAX_SSAA: u32 = 8;
+
+/// The keys that recall the scene slots, and save to them with Shift held, in the order of [SLOTS]
+const SLOT_KEYS: [VirtualKeyCode; 9] = [
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+];
+
+/// The grid spacing the G key snaps to when no other spacing has been set
+const DEFAULT_SNAP_SPACING: u32 = 16;
 
-/// The minimum metaball count for the provided generation function
-const MIN_METABALL_COUNT: u32 = 3;
+/// The format of the window's surface, which the pixel buffer and the control panel both draw to
+const SURFACE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Bgra8UnormSrgb;
 
 /// Load help.txt for outputting to command line
-const HELP: &'static str = include_str!("help.txt");
+const HELP: &str = include_str!("help.txt");
 
-/// The pixel color to draw for being inside the shape
-const ON_PIXEL: Rgba<u8> = Rgba([255u8, 0, 0, 255]);
+/// Command line options
+#[derive(StructOpt, Debug)]
+#[structopt(name = "metaballs", about = "Renders metaballs in a window")]
+struct Opt {
+    #[structopt(flatten)]
+    scene: SceneOpts,
 
-/// The background pixel
-const OFF_PIXEL: Rgba<u8> = Rgba([0u8, 0, 0, 255]);
+    /// A set of colors that go together, in place of --fg, --bg, --cross-color and --isoline-color. The N
+    /// key cycles through them.
+    #[structopt(long, possible_values = &["classic", "neon", "mono", "pastel"])]
+    theme: Option<Theme>,
 
-/// Print the help information to STDOUT
-fn print_help() {
-    println!("{}", HELP);
+    /// Color of the inside of the metaballs, as hex RRGGBB or RRGGBBAA
+    #[structopt(long, default_value = "#ff0000", parse(try_from_str = parse_color))]
+    fg: Rgba<u8>,
+
+    /// Color of the background, as hex RRGGBB or RRGGBBAA
+    #[structopt(long, default_value = "#000000", parse(try_from_str = parse_color))]
+    bg: Rgba<u8>,
+
+    /// Give each metaball its own color, mixing them where metaballs meet
+    #[structopt(long)]
+    ball_colors: bool,
+
+    /// Draw a histogram of the field values across the frame over its bottom left corner, with the threshold
+    /// marked, to see how much of the frame a change to the threshold takes in or out of the shape
+    #[structopt(long)]
+    histogram: bool,
+
+    /// Write the background fully transparent in exported images and recordings, whatever its color, so the
+    /// metaballs composite cleanly onto other artwork
+    #[structopt(long)]
+    transparent: bool,
+
+    /// An image to draw the metaballs over instead of the background color
+    #[structopt(long, parse(from_os_str))]
+    bg_image: Option<PathBuf>,
+
+    /// Color of the center point indicators, as hex RRGGBB or RRGGBBAA
+    #[structopt(long, default_value = "#0000ff", parse(try_from_str = parse_color))]
+    cross_color: Rgba<u8>,
+
+    /// Shape of the center point indicators
+    #[structopt(long, default_value = "cross", possible_values = &["cross", "dot", "ring"])]
+    cross_style: IndicatorStyle,
+
+    /// How many pixels the center point indicators reach out from the center
+    #[structopt(long, default_value = "1")]
+    cross_radius: u32,
+
+    /// Field levels to draw contour lines at, separated by commas
+    #[structopt(long, use_delimiter = true)]
+    isolines: Vec<f64>,
+
+    /// Color of the contour lines, as hex RRGGBB or RRGGBBAA
+    #[structopt(long, default_value = "#ffffff", parse(try_from_str = parse_color))]
+    isoline_color: Rgba<u8>,
+
+    /// Color of the edge in the outline mode, as hex RRGGBB or RRGGBBAA, the foreground color otherwise
+    #[structopt(long, parse(try_from_str = parse_color))]
+    outline_color: Option<Rgba<u8>>,
+
+    /// Draw bands of color instead of the shape, coloring each pixel by the highest field level it's above.
+    /// The stops are level:color separated by commas, such as 0.3:#000080,0.5:#0000ff. Only the naive and
+    /// quadtree modes draw bands.
+    #[structopt(long, parse(try_from_str = parse_bands))]
+    bands: Option<Bands>,
+
+    /// Snap the metaballs added with the add command to a grid this many pixels apart
+    #[structopt(long, parse(try_from_str = parse_spacing))]
+    snap: Option<u32>,
+
+    /// How to turn the field into pixels: threshold every pixel, interpolate the field across cells with
+    /// marching squares for smoother edges, color every pixel by the field value as a heatmap, raymarch
+    /// the metaballs as balls in 3D, shade the shape as if it were glossy and lit from the top left, only
+    /// draw the outline of the shape, dither the field in the foreground and background colors for a
+    /// 1-bit look, color the shape by which metaball adds the most to the field, or threshold the field
+    /// like naive while only evaluating it near the edge of the shape
+    #[structopt(
+        long,
+        default_value = "naive",
+        possible_values = &["naive", "marching-squares", "heatmap", "raymarch", "lit", "outline", "dither", "regions", "quadtree"]
+    )]
+    mode: RenderMode,
+
+    /// How precisely the field is evaluated at each pixel. f32 is often a few times faster with no visible
+    /// difference, for the summed field, lut looks the inverse power falloff up in a table instead of
+    /// working out powers, and the bench command times any of them.
+    #[structopt(long, default_value = "f64", possible_values = &["f64", "f32", "lut"])]
+    precision: Precision,
+
+    /// The colors of the heatmap mode, the classic black to red or the viridis, magma and turbo colormaps
+    /// used for figures
+    #[structopt(long, default_value = "classic", possible_values = &["classic", "viridis", "magma", "turbo"])]
+    colormap: Colormap,
+
+    /// Which field values the heatmap runs from cold to hot: threshold puts the shape's edge halfway,
+    /// frame spreads the colormap from the lowest to the highest field value in each frame, and a range
+    /// like 0:2 fixes it, so frames and images can be compared
+    #[structopt(long, default_value = "threshold")]
+    heat_scale: HeatScale,
+
+    /// Degrees the raymarched 3D view's camera starts around the image, see --mode
+    #[structopt(long, default_value = "0", allow_hyphen_values = true)]
+    yaw: f64,
+
+    /// Degrees the raymarched 3D view's camera starts above the image, within (-90, 90)
+    #[structopt(long, default_value = "0", allow_hyphen_values = true, parse(try_from_str = parse_pitch))]
+    pitch: f64,
+
+    /// Render the raymarched 3D view as a red-cyan anaglyph, from two eyes a little apart, so the metaballs
+    /// pop out of the screen through 3D glasses
+    #[structopt(long)]
+    anaglyph: bool,
+
+    /// How many pixels wide the marching squares cells are, bigger cells are faster but less accurate
+    #[structopt(long, default_value = "4", parse(try_from_str = parse_cell_size))]
+    cell_size: u32,
+
+    /// Supersampling factor, evaluates the field at N×N samples per pixel and averages them to smooth the
+    /// edges, within [1, 8]. --aa works too.
+    #[structopt(long, alias = "aa", default_value = "1", parse(try_from_str = parse_ssaa))]
+    ssaa: u32,
+
+    /// Render at a fraction of the window's resolution, each pixel drawn N×N pixels big, so big windows and
+    /// fullscreen with F11 stay smooth. The window starts N times the --width and --height.
+    #[structopt(long, default_value = "1", parse(try_from_str = parse_downscale))]
+    downscale: u32,
+
+    /// Render the window at the display's physical resolution, a pixel of the scene for each pixel of the
+    /// screen on HiDPI displays, for crisp output. Otherwise each pixel of the scene is as many pixels of the
+    /// screen as the scale factor rounded down, so 2x displays show the same scene as 1x ones.
+    #[structopt(long)]
+    native_resolution: bool,
+
+    /// Log more of what's going on to STDERR: how long each render takes and each of its steps with -v,
+    /// and which way the field is evaluated too with -vv
+    #[structopt(short, long, parse(from_occurrences))]
+    verbose: u8,
+
+    /// Only log errors to STDERR, leaving out the notices and warnings
+    #[structopt(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Print how long each render takes, or the average frame rate while animating. `--bench` is the same.
+    #[structopt(long, alias = "bench")]
+    timing: bool,
+
+    /// Start with the metaballs moving, as if P had been pressed. In batch mode GIF exports record the
+    /// animation instead of new random scenes.
+    #[structopt(long)]
+    animate: bool,
+
+    /// Draw at most this many frames a second while anything's moving, 62.5 by default, or as many as it
+    /// can with 0. Below that each frame moves the animation on by as many ticks as it lasts so it keeps
+    /// its pace, and above it or uncapped every frame is a tick, speeding the animation up.
+    #[structopt(long, parse(try_from_str = parse_frame_cap))]
+    fps: Option<f64>,
+
+    /// Start with vsync off, as if V had been pressed, so frames aren't held back to the display's refresh
+    /// rate
+    #[structopt(long)]
+    no_vsync: bool,
+
+    /// What renders the field, cpu or gpu. The GPU evaluates it in a compute shader when the build has the
+    /// gpu feature and an adapter is found, falling back to the CPU otherwise and for anything it can't draw.
+    #[structopt(long, default_value = "cpu", possible_values = &["cpu", "gpu"])]
+    backend: Backend,
+
+    /// React to the default audio input in the window, like a music visualizer: the bass swells the
+    /// metaballs, the mids melt them together and the treble sharpens them. Needs the audio feature.
+    #[structopt(long)]
+    audio: bool,
+
+    /// Run the commands in a file, one per line, before reading commands from STDIN.
+    /// A `sleep <ms>` line waits before moving on to the next command.
+    #[structopt(long, parse(from_os_str))]
+    script: Option<PathBuf>,
+
+    /// Listen for OSC messages on a UDP port, or an address and port, and apply them like the commands from
+    /// STDIN. The addresses are /metaballs/goo, /metaballs/threshold, /metaballs/ball/<index>/pos,
+    /// /metaballs/ball/<index>/size, /metaballs/ball/<index>/remove, /metaballs/add, /metaballs/preset,
+    /// /metaballs/randomize and /metaballs/reset.
+    #[structopt(long, parse(try_from_str = parse_socket_address))]
+    osc: Option<SocketAddr>,
+
+    /// Accept connections on a TCP port, or an address and port, sending the same commands as STDIN a line
+    /// at a time. Connections starting with a WebSocket handshake send them as text messages instead.
+    #[structopt(long, parse(try_from_str = parse_socket_address))]
+    listen: Option<SocketAddr>,
+
+    /// Accept connections on a Unix domain socket at a path, sending the same commands as STDIN a line at a
+    /// time, so several controllers can attach at once. A socket left behind at the path is replaced.
+    /// Only on Unix.
+    #[structopt(long, parse(from_os_str))]
+    control_socket: Option<PathBuf>,
+
+    /// Drive the scene from a gamepad, read from a Linux joystick device such as /dev/input/js0. The left
+    /// stick moves the selected metaball and the bumpers select another, the triggers change the goo, or
+    /// the threshold while X is held, and A randomizes the scene.
+    #[structopt(long, parse(from_os_str))]
+    gamepad: Option<PathBuf>,
+
+    /// Drive the scene from the knobs of a MIDI controller, read from a raw MIDI device such as
+    /// /dev/snd/midiC1D0. Control changes 1 and 2 set the goo and threshold and 3 to 10 the sizes of the
+    /// first eight metaballs, unless --midi-map says otherwise.
+    #[structopt(long, parse(from_os_str))]
+    midi: Option<PathBuf>,
+
+    /// A TOML file mapping the --midi control changes onto the goo, threshold and metaball sizes, with a
+    /// [[cc]] table for each of number, target ("goo", "threshold" or "size"), min and max, and the index
+    /// of the metaball for a size. A channel from 1 to 16 limits it to that channel.
+    #[structopt(long, parse(from_os_str), requires = "midi")]
+    midi_map: Option<PathBuf>,
+
+    /// Run a program that's sent the scene as a line of JSON each animation tick and answers with a line
+    /// of JSON moving, resizing, adding or removing metaballs or changing the goo and threshold, for
+    /// custom animations without rebuilding. See the README for what's sent and answered.
+    #[structopt(long, parse(from_os_str))]
+    behavior: Option<PathBuf>,
+
+    /// Render the starting scene to the --output image and exit without opening the window, the same as
+    /// the render command
+    #[structopt(long, requires = "output")]
+    headless: bool,
+
+    /// Draw the scene in the terminal instead of opening a window, with most of the window's keys and the
+    /// commands typed after a colon. STDIN commands still work when they're piped in.
+    #[structopt(long)]
+    tui: bool,
+
+    /// Draw the scene in the --tui with Braille dots, four times as many as the half blocks it's otherwise
+    /// drawn with but in one color a character
+    #[structopt(long, requires = "tui")]
+    braille: bool,
+
+    /// Draw the scene in the --tui as an image with the kitty graphics protocol or sixel, for terminals
+    /// that show images, such as over SSH without X forwarding. --downscale renders it at a fraction of the
+    /// terminal's resolution.
+    #[structopt(long, requires = "tui", conflicts_with = "braille")]
+    graphics: Option<Graphics>,
+
+    /// The image --headless writes
+    #[structopt(long, parse(from_os_str), requires = "headless")]
+    output: Option<PathBuf>,
+
+    /// Write the outline of the starting scene to an SVG file before doing anything else
+    #[structopt(long, parse(from_os_str))]
+    export_svg: Option<PathBuf>,
+
+    /// Write the surface of the starting scene in 3D, like the raymarched view, to an OBJ or STL file
+    /// before doing anything else
+    #[structopt(long, parse(from_os_str))]
+    export_mesh: Option<PathBuf>,
+
+    /// How many pixels wide the cubes are that --export-mesh samples the field in, smaller ones make finer
+    /// meshes
+    #[structopt(long, default_value = "2", parse(try_from_str = parse_mesh_cell))]
+    mesh_cell: f64,
+
+    /// Write an approximate signed distance field of the starting scene's shape to a 16-bit grayscale PNG,
+    /// or raw f32 distances in pixels for a .f32 path, before doing anything else
+    #[structopt(long, parse(from_os_str))]
+    export_sdf: Option<PathBuf>,
+
+    /// How many pixels either side of the edge an --export-sdf PNG reaches before it's white or black
+    #[structopt(long, default_value = "16", parse(try_from_str = parse_sdf_spread))]
+    sdf_spread: f64,
+
+    /// Record the starting scene to an animated GIF, or PNG if the path ends in .png, before doing anything
+    /// else. With --keyframes it records them, with --animate the animation, and otherwise it morphs
+    /// between random scenes. A .y4m path or - for stdout records raw Y4M video instead, and a .mp4, .webm,
+    /// .mkv or .mov path a video encoded by ffmpeg.
+    #[structopt(long, alias = "export-video", parse(from_os_str))]
+    export_gif: Option<PathBuf>,
+
+    /// Record the same frames as --export-gif in a grid on one PNG, a sprite sheet, with a JSON map of where
+    /// each frame is in it written next to it
+    #[structopt(long, parse(from_os_str))]
+    export_sheet: Option<PathBuf>,
+
+    /// How many frames --export-gif records, it records the --keyframes once through instead
+    #[structopt(long, default_value = "60", parse(try_from_str = parse_frame_count))]
+    export_frames: u32,
+
+    /// How many milliseconds each frame --export-gif records is shown for, an animation tick by default
+    #[structopt(long)]
+    export_delay: Option<u64>,
+
+    /// How many frames a second --export-gif and --record-video record, in place of --export-delay
+    #[structopt(long, conflicts_with = "export-delay", parse(try_from_str = parse_fps))]
+    export_fps: Option<f64>,
+
+    /// Record everything shown in the window to a video as it happens, as raw Y4M to a .y4m path or
+    /// encoded by ffmpeg to a .mp4, .webm, .mkv or .mov one, one frame per --export-delay
+    #[structopt(long, parse(from_os_str))]
+    record_video: Option<PathBuf>,
+
+    /// How much the Up and Down arrow keys change the threshold by, a tenth as much with Shift held
+    #[structopt(long, default_value = "0.05")]
+    threshold_step: f64,
+
+    /// How much the Left and Right arrow keys change the goo by, a tenth as much with Shift held
+    #[structopt(long, default_value = "0.05")]
+    goo_step: f64,
+
+    /// How many degrees the inside color's hue moves each animation tick when cycling it with the H key
+    #[structopt(long, default_value = "1")]
+    hue_speed: f64,
+
+    /// How many animation ticks the T key takes to morph into a new random scene
+    #[structopt(long, default_value = "30")]
+    tween_frames: u32,
+
+    /// Make SPACE morph into the new random scene like T does, instead of swapping to it at once
+    #[structopt(long)]
+    morph: bool,
+
+    /// Start with the metaball at this index following the mouse cursor, for stirring the scene around.
+    /// M toggles following, with the first metaball if not given.
+    #[structopt(long)]
+    follow: Option<usize>,
+
+    /// How far behind the cursor the followed metaball lags, within [0, 1). It's left this fraction of the
+    /// way from the cursor each frame, so 0 keeps it right on the cursor.
+    #[structopt(long, default_value = "0", parse(try_from_str = parse_lag))]
+    follow_lag: f64,
+
+    /// How many milliseconds the window eases the goo and threshold over when they change, 0 to snap to
+    /// the new values
+    #[structopt(long, default_value = "200")]
+    ease: u64,
+
+    /// While the scene's being edited faster than it can be rendered, such as dragging a metaball or a
+    /// slider in an expensive scene, render it at a quarter of the resolution and sharpen it once the edits
+    /// settle
+    #[structopt(long)]
+    progressive: bool,
+
+    /// Play the keyframes in a TOML file, starting from the first one. Each keyframe has a time in seconds
+    /// and the goo, threshold and metaballs like a --config scene, see the help text for an example.
+    #[structopt(long, parse(from_os_str))]
+    keyframes: Option<PathBuf>,
+
+    /// The TOML file Shift and the number keys save scenes to, for the number keys to recall them
+    #[structopt(long, parse(from_os_str), default_value = "metaballs-slots.toml")]
+    slots: PathBuf,
+
+    /// How many scene edits Ctrl+Z and the undo command can undo, older ones are forgotten
+    #[structopt(long, default_value = "100")]
+    history_depth: usize,
+
+    /// Load the --scene file again whenever it changes, for editing it in an editor and seeing the changes
+    /// straight away
+    #[structopt(long, requires = "config")]
+    watch: bool,
+
+    #[structopt(subcommand)]
+    command: Option<Command>,
 }
 
-// TODO: Add faster algorithm
-/// A naive implementation to render metaballs. This is slow, but works.
-fn naive_impl(width: u32, height: u32, metaball_data: &MetaballData) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
-    let math_func = |x, y| {
-        // sum the metaball values
-        let sum = metaball_data.metaballs.iter().fold(0f64, |acc, metaball| {
-            let numerator = metaball.size; // the size of the metaball
+/// The options for the starting scene, which every command shares
+#[derive(StructOpt, Debug)]
+struct SceneOpts {
+    /// Seed the random scenes so they're the same every time
+    #[structopt(long)]
+    seed: Option<u64>,
 
-            // the distance of the metaball
-            let denominator = metaball.location.distance(&Point { x, y }).powf(metaball_data.goo);
+    /// Width of the image in pixels
+    #[structopt(long, default_value = "256", parse(try_from_str = parse_dimension))]
+    width: u32,
 
-            acc + numerator / denominator
-        });
-        // if the sum if greater than the threshold then draw a pixel
-        sum > metaball_data.threshold
-    };
-    // Use the above closure to determine whether each individual pixel should be on or off
-    let image = ImageBuffer::from_fn(width, height, |x, y| {
-        if math_func(x, y) {
-            ON_PIXEL
-        } else {
-            OFF_PIXEL
-        }
-    });
-    image
+    /// Height of the image in pixels
+    #[structopt(long, default_value = "256", parse(try_from_str = parse_dimension))]
+    height: u32,
+
+    /// How quickly the field falls off with distance from each metaball, for random scenes
+    #[structopt(long, default_value = "1.6", parse(try_from_str = parse_positive))]
+    goo: f64,
+
+    /// The field value at the edge of the shape, for random scenes
+    #[structopt(long, default_value = "0.5", parse(try_from_str = parse_positive))]
+    threshold: f64,
+
+    /// Fraction of the width and height on each side that random metaballs are kept out of, within [0, 0.5)
+    #[structopt(long, default_value = "0", parse(try_from_str = parse_margin))]
+    margin: f64,
+
+    /// The typical size of random metaballs, which the size spread scales
+    #[structopt(long, default_value = "90", parse(try_from_str = parse_positive))]
+    base_size: f64,
+
+    /// The fewest metaballs a random scene has, at most 1000
+    #[structopt(long, default_value = "3", parse(try_from_str = parse_count))]
+    min_count: u32,
+
+    /// How quickly scenes with more metaballs than the minimum get less likely, bigger factors make
+    /// sparser scenes. It's at least 0.01, and scenes have at most 1000 metaballs whatever it is.
+    #[structopt(long, default_value = "0.5", parse(try_from_str = parse_count_factor))]
+    count_factor: f64,
+
+    /// Give every random scene exactly this many metaballs, instead of a random number past the minimum, at
+    /// most 1000
+    #[structopt(long, parse(try_from_str = parse_count))]
+    count: Option<u32>,
+
+    /// Random metaball sizes are within [spread / 2, spread * 1.5] times the base size, within (0, 1)
+    #[structopt(long, default_value = "0.5", parse(try_from_str = parse_spread))]
+    size_spread: f64,
+
+    /// The fraction of random metaballs that are negative, carving holes out of the shape, within [0, 1]
+    #[structopt(long, default_value = "0", parse(try_from_str = parse_fraction))]
+    negative_fraction: f64,
+
+    /// Blend the metaballs with a smooth union of their distances instead of summing them, merging
+    /// metaballs whose edges are within this many pixels of each other
+    #[structopt(long, parse(try_from_str = parse_positive))]
+    smooth_union: Option<f64>,
+
+    /// How what each metaball adds to the field falls off with distance: inverse-power, a gaussian bell
+    /// curve, or the wyvill soft object polynomial which stops at twice the distance it adds 1 at
+    #[structopt(long, default_value = "inverse-power", possible_values = &["inverse-power", "gaussian", "wyvill"])]
+    falloff: Falloff,
+
+    /// Correct for pixels displayed this many times wider than they are tall, so metaballs render as
+    /// circles rather than ellipses. The default of 1 is for square pixels.
+    #[structopt(long, default_value = "1", parse(try_from_str = parse_positive))]
+    pixel_aspect: f64,
+
+    /// Wrap the field around the edges, making a seamlessly tiling image, the exports too. The animation
+    /// carries metaballs off one edge onto the other. --wrap works too.
+    #[structopt(long, alias = "wrap")]
+    tile: bool,
+
+    /// Start with the physics mode on, where animating drops the metaballs under gravity to settle and merge
+    #[structopt(long)]
+    physics: bool,
+
+    /// How many pixels per tick the physics mode speeds the metaballs up downwards by each tick, upwards
+    /// when negative and not at all at 0
+    #[structopt(long, default_value = "0.2", allow_hyphen_values = true, parse(try_from_str = parse_finite))]
+    gravity: f64,
+
+    /// The fraction of their speed the metaballs keep each tick of the physics mode, within [0, 1]
+    #[structopt(long, default_value = "0.98", parse(try_from_str = parse_fraction))]
+    damping: f64,
+
+    /// How strongly the metaballs pull on each other in the physics mode, pushing each other away when
+    /// negative
+    #[structopt(long, default_value = "0.5", allow_hyphen_values = true, parse(try_from_str = parse_finite))]
+    attraction: f64,
+
+    /// How stiffly the metaballs bounce off each other in the physics mode where they overlap, instead of
+    /// merging at 0, such as 0.05 for soft collisions
+    #[structopt(long, default_value = "0", parse(try_from_str = parse_non_negative))]
+    collision: f64,
+
+    /// Mirror every metaball across the middle of the image, or repeat it around the middle, both for random
+    /// metaballs and ones being added or edited: none, horizontal, vertical, both or radial:<copies>
+    #[structopt(long, default_value = "none")]
+    symmetry: Symmetry,
+
+    /// Wobble the edges of the shape by multiplying the field with Perlin noise, scaling it up or down by
+    /// as much as this fraction of it, within [0, 1]
+    #[structopt(long, parse(try_from_str = parse_fraction))]
+    noise: Option<f64>,
+
+    /// About how many pixels apart the bumps of the --noise are
+    #[structopt(long, default_value = "32", parse(try_from_str = parse_positive))]
+    noise_scale: f64,
+
+    /// Send every metaball around a random orbit or Lissajous curve of its own when animating, instead of
+    /// bouncing around, which loops forever
+    #[structopt(long, possible_values = &["orbit", "lissajous"])]
+    motion: Option<PathKind>,
+
+    /// Make every metaball breathe when animating, swinging its size this fraction of it either way, within
+    /// [0, 1), each at a random rate and starting point of its own
+    #[structopt(long, parse(try_from_str = parse_amplitude))]
+    pulse: Option<f64>,
+
+    /// Start from a named preset scene instead of a random one: binary, ring, grid or line
+    #[structopt(long, conflicts_with = "config")]
+    preset: Option<String>,
+
+    /// Start from the scene in a TOML file instead of a random one, such as one saved with the save command
+    #[structopt(long, alias = "scene", parse(from_os_str))]
+    config: Option<PathBuf>,
 }
 
-fn control_stdin(tx: Sender<ControlCommand>) {
-    std::thread::spawn(
+impl SceneOpts {
+    /// How random scenes and metaballs are generated
+    pub fn generation(&self) -> Generation {
+        Generation {
+            margin: self.margin,
+            base_size: self.base_size,
+            min_count: self.min_count,
+            count_factor: self.count_factor,
+            count: self.count,
+            size_spread: self.size_spread,
+            negative_fraction: self.negative_fraction,
+        }
+    }
 
-        move || {
-            // set up reading from stdin
-            let stdinput = stdin();
-            let mut input = stdinput.lock();
-            let mut linebuf = String::new();
-            loop {
-                // read input line
-                input.read_line(&mut linebuf).unwrap();
-                let line = linebuf.trim();
-                let first_char = line.chars().next();
-                if first_char.is_none() { continue; }
-
-                match first_char.unwrap() {
-                    // Goo
-                    'g' => {
-                        match f64::from_str(&line[1..]) {
-                            Ok(val) => {tx.send(ControlCommand::Goo(val)).unwrap();}
-                            Err(_) => {println!("Unable to parse to float \"{}\"", &line[1..])}
-                        }
-                    },
-                    // Threshold
-                    't' => {
-                        match f64::from_str(&line[1..]) {
-                            Ok(val) => {tx.send(ControlCommand::Threshold(val)).unwrap();}
-                            Err(_) => {println!("Unable to parse to float \"{}\"", &line[1..])}
-                        }
-                    },
-                    _ => {
-                        println!("Unknown command.")
-                    }
-                }
-                linebuf.clear();
+    /// The random number generator for the scene, seeded if asked to be
+    pub fn rng(&self) -> StdRng {
+        match self.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        }
+    }
 
+    /// Generate or load the starting scene
+    pub fn scene(&self, rng: &mut impl Rng) -> Result<MetaballData, String> {
+        let (width, height) = (self.width, self.height);
+        let mut metadata = match (&self.config, &self.preset) {
+            (Some(path), _) => SceneConfig::load(path)
+                .and_then(|config| config.into_data(width, height))
+                .map_err(|err| format!("Unable to load {}: {}", path.display(), err))?,
+            (None, Some(name)) => preset(name, width, height)?,
+            (None, None) => {
+                let mut metadata = MetaballData::from_rng(self.goo, self.threshold, width, height, &self.generation(), rng);
+                // the random count is of all the copies
+                metadata.metaballs.truncate(metadata.metaballs.len().div_ceil(self.symmetry.copies()));
+                metadata
             }
+        };
+        if let Some(k) = self.smooth_union {
+            metadata.blend = BlendMode::SmoothUnion { k };
         }
-    );
+        metadata.falloff = self.falloff;
+        metadata.pixel_aspect = self.pixel_aspect;
+        metadata.tile = self.tile;
+        metadata.physics.enabled = self.physics;
+        metadata.physics.gravity = self.gravity;
+        metadata.physics.damping = self.damping;
+        metadata.physics.attraction = self.attraction;
+        metadata.physics.collision = self.collision;
+        if let Some(kind) = self.motion {
+            for metaball in &mut metadata.metaballs {
+                metaball.motion = Some(Motion::starting_at(kind.random(rng), metaball.location, MOTION_PERIOD));
+            }
+        }
+        if let Some(amplitude) = self.pulse {
+            for metaball in &mut metadata.metaballs {
+                metaball.set_pulse(Some(Pulse::new(amplitude, rng.gen_range(0.2..0.6), rng.gen())));
+            }
+        }
+        metadata.set_symmetry(self.symmetry);
+        metadata.noise = self.noise.map(|amplitude| Noise { amplitude, scale: self.noise_scale });
+        Ok(metadata)
+    }
 }
 
-/// A control command that can be sent from one thread to another
-#[derive(Debug)]
-enum ControlCommand {
-    /// Adjust goo factor
-    Goo(f64),
+/// Things to do instead of opening the window
+#[derive(StructOpt, Debug)]
+enum Command {
+    /// Open the window, which is what happens without a command
+    Run,
 
-    /// Adjust the threshold factor
-    Threshold(f64),
-}
+    /// Run commands from the --script file and STDIN without opening the window, exiting at the end of
+    /// the input. Useful for exporting fields, outlines and GIFs from a script.
+    Batch,
 
-lazy_static! {
-    /// The relative points to the center point to draw a cross
-    static ref CROSS: Vec<RelPoint> = {
-        vec![RelPoint{x: 0, y: 0}, RelPoint{x: 1, y: 0}, RelPoint{x: -1, y: 0}, RelPoint{x: 0, y: 1}, RelPoint{x: 0, y: -1}]
-    };
+    /// Time rendering over a range of resolutions and metaball counts
+    Bench {
+        /// Square image sizes to render, separated by commas
+        #[structopt(long, use_delimiter = true, default_value = "128,256,512,1024")]
+        resolutions: Vec<u32>,
+
+        /// Numbers of metaballs to render, separated by commas
+        #[structopt(long, use_delimiter = true, default_value = "4,16,64")]
+        counts: Vec<u32>,
+
+        /// How many times to render each combination
+        #[structopt(long, default_value = "5")]
+        iterations: u32,
+    },
+
+    /// Render random scenes to numbered PNG files without opening the window, for datasets of metaball
+    /// images. The first is the starting scene and the rest are random like SPACE makes them.
+    Generate {
+        /// The directory to write the images to, which is made if it isn't there
+        #[structopt(parse(from_os_str))]
+        dir: PathBuf,
+
+        /// How many scenes to render
+        #[structopt(long, short = "n", default_value = "100")]
+        count: u32,
+
+        /// Label each image with the parameters of its scene's metaballs, in a JSON file of the same name
+        #[structopt(long)]
+        json: bool,
+    },
+
+    /// Render the starting scene to an image file without opening the window
+    Render {
+        /// The image to write, in any format the extension names such as .png
+        #[structopt(parse(from_os_str), required_unless = "raw")]
+        out: Option<PathBuf>,
+
+        /// Write the raw RGBA bytes of the image to STDOUT instead, row by row with 4 bytes a pixel, for
+        /// piping into other tools. Nothing else is written to STDOUT.
+        #[structopt(long, conflicts_with = "out")]
+        raw: bool,
+    },
 }
 
-#[derive(Default)]
-struct RenderOpts {
-    pub crosses: bool,
+/// What renders the field
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum Backend {
+    Cpu,
+    Gpu,
 }
 
-/// Use the metaball formula to detect which pixels should be highlighted to create a metaball image
-fn render_metaballs(screenbuffer: &mut [u8], metaballs: &MetaballData, opts: &RenderOpts) {
-    // draw base metaballs
-    let mut meta = naive_impl(256, 256, &metaballs);
+impl FromStr for Backend {
+    type Err = String;
 
-    // draw center point indicators
-    if opts.crosses {
-        for ball in &metaballs.metaballs {
-            let pos = ball.location;
-            for modifier in CROSS.iter() {
-                *(meta.get_pixel_mut((pos.x as i64 + modifier.x) as u32, (pos.y as i64 + modifier.y) as u32)) = Rgba([0u8, 0, 255, 255])
-            }
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cpu" => Ok(Backend::Cpu),
+            "gpu" => Ok(Backend::Gpu),
+            _ => Err(format!("Unknown backend \"{}\", expected cpu or gpu", s)),
         }
     }
+}
 
+/// Parse the width of the cubes meshes sample the field in, which needs to be positive
+fn parse_mesh_cell(cell: &str) -> Result<f64, String> {
+    let cell = f64::from_str(cell).map_err(|err| err.to_string())?;
+    if cell.is_finite() && cell > 0.0 {
+        Ok(cell)
+    } else {
+        Err("the cell size has to be a positive number of pixels".to_string())
+    }
+}
 
+/// Parse a frame rate to record at, which is at least [MIN_FPS]
+fn parse_fps(fps: &str) -> Result<f64, String> {
+    let fps = f64::from_str(fps).map_err(|err| err.to_string())?;
+    if fps.is_finite() && fps >= MIN_FPS {
+        Ok(fps)
+    } else {
+        Err(format!("the frame rate has to be a number of frames a second of at least {}", MIN_FPS))
+    }
+}
 
+/// Parse a cap on how many frames are drawn a second, where 0 leaves it uncapped and anything else has to
+/// be at least [MIN_FPS]
+fn parse_frame_cap(fps: &str) -> Result<f64, String> {
+    match f64::from_str(fps) {
+        Ok(fps) if fps == 0.0 || (fps.is_finite() && fps >= MIN_FPS) => Ok(fps),
+        Ok(fps) => Err(format!("{} is not a frame rate of at least {}, use 0 to draw as many frames as possible", fps, MIN_FPS)),
+        Err(err) => Err(err.to_string()),
+    }
+}
 
-    // copy to buffer
-    screenbuffer.copy_from_slice(meta.as_raw().as_slice());
+/// Parse how far a signed distance field PNG reaches
+fn parse_sdf_spread(spread: &str) -> Result<f64, String> {
+    let spread = f64::from_str(spread).map_err(|err| err.to_string())?;
+    if spread.is_finite() && spread > 0.0 {
+        Ok(spread)
+    } else {
+        Err("the spread has to be a positive number of pixels".to_string())
+    }
 }
 
-/// Main
-fn main() {
-    print_help();
+/// Parse where to listen for OSC or connections, where a port on its own listens on every interface
+fn parse_socket_address(address: &str) -> Result<SocketAddr, String> {
+    match u16::from_str(address) {
+        Ok(port) => Ok(SocketAddr::from(([0, 0, 0, 0], port))),
+        Err(_) => SocketAddr::from_str(address).map_err(|err| err.to_string()),
+    }
+}
 
-    // Create Window
-    let event_loop = EventLoop::new();
-    let window = WindowBuilder::new()
-        .with_inner_size(LogicalSize::new(256, 256))
-        .with_resizable(false)
-        .with_title("Metaballs")
-        .build(&event_loop).unwrap();
-    let mut input = WinitInputHelper::new();
+/// Parse a camera pitch in degrees, short of looking straight down or up at the image
+fn parse_pitch(pitch: &str) -> Result<f64, String> {
+    let pitch = f64::from_str(pitch).map_err(|err| err.to_string())?;
+    if pitch.abs() < 90.0 {
+        Ok(pitch)
+    } else {
+        Err("the pitch has to be within (-90, 90) degrees".to_string())
+    }
+}
 
-    // Get window's texture and bind renderer to it
-    let surface_texture = SurfaceTexture::new(window.inner_size().width, window.inner_size().height, &window);
-    let mut pix = pixels::PixelsBuilder::new(256, 256, surface_texture).enable_vsync(true).build().expect("PixelBuffer");
+/// Parse an image width or height, which needs to be at least a pixel
+fn parse_dimension(size: &str) -> Result<u32, String> {
+    match u32::from_str(size) {
+        Ok(0) => Err("the image needs to be at least 1 pixel wide and tall".to_string()),
+        Ok(size) => Ok(size),
+        Err(err) => Err(err.to_string()),
+    }
+}
 
-    // Start thread to listen for commands on STDIN
-    let (tx, rx) = std::sync::mpsc::channel();
-    control_stdin(tx);
+/// Parse how many frames to record, which needs to be at least one
+fn parse_frame_count(frames: &str) -> Result<u32, String> {
+    match u32::from_str(frames) {
+        Ok(0) => Err("there needs to be at least 1 frame to record".to_string()),
+        Ok(frames) => Ok(frames),
+        Err(_) => Err(format!("Unable to parse to frame count \"{}\"", frames)),
+    }
+}
 
+/// Parse color bands, as comma separated `level:color` stops at finite levels
+fn parse_bands(bands: &str) -> Result<Bands, String> {
+    bands.split(',')
+        .map(str::trim)
+        .filter(|band| !band.is_empty())
+        .map(|band| {
+            let (level, color) = band.split_once(':').ok_or_else(|| format!("\"{}\" is not a level:color stop", band))?;
+            let level = f64::from_str(level.trim()).map_err(|err| format!("\"{}\" is not a level: {}", level.trim(), err))?;
+            if !level.is_finite() {
+                return Err(format!("the band level {} needs to be finite", level));
+            }
+            Ok((level, parse_color(color.trim())?))
+        })
+        .collect()
+}
 
-    // Generate and render initial metaballs
-    let mut render_opts = RenderOpts::default();
-    let mut metadata = MetaballData::from_random(1.6, 0.5, 256, 256);
-    render_metaballs(pix.get_frame(), &metadata, &render_opts);
+/// Parse a layer a metaball can be on, from the bottom one at 0 up to [MAX_LAYER]
+fn parse_layer(layer: &str) -> Result<usize, String> {
+    match usize::from_str(layer) {
+        Ok(layer) if layer <= MAX_LAYER => Ok(layer),
+        Ok(layer) => Err(format!("there are only layers up to {}, not {}", MAX_LAYER, layer)),
+        Err(_) => Err(format!("Unable to parse to layer \"{}\"", layer)),
+    }
+}
 
-    // Start the window event loop
-    event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Wait;
+/// Parse comma separated thresholds to draw bands at, which all need to be finite and above 0
+fn parse_thresholds(levels: &str) -> Result<Vec<f64>, String> {
+    levels.split(',')
+        .map(str::trim)
+        .filter(|level| !level.is_empty())
+        .map(|level| match f64::from_str(level) {
+            Ok(number) if number.is_finite() && number > 0.0 => Ok(number),
+            Ok(_) => Err(format!("the threshold {} needs to be finite and above 0", level)),
+            Err(_) => Err(format!("\"{}\" is not a threshold", level)),
+        })
+        .collect()
+}
 
-        match event {
-            Event::WindowEvent {
-                event: WindowEvent::CloseRequested, // If a close is requested
-                window_id,
-            } if window_id == window.id() => *control_flow = ControlFlow::Exit,
-            Event::RedrawRequested(_) => { // Render the pixel buffer on redraw
-                pix.render().unwrap();
-            }
-            _ => (),
-        }
-        // Check for received commands from STDIN
-        match rx.try_recv() {
-            Ok(command) => {
-                match command
-                {
-                    ControlCommand::Goo(goo) => {
-                        metadata.goo = goo;
-                        println!("Set goo to {}", goo);
-                    }
-                    ControlCommand::Threshold(threshold) => {
-                        metadata.threshold = threshold;
-                        println!("Set threshold to {}", threshold);
-                    }
-                }
-                // re-render metaballs and request a redraw
-                render_metaballs(pix.get_frame(), &metadata, &render_opts);
-                window.request_redraw();
-            }
-            Err(err) => {
-                match err {
-                    // No command (no error)
-                    TryRecvError::Empty => {},
+/// Parse a grid spacing, which needs to be at least a pixel
+fn parse_spacing(spacing: &str) -> Result<u32, String> {
+    match u32::from_str(spacing) {
+        Ok(0) => Err("the grid needs to be at least 1 pixel apart".to_string()),
+        Ok(spacing) => Ok(spacing),
+        Err(err) => Err(err.to_string()),
+    }
+}
 
-                    // The command thread panicked for some reason
-                    TryRecvError::Disconnected => {println!("STDIN hung up!"); std::process::exit(-1); },
-                }
-            }
-        }
+/// Parse a size spread for [centered_random], which needs to be within (0, 1)
+fn parse_spread(spread: &str) -> Result<f64, String> {
+    match f64::from_str(spread) {
+        Ok(spread) if spread > 0.0 && spread < 1.0 => Ok(spread),
+        Ok(spread) => Err(format!("{} is not within (0, 1)", spread)),
+        Err(err) => Err(err.to_string()),
+    }
+}
 
-        if input.update(&event) {
-            // randomizing control
-            if input.key_pressed(VirtualKeyCode::Space) {
-                println!("randomizing");
-                metadata = MetaballData::from_random(metadata.goo, metadata.threshold, metadata.width, metadata.height);
-                render_metaballs(pix.get_frame(), &metadata, &render_opts);
-            }
+/// Parse a margin, which must leave some room in the middle of the image
+fn parse_margin(margin: &str) -> Result<f64, String> {
+    match f64::from_str(margin) {
+        Ok(margin) if (0.0..0.5).contains(&margin) => Ok(margin),
+        Ok(margin) => Err(format!("{} is not within [0, 0.5)", margin)),
+        Err(err) => Err(err.to_string()),
+    }
+}
 
-            // center indicator control
-            if input.key_pressed(VirtualKeyCode::C) {
-                println!("crosses toggled");
-                render_opts.crosses = !render_opts.crosses;
-                render_metaballs(pix.get_frame(), &metadata, &render_opts);
-            }
-            // if any input happened request a redraw
-            window.request_redraw();
-        }
+/// Parse how much something lags behind, within [0, 1)
+fn parse_lag(lag: &str) -> Result<f64, String> {
+    match f64::from_str(lag) {
+        Ok(lag) if (0.0..1.0).contains(&lag) => Ok(lag),
+        Ok(lag) => Err(format!("{} is not within [0, 1)", lag)),
+        Err(err) => Err(err.to_string()),
+    }
+}
 
-    });
+/// Parse a fraction of something, within [0, 1]
+fn parse_fraction(fraction: &str) -> Result<f64, String> {
+    match f64::from_str(fraction) {
+        Ok(fraction) if (0.0..=1.0).contains(&fraction) => Ok(fraction),
+        Ok(fraction) => Err(format!("{} is not within [0, 1]", fraction)),
+        Err(err) => Err(err.to_string()),
+    }
 }
 
-/// Defines factors/exponents and positions for rendering a set of metaballs
-#[derive(Clone, Debug)]
-struct MetaballData {
-    pub goo: f64,
-    pub threshold: f64,
-    pub width: u32,
-    pub height: u32,
-    pub metaballs: Vec<Metaball>,
-}
-
-impl MetaballData {
-    /// Generate a bunch of metaballs randomly.
-    pub fn from_random(goo: f64, threshold: f64, width: u32, height: u32) -> MetaballData {
-        let count = random_count_metaballs();
-        let mut metaballs = vec![];
-        for _ in 0..count {
-            let metaball = Metaball {
-                size: centered_random(0.5) * BASE_METABALL_SIZE,
-                location: Point {
-                    x: (width as f64 * centered_random(0.5)) as u32,
-                    y: (height as f64 * centered_random(0.5)) as u32,
-                },
-            };
-            metaballs.push(metaball)
-        }
-        MetaballData {
-            goo,
-            width,
-            height,
-            threshold,
-            metaballs,
+/// Parse the arguments of the motion command, `<index> static`, `<index> orbit <radius> [period]` or
+/// `<index> lissajous <x amplitude> <y amplitude> <x frequency> <y frequency> [period]`
+fn parse_motion(args: &[&str]) -> Result<(usize, Option<motion::Path>, f64), String> {
+    let number = |arg: &str| f64::from_str(arg).map_err(|err| format!("\"{}\": {}", arg, err));
+    let frequency = |arg: &str| match u32::from_str(arg) {
+        Ok(frequency) if frequency > 0 => Ok(frequency),
+        _ => Err(format!("\"{}\" is not a frequency, expected a whole number above 0", arg)),
+    };
+    let (index, kind, rest) = match args {
+        [index, kind, rest @ ..] => (usize::from_str(index).map_err(|_| format!("unable to parse to index \"{}\"", index))?, *kind, rest),
+        _ => return Err("expected <index> static, orbit or lissajous".to_string()),
+    };
+    let (path, period) = match (kind, rest) {
+        ("static", []) => return Ok((index, None, MOTION_PERIOD)),
+        ("orbit", [radius, period @ ..]) => (motion::Path::Orbit { radius: number(radius)? }, period),
+        ("lissajous", [x, y, a, b, period @ ..]) => {
+            (motion::Path::Lissajous { amplitude: (number(x)?, number(y)?), frequencies: (frequency(a)?, frequency(b)?) }, period)
         }
+        _ => return Err("expected static, orbit <radius> [period] or lissajous <x amplitude> <y amplitude> <x frequency> <y frequency> [period]".to_string()),
+    };
+    let period = match period {
+        [] => MOTION_PERIOD,
+        [period] => parse_positive(period)?,
+        _ => return Err("too many arguments".to_string()),
+    };
+    Ok((index, Some(path), period))
+}
+
+/// Parse the arguments of the pulse command, `<index> <amplitude> <frequency> [phase]` or `<index> off`
+fn parse_pulse(args: &[&str]) -> Result<(usize, Option<Pulse>), String> {
+    let number = |arg: &str| f64::from_str(arg).map_err(|err| format!("\"{}\": {}", arg, err));
+    let (index, rest) = match args {
+        [index, rest @ ..] => (usize::from_str(index).map_err(|_| format!("unable to parse to index \"{}\"", index))?, rest),
+        _ => return Err("expected <index> <amplitude> <frequency> [phase] or <index> off".to_string()),
+    };
+    let (amplitude, frequency, phase) = match rest {
+        ["off"] => return Ok((index, None)),
+        [amplitude, frequency] => (number(amplitude)?, number(frequency)?, 0.0),
+        [amplitude, frequency, phase] => (number(amplitude)?, number(frequency)?, number(phase)?),
+        _ => return Err("expected <amplitude> <frequency> [phase] or off".to_string()),
+    };
+    Pulse::check(amplitude, frequency, phase)?;
+    Ok((index, Some(Pulse::new(amplitude, frequency, phase))))
+}
+
+/// Parse the amplitude of a pulse, within [0, 1)
+fn parse_amplitude(amplitude: &str) -> Result<f64, String> {
+    let amplitude = f64::from_str(amplitude).map_err(|err| err.to_string())?;
+    Pulse::check(amplitude, 1.0, 0.0).map(|_| amplitude)
+}
+
+/// Parse a number that isn't infinite or NaN
+fn parse_finite(number: &str) -> Result<f64, String> {
+    match f64::from_str(number) {
+        Ok(number) if number.is_finite() => Ok(number),
+        Ok(number) => Err(format!("{} is not a finite number", number)),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Parse a number that must be 0 or above
+fn parse_non_negative(number: &str) -> Result<f64, String> {
+    match f64::from_str(number) {
+        Ok(number) if number >= 0.0 && number.is_finite() => Ok(number),
+        Ok(number) => Err(format!("{} is not 0 or above", number)),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Parse how many metaballs a random scene has, which is at most [MAX_METABALL_COUNT]
+fn parse_count(count: &str) -> Result<u32, String> {
+    match u32::from_str(count) {
+        Ok(count) if count <= MAX_METABALL_COUNT => Ok(count),
+        Ok(count) => Err(format!("{} is more than the {} metaballs a scene can have", count, MAX_METABALL_COUNT)),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Parse the count factor of random scenes, which is at least [MIN_COUNT_FACTOR]
+fn parse_count_factor(factor: &str) -> Result<f64, String> {
+    match f64::from_str(factor) {
+        Ok(number) if number.is_finite() && number >= MIN_COUNT_FACTOR => Ok(number),
+        Ok(_) => Err(format!("{} is not a finite count factor of at least {}", factor, MIN_COUNT_FACTOR)),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Parse a finite number that must be above 0
+fn parse_positive(number: &str) -> Result<f64, String> {
+    match f64::from_str(number) {
+        Ok(number) if number > 0.0 && number.is_finite() => Ok(number),
+        Ok(number) => Err(format!("{} is not a finite number above 0", number)),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Parse a supersampling factor. Each sample is a pixel of an image rendered that many times bigger each
+/// way, so it's kept small enough for that image to fit in memory.
+fn parse_ssaa(factor: &str) -> Result<u32, String> {
+    match u32::from_str(factor) {
+        Ok(factor) if (1..=MAX_SSAA).contains(&factor) => Ok(factor),
+        Ok(factor) => Err(format!("{} is not within [1, {}]", factor, MAX_SSAA)),
+        Err(err) => Err(err.to_string()),
     }
 }
 
-/// Calculates the number of metaballs using RNG
-fn random_count_metaballs() -> u32 {
-    random_exponential_distribution(0.5).floor() as u32 + MIN_METABALL_COUNT
+/// Parse a marching squares cell size, which needs to be at least a pixel
+fn parse_cell_size(size: &str) -> Result<u32, String> {
+    match u32::from_str(size) {
+        Ok(0) => Err("cells need to be at least 1 pixel wide".to_string()),
+        Ok(size) => Ok(size),
+        Err(err) => Err(err.to_string()),
+    }
 }
 
-/// Generates a random number following an exponential distribution.
-/// This would be like the number of coin flips if on heads flip again, if tails halt.
-fn random_exponential_distribution(factor: f64) -> f64 {
-    let random = rand::random::<f64>();
-    f64::ln(1f64 - random) / (-factor)
+/// Parse how many times smaller than the window to render, which needs to be at least 1
+fn parse_downscale(factor: &str) -> Result<u32, String> {
+    match u32::from_str(factor) {
+        Ok(0) => Err("the render can't be 0 times the window's size".to_string()),
+        Ok(factor) => Ok(factor),
+        Err(err) => Err(err.to_string()),
+    }
 }
 
-/// Generates a random number that will be within \[inner / 2, inner * 1.5\]
-///
-/// Example:
-/// ```
-/// for _ in 0.1000 {
-///     let num = centered_random(0.5);
-///     assert!(num >= 0.25 && num <= 0.75);
-/// }
-/// ```
-fn centered_random(inner: f64) -> f64 {
-    assert!(inner < 1.0 && inner > 0.0, "Inner should be within (0, 1)");
-    let random = rand::random::<f64>();
-    random * inner + (inner / 2.0)
+/// How long each recorded frame is shown for, when it's been given in milliseconds or as a frame rate
+fn export_delay(opt: &Opt) -> Option<Duration> {
+    opt.export_delay.map(Duration::from_millis).or_else(|| opt.export_fps.map(|fps| Duration::from_secs_f64(1.0 / fps)))
 }
 
-/// Represents a metaball position and size.
-#[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
-struct Metaball {
-    pub location: Point,
-    pub size: f64,
+/// A touch gesture under way, with the scene from before it for undoing the whole gesture at once
+struct TouchEdit {
+    before: MetaballData,
+    /// The metaball the finger is dragging and where it was when the drag started
+    dragged: Option<(usize, Pointf)>,
+    /// The threshold from when the pinch started
+    threshold: Option<f64>,
 }
 
-/// Represents a point on an image or screen
-#[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
-struct Point {
-    pub x: u32,
-    pub y: u32,
+/// How often frames are drawn while anything's moving, and how many animation ticks each one moves on by
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct Pacing {
+    interval: Duration,
+    ticks: u32,
 }
 
-/// Like [Point] but signed integers to allow for negatives. Not used directly for rendering
-#[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
-struct RelPoint {
-    pub x: i64,
-    pub y: i64,
+impl Pacing {
+    /// Frames at a rate from --fps, a tick each at the animation's own pace by default and uncapped with 0
+    fn new(fps: Option<f64>) -> Pacing {
+        match fps {
+            None => Pacing { interval: ANIMATION_TICK, ticks: 1 },
+            Some(0.0) => Pacing { interval: Duration::ZERO, ticks: 1 },
+            Some(fps) => {
+                let interval = Duration::from_secs_f64(1.0 / fps);
+                Pacing { interval, ticks: (interval.as_secs_f64() / ANIMATION_TICK.as_secs_f64()).round().max(1.0) as u32 }
+            }
+        }
+    }
 }
 
-impl Point {
-    /// Distance to another point
-    pub fn distance(&self, other: &Point) -> f64 {
-        f64::sqrt(((self.x as f64 - other.x as f64).powf(2f64)) + ((self.y as f64 - other.y as f64).powf(2f64)))
+/// Print the help information to STDOUT
+fn print_help() {
+    println!("{}", HELP);
+}
+
+/// Sends commands on to whatever's applying them to the scene
+#[derive(Clone)]
+struct CommandSender {
+    target: CommandTarget,
+    /// Whether a command couldn't be sent because nothing's taking them any more, for the thread sending
+    /// them to stop
+    closed: Cell<bool>,
+}
+
+/// Where the commands go
+#[derive(Clone)]
+enum CommandTarget {
+    /// A channel, without a window
+    Channel(Sender<ControlCommand>),
+    /// The window's event loop, as events of their own, so it sleeps until one comes in or the window
+    /// needs it
+    Window(EventLoopProxy<ControlCommand>),
+}
+
+impl CommandSender {
+    fn new(tx: Sender<ControlCommand>) -> CommandSender {
+        CommandSender { target: CommandTarget::Channel(tx), closed: Cell::new(false) }
+    }
+
+    /// Send the commands to the window's event loop
+    fn to_window(proxy: EventLoopProxy<ControlCommand>) -> CommandSender {
+        CommandSender { target: CommandTarget::Window(proxy), closed: Cell::new(false) }
+    }
+
+    pub fn send(&self, command: ControlCommand) {
+        let sent = match &self.target {
+            CommandTarget::Channel(tx) => tx.send(command).is_ok(),
+            // the event loop only goes away when exiting
+            CommandTarget::Window(proxy) => proxy.send_event(command).is_ok(),
+        };
+        if !sent {
+            self.closed.set(true);
+        }
+    }
+
+    /// Stop with [MetaballError::Closed] once a command couldn't be sent
+    fn check_open(&self) -> Result<(), MetaballError> {
+        if self.closed.get() {
+            Err(MetaballError::Closed)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Start a thread reading commands from the script, if any, and then from STDIN
+fn control_stdin(tx: CommandSender, script: Option<File>) {
+    std::thread::spawn(move || match read_control(script, &tx) {
+        // there's nothing left to tell
+        Err(MetaballError::Closed) => {}
+        read => {
+            if let Err(err) = read {
+                println!("{}", err);
+            }
+            // let the main loop know no more commands are coming
+            tx.send(ControlCommand::EndOfInput);
+        }
+    });
+}
+
+/// Read the commands from the script and then from STDIN, going on to STDIN even when the script can't be
+/// read to the end
+fn read_control(script: Option<File>, tx: &CommandSender) -> Result<(), MetaballError> {
+    if let Some(script) = script {
+        match read_commands(BufReader::new(script), tx) {
+            Err(err @ MetaballError::Read(_)) => println!("{}", err),
+            read => read?,
+        }
+    }
+    read_commands(stdin().lock(), tx)
+}
+
+/// The sockets commands come in on besides STDIN, opened before starting so a port that's taken is an error
+/// straight away, and the scene file that's loaded again when it changes
+struct Remote {
+    osc: Option<UdpSocket>,
+    listener: Option<TcpListener>,
+    #[cfg(unix)]
+    control_socket: Option<UnixListener>,
+    watch: Option<PathBuf>,
+    gamepad: Option<File>,
+    midi: Option<(File, MidiMap)>,
+}
+
+impl Remote {
+    /// Start the threads sending on the commands from each socket and the watched file. Returns whether any
+    /// are listening, since they keep sending commands after STDIN closes.
+    fn start(self, tx: &CommandSender) -> bool {
+        #[cfg(unix)]
+        let socket = self.control_socket.is_some();
+        #[cfg(not(unix))]
+        let socket = false;
+        let listening = self.osc.is_some() || self.listener.is_some() || socket || self.watch.is_some() || self.gamepad.is_some() || self.midi.is_some();
+        if let Some(socket) = self.osc {
+            control_osc(tx.clone(), socket);
+        }
+        if let Some(listener) = self.listener {
+            control_listen(tx.clone(), listener);
+        }
+        #[cfg(unix)]
+        if let Some(listener) = self.control_socket {
+            control_socket(tx.clone(), listener);
+        }
+        if let Some(path) = self.watch {
+            watch_scene(tx.clone(), path);
+        }
+        if let Some(device) = self.gamepad {
+            control_gamepad(tx.clone(), device);
+        }
+        if let Some((device, map)) = self.midi {
+            control_midi(tx.clone(), device, map);
+        }
+        listening
+    }
+}
+
+/// Open a joystick device for reading without waiting, so the stick acts on every tick it's held whether
+/// or not any events come in
+fn open_gamepad(path: &Path) -> io::Result<File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.read(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::custom_flags(&mut options, libc::O_NONBLOCK);
+    options.open(path)
+}
+
+/// Start a thread sending on what the gamepad does, taking in its events and acting on what's held each
+/// tick, until nothing's taking commands or the pad is unplugged
+fn control_gamepad(tx: CommandSender, mut device: File) {
+    std::thread::spawn(move || {
+        let mut pad = Gamepad::default();
+        let mut event = [0; gamepad::EVENT_SIZE];
+        while tx.check_open().is_ok() {
+            loop {
+                match device.read_exact(&mut event) {
+                    Ok(()) => {
+                        if let Some(action) = gamepad::decode(event).and_then(|event| pad.event(event)) {
+                            tx.send(ControlCommand::Gamepad(action));
+                        }
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(err) => {
+                        log::warn!("Unable to read the gamepad: {}", err);
+                        return;
+                    }
+                }
+            }
+            for action in pad.tick() {
+                tx.send(ControlCommand::Gamepad(action));
+            }
+            std::thread::sleep(ANIMATION_TICK);
+        }
+    });
+}
+
+/// Start a thread sending on the parameters the MIDI controller's knobs set, until nothing's taking commands
+/// or the controller is unplugged
+fn control_midi(tx: CommandSender, device: File, map: MidiMap) {
+    std::thread::spawn(move || {
+        let mut parser = midi::Parser::default();
+        for byte in BufReader::new(device).bytes() {
+            let byte = match byte {
+                Ok(byte) => byte,
+                Err(err) => {
+                    log::warn!("Unable to read the MIDI controller: {}", err);
+                    return;
+                }
+            };
+            for change in parser.push(byte).map(|control| map.changes(control)).unwrap_or_default() {
+                tx.send(match change {
+                    Change::Goo(goo) => ControlCommand::Goo(goo),
+                    Change::Threshold(threshold) => ControlCommand::Threshold(threshold),
+                    Change::Size { index, size } => ControlCommand::Size { index, size },
+                });
+            }
+            if tx.check_open().is_err() {
+                return;
+            }
+        }
+    });
+}
+
+/// Start the --behavior script, if there is one
+fn spawn_behavior(opt: &Opt) -> Option<Behavior> {
+    let path = opt.behavior.as_ref()?;
+    match Behavior::spawn(path) {
+        Ok(behavior) => Some(behavior),
+        Err(err) => {
+            eprintln!("Unable to run the behavior script {}: {}", path.display(), err);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Have the behavior script, if it's running, change the scene for a tick, stopping the script if it goes
+/// wrong. Returns whether it changed anything.
+fn run_behavior(behavior: &mut Option<Behavior>, metadata: &mut MetaballData) -> bool {
+    let changed = behavior.as_mut().map(|script| script.update(metadata));
+    match changed {
+        Some(Ok(changed)) => changed,
+        Some(Err(err)) => {
+            println!("Stopped the behavior script: {}", err);
+            *behavior = None;
+            false
+        }
+        None => false,
+    }
+}
+
+/// Start a thread loading a scene file again each time it's modified, until nothing's taking commands. It
+/// polls the modification time every [WATCH_INTERVAL] instead of asking the OS for file events, which
+/// works the same everywhere with nothing more than `std`.
+fn watch_scene(tx: CommandSender, path: PathBuf) {
+    std::thread::spawn(move || {
+        let modified = |path: &Path| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+        let mut last = modified(&path);
+        while tx.check_open().is_ok() {
+            std::thread::sleep(WATCH_INTERVAL);
+            // editors that save by replacing the file leave it missing for a moment, which isn't a change
+            let now = modified(&path);
+            if now.is_some() && now != last {
+                last = now;
+                log::info!("{} changed, loading it again", path.display());
+                tx.send(ControlCommand::Load(path.clone()));
+            }
+        }
+    });
+}
+
+/// Start a thread accepting connections, each read in a thread of its own till the client hangs up
+fn control_listen(tx: CommandSender, listener: TcpListener) {
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let tx = tx.clone();
+                    std::thread::spawn(move || serve_connection(stream, &tx));
+                }
+                Err(err) => log::warn!("Unable to accept a connection: {}", err),
+            }
+        }
+    });
+}
+
+/// Start a thread accepting connections on a Unix socket, each read a line at a time in a thread of its own
+#[cfg(unix)]
+fn control_socket(tx: CommandSender, listener: UnixListener) {
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    let tx = tx.clone();
+                    std::thread::spawn(move || {
+                        if let Err(err @ MetaballError::Read(_)) = read_commands(BufReader::new(stream), &tx) {
+                            println!("{}", err);
+                        }
+                    });
+                }
+                Err(err) => log::warn!("Unable to accept a connection: {}", err),
+            }
+        }
+    });
+}
+
+/// Listen on a Unix socket at a path, replacing a socket left there by a run that didn't get to remove it.
+/// Anything else at the path is left alone and fails to bind.
+#[cfg(unix)]
+fn bind_control_socket(path: &Path) -> io::Result<UnixListener> {
+    use std::os::unix::fs::FileTypeExt;
+    if std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+        std::fs::remove_file(path)?;
+    }
+    UnixListener::bind(path)
+}
+
+/// Send on the commands from a connection, as lines of text or WebSocket text messages
+fn serve_connection(stream: TcpStream, tx: &CommandSender) {
+    // a WebSocket client starts by asking for the upgrade in an HTTP request
+    let mut start = [0; 4];
+    if !matches!(stream.peek(&mut start), Ok(4)) || &start != b"GET " {
+        if let Err(err @ MetaballError::Read(_)) = read_commands(BufReader::new(stream), tx) {
+            println!("{}", err);
+        }
+        return;
+    }
+    let mut socket = match tungstenite::accept(stream) {
+        Ok(socket) => socket,
+        Err(err) => {
+            log::warn!("Unable to start a WebSocket: {}", err);
+            return;
+        }
+    };
+    loop {
+        match socket.read() {
+            Ok(tungstenite::Message::Text(text)) => {
+                text.lines().for_each(|line| run_line(line, tx));
+                if tx.check_open().is_err() {
+                    break;
+                }
+            }
+            Ok(tungstenite::Message::Close(_)) => break,
+            Ok(_) => {}
+            Err(tungstenite::Error::ConnectionClosed) => break,
+            Err(err) => {
+                println!("Unable to read commands: {}", err);
+                break;
+            }
+        }
+    }
+}
+
+/// Start a thread applying the OSC messages that come in on a socket, for as long as the program runs
+fn control_osc(tx: CommandSender, socket: UdpSocket) {
+    std::thread::spawn(move || {
+        // the biggest a UDP packet can be
+        let mut packet = vec![0; 65536];
+        loop {
+            let size = match socket.recv(&mut packet) {
+                Ok(size) => size,
+                Err(err) => {
+                    log::warn!("Unable to receive OSC: {}", err);
+                    continue;
+                }
+            };
+            let messages = match osc::decode(&packet[..size]) {
+                Ok(messages) => messages,
+                Err(err) => {
+                    log::warn!("Unable to decode OSC: {}", err);
+                    continue;
+                }
+            };
+            for message in messages {
+                match osc_command(&message.address, &message.args) {
+                    Ok(Some(command)) => tx.send(command),
+                    Ok(None) => {}
+                    Err(err) => println!("{}: {}", message.address, err),
+                }
+            }
+            if tx.check_open().is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Run a JSON command, a line like `{"cmd": "set", "goo": 1.8}`, answering on STDOUT with a line of JSON
+/// saying whether it was understood before the command runs. Anything else it prints is plain text, so
+/// the JSON lines are the ones starting with a {.
+fn run_json(line: &str, tx: &CommandSender) {
+    let command = json::parse(line).and_then(|command| {
+        let cmd = command.get("cmd").and_then(Json::as_str).ok_or("expected a \"cmd\" string")?.to_string();
+        Ok((json_commands(&cmd, &command)?, cmd, command))
+    });
+    match command {
+        Ok((commands, cmd, command)) => {
+            println!("{{\"ok\": true, \"cmd\": {}}}", json::quote(&cmd));
+            // any other command can be sent as its line of text
+            if let Some(text) = command.get("text").and_then(Json::as_str).filter(|_| cmd == "run") {
+                run_line(text, tx);
+            }
+            commands.into_iter().for_each(|command| tx.send(command));
+        }
+        Err(err) => println!("{{\"ok\": false, \"error\": {}}}", json::quote(&err)),
+    }
+}
+
+/// The commands a JSON command object is for, none for one that runs a line of text. The values are all
+/// checked here, so a command that's acknowledged is one that can run.
+fn json_commands(cmd: &str, command: &Json) -> Result<Vec<ControlCommand>, String> {
+    // JSON has no infinities, but a number too big for an f64 still parses as one
+    let number = |key: &str| match command.get(key).and_then(Json::as_f64) {
+        Some(number) if number.is_finite() => Ok(number),
+        Some(number) => Err(format!("{} for \"{}\" is out of range", number, key)),
+        None => Err(format!("expected a number for \"{}\"", key)),
+    };
+    let positive = |key: &str| match number(key)? {
+        number if number > 0.0 => Ok(number),
+        number => Err(format!("{} for \"{}\" is not above 0", number, key)),
+    };
+    // an f64 can't hold every whole number past 2^53, so a bigger one may not be the number that was sent
+    let whole = |key: &str, what: &str| match number(key)? {
+        whole if (0.0..=(1u64 << 53) as f64).contains(&whole) && whole.fract() == 0.0 => Ok(whole as u64),
+        whole => Err(format!("{} is not {}", whole, what)),
+    };
+    let index = |key: &str| whole(key, "an index").map(|index| index as usize);
+    let string = |key: &str| command.get(key).and_then(Json::as_str).ok_or(format!("expected a string for \"{}\"", key));
+    let location = || Ok::<_, String>(Pointf { x: number("x")?, y: number("y")? });
+    Ok(match cmd {
+        "set" => {
+            let mut commands = vec![];
+            if command.get("goo").is_some() {
+                commands.push(ControlCommand::Goo(positive("goo")?));
+            }
+            if command.get("threshold").is_some() {
+                commands.push(ControlCommand::Threshold(positive("threshold")?));
+            }
+            if command.get("falloff").is_some() {
+                commands.push(ControlCommand::Falloff(Falloff::from_str(string("falloff")?)?));
+            }
+            if commands.is_empty() {
+                return Err("expected a \"goo\", \"threshold\" or \"falloff\" to set".to_string());
+            }
+            commands
+        }
+        "add" => {
+            let size = command.get("size").map_or(Ok(BASE_METABALL_SIZE), |_| number("size"))?;
+            let radius = command.get("radius").map(|_| positive("radius")).transpose()?;
+            vec![ControlCommand::Add { location: location()?, size, radius }]
+        }
+        "move" => vec![ControlCommand::Move { index: index("index")?, location: location()? }],
+        "size" => vec![ControlCommand::Size { index: index("index")?, size: number("size")? }],
+        "color" => vec![ControlCommand::Color { index: index("index")?, color: parse_color(string("color")?)? }],
+        "remove" => vec![ControlCommand::Remove(index("index")?)],
+        "preset" => vec![ControlCommand::Preset(string("name")?.to_string())],
+        "save" => vec![ControlCommand::Save(PathBuf::from(string("path")?))],
+        "load" => vec![ControlCommand::Load(PathBuf::from(string("path")?))],
+        "seed" => vec![ControlCommand::Seed(whole("seed", "a seed")?)],
+        "clear" => vec![ControlCommand::Clear],
+        "randomize" => vec![ControlCommand::Randomize],
+        "reset" => vec![ControlCommand::Reset],
+        "undo" => vec![ControlCommand::Undo],
+        "redo" => vec![ControlCommand::Redo],
+        "state" => vec![ControlCommand::State],
+        "run" => {
+            string("text")?;
+            vec![]
+        }
+        _ => return Err(format!("unknown command \"{}\"", cmd)),
+    })
+}
+
+/// The command an OSC message is for. Triggers such as /metaballs/reset go off when they have no argument
+/// or a non-zero one, so a button does it once when pressed and not again when let go.
+fn osc_command(address: &str, args: &[Arg]) -> Result<Option<ControlCommand>, String> {
+    let path: Vec<&str> = address.strip_prefix("/metaballs/").ok_or("unknown address")?.split('/').collect();
+    let numbers: Option<Vec<f64>> = args.iter().map(Arg::number).collect();
+    let index = |index: &str| usize::from_str(index).map_err(|_| format!("unable to parse to index \"{}\"", index));
+    let triggered = args.first().is_none_or(|arg| arg.number() != Some(0.0));
+    let command = match (path.as_slice(), numbers.as_deref()) {
+        (["goo"], Some(&[goo])) => ControlCommand::Goo(goo),
+        (["threshold"], Some(&[threshold])) => ControlCommand::Threshold(threshold),
+        (["ball", i, "pos"], Some(&[x, y])) => ControlCommand::Move { index: index(i)?, location: Pointf { x, y } },
+        (["ball", i, "size"], Some(&[size])) => ControlCommand::Size { index: index(i)?, size },
+        (["ball", i, "remove"], _) => ControlCommand::Remove(index(i)?),
+        (["add"], Some(&[x, y, size])) => ControlCommand::Add { location: Pointf { x, y }, size, radius: None },
+        (["randomize"], _) => ControlCommand::Randomize,
+        (["reset"], _) => ControlCommand::Reset,
+        (["preset"], _) => match args {
+            [Arg::String(name)] => ControlCommand::Preset(name.clone()),
+            _ => return Err("expected a preset name".to_string()),
+        },
+        (["goo"], _) | (["threshold"], _) | (["ball", _, "size"], _) => return Err("expected a number".to_string()),
+        (["ball", _, "pos"], _) => return Err("expected <x> <y>".to_string()),
+        (["add"], _) => return Err("expected <x> <y> <size>".to_string()),
+        _ => return Err("unknown address".to_string()),
+    };
+    let trigger = matches!(command, ControlCommand::Remove(_) | ControlCommand::Randomize | ControlCommand::Reset);
+    Ok(if trigger && !triggered { None } else { Some(command) })
+}
+
+/// Parse commands a line at a time and send them on until the end of the input, or until nothing's taking
+/// them any more
+fn read_commands(mut input: impl BufRead, tx: &CommandSender) -> Result<(), MetaballError> {
+    let mut linebuf = String::new();
+    loop {
+        // read input line, stopping at the end of the input
+        linebuf.clear();
+        if input.read_line(&mut linebuf)? == 0 {
+            return Ok(());
+        }
+        run_line(&linebuf, tx);
+        tx.check_open()?;
+    }
+}
+
+/// Parse a line of commands and send the command on, when it's not blank or a comment. Everything that
+/// controls the scene from outside the window goes through here, whatever the lines are read from.
+fn run_line(line: &str, tx: &CommandSender) {
+    // JSON commands can have a # in their strings, so they're told apart before the comments are cut off
+    if line.trim_start().starts_with('{') {
+        run_json(line, tx);
+        return;
+    }
+    // anything after a # is a comment
+    let line = line.split('#').next().unwrap().trim();
+    let first_char = match line.chars().next() {
+        Some(first_char) => first_char,
+        None => return,
+    };
+
+    // hold off on the next command, for staging scripts
+    if let Some(ms) = line.strip_prefix("sleep") {
+        match u64::from_str(ms.trim()) {
+            Ok(ms) => std::thread::sleep(Duration::from_millis(ms)),
+            Err(_) => {println!("Unable to parse to milliseconds \"{}\"", ms.trim())}
+        }
+        return;
+    }
+
+    match first_char {
+        // GIF export
+        'g' if line.starts_with("gif") => {
+            let args: Vec<&str> = line[3..].split_whitespace().collect();
+            match args.as_slice() {
+                [path, frames] => match parse_frame_count(frames) {
+                    Ok(frames) => {tx.send(ControlCommand::ExportGif(PathBuf::from(path), frames));}
+                    Err(err) => {println!("{}", err)}
+                },
+                _ => {println!("Expected gif <path> <frames>")}
+            }
+        },
+        // Physics settings
+        'g' if line.starts_with("gravity") => {
+            send_float(&line["gravity".len()..], ControlCommand::Gravity, tx);
+        },
+        'd' if line.starts_with("damping") => {
+            send_float(&line["damping".len()..], ControlCommand::Damping, tx);
+        },
+        'a' if line.starts_with("attraction") => {
+            send_float(&line["attraction".len()..], ControlCommand::Attraction, tx);
+        },
+        'c' if line.starts_with("collision") => {
+            send_float(&line["collision".len()..], ControlCommand::Collision, tx);
+        },
+        'p' if line == "physics" => {
+            tx.send(ControlCommand::TogglePhysics);
+        },
+        'a' if line == "anaglyph" => {
+            tx.send(ControlCommand::ToggleAnaglyph);
+        },
+        'h' if line == "histogram" => {
+            tx.send(ControlCommand::ToggleHistogram);
+        },
+        'c' if line.starts_with("colormap") => {
+            let args: Vec<&str> = line["colormap".len()..].split_whitespace().collect();
+            let parsed = match args.as_slice() {
+                [colormap] => colormap.parse().map(|colormap| (colormap, None)),
+                [colormap, scale] => colormap.parse().and_then(|colormap| Ok((colormap, Some(scale.parse()?)))),
+                _ => Err("Expected colormap <name> [scale]".to_string()),
+            };
+            match parsed {
+                Ok((colormap, scale)) => {tx.send(ControlCommand::Colormap(colormap, scale));}
+                Err(err) => {println!("{}", err)}
+            }
+        },
+        'p' if line.starts_with("pulse") => {
+            let args: Vec<&str> = line["pulse".len()..].split_whitespace().collect();
+            match parse_pulse(&args) {
+                Ok((index, pulse)) => {tx.send(ControlCommand::Pulse { index, pulse });}
+                Err(err) => {println!("Unable to parse the pulse \"{}\": {}", args.join(" "), err)}
+            }
+        },
+        // Probe the field at a point
+        'p' if line[1..].starts_with(' ') => {
+            let args: Vec<&str> = line[1..].split_whitespace().collect();
+            match args.as_slice() {
+                [x, y] => match (f64::from_str(x), f64::from_str(y)) {
+                    (Ok(x), Ok(y)) => {tx.send(ControlCommand::Probe(Pointf { x, y }));}
+                    _ => {println!("Unable to parse \"{}\" as <x> <y>", line[1..].trim())}
+                },
+                _ => {println!("Expected p <x> <y>")}
+            }
+        },
+        // Goo
+        'g' => {
+            match f64::from_str(&line[1..]) {
+                Ok(val) => {tx.send(ControlCommand::Goo(val));}
+                Err(_) => {println!("Unable to parse to float \"{}\"", &line[1..])}
+            }
+        },
+        // Threshold
+        't' if line.contains(',') => {
+            match parse_thresholds(&line[1..]) {
+                Ok(levels) => {tx.send(ControlCommand::Thresholds(levels));}
+                Err(err) => {println!("Unable to parse the thresholds \"{}\": {}", &line[1..], err)}
+            }
+        },
+        't' => {
+            match f64::from_str(&line[1..]) {
+                Ok(val) => {tx.send(ControlCommand::Threshold(val));}
+                Err(_) => {println!("Unable to parse to float \"{}\"", &line[1..])}
+            }
+        },
+        // Color bands
+        'b' if line.starts_with("bands") => {
+            match parse_bands(&line["bands".len()..]) {
+                Ok(bands) => {tx.send(ControlCommand::Bands(bands));}
+                Err(err) => {println!("Unable to parse bands: {}", err)}
+            }
+        },
+        // Intensity gradient
+        'i' => {
+            tx.send(ControlCommand::ToggleGradient);
+        },
+        // Add a metaball
+        'a' => {
+            let args: Vec<&str> = line[1..].split_whitespace().collect();
+            match args.as_slice() {
+                [x, y, size] => match (f64::from_str(x), f64::from_str(y), f64::from_str(size)) {
+                    (Ok(x), Ok(y), Ok(size)) if size.is_finite() => {tx.send(ControlCommand::Add { location: Pointf { x, y }, size, radius: None });}
+                    (Ok(_), Ok(_), Ok(size)) => {println!("Not adding a metaball of size {}: it needs to be a finite number", size)}
+                    _ => {println!("Unable to parse \"{}\" as <x> <y> <size>", line[1..].trim())}
+                },
+                [x, y, size, radius] => match (f64::from_str(x), f64::from_str(y), f64::from_str(size), f64::from_str(radius)) {
+                    (Ok(x), Ok(y), Ok(size), Ok(radius)) if size.is_finite() => {
+                        tx.send(ControlCommand::Add { location: Pointf { x, y }, size, radius: Some(radius) });
+                    }
+                    (Ok(_), Ok(_), Ok(size), Ok(_)) => {println!("Not adding a metaball of size {}: it needs to be a finite number", size)}
+                    _ => {println!("Unable to parse \"{}\" as <x> <y> <size> <radius>", line[1..].trim())}
+                },
+                _ => {println!("Expected a <x> <y> <size> [radius]")}
+            }
+        },
+        // Delete a metaball
+        'd' => {
+            match usize::from_str(line[1..].trim()) {
+                Ok(index) => {tx.send(ControlCommand::Remove(index));}
+                Err(_) => {println!("Unable to parse to index \"{}\"", line[1..].trim())}
+            }
+        },
+        'm' if line.starts_with("motion") => {
+            let args: Vec<&str> = line["motion".len()..].split_whitespace().collect();
+            match parse_motion(&args) {
+                Ok((index, path, period)) => {tx.send(ControlCommand::Motion { index, path, period });}
+                Err(err) => {println!("Unable to parse the motion \"{}\": {}", args.join(" "), err)}
+            }
+        },
+        // Mesh export
+        'm' if line.starts_with("mesh") => {
+            let args: Vec<&str> = line["mesh".len()..].split_whitespace().collect();
+            match args.as_slice() {
+                [path] => {tx.send(ControlCommand::ExportMesh(PathBuf::from(path), MESH_CELL));}
+                [path, cell] => match parse_mesh_cell(cell) {
+                    Ok(cell) => {tx.send(ControlCommand::ExportMesh(PathBuf::from(path), cell));}
+                    Err(err) => {println!("Unable to parse to cell size \"{}\": {}", cell, err)}
+                },
+                _ => {println!("Expected mesh <path> [cell size]")}
+            }
+        },
+        // Move a metaball
+        'm' => {
+            let args: Vec<&str> = line[1..].split_whitespace().collect();
+            match args.as_slice() {
+                [index, x, y] => match (usize::from_str(index), f64::from_str(x), f64::from_str(y)) {
+                    (Ok(index), Ok(x), Ok(y)) => {tx.send(ControlCommand::Move { index, location: Pointf { x, y } });}
+                    _ => {println!("Unable to parse \"{}\" as <index> <x> <y>", line[1..].trim())}
+                },
+                _ => {println!("Expected m <index> <x> <y>")}
+            }
+        },
+        // How a layer is combined with the ones under it
+        'l' if line.starts_with("layer-blend") => {
+            let args: Vec<&str> = line["layer-blend".len()..].split_whitespace().collect();
+            match args.as_slice() {
+                [layer, blend] => match (parse_layer(layer), LayerBlend::from_str(blend)) {
+                    (Ok(layer), Ok(blend)) => {tx.send(ControlCommand::LayerBlend { layer, blend });}
+                    (Err(err), _) | (_, Err(err)) => {println!("{}", err)}
+                },
+                _ => {println!("Expected layer-blend <layer> <sum|max|min|multiply>")}
+            }
+        },
+        // Put a metaball on another layer
+        'l' if line.starts_with("layer") => {
+            let args: Vec<&str> = line["layer".len()..].split_whitespace().collect();
+            match args.as_slice() {
+                [index, layer] => match (usize::from_str(index), parse_layer(layer)) {
+                    (Ok(index), Ok(layer)) => {tx.send(ControlCommand::Layer { index, layer });}
+                    (Err(_), _) => {println!("Unable to parse to index \"{}\"", index)}
+                    (_, Err(err)) => {println!("{}", err)}
+                },
+                _ => {println!("Expected layer <index> <layer>")}
+            }
+        },
+        // Turn a metaball into a metaline, or back
+        'l' if line.starts_with("line") => {
+            let args: Vec<&str> = line["line".len()..].split_whitespace().collect();
+            match args.as_slice() {
+                [index, "off"] => match usize::from_str(index) {
+                    Ok(index) => {tx.send(ControlCommand::Line { index, end: None });}
+                    Err(_) => {println!("Unable to parse to index \"{}\"", index)}
+                },
+                [index, x, y] => match (usize::from_str(index), f64::from_str(x), f64::from_str(y)) {
+                    (Ok(index), Ok(x), Ok(y)) if x.is_finite() && y.is_finite() => {tx.send(ControlCommand::Line { index, end: Some(Pointf { x, y }) });}
+                    (Ok(_), Ok(x), Ok(y)) => {println!("The line can't end at ({}, {}), it needs to be a finite point", x, y)}
+                    _ => {println!("Unable to parse \"{}\" as <index> <x> <y>", args.join(" "))}
+                },
+                _ => {println!("Expected line <index> <x> <y> or line <index> off")}
+            }
+        },
+        // Measure a metaball's field from a rounded box around it
+        'b' if line.starts_with("box") => {
+            let args: Vec<&str> = line["box".len()..].split_whitespace().collect();
+            let (index, half, corner) = match args.as_slice() {
+                [index, x, y] => (index, [x, y], "0"),
+                [index, x, y, corner] => (index, [x, y], *corner),
+                _ => {
+                    println!("Expected box <index> <half width> <half height> [corner radius]");
+                    return;
+                }
+            };
+            match (usize::from_str(index), parse_positive(half[0]), parse_positive(half[1]), f64::from_str(corner)) {
+                (Ok(index), Ok(x), Ok(y), Ok(corner)) if (0.0..=x.min(y)).contains(&corner) => {
+                    tx.send(ControlCommand::Shape { index, primitive: Primitive::RoundedBox { half: (x, y), corner } });
+                }
+                (Err(_), ..) => {println!("Unable to parse to index \"{}\"", index)}
+                (_, Err(err), ..) | (_, _, Err(err), _) => {println!("Unable to parse the half sizes: {}", err)}
+                _ => {println!("The corner radius has to be from 0 to the smaller half size, not \"{}\"", corner)}
+            }
+        },
+        // Measure a metaball's field from a ring around it
+        'r' if line.starts_with("ring") => {
+            let args: Vec<&str> = line["ring".len()..].split_whitespace().collect();
+            match args.as_slice() {
+                [index, radius] => match (usize::from_str(index), parse_positive(radius)) {
+                    (Ok(index), Ok(radius)) => {tx.send(ControlCommand::Shape { index, primitive: Primitive::Ring { radius } });}
+                    (Err(_), _) => {println!("Unable to parse to index \"{}\"", index)}
+                    (_, Err(err)) => {println!("Unable to parse the radius: {}", err)}
+                },
+                _ => {println!("Expected ring <index> <radius>")}
+            }
+        },
+        // Stretch a metaball into an ellipse
+        's' if line.starts_with("stretch") => {
+            let args: Vec<&str> = line["stretch".len()..].split_whitespace().collect();
+            let (index, axes, angle) = match args.as_slice() {
+                [index, x, y] => (index, [x, y], "0"),
+                [index, x, y, angle] => (index, [x, y], *angle),
+                _ => {
+                    println!("Expected stretch <index> <x axis> <y axis> [degrees]");
+                    return;
+                }
+            };
+            match (usize::from_str(index), parse_positive(axes[0]), parse_positive(axes[1]), f64::from_str(angle)) {
+                (Ok(index), Ok(x), Ok(y), Ok(angle)) if angle.is_finite() => {
+                    tx.send(ControlCommand::Stretch { index, axes: (x, y), angle });
+                }
+                (Err(_), ..) => {println!("Unable to parse to index \"{}\"", index)}
+                (_, Err(err), ..) | (_, _, Err(err), _) => {println!("Unable to parse the axes: {}", err)}
+                _ => {println!("Unable to parse to an angle in degrees \"{}\"", angle)}
+            }
+        },
+        's' if line.starts_with("symmetry") => {
+            match Symmetry::from_str(line["symmetry".len()..].trim()) {
+                Ok(symmetry) => {tx.send(ControlCommand::Symmetry(symmetry));}
+                Err(err) => {println!("{}", err)}
+            }
+        },
+        // Reseed the random scenes
+        's' if line.starts_with("seed") => {
+            match u64::from_str(line["seed".len()..].trim()) {
+                Ok(seed) => {tx.send(ControlCommand::Seed(seed));}
+                Err(_) => {println!("Unable to parse to seed \"{}\"", line["seed".len()..].trim())}
+            }
+        },
+        // Grid snapping
+        's' if line.starts_with("snap") => {
+            match line["snap".len()..].trim() {
+                "off" => {tx.send(ControlCommand::Snap(None));}
+                spacing => match parse_spacing(spacing) {
+                    Ok(spacing) => {tx.send(ControlCommand::Snap(Some(spacing)));}
+                    Err(err) => {println!("Unable to parse to grid spacing \"{}\": {}", spacing, err)}
+                },
+            }
+        },
+        // SVG export, x for short
+        's' | 'x' if line.starts_with("svg") || line.starts_with('x') => {
+            let path = line.strip_prefix("svg").unwrap_or(&line[1..]).trim();
+            if path.is_empty() {
+                println!("Expected svg <path>");
+            } else {
+                tx.send(ControlCommand::ExportSvg(PathBuf::from(path)));
+            }
+        },
+        // Signed distance field export
+        's' if line.starts_with("sdf") => {
+            let args: Vec<&str> = line["sdf".len()..].split_whitespace().collect();
+            match args.as_slice() {
+                [path] => {tx.send(ControlCommand::ExportSdf(PathBuf::from(path), SDF_SPREAD));}
+                [path, spread] => match parse_sdf_spread(spread) {
+                    Ok(spread) => {tx.send(ControlCommand::ExportSdf(PathBuf::from(path), spread));}
+                    Err(err) => {println!("Unable to parse to spread \"{}\": {}", spread, err)}
+                },
+                _ => {println!("Expected sdf <path> [spread]")}
+            }
+        },
+        // Switch to a preset scene
+        'p' if line.starts_with("preset") => {
+            let name = line["preset".len()..].trim();
+            if name.is_empty() {
+                println!("Expected preset <name>");
+            } else {
+                tx.send(ControlCommand::Preset(name.to_string()));
+            }
+        },
+        // Save the scene to a file, or load one
+        's' if line.starts_with("save") => {
+            let path = line["save".len()..].trim();
+            if path.is_empty() {
+                println!("Expected save <path>");
+            } else {
+                tx.send(ControlCommand::Save(PathBuf::from(path)));
+            }
+        },
+        'l' if line.starts_with("load") => {
+            let path = line["load".len()..].trim();
+            if path.is_empty() {
+                println!("Expected load <path>");
+            } else {
+                tx.send(ControlCommand::Load(PathBuf::from(path)));
+            }
+        },
+        // Step through the edits to the scene
+        'u' if line == "undo" => {
+            tx.send(ControlCommand::Undo);
+        },
+        'r' if line == "redo" => {
+            tx.send(ControlCommand::Redo);
+        },
+        // Go back to the starting scene
+        'r' if line == "reset" => {
+            tx.send(ControlCommand::Reset);
+        },
+        // Re-roll part of the metaballs
+        'r' if line.starts_with("rand") => {
+            match line["rand".len()..].trim() {
+                "" => {tx.send(ControlCommand::Randomize);}
+                "pos" => {tx.send(ControlCommand::RandomizeLocations);}
+                "size" => {tx.send(ControlCommand::RandomizeSizes);}
+                _ => {println!("Expected rand, rand pos or rand size")}
+            }
+        },
+        // Record the session to a file, or stop
+        'r' if line.starts_with("record") => {
+            match line["record".len()..].trim() {
+                "" => {println!("Expected record <path> or record off")}
+                "off" => {tx.send(ControlCommand::Record(None));}
+                path => {tx.send(ControlCommand::Record(Some(PathBuf::from(path))));}
+            }
+        },
+        // Play a recorded session, or any other script, from here on the same thread
+        'r' if line.starts_with("replay") => {
+            let path = line["replay".len()..].trim();
+            match File::open(path) {
+                Ok(file) => {
+                    println!("Replaying {}", path);
+                    match read_commands(BufReader::new(file), tx) {
+                        Ok(()) => println!("Finished replaying {}", path),
+                        // whoever's sending this line stops too
+                        Err(MetaballError::Closed) => {}
+                        Err(err) => println!("{}, stopped replaying {}", err, path),
+                    }
+                }
+                Err(err) if path.is_empty() => {println!("Expected replay <path>: {}", err)}
+                Err(err) => {println!("Unable to replay {}: {}", path, err)}
+            }
+        },
+        // Delete a metaball, the same as d
+        'r' => {
+            match usize::from_str(line[1..].trim()) {
+                Ok(index) => {tx.send(ControlCommand::Remove(index));}
+                Err(_) => {println!("Unable to parse to index \"{}\"", line[1..].trim())}
+            }
+        },
+        // List the metaballs
+        'l' if line == "ls" || line == "l" => {
+            tx.send(ControlCommand::List);
+        },
+        // Change how the field falls off around each metaball
+        // Foreground and background colors
+        'f' | 'b' if line.starts_with("fg") || line.starts_with("bg") => {
+            match parse_color(line[2..].trim()) {
+                Ok(color) if line.starts_with('f') => {tx.send(ControlCommand::Foreground(color));}
+                Ok(color) => {tx.send(ControlCommand::Background(color));}
+                Err(err) => {println!("{}", err)}
+            }
+        },
+        'n' if line.starts_with("noise") => {
+            let args: Vec<&str> = line["noise".len()..].split_whitespace().collect();
+            let noise = match args.as_slice() {
+                ["off"] => Ok(None),
+                [amplitude] => parse_fraction(amplitude).map(|amplitude| Some(Noise { amplitude, scale: NOISE_SCALE })),
+                [amplitude, scale] => parse_fraction(amplitude)
+                    .and_then(|amplitude| parse_positive(scale).map(|scale| Some(Noise { amplitude, scale }))),
+                _ => Err("expected noise <amplitude> [scale] or noise off".to_string()),
+            };
+            match noise {
+                Ok(noise) => {tx.send(ControlCommand::Noise(noise));}
+                Err(err) => {println!("Unable to set the noise to \"{}\": {}", args.join(" "), err)}
+            }
+        },
+        'f' if line.starts_with("falloff") => {
+            match Falloff::from_str(line["falloff".len()..].trim()) {
+                Ok(falloff) => {tx.send(ControlCommand::Falloff(falloff));}
+                Err(err) => {println!("{}", err)}
+            }
+        },
+        // Field export
+        'f' => {
+            let path = line[1..].trim();
+            if path.is_empty() {
+                println!("Expected a file to export the field to");
+            } else {
+                tx.send(ControlCommand::ExportField(PathBuf::from(path)));
+            }
+        },
+        // Recording export
+        'e' => {
+            let args: Vec<&str> = line[1..].split_whitespace().collect();
+            let (path, frames, delay) = match args.as_slice() {
+                [path, frames] => (path, frames, None),
+                [path, frames, delay] => (path, frames, Some(delay)),
+                _ => {
+                    println!("Expected e <path> <frames> [delay ms]");
+                    return;
+                }
+            };
+            let frames = match parse_frame_count(frames) {
+                Ok(frames) => frames,
+                Err(err) => {
+                    println!("{}", err);
+                    return;
+                }
+            };
+            match delay.map(|delay| u64::from_str(delay).map_err(|_| delay)).transpose() {
+                Ok(delay) => {tx.send(ControlCommand::Export(PathBuf::from(path), frames, delay.map(Duration::from_millis)));}
+                Err(delay) => {println!("Unable to parse to milliseconds \"{}\"", delay)}
+            }
+        },
+        // Remove every metaball
+        'c' if line == "clear" => {
+            tx.send(ControlCommand::Clear);
+        },
+        // Color a metaball for when each one is colored separately
+        'c' if line.starts_with("color") => {
+            let args: Vec<&str> = line["color".len()..].split_whitespace().collect();
+            match args.as_slice() {
+                [index, color] => match (usize::from_str(index), parse_color(color)) {
+                    (Ok(index), Ok(color)) => {tx.send(ControlCommand::Color { index, color });}
+                    (Err(_), _) => {println!("Unable to parse to index \"{}\"", index)}
+                    (_, Err(err)) => {println!("{}", err)}
+                },
+                _ => {println!("Expected color <index> <color>")}
+            }
+        },
+        // Render the metaballs again beside the scene with other parameters, for comparing them
+        'c' if line.starts_with("compare") => {
+            let args: Vec<&str> = line["compare".len()..].split_whitespace().collect();
+            match args.as_slice() {
+                [] => {tx.send(ControlCommand::CompareCurrent);}
+                ["off"] => {tx.send(ControlCommand::Compare(None));}
+                [goo, threshold] | [goo, threshold, _] => match (parse_positive(goo), parse_positive(threshold), args.get(2).map_or(Ok(Falloff::default()), |falloff| Falloff::from_str(falloff))) {
+                    (Ok(goo), Ok(threshold), Ok(falloff)) => {tx.send(ControlCommand::Compare(Some(Comparison { goo, threshold, falloff })));}
+                    (Err(err), ..) | (_, Err(err), _) => {println!("Unable to parse the goo and threshold: {}", err)}
+                    (.., Err(err)) => {println!("{}", err)}
+                },
+                _ => {println!("Expected compare, compare <goo> <threshold> [falloff] or compare off")}
+            }
+        },
+        // Contour lines
+        'c' => {
+            let levels = line[1..].split(',').map(str::trim).filter(|level| !level.is_empty());
+            match levels.map(f64::from_str).collect::<Result<Vec<_>, _>>() {
+                Ok(levels) => {tx.send(ControlCommand::Isolines(levels));}
+                Err(_) => {println!("Unable to parse to floats \"{}\"", &line[1..])}
+            }
+        },
+        _ => {
+            println!("Unknown command.")
+        }
+    }
+}
+
+/// Parse an argument to a float and send the command made from it
+fn send_float(arg: &str, command: fn(f64) -> ControlCommand, tx: &CommandSender) {
+    match f64::from_str(arg.trim()) {
+        Ok(val) => tx.send(command(val)),
+        Err(_) => println!("Unable to parse to float \"{}\"", arg.trim()),
+    }
+}
+
+/// A control command that can be sent from one thread to another
+#[derive(Debug)]
+enum ControlCommand {
+    /// Adjust goo factor
+    Goo(f64),
+
+    /// Adjust the threshold factor
+    Threshold(f64),
+
+    /// Change how the field falls off around each metaball
+    Falloff(Falloff),
+
+    /// Render the metaballs again beside the scene with other parameters, or stop with None
+    Compare(Option<Comparison>),
+
+    /// Render the metaballs beside the scene with the parameters it has now, to compare changes against
+    CompareCurrent,
+
+    /// Mirror or repeat the metaballs around the middle of the image
+    Symmetry(Symmetry),
+
+    /// Set how a layer above the bottom one is combined with the layers under it
+    LayerBlend { layer: usize, blend: LayerBlend },
+
+    /// Wobble the field with noise, or stop with None
+    Noise(Option<Noise>),
+
+    /// Print the field at a point
+    Probe(Pointf),
+
+    /// Send a metaball around a path from where it is, taking this many animation ticks a loop, or stop it
+    /// with None
+    Motion { index: usize, path: Option<motion::Path>, period: f64 },
+
+    /// Swing a metaball's size around its resting size when animating, or stop it with None
+    Pulse { index: usize, pulse: Option<Pulse> },
+
+    /// Toggle coloring the shape by field intensity
+    ToggleGradient,
+
+    /// Set the field levels to draw contour lines at
+    Isolines(Vec<f64>),
+
+    /// Set the color bands to draw
+    Bands(Bands),
+
+    /// Set the color of the inside of the shape
+    Foreground(Rgba<u8>),
+
+    /// Set the color of the background
+    Background(Rgba<u8>),
+
+    /// Draw bands at several thresholds, shaded from the background to the inside color
+    Thresholds(Vec<f64>),
+
+    /// Snap added metaballs to a grid with this spacing, or stop snapping them
+    Snap(Option<u32>),
+
+    /// Write the field values to a file
+    ExportField(PathBuf),
+
+    /// Write the outline of the shape to an SVG file
+    ExportSvg(PathBuf),
+
+    /// Write a signed distance field of the shape to a PNG, reaching this many pixels either side of the
+    /// edge, or an f32 file
+    ExportSdf(PathBuf, f64),
+
+    /// Write the surface of the shape in 3D to an OBJ or STL file, sampling the field in cubes this many
+    /// pixels wide
+    ExportMesh(PathBuf, f64),
+
+    /// Add a metaball
+    Add { location: Pointf, size: f64, radius: Option<f64> },
+
+    /// Remove the metaball at an index
+    Remove(usize),
+
+    /// Move the metaball at an index
+    Move { index: usize, location: Pointf },
+
+    /// Set the size of the metaball at an index
+    Size { index: usize, size: f64 },
+
+    /// Set the color of the metaball at an index
+    Color { index: usize, color: Rgba<u8> },
+
+    /// Measure the field of the metaball at an index from the line to an end, or from its center with None
+    Line { index: usize, end: Option<Pointf> },
+
+    /// Measure the field of the metaball at an index from a rounded box or a ring around it
+    Shape { index: usize, primitive: Primitive },
+
+    /// Move the metaball at an index onto a layer
+    Layer { index: usize, layer: usize },
+
+    /// Stretch the metaball at an index along its own axes and turn it clockwise by an angle in degrees
+    Stretch { index: usize, axes: (f64, f64), angle: f64 },
+
+    /// Remove all the metaballs
+    Clear,
+
+    /// Replace the scene with a named preset
+    Preset(String),
+
+    /// Save the goo, threshold and metaballs to a TOML file
+    Save(PathBuf),
+
+    /// Replace the scene with one from a TOML file
+    Load(PathBuf),
+
+    /// Print the current parameters and metaballs
+    List,
+
+    /// Print the whole scene as a line of JSON, for the JSON commands
+    State,
+
+    /// Go back to the scene from the start
+    Reset,
+
+    /// Replace the metaballs with random ones
+    Randomize,
+
+    /// Seed the random scenes, and replace the metaballs with the random ones it starts with
+    Seed(u64),
+
+    /// Move the metaballs to random places
+    RandomizeLocations,
+
+    /// Give the metaballs random sizes
+    RandomizeSizes,
+
+    /// Record a number of frames to a GIF file
+    ExportGif(PathBuf, u32),
+
+    /// Record a number of frames of the animation or of morphing between random scenes, with an optional
+    /// delay between them
+    Export(PathBuf, u32, Option<Duration>),
+
+    /// Toggle moving the metaballs by the physics while animating
+    TogglePhysics,
+
+    /// Toggle rendering the raymarched 3D view as a red-cyan anaglyph
+    ToggleAnaglyph,
+
+    /// Toggle drawing the histogram of the field values over the frame
+    ToggleHistogram,
+
+    /// Set the heatmap's colormap, and its heat scale if there's one
+    Colormap(Colormap, Option<HeatScale>),
+
+    /// Set the physics' gravity
+    Gravity(f64),
+
+    /// Set the physics' damping
+    Damping(f64),
+
+    /// Set how strongly the metaballs attract each other in the physics mode
+    Attraction(f64),
+
+    /// Set how stiffly the metaballs push apart where they overlap in the physics mode
+    Collision(f64),
+
+    /// Go back to the scene before the last edit
+    Undo,
+
+    /// Go forward to the scene the last undo went back from
+    Redo,
+
+    /// Start recording the session to a file, or stop recording with no file
+    Record(Option<PathBuf>),
+
+    /// Something the gamepad did, see [gamepad]
+    Gamepad(Action),
+
+    /// There are no more commands, the input has ended
+    EndOfInput,
+}
+
+/// Time `naive_impl` for every combination of resolution and metaball count, printing a table of the results.
+/// The scenes come from a fixed seed, so every run renders the same thing.
+fn run_bench(resolutions: &[u32], counts: &[u32], iterations: u32, precision: Precision) {
+    let opts = RenderOpts { precision, ..RenderOpts::default() };
+    let iterations = iterations.max(1);
+    println!("{:>10} {:>6} {:>10} {:>10} {:>10}", "resolution", "balls", "mean ms", "min ms", "max ms");
+    for &resolution in resolutions {
+        for &count in counts {
+            let metadata = bench_scene(resolution, count);
+            let mut times = vec![];
+            for _ in 0..iterations {
+                let start = Instant::now();
+                naive_impl(resolution, resolution, 1, &metadata, &opts);
+                times.push(start.elapsed().as_secs_f64() * 1000.0);
+            }
+            let mean = times.iter().sum::<f64>() / times.len() as f64;
+            let min = times.iter().cloned().fold(f64::INFINITY, f64::min);
+            let max = times.iter().cloned().fold(0.0, f64::max);
+            println!("{:>10} {:>6} {:>10.2} {:>10.2} {:>10.2}", resolution, count, mean, min, max);
+        }
+    }
+}
+
+/// A random looking but always identical scene for benchmarking, sized like [MetaballData::from_rng]'s
+/// scenes scaled up to the resolution
+fn bench_scene(resolution: u32, count: u32) -> MetaballData {
+    let mut rng = StdRng::seed_from_u64(BENCH_SEED);
+    let goo = 1.6;
+    // the field falls off with distance to the goo, so this keeps the shapes the same relative size
+    let size_scale = (resolution as f64 / 256.0).powf(goo);
+    let metaballs = (0..count)
+        .map(|_| {
+            let location = Pointf { x: rng.gen_range(0..resolution) as f64, y: rng.gen_range(0..resolution) as f64 };
+            Metaball::new(location, rng.gen_range(0.25..0.75) * BASE_METABALL_SIZE * size_scale)
+        })
+        .collect();
+    MetaballData::new(goo, 0.5, resolution, resolution, metaballs)
+}
+
+/// Reports how long renders take, printing every render or the average once a second while animating, and
+/// showing the latest in the window's title
+struct FrameStats {
+    /// Whether to print the reports
+    enabled: bool,
+    /// Whether to show the latest report in the window's title, toggled with F
+    pub hud: bool,
+    frames: u32,
+    render_time: Duration,
+    since: Instant,
+    /// The last render's time in milliseconds, or the average over the last second of animating with the
+    /// frame rate
+    latest: Option<(f64, Option<f64>)>,
+}
+
+impl FrameStats {
+    pub fn new(enabled: bool) -> FrameStats {
+        FrameStats { enabled, hud: false, frames: 0, render_time: Duration::ZERO, since: Instant::now(), latest: None }
+    }
+
+    /// Report a single render
+    pub fn record(&mut self, elapsed: Duration) {
+        let milliseconds = elapsed.as_secs_f64() * 1000.0;
+        if self.enabled {
+            println!("Rendered in {:.2}ms", milliseconds);
+        }
+        // the frame rate carries on while animating
+        let fps = self.latest.and_then(|(_, fps)| fps).filter(|_| self.since.elapsed() < Duration::from_secs(2));
+        self.latest = Some((milliseconds, fps));
+    }
+
+    /// Add an animation frame to the average, reporting it if a second has passed
+    pub fn record_animated(&mut self, elapsed: Duration) {
+        if !self.enabled && !self.hud {
+            return;
+        }
+        self.frames += 1;
+        self.render_time += elapsed;
+        let window = self.since.elapsed();
+        if window >= Duration::from_secs(1) {
+            let fps = self.frames as f64 / window.as_secs_f64();
+            let milliseconds = self.render_time.as_secs_f64() * 1000.0 / self.frames as f64;
+            if self.enabled {
+                println!("{:.1} fps, rendered in {:.2}ms on average", fps, milliseconds);
+            }
+            self.latest = Some((milliseconds, Some(fps)));
+            self.reset();
+        }
+    }
+
+    /// The window's title, with the latest report when the HUD is on
+    pub fn title(&self) -> String {
+        match self.latest.filter(|_| self.hud) {
+            Some((milliseconds, Some(fps))) => format!("Metaballs - {:.2}ms per render, {:.1} fps", milliseconds, fps),
+            Some((milliseconds, None)) => format!("Metaballs - {:.2}ms per render", milliseconds),
+            None => "Metaballs".to_string(),
+        }
+    }
+
+    /// Start a new averaging window, such as when the animation starts
+    pub fn reset(&mut self) {
+        self.frames = 0;
+        self.render_time = Duration::ZERO;
+        self.since = Instant::now();
+    }
+}
+
+/// Main
+fn main() {
+    let opt = Opt::from_args();
+    logging::init(opt.verbose, opt.quiet);
+    if let Some(Command::Bench { resolutions, counts, iterations }) = &opt.command {
+        run_bench(resolutions, counts, *iterations, opt.precision);
+        return;
+    }
+
+    // Generate or load the initial metaballs
+    let mut render_opts = RenderOpts {
+        on_color: opt.fg,
+        off_color: opt.bg,
+        cross_color: opt.cross_color,
+        cross_style: opt.cross_style,
+        cross_radius: opt.cross_radius,
+        isolines: opt.isolines.clone(),
+        isoline_color: opt.isoline_color,
+        outline_color: opt.outline_color,
+        mode: opt.mode,
+        cell_size: opt.cell_size,
+        ssaa: opt.ssaa,
+        ball_colors: opt.ball_colors,
+        hue_cycle: HueCycle { enabled: false, hue: 0.0, speed: opt.hue_speed },
+        bands: opt.bands.clone().unwrap_or_default(),
+        snap: opt.snap,
+        camera: Camera { yaw: opt.yaw.to_radians(), pitch: opt.pitch.to_radians(), anaglyph: opt.anaglyph },
+        precision: opt.precision,
+        colormap: opt.colormap,
+        heat_scale: opt.heat_scale,
+        transparent_export: opt.transparent,
+        histogram: opt.histogram,
+        ..RenderOpts::default()
+    };
+    if let Some(theme) = &opt.theme {
+        theme.apply(&mut render_opts);
+    }
+    let mut rng = opt.scene.rng();
+    let mut metadata = opt.scene.scene(&mut rng).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    let keyframes = opt.keyframes.as_ref().map(|path| match Keyframes::load(path, metadata.width, metadata.height) {
+        Ok(keyframes) => keyframes,
+        Err(err) => {
+            eprintln!("Unable to load keyframes {}: {}", path.display(), err);
+            std::process::exit(1);
+        }
+    });
+    if let Some(keyframes) = &keyframes {
+        metadata.set_scene(keyframes.scene_at(0.0));
+    }
+    if let Some(path) = &opt.bg_image {
+        match load_background(path, metadata.width, metadata.height) {
+            Ok(background) => render_opts.background = Some(background),
+            Err(err) => {
+                eprintln!("Unable to load background image {}: {}", path.display(), err);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if let Some(path) = &opt.export_svg {
+        if let Err(err) = export_svg(path, &metadata, render_opts.fill_color()) {
+            eprintln!("Unable to export outline to {}: {}", path.display(), err);
+            std::process::exit(1);
+        }
+    }
+    if let Some(path) = &opt.export_mesh {
+        if let Err(err) = export_mesh(path, &metadata, opt.mesh_cell) {
+            eprintln!("Unable to export mesh to {}: {}", path.display(), err);
+            std::process::exit(1);
+        }
+    }
+    if let Some(path) = &opt.export_sdf {
+        if let Err(err) = export_sdf(path, &metadata, opt.sdf_spread) {
+            eprintln!("Unable to export signed distance field to {}: {}", path.display(), err);
+            std::process::exit(1);
+        }
+    }
+    for (path, sheet) in opt.export_gif.iter().map(|path| (path, false)).chain(opt.export_sheet.iter().map(|path| (path, true))) {
+        let sequence = match &keyframes {
+            Some(keyframes) => Sequence::Keyframes(keyframes.clone()),
+            None if opt.animate => Sequence::Animation,
+            None => Sequence::Tween(opt.tween_frames),
+        };
+        let mut recording = Recording::new(opt.export_frames, sequence);
+        if let Some(delay) = export_delay(&opt) {
+            recording.delay = delay;
+        }
+        if let Some(keyframes) = &keyframes {
+            recording.frames = keyframes.frames(recording.delay);
+        }
+        // record with a copy of the random numbers, so the scenes after starting are the same either way
+        let recorded = if sheet { record_sheet } else { record };
+        if let Err(err) = recorded(path, &recording, &metadata, &render_opts, &opt.scene.generation(), &mut rng.clone()) {
+            eprintln!("Unable to export recording to {}: {}", path.display(), err);
+            std::process::exit(1);
+        }
+    }
+    if let Some(path) = &opt.record_video {
+        // stdout is where the window's messages go
+        if path == Path::new("-") || !is_video(path) {
+            eprintln!("Unable to record video to {}, expected a .y4m, .mp4, .webm, .mkv or .mov path", path.display());
+            std::process::exit(1);
+        }
+    }
+
+    let script = opt.script.as_ref().map(|path| match File::open(path) {
+        Ok(script) => script,
+        Err(err) => {
+            eprintln!("Unable to open script {}: {}", path.display(), err);
+            std::process::exit(1);
+        }
+    });
+    #[cfg(not(unix))]
+    if opt.control_socket.is_some() {
+        eprintln!("--control-socket needs Unix domain sockets, use --listen on this platform");
+        std::process::exit(1);
+    }
+    let remote = Remote {
+        osc: opt.osc.map(|address| match UdpSocket::bind(address) {
+            Ok(socket) => {
+                log::info!("Listening for OSC on {}", address);
+                socket
+            }
+            Err(err) => {
+                eprintln!("Unable to listen for OSC on {}: {}", address, err);
+                std::process::exit(1);
+            }
+        }),
+        listener: opt.listen.map(|address| match TcpListener::bind(address) {
+            Ok(listener) => {
+                log::info!("Listening for commands on {}", address);
+                listener
+            }
+            Err(err) => {
+                eprintln!("Unable to listen for commands on {}: {}", address, err);
+                std::process::exit(1);
+            }
+        }),
+        #[cfg(unix)]
+        control_socket: opt.control_socket.as_ref().map(|path| match bind_control_socket(path) {
+            Ok(listener) => {
+                log::info!("Listening for commands on {}", path.display());
+                listener
+            }
+            Err(err) => {
+                eprintln!("Unable to listen for commands on {}: {}", path.display(), err);
+                std::process::exit(1);
+            }
+        }),
+        watch: opt.scene.config.clone().filter(|_| opt.watch),
+        gamepad: opt.gamepad.as_ref().map(|path| match open_gamepad(path) {
+            Ok(device) => {
+                log::info!("Reading the gamepad {}", path.display());
+                device
+            }
+            Err(err) => {
+                eprintln!("Unable to open the gamepad {}: {}", path.display(), err);
+                std::process::exit(1);
+            }
+        }),
+        midi: opt.midi.as_ref().map(|path| {
+            let map = match &opt.midi_map {
+                Some(map_path) => MidiMap::load(map_path).unwrap_or_else(|err| {
+                    eprintln!("Unable to load the MIDI mapping {}: {}", map_path.display(), err);
+                    std::process::exit(1);
+                }),
+                None => MidiMap::default(),
+            };
+            match File::open(path) {
+                Ok(device) => {
+                    log::info!("Reading the MIDI controller {}", path.display());
+                    (device, map)
+                }
+                Err(err) => {
+                    eprintln!("Unable to open the MIDI controller {}: {}", path.display(), err);
+                    std::process::exit(1);
+                }
+            }
+        }),
+    };
+
+    let headless = if opt.headless { Some(Command::Render { out: opt.output.clone(), raw: false }) } else { None };
+    match headless.as_ref().or(opt.command.as_ref()) {
+        Some(Command::Render { out, raw }) => {
+            let image = Renderer::new(opt.backend).render_image(metadata.width, metadata.height, &metadata, &render_opts.export_opts());
+            if *raw {
+                let stdout = io::stdout();
+                let mut stdout = stdout.lock();
+                if let Err(err) = stdout.write_all(image.as_raw()).and_then(|()| stdout.flush()) {
+                    eprintln!("Unable to write the image to STDOUT: {}", err);
+                    std::process::exit(1);
+                }
+            } else if let Some(out) = out {
+                match image.save(out) {
+                    Ok(()) => log::info!("Rendered to {}", out.display()),
+                    Err(err) => {
+                        eprintln!("Unable to render to {}: {}", out.display(), err);
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+        Some(Command::Generate { dir, count, json }) => {
+            if let Err(err) = run_generate(&opt, dir, *count, *json, metadata, &render_opts, rng) {
+                eprintln!("Unable to generate scenes in {}: {}", dir.display(), err);
+                std::process::exit(1);
+            }
+        }
+        Some(Command::Batch) => run_batch(&opt, metadata, render_opts, rng, script, remote),
+        _ if opt.tui => {
+            if let Err(err) = run_tui(&opt, metadata, render_opts, rng, script, remote) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+        _ => {
+            if let Err(err) = run_window(&opt, metadata, render_opts, rng, script, remote, keyframes) {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// Render the starting scene and then random ones to numbered PNG files in a directory, each with a JSON
+/// label if asked for
+fn run_generate(opt: &Opt, dir: &Path, count: u32, json: bool, mut metadata: MetaballData, render_opts: &RenderOpts, mut rng: StdRng) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    let mut renderer = Renderer::new(opt.backend);
+    let render_opts = &render_opts.export_opts();
+    let digits = count.saturating_sub(1).to_string().len();
+    for index in 0..count {
+        if index > 0 {
+            metadata.randomize_with(&opt.scene.generation(), &mut rng);
+        }
+        let name = format!("{:0digits$}", index, digits = digits);
+        let image = renderer.render_image(metadata.width, metadata.height, &metadata, render_opts);
+        image.save(dir.join(format!("{}.png", name))).map_err(|err| err.to_string())?;
+        if json {
+            std::fs::write(dir.join(format!("{}.json", name)), scene_json(&metadata)).map_err(|err| err.to_string())?;
+        }
+    }
+    log::info!("Generated {} scenes in {}", count, dir.display());
+    Ok(())
+}
+
+/// Apply the commands from the script, STDIN and the remote controls to the scene until the end of STDIN,
+/// without a window
+fn run_batch(opt: &Opt, mut metadata: MetaballData, mut render_opts: RenderOpts, mut rng: StdRng, script: Option<File>, remote: Remote) {
+    let (generation, animating) = (&opt.scene.generation(), opt.animate);
+    let (tx, rx) = std::sync::mpsc::channel();
+    let tx = CommandSender::new(tx);
+    remote.start(&tx);
+    control_stdin(tx, script);
+    let initial = metadata.clone();
+    let mut recorder = None;
+    let mut history = History::new(opt.history_depth);
+    loop {
+        match rx.recv() {
+            Ok(ControlCommand::EndOfInput) => break,
+            Ok(ControlCommand::Record(path)) => session::set_recording(&mut recorder, path, &metadata),
+            Ok(step @ ControlCommand::Undo) | Ok(step @ ControlCommand::Redo) => {
+                if history.step(matches!(step, ControlCommand::Undo), &mut metadata) {
+                    session::record(&mut recorder, None, &metadata);
+                }
+            }
+            Ok(command) => {
+                let before = metadata.clone();
+                let line = session::command_line(&command);
+                apply_command(command, &mut metadata, &initial, &mut render_opts, animating, generation, &mut rng);
+                session::record(&mut recorder, line, &metadata);
+                if metadata != before {
+                    history.record(before);
+                }
+            }
+            // The command thread panicked before the end of the input
+            Err(_) => {
+                println!("STDIN hung up!");
+                std::process::exit(-1);
+            }
+        }
+    }
+}
+
+/// Draw the scene in the terminal until Q or Escape is pressed, applying the keys pressed in it and the
+/// commands from the script, STDIN and the remote controls
+fn run_tui(opt: &Opt, mut metadata: MetaballData, mut render_opts: RenderOpts, mut rng: StdRng, script: Option<File>, remote: Remote) -> Result<(), MetaballError> {
+    let generation = opt.scene.generation();
+    let glyphs = if opt.braille { Glyphs::Braille } else { Glyphs::HalfBlocks };
+    let mut terminal = Terminal::open().map_err(MetaballError::Terminal)?;
+    let keys = terminal.keys().map_err(MetaballError::Terminal)?;
+    let (tx, rx) = std::sync::mpsc::channel();
+    let tx = CommandSender::new(tx);
+    let listening = remote.start(&tx);
+    if keys.is_some() && io::stdin().is_terminal() {
+        // STDIN is the keys, so commands are typed after a colon instead
+        if let Some(script) = script {
+            let tx = tx.clone();
+            std::thread::spawn(move || {
+                if let Err(err @ MetaballError::Read(_)) = read_commands(BufReader::new(script), &tx) {
+                    println!("{}", err);
+                }
+            });
+        }
+    } else {
+        control_stdin(tx.clone(), script);
+    }
+
+    let initial = metadata.clone();
+    let mut recorder = None;
+    let mut history = History::new(opt.history_depth);
+    let mut renderer = Renderer::new(opt.backend);
+    let mut animating = opt.animate;
+    let mut tween: Option<Tween> = None;
+    let pacing = Pacing::new(opt.fps);
+    let mut behavior = spawn_behavior(opt);
+    let mut theme = opt.theme.unwrap_or(THEMES[0]);
+    let mut shape_mode = if opt.mode == RenderMode::Heatmap { RenderMode::Naive } else { opt.mode };
+    // the command being typed after a colon
+    let mut typing: Option<String> = None;
+    let mut terminal_size = terminal.size();
+    let mut changed = true;
+    loop {
+        let tick = Instant::now();
+
+        // fill the terminal, starting the messages under the scene again when it's resized
+        if terminal.size() != terminal_size {
+            terminal_size = terminal.size();
+            terminal.resized().map_err(MetaballError::Terminal)?;
+            changed = true;
+        }
+        let size = terminal.image_size(glyphs, opt.graphics, opt.downscale);
+        if size != (metadata.width, metadata.height) {
+            metadata.resize(size.0, size.1);
+            tween = None;
+            changed = true;
+        }
+
+        // the keys, which edit the scene directly or send the same commands as STDIN
+        let before = metadata.clone();
+        for key in keys.iter().flat_map(|keys| keys.try_iter()) {
+            changed = true;
+            if let Some(line) = &mut typing {
+                match key {
+                    Key::Enter => run_line(&typing.take().unwrap_or_default(), &tx),
+                    Key::Escape => typing = None,
+                    Key::Backspace => {
+                        line.pop();
+                    }
+                    Key::Char(c) => line.push(c),
+                    _ => {}
+                }
+                continue;
+            }
+            match key {
+                Key::Char('q') | Key::Escape | Key::Control('c') => return Ok(()),
+                Key::Char(':') => typing = Some(String::new()),
+                Key::Char(' ') => tx.send(ControlCommand::Randomize),
+                Key::Control('z') => tx.send(ControlCommand::Undo),
+                Key::Control('y') => tx.send(ControlCommand::Redo),
+                Key::Control('r') => tx.send(ControlCommand::Reset),
+                Key::Char('t') => {
+                    println!("tweening to a random scene");
+                    let mut target = metadata.clone();
+                    target.randomize_with(&generation, &mut rng);
+                    tween = Some(Tween::new(metadata.clone(), target, opt.tween_frames));
+                }
+                Key::Char('c') => {
+                    println!("crosses toggled");
+                    render_opts.crosses = !render_opts.crosses;
+                }
+                Key::Char('+') | Key::Char('=') => {
+                    let index = metadata.add_metaball(random_metaball(metadata.width, metadata.height, &generation, &mut rng));
+                    println!("Added metaball {}", index);
+                }
+                Key::Char('-') if metadata.metaballs.len() > generation.min_count as usize => {
+                    metadata.remove_metaball(metadata.metaballs.len() - 1);
+                    println!("Removed metaball {}", metadata.metaballs.len());
+                }
+                Key::Char('-') => println!("Keeping at least {} metaballs", generation.min_count),
+                Key::Char('b') => {
+                    println!("metaball colors toggled");
+                    render_opts.ball_colors = !render_opts.ball_colors;
+                }
+                Key::Char('p') => {
+                    animating = !animating;
+                    println!("animation {}", if animating { "started" } else { "paused" });
+                }
+                Key::Char('h') => {
+                    render_opts.mode = match render_opts.mode {
+                        RenderMode::Heatmap => shape_mode,
+                        mode => {
+                            shape_mode = mode;
+                            RenderMode::Heatmap
+                        }
+                    };
+                    println!("heatmap {}", if render_opts.mode == RenderMode::Heatmap { "enabled" } else { "disabled" });
+                }
+                Key::Char('H') => {
+                    render_opts.hue_cycle.enabled = !render_opts.hue_cycle.enabled;
+                    println!("hue cycling {}", if render_opts.hue_cycle.enabled { "enabled" } else { "disabled" });
+                }
+                Key::Char('n') => {
+                    theme = theme.next();
+                    theme.apply(&mut render_opts);
+                    println!("theme {}", theme.name);
+                }
+                Key::Char('i') => {
+                    render_opts.histogram = !render_opts.histogram;
+                    println!("histogram {}", if render_opts.histogram { "shown" } else { "hidden" });
+                }
+                Key::Up | Key::Down => {
+                    let threshold = metadata.threshold + if key == Key::Up { opt.threshold_step } else { -opt.threshold_step };
+                    match metadata.set_threshold(threshold) {
+                        Ok(()) => println!("Set threshold to {}", threshold),
+                        Err(err) => println!("Not setting threshold to {}: {}", threshold, err),
+                    }
+                }
+                Key::Right | Key::Left => {
+                    let goo = metadata.goo + if key == Key::Right { opt.goo_step } else { -opt.goo_step };
+                    match metadata.set_goo(goo) {
+                        Ok(()) => println!("Set goo to {}", goo),
+                        Err(err) => println!("Not setting goo to {}: {}", goo, err),
+                    }
+                }
+                _ => {}
+            }
+        }
+        if metadata != before {
+            history.record(before);
+        }
+
+        // the commands, handled the same as without a window
+        for command in rx.try_iter() {
+            changed = true;
+            match command {
+                // without any keys or remote controls that's the end of everything
+                ControlCommand::EndOfInput if keys.is_none() && !listening => return Ok(()),
+                ControlCommand::EndOfInput => println!("STDIN closed, the keys still work"),
+                ControlCommand::Record(path) => session::set_recording(&mut recorder, path, &metadata),
+                step @ ControlCommand::Undo | step @ ControlCommand::Redo => {
+                    if history.step(matches!(step, ControlCommand::Undo), &mut metadata) {
+                        tween = None;
+                        session::record(&mut recorder, None, &metadata);
+                    }
+                }
+                command => {
+                    let before = metadata.clone();
+                    let line = session::command_line(&command);
+                    apply_command(command, &mut metadata, &initial, &mut render_opts, animating, &generation, &mut rng);
+                    session::record(&mut recorder, line, &metadata);
+                    if metadata != before {
+                        history.record(before);
+                        tween = None;
+                    }
+                }
+            }
+        }
+
+        for _ in 0..pacing.ticks {
+            if let Some(transition) = &mut tween {
+                metadata = transition.step();
+                if transition.finished() {
+                    tween = None;
+                }
+                changed = true;
+            } else if animating {
+                changed |= metadata.step();
+                changed |= run_behavior(&mut behavior, &mut metadata);
+            }
+            if animating && render_opts.hue_cycle.enabled {
+                render_opts.hue_cycle.advance();
+                changed = true;
+            }
+        }
+
+        if changed {
+            let image = renderer.render_image(metadata.width, metadata.height, &metadata, &render_opts);
+            let status = match &typing {
+                Some(line) => format!(":{}", line),
+                None => format!(
+                    " threshold {} goo {} metaballs {}  [:] type a command  [Q] quit",
+                    metadata.threshold,
+                    metadata.goo,
+                    metadata.metaballs.len()
+                ),
+            };
+            match opt.graphics {
+                Some(graphics) => terminal.draw_image(&image, graphics, &status),
+                None => terminal.draw(&glyphs.lines(&image, render_opts.off_color), &status),
+            }
+            .map_err(MetaballError::Terminal)?;
+            changed = false;
+        }
+        // the keys are still looked at every tick when nothing's moving
+        let interval = if animating || tween.is_some() { pacing.interval } else { ANIMATION_TICK };
+        std::thread::sleep(interval.saturating_sub(tick.elapsed()));
+    }
+}
+
+/// Open the window and run it until it's closed
+fn run_window(opt: &Opt, mut metadata: MetaballData, mut render_opts: RenderOpts, mut rng: StdRng, script: Option<File>, remote: Remote, keyframes: Option<Keyframes>) -> Result<(), MetaballError> {
+    let generation = opt.scene.generation();
+    let (threshold_step, goo_step) = (opt.threshold_step, opt.goo_step);
+    let tween_frames = opt.tween_frames;
+    let morph = opt.morph;
+    let (mut follow, follow_lag) = (opt.follow, opt.follow_lag);
+    // which metaball M makes follow the cursor
+    let follower = opt.follow.unwrap_or(0);
+    let (downscale, native_resolution) = (opt.downscale, opt.native_resolution);
+    let timing = opt.timing;
+    let mut history = History::new(opt.history_depth);
+    let mut snap_spacing = opt.snap.unwrap_or(DEFAULT_SNAP_SPACING);
+    // the mode the H, D, L and O keys go back to from the heatmap, the 3D view, the lit shape and the outline
+    let mut shape_mode = match opt.mode {
+        RenderMode::Heatmap | RenderMode::Raymarch | RenderMode::Lit | RenderMode::Outline => RenderMode::Naive,
+        mode => mode,
+    };
+    // the theme the N key moves on from
+    let mut theme = opt.theme.unwrap_or(THEMES[0]);
+    let initial = metadata.clone();
+    let slots_path = opt.slots.clone();
+    let mut slots = match Slots::load(&slots_path) {
+        Ok(slots) => slots,
+        Err(err) => {
+            eprintln!("Unable to load the scene slots from {}: {}", slots_path.display(), err);
+            std::process::exit(1);
+        }
+    };
+
+    let video_delay = export_delay(opt).unwrap_or(ANIMATION_TICK);
+    let mut video = opt.record_video.as_ref().map(|path| match VideoWriter::create(path, metadata.width, metadata.height, video_delay) {
+        Ok(video) => {
+            log::info!("Recording video to {}", path.display());
+            LiveRecording::new(video, video_delay)
+        }
+        Err(err) => {
+            eprintln!("Unable to record video to {}: {}", path.display(), err);
+            std::process::exit(1);
+        }
+    });
+
+    print_help();
+
+    // Create Window
+    let event_loop = EventLoop::with_user_event();
+    let window = WindowBuilder::new()
+        .with_inner_size(LogicalSize::new(metadata.width * downscale, metadata.height * downscale))
+        .with_title("Metaballs")
+        .build(&event_loop)?;
+    let mut input = WinitInputHelper::new();
+
+    // Get window's texture and bind renderer to it, with the scene laid out on as many pixels as the
+    // display gives the window
+    let mut vsync = !opt.no_vsync;
+    let mut size = buffer_size(window.inner_size(), window.scale_factor(), downscale, native_resolution);
+    if size != (metadata.width, metadata.height) {
+        metadata.resize(size.0, size.1);
+    }
+    let mut screen = Screen::new(&window, size, vsync, Renderer::new(opt.backend), Duration::from_millis(opt.ease))?;
+    if opt.audio {
+        screen.listen();
+    }
+    if opt.progressive {
+        screen.progressive = Some(Progressive::default());
+    }
+    let mut panel = Panel::new(&window);
+
+    // Start threads to listen for commands on STDIN and the remote controls
+    let tx = CommandSender::to_window(event_loop.create_proxy());
+    remote.start(&tx);
+    control_stdin(tx, script);
+
+    // Render initial metaballs
+    let mut frame_stats = FrameStats::new(timing);
+    let mut shown_title = frame_stats.title();
+    // the field under the cursor and where that is, shown in the title
+    let mut probed: Option<(Pointf, f64)> = None;
+    frame_stats.record(screen.render(&metadata, &render_opts));
+
+    // Animation state, the loop wakes up on a timer instead of waiting for input while animating
+    let mut animating = opt.animate;
+    let pacing = Pacing::new(opt.fps);
+    let mut next_tick = Instant::now();
+    let mut tween: Option<Tween> = None;
+    let mut behavior = spawn_behavior(opt);
+    let mut playback = keyframes.clone().map(Playback::new);
+
+    // The metaball being dragged with the mouse, and the scene from before the drag for undoing it
+    let mut drag: Option<(usize, MetaballData)> = None;
+
+    // The scene from before the control panel's widget being dragged, so the whole drag is undone at once
+    let mut panel_edit: Option<MetaballData> = None;
+
+    // The fingers on a touchscreen, and the gesture they're making
+    let mut touches = Touches::default();
+    let mut touch_edit: Option<TouchEdit> = None;
+
+    // Where the session is being recorded to with the record command, if anywhere
+    let mut recorder: Option<session::Recorder> = None;
+
+    // Start the window event loop
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = if animating || tween.is_some() || playback.is_some() || screen.changing() { ControlFlow::WaitUntil(next_tick) } else { ControlFlow::Wait };
+        // a coarse render is sharpened once the edits settle
+        if let (ControlFlow::Wait, Some(refine_at)) = (*control_flow, screen.refine_at()) {
+            *control_flow = ControlFlow::WaitUntil(refine_at);
+        }
+
+        // Commands from STDIN and the remote controls come in as events of their own, which are nothing
+        // to the window controls
+        if let Event::UserEvent(command) = event {
+            match command {
+                ControlCommand::EndOfInput => println!("STDIN closed, the window controls still work"),
+                ControlCommand::Record(path) => session::set_recording(&mut recorder, path, &metadata),
+                step @ ControlCommand::Undo | step @ ControlCommand::Redo => {
+                    if history.step(matches!(step, ControlCommand::Undo), &mut metadata) {
+                        tween = None;
+                        playback = None;
+                        drag = None;
+                        session::record(&mut recorder, None, &metadata);
+                        frame_stats.record(screen.render(&metadata, &render_opts));
+                        window.request_redraw();
+                    }
+                }
+                command => {
+                    let before = metadata.clone();
+                    let line = session::command_line(&command);
+                    apply_command(command, &mut metadata, &initial, &mut render_opts, animating, &generation, &mut rng);
+                    session::record(&mut recorder, line, &metadata);
+                    if metadata != before {
+                        history.record(before);
+                    }
+                    // re-render metaballs and request a redraw
+                    frame_stats.record(screen.render(&metadata, &render_opts));
+                    window.request_redraw();
+                }
+            }
+            return;
+        }
+
+        // input over the control panel is the panel's, the window controls don't see it
+        let captured = panel.handle_event(&event);
+
+        // Fill the resized window, scaling the scene to it. Moving to a monitor with another scale factor
+        // resizes the window without a Resized event on some platforms, and the same window size is a
+        // different buffer size at the new scale, see buffer_size.
+        let resized = match &event {
+            Event::WindowEvent { event: WindowEvent::Resized(surface), window_id } if *window_id == window.id() => {
+                Some((*surface, window.scale_factor()))
+            }
+            Event::WindowEvent { event: WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size }, window_id } if *window_id == window.id() => {
+                Some((**new_inner_size, *scale_factor))
+            }
+            _ => None,
+        };
+        if let Some((surface, scale_factor)) = resized.filter(|(surface, _)| surface.width > 0 && surface.height > 0) {
+            size = buffer_size(surface, scale_factor, downscale, native_resolution);
+            if size != (metadata.width, metadata.height) {
+                metadata.resize(size.0, size.1);
+                tween = None;
+                // the keyframes are laid out on the image from the start
+                playback = None;
+            }
+            screen.resize((surface.width, surface.height), size);
+            frame_stats.record(screen.render(&metadata, &render_opts));
+            window.request_redraw();
+        }
+
+        match event {
+            Event::WindowEvent {
+                event: WindowEvent::CloseRequested, // If a close is requested
+                window_id,
+            } if window_id == window.id() => *control_flow = ControlFlow::Exit,
+            Event::LoopDestroyed => {
+                // the video is only playable once everything's been written and ffmpeg has finished it
+                if let Some(err) = video.take().and_then(|video| video.finish(Instant::now()).err()) {
+                    log::error!("Unable to finish the video: {}", err);
+                }
+            }
+            Event::WindowEvent { event: WindowEvent::DroppedFile(ref path), window_id } if window_id == window.id() => {
+                // a scene dropped onto the window is loaded just like with the load command
+                if path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("toml")) {
+                    let before = metadata.clone();
+                    let command = ControlCommand::Load(path.clone());
+                    let line = session::command_line(&command);
+                    apply_command(command, &mut metadata, &initial, &mut render_opts, animating, &generation, &mut rng);
+                    session::record(&mut recorder, line, &metadata);
+                    if metadata != before {
+                        history.record(before);
+                        tween = None;
+                        playback = None;
+                        drag = None;
+                    }
+                    frame_stats.record(screen.render(&metadata, &render_opts));
+                    window.request_redraw();
+                } else {
+                    println!("Unable to load {}: only TOML scene files can be dropped onto the window", path.display());
+                }
+            }
+            Event::RedrawRequested(_) => { // Apply the control panel's changes and render the pixel buffer on redraw
+                let commands = panel.update(&window, &metadata);
+                if !commands.is_empty() {
+                    panel_edit.get_or_insert_with(|| metadata.clone());
+                    for command in commands {
+                        let line = session::command_line(&command);
+                        apply_command(command, &mut metadata, &initial, &mut render_opts, animating, &generation, &mut rng);
+                        session::record(&mut recorder, line, &metadata);
+                    }
+                    frame_stats.record(screen.render(&metadata, &render_opts));
+                }
+                if !panel.is_editing() {
+                    if let Some(start) = panel_edit.take() {
+                        if metadata != start {
+                            history.record(start);
+                        }
+                    }
+                }
+                if let Err(err) = screen.present(panel.frame()) {
+                    exit_with(err.into());
+                }
+                if let Some(err) = video.as_mut().and_then(|video| video.show(screen.screenshot(), Instant::now()).err()) {
+                    log::error!("Unable to record video, stopped recording: {}", err);
+                    video = None;
+                }
+                if panel.needs_repaint() {
+                    window.request_redraw();
+                }
+            }
+            Event::MainEventsCleared if tween.is_some() && Instant::now() >= next_tick => { // Advance the tween
+                next_tick = Instant::now() + pacing.interval;
+                *control_flow = ControlFlow::WaitUntil(next_tick);
+                for _ in 0..pacing.ticks {
+                    if let Some(transition) = &mut tween {
+                        metadata = transition.step();
+                        if transition.finished() {
+                            tween = None;
+                            println!("tween finished");
+                        }
+                    }
+                }
+                frame_stats.record_animated(screen.render_full(&metadata, &render_opts));
+                window.request_redraw();
+            }
+            Event::MainEventsCleared if playback.is_some() && Instant::now() >= next_tick => { // Play the keyframes
+                next_tick = Instant::now() + pacing.interval;
+                *control_flow = ControlFlow::WaitUntil(next_tick);
+                for _ in 0..pacing.ticks {
+                    if let Some(keyframes) = &mut playback {
+                        metadata.set_scene(keyframes.step());
+                        if keyframes.finished() {
+                            playback = None;
+                            println!("keyframes finished");
+                        }
+                    }
+                }
+                frame_stats.record_animated(screen.render_full(&metadata, &render_opts));
+                window.request_redraw();
+            }
+            Event::MainEventsCleared if animating && Instant::now() >= next_tick => { // Advance the animation
+                next_tick = Instant::now() + pacing.interval;
+                *control_flow = ControlFlow::WaitUntil(next_tick);
+                let mut moved = false;
+                for _ in 0..pacing.ticks {
+                    moved |= metadata.step();
+                    moved |= run_behavior(&mut behavior, &mut metadata);
+                    if render_opts.hue_cycle.enabled {
+                        render_opts.hue_cycle.advance();
+                    }
+                }
+                // only re-render if something actually changed
+                if moved || render_opts.hue_cycle.enabled || screen.changing() {
+                    frame_stats.record_animated(screen.render_full(&metadata, &render_opts));
+                    window.request_redraw();
+                }
+            }
+            Event::MainEventsCleared if screen.refine_at().is_some_and(|refine_at| Instant::now() >= refine_at) => { // Sharpen a coarse render
+                frame_stats.record(screen.render_full(&metadata, &render_opts));
+                window.request_redraw();
+            }
+            Event::MainEventsCleared if screen.changing() && Instant::now() >= next_tick => { // Follow the audio or the easing
+                next_tick = Instant::now() + pacing.interval;
+                *control_flow = ControlFlow::WaitUntil(next_tick);
+                frame_stats.record_animated(screen.render_full(&metadata, &render_opts));
+                window.request_redraw();
+            }
+            // tapping adds a metaball, dragging moves the nearest one and pinching spreads the shape out by
+            // lowering the threshold, or draws it in by raising it
+            Event::WindowEvent { event: WindowEvent::Touch(finger), window_id } if window_id == window.id() && !captured => {
+                let position = (finger.location.x as f32, finger.location.y as f32);
+                let (x, y) = screen.pix.window_pos_to_pixel(position).unwrap_or_else(|pos| screen.pix.clamp_pixel_pos(pos));
+                let phase = match finger.phase {
+                    TouchPhase::Started => touch::Phase::Started,
+                    TouchPhase::Moved => touch::Phase::Moved,
+                    TouchPhase::Ended => touch::Phase::Ended,
+                    TouchPhase::Cancelled => touch::Phase::Cancelled,
+                };
+                let (width, height) = (metadata.width, metadata.height);
+                let opts = &render_opts;
+                let field = |point: Pointf| {
+                    let (x, y) = opts.field_point(point.x, point.y, width, height);
+                    Pointf { x, y }
+                };
+                let gesture = touches.touch(finger.id, phase, Pointf { x: x as f64, y: y as f64 });
+                match gesture {
+                    Some(Gesture::Tap(point)) => {
+                        let location = render_opts.snapped(field(point), metadata.width, metadata.height);
+                        if (0.0..metadata.width as f64).contains(&location.x) && (0.0..metadata.height as f64).contains(&location.y) {
+                            history.record(metadata.clone());
+                            let index = metadata.add_metaball(Metaball::new(location, generation.base_size));
+                            println!("Added metaball {} at ({}, {})", index, location.x, location.y);
+                        }
+                    }
+                    Some(Gesture::Drag { start, to }) => {
+                        let edit = touch_edit.get_or_insert_with(|| TouchEdit { before: metadata.clone(), dragged: None, threshold: None });
+                        let start = field(start);
+                        if edit.dragged.is_none() {
+                            edit.dragged = metadata.metaball_near(start, f64::INFINITY).map(|index| (index, metadata.metaballs[index].location));
+                        }
+                        // the metaball may have been removed from STDIN while it was held
+                        if let Some((index, from)) = edit.dragged.filter(|(index, _)| *index < metadata.metaballs.len()) {
+                            let to = field(to);
+                            let point = render_opts.snapped(Pointf { x: from.x + to.x - start.x, y: from.y + to.y - start.y }, metadata.width, metadata.height);
+                            metadata.metaballs[index].move_to(Pointf {
+                                x: point.x.clamp(0.0, (metadata.width - 1) as f64),
+                                y: point.y.clamp(0.0, (metadata.height - 1) as f64),
+                            });
+                            metadata.symmetrize_from(index);
+                        }
+                    }
+                    Some(Gesture::Pinch(spread)) => {
+                        let edit = touch_edit.get_or_insert_with(|| TouchEdit { before: metadata.clone(), dragged: None, threshold: None });
+                        let threshold = *edit.threshold.get_or_insert(metadata.threshold);
+                        // the fingers can come too close together to be a threshold, which just stops there
+                        let _ = metadata.set_threshold(threshold / spread);
+                    }
+                    Some(Gesture::Done) => {
+                        if let Some(edit) = touch_edit.take() {
+                            if metadata != edit.before {
+                                history.record(edit.before);
+                            }
+                        }
+                    }
+                    None => {}
+                }
+                if gesture.is_some() {
+                    frame_stats.record(screen.render(&metadata, &render_opts));
+                    window.request_redraw();
+                }
+            }
+            _ => (),
+        }
+        if !captured && input.update(&event) {
+            // edits to the scene are snapshotted for undoing once all the controls have been handled
+            let before = metadata.clone();
+
+            // randomizing control
+            if input.key_pressed(VirtualKeyCode::Space) && !morph {
+                println!("randomizing");
+                metadata.randomize_with(&generation, &mut rng);
+                frame_stats.record(screen.render(&metadata, &render_opts));
+            }
+
+            // reset control
+            if input.held_control() && input.key_pressed(VirtualKeyCode::R) {
+                println!("Reset the scene");
+                metadata = initial.clone();
+                metadata.resize(size.0, size.1);
+                tween = None;
+                playback = None;
+                frame_stats.record(screen.render(&metadata, &render_opts));
+            }
+
+            // tween to a random scene control
+            if input.key_pressed(VirtualKeyCode::T) || (morph && input.key_pressed(VirtualKeyCode::Space)) {
+                println!("tweening to a random scene");
+                history.record(metadata.clone());
+                let mut target = metadata.clone();
+                target.randomize_with(&generation, &mut rng);
+                tween = Some(Tween::new(metadata.clone(), target, tween_frames));
+                next_tick = Instant::now();
+            }
+
+            // center indicator control
+            if input.key_pressed(VirtualKeyCode::C) && !input.held_control() {
+                println!("crosses toggled");
+                render_opts.crosses = !render_opts.crosses;
+                frame_stats.record(screen.render(&metadata, &render_opts));
+            }
+
+            // camera controls, the arrow keys orbit the 3D view instead of changing the threshold and goo
+            let orbiting = render_opts.mode == RenderMode::Raymarch;
+            if orbiting {
+                let yaw = key_nudge(&input, VirtualKeyCode::Right, VirtualKeyCode::Left, ORBIT_STEP);
+                let pitch = key_nudge(&input, VirtualKeyCode::Up, VirtualKeyCode::Down, ORBIT_STEP);
+                if yaw != 0.0 || pitch != 0.0 {
+                    render_opts.camera.orbit(yaw, pitch);
+                    frame_stats.record(screen.render(&metadata, &render_opts));
+                }
+            }
+
+            // threshold and goo controls, in smaller steps with Shift held
+            let fine = if input.held_shift() { FINE_STEP } else { 1.0 };
+            let threshold_change = if orbiting { 0.0 } else { key_nudge(&input, VirtualKeyCode::Up, VirtualKeyCode::Down, threshold_step * fine) };
+            if threshold_change != 0.0 {
+                let threshold = metadata.threshold + threshold_change;
+                match metadata.set_threshold(threshold) {
+                    Ok(()) => println!("Set threshold to {}", threshold),
+                    Err(err) => println!("Not setting threshold to {}: {}", threshold, err),
+                }
+            }
+            let goo_change = if orbiting { 0.0 } else { key_nudge(&input, VirtualKeyCode::Right, VirtualKeyCode::Left, goo_step * fine) };
+            if goo_change != 0.0 {
+                let goo = metadata.goo + goo_change;
+                match metadata.set_goo(goo) {
+                    Ok(()) => println!("Set goo to {}", goo),
+                    Err(err) => println!("Not setting goo to {}: {}", goo, err),
+                }
+            }
+            if threshold_change != 0.0 || goo_change != 0.0 {
+                frame_stats.record(screen.render(&metadata, &render_opts));
+            }
+
+            // view controls, zooming around the cursor and panning with the middle mouse button
+            let mut view_changed = false;
+            let scroll = input.scroll_diff();
+            if scroll != 0.0 {
+                if let Some(cursor) = input.mouse() {
+                    let (x, y) = screen.pix.window_pos_to_pixel(cursor).unwrap_or_else(|pos| screen.pix.clamp_pixel_pos(pos));
+                    render_opts.view.zoom_at(ZOOM_STEP.powf(scroll as f64), x as f64, y as f64);
+                    view_changed = true;
+                }
+            }
+            let (dx, dy) = input.mouse_diff();
+            if input.mouse_held(2) && (dx != 0.0 || dy != 0.0) {
+                // the mouse moves in physical pixels, which may be bigger or smaller than those of the buffer
+                let buffer_scale = metadata.width as f64 / window.inner_size().width as f64;
+                render_opts.view.pan(dx as f64 * buffer_scale, dy as f64 * buffer_scale);
+                view_changed = true;
+            }
+            if input.key_pressed(VirtualKeyCode::Home) {
+                println!("view reset");
+                render_opts.view = View::default();
+                view_changed = true;
+            }
+            if view_changed {
+                frame_stats.record(screen.render(&metadata, &render_opts));
+            }
+
+            // dragging metaballs around by their centers with the left mouse button, clicking empty space
+            // adds one there and right clicking one deletes it
+            if let Some(cursor) = input.mouse() {
+                let (x, y) = screen.pix.window_pos_to_pixel(cursor).unwrap_or_else(|pos| screen.pix.clamp_pixel_pos(pos));
+                let (x, y) = render_opts.field_point(x as f64, y as f64, metadata.width, metadata.height);
+                let point = Pointf { x, y };
+                let picked = metadata.metaball_near(point, PICK_RADIUS / render_opts.view.zoom);
+                if input.mouse_pressed(0) {
+                    let start = metadata.clone();
+                    drag = match picked {
+                        Some(index) => Some((index, start)),
+                        None => {
+                            let location = render_opts.snapped(point, metadata.width, metadata.height);
+                            if (0.0..metadata.width as f64).contains(&location.x) && (0.0..metadata.height as f64).contains(&location.y) {
+                                let index = metadata.add_metaball(Metaball::new(location, generation.base_size));
+                                println!("Added metaball {} at ({}, {})", index, location.x, location.y);
+                                frame_stats.record(screen.render(&metadata, &render_opts));
+                                // the new metaball can be dragged into place before letting go
+                                Some((index, start))
+                            } else {
+                                None
+                            }
+                        }
+                    };
+                } else if input.mouse_pressed(1) && drag.is_none() {
+                    if let Some(index) = picked {
+                        metadata.remove_metaball(index);
+                        println!("Removed metaball {}", index);
+                        frame_stats.record(screen.render(&metadata, &render_opts));
+                    }
+                } else if let Some((index, _)) = drag {
+                    let point = render_opts.snapped(point, metadata.width, metadata.height);
+                    let location = Pointf {
+                        x: point.x.clamp(0.0, (metadata.width - 1) as f64),
+                        y: point.y.clamp(0.0, (metadata.height - 1) as f64),
+                    };
+                    // the metaball may have been removed from STDIN while it was held
+                    if let Some(metaball) = metadata.metaballs.get_mut(index) {
+                        if input.mouse_held(0) && metaball.location != location {
+                            metaball.move_to(location);
+                            metadata.symmetrize_from(index);
+                            frame_stats.record(screen.render(&metadata, &render_opts));
+                        }
+                    }
+                }
+            }
+            if input.mouse_released(0) {
+                // the whole drag is one edit
+                if let Some((_, start)) = drag.take() {
+                    if metadata != start {
+                        history.record(start);
+                    }
+                }
+            }
+
+            // the field under the cursor for the title, while it's over the image
+            probed = input.mouse().and_then(|cursor| screen.pix.window_pos_to_pixel(cursor).ok()).map(|(x, y)| {
+                let (x, y) = render_opts.field_point(x as f64, y as f64, metadata.width, metadata.height);
+                (Pointf { x, y }, field_at(x, y, &metadata))
+            });
+
+            // a metaball following the cursor, which can still be dragged like the rest
+            if input.key_pressed(VirtualKeyCode::M) {
+                follow = match follow {
+                    Some(_) => None,
+                    None => Some(follower),
+                };
+                match follow {
+                    Some(index) => println!("Metaball {} is following the cursor", index),
+                    None => println!("Stopped following the cursor"),
+                }
+            }
+            if let (Some(index), Some(cursor), None) = (follow, input.mouse(), &drag) {
+                let (x, y) = screen.pix.window_pos_to_pixel(cursor).unwrap_or_else(|pos| screen.pix.clamp_pixel_pos(pos));
+                let (x, y) = render_opts.field_point(x as f64, y as f64, metadata.width, metadata.height);
+                let target = Pointf { x: x.clamp(0.0, (metadata.width - 1) as f64), y: y.clamp(0.0, (metadata.height - 1) as f64) };
+                if let Some(metaball) = metadata.metaballs.get_mut(index) {
+                    let lagging = Pointf {
+                        x: target.x + (metaball.location.x - target.x) * follow_lag,
+                        y: target.y + (metaball.location.y - target.y) * follow_lag,
+                    };
+                    // close enough is on the cursor, so it stops rendering once it's caught up
+                    let location = if lagging.distance_to(target.x, target.y, 1.0) < FOLLOW_CLOSE_ENOUGH { target } else { lagging };
+                    if metaball.location != location {
+                        metaball.move_to(location);
+                        metadata.symmetrize_from(index);
+                        frame_stats.record(screen.render(&metadata, &render_opts));
+                    }
+                }
+            }
+
+            // metaball count controls
+            if input.key_pressed(VirtualKeyCode::Plus) || input.key_pressed(VirtualKeyCode::Equals) || input.key_pressed(VirtualKeyCode::NumpadAdd) {
+                let index = metadata.add_metaball(random_metaball(metadata.width, metadata.height, &generation, &mut rng));
+                println!("Added metaball {}", index);
+                frame_stats.record(screen.render(&metadata, &render_opts));
+            }
+            if input.key_pressed(VirtualKeyCode::Minus) || input.key_pressed(VirtualKeyCode::NumpadSubtract) {
+                if metadata.metaballs.len() > generation.min_count as usize {
+                    metadata.remove_metaball(metadata.metaballs.len() - 1);
+                    println!("Removed metaball {}", metadata.metaballs.len());
+                    frame_stats.record(screen.render(&metadata, &render_opts));
+                } else {
+                    println!("Keeping at least {} metaballs", generation.min_count);
+                }
+            }
+
+            // scene slot controls, saving the scene with Shift held and switching to it otherwise
+            for (slot, &key) in SLOTS.zip(&SLOT_KEYS) {
+                if !input.key_pressed(key) {
+                    continue;
+                }
+                if input.held_shift() {
+                    slots.store(slot, &metadata);
+                    match slots.save(&slots_path) {
+                        Ok(()) => println!("Saved the scene to slot {}", slot),
+                        Err(err) => println!("Unable to save slot {} to {}: {}", slot, slots_path.display(), err),
+                    }
+                    continue;
+                }
+                match slots.recall(slot, metadata.width, metadata.height) {
+                    Some(Ok(scene)) => {
+                        metadata.set_scene(scene);
+                        tween = None;
+                        playback = None;
+                        drag = None;
+                        println!("Switched to the scene in slot {}", slot);
+                        frame_stats.record(screen.render(&metadata, &render_opts));
+                    }
+                    Some(Err(err)) => println!("Unable to switch to slot {}: {}", slot, err),
+                    None => println!("Nothing saved in slot {}, Shift+{} saves the scene there", slot, slot),
+                }
+            }
+
+            // grid snap control, the metaballs already placed stay where they are
+            if input.key_pressed(VirtualKeyCode::G) {
+                render_opts.snap = match render_opts.snap {
+                    Some(spacing) => {
+                        snap_spacing = spacing;
+                        None
+                    }
+                    None => Some(snap_spacing),
+                };
+                match render_opts.snap {
+                    Some(spacing) => println!("Snapping added metaballs to a {} pixel grid", spacing),
+                    None => println!("Not snapping added metaballs"),
+                }
+            }
+
+            // metaball color control
+            if input.key_pressed(VirtualKeyCode::B) {
+                println!("metaball colors toggled");
+                render_opts.ball_colors = !render_opts.ball_colors;
+                frame_stats.record(screen.render(&metadata, &render_opts));
+            }
+
+            // keyframe playback control
+            if input.key_pressed(VirtualKeyCode::K) {
+                match (&keyframes, playback.take()) {
+                    (Some(_), Some(_)) => println!("keyframes stopped"),
+                    (Some(keyframes), None) if keyframes.size() != size => {
+                        println!("The keyframes are for a {}x{} window", keyframes.size().0, keyframes.size().1);
+                    }
+                    (Some(keyframes), None) => {
+                        println!("playing the keyframes");
+                        metadata.set_scene(keyframes.scene_at(0.0));
+                        playback = Some(Playback::new(keyframes.clone()));
+                        next_tick = Instant::now();
+                        frame_stats.record(screen.render(&metadata, &render_opts));
+                    }
+                    (None, _) => println!("No keyframes to play, load them with --keyframes"),
+                }
+            }
+
+            // animation control
+            if input.key_pressed(VirtualKeyCode::P) {
+                animating = !animating;
+                println!("animation {}", if animating { "started" } else { "paused" });
+                next_tick = Instant::now();
+                frame_stats.reset();
+            }
+
+            // heatmap control
+            if input.key_pressed(VirtualKeyCode::H) && !input.held_shift() {
+                render_opts.mode = match render_opts.mode {
+                    RenderMode::Heatmap => shape_mode,
+                    mode => {
+                        shape_mode = mode;
+                        RenderMode::Heatmap
+                    }
+                };
+                println!("heatmap {}", if render_opts.mode == RenderMode::Heatmap { "enabled" } else { "disabled" });
+                frame_stats.record(screen.render(&metadata, &render_opts));
+            }
+
+            // 3D view control
+            if input.key_pressed(VirtualKeyCode::D) {
+                render_opts.mode = match render_opts.mode {
+                    RenderMode::Raymarch => shape_mode,
+                    mode => {
+                        shape_mode = mode;
+                        RenderMode::Raymarch
+                    }
+                };
+                println!("3D view {}", if render_opts.mode == RenderMode::Raymarch { "enabled" } else { "disabled" });
+                frame_stats.record(screen.render(&metadata, &render_opts));
+            }
+
+            // lighting control
+            if input.key_pressed(VirtualKeyCode::L) {
+                render_opts.mode = match render_opts.mode {
+                    RenderMode::Lit => shape_mode,
+                    mode => {
+                        shape_mode = mode;
+                        RenderMode::Lit
+                    }
+                };
+                println!("lighting {}", if render_opts.mode == RenderMode::Lit { "enabled" } else { "disabled" });
+                frame_stats.record(screen.render(&metadata, &render_opts));
+            }
+
+            // outline control
+            if input.key_pressed(VirtualKeyCode::O) {
+                render_opts.mode = match render_opts.mode {
+                    RenderMode::Outline => shape_mode,
+                    mode => {
+                        shape_mode = mode;
+                        RenderMode::Outline
+                    }
+                };
+                println!("outline {}", if render_opts.mode == RenderMode::Outline { "enabled" } else { "disabled" });
+                frame_stats.record(screen.render(&metadata, &render_opts));
+            }
+
+            // theme control
+            if input.key_pressed(VirtualKeyCode::N) {
+                theme = theme.next();
+                theme.apply(&mut render_opts);
+                println!("theme {}", theme.name);
+                frame_stats.record(screen.render(&metadata, &render_opts));
+            }
+
+            // field histogram control
+            if input.key_pressed(VirtualKeyCode::I) {
+                render_opts.histogram = !render_opts.histogram;
+                println!("histogram {}", if render_opts.histogram { "shown" } else { "hidden" });
+                frame_stats.record(screen.render(&metadata, &render_opts));
+            }
+
+            // hue cycling controls
+            if input.key_pressed(VirtualKeyCode::H) && input.held_shift() {
+                render_opts.hue_cycle.enabled = !render_opts.hue_cycle.enabled;
+                println!("hue cycling {}", if render_opts.hue_cycle.enabled { "enabled" } else { "disabled" });
+                frame_stats.record(screen.render(&metadata, &render_opts));
+            }
+            let hue_speed_change = key_nudge(&input, VirtualKeyCode::RBracket, VirtualKeyCode::LBracket, HUE_SPEED_STEP);
+            if hue_speed_change != 0.0 {
+                render_opts.hue_cycle.speed += hue_speed_change;
+                println!("Set hue cycling speed to {} degrees per tick", render_opts.hue_cycle.speed);
+            }
+
+            // control panel control
+            if input.key_pressed(VirtualKeyCode::Tab) {
+                panel.visible = !panel.visible;
+                println!("control panel {}", if panel.visible { "shown" } else { "hidden" });
+            }
+
+            // fullscreen control, borderless on the monitor the window is on, which resizes the window
+            if input.key_pressed(VirtualKeyCode::F11) {
+                let fullscreen = window.fullscreen().is_none();
+                window.set_fullscreen(if fullscreen { Some(Fullscreen::Borderless(None)) } else { None });
+                println!("fullscreen {}", if fullscreen { "enabled" } else { "disabled" });
+            }
+
+            // vsync control, pixels only takes it when building so the buffer is rebuilt
+            if input.key_pressed(VirtualKeyCode::V) {
+                vsync = !vsync;
+                if let Err(err) = screen.set_vsync(&window, vsync) {
+                    exit_with(err.into());
+                }
+                frame_stats.record(screen.render(&metadata, &render_opts));
+                println!("vsync {}", if vsync { "enabled" } else { "disabled" });
+            }
+
+            // render time readout control, in the title bar
+            if input.key_pressed(VirtualKeyCode::F) {
+                frame_stats.hud = !frame_stats.hud;
+                frame_stats.reset();
+                println!("render times {}", if frame_stats.hud { "shown" } else { "hidden" });
+            }
+
+            // screenshot control, saving the frame as it's shown without the control panel
+            if input.key_pressed(VirtualKeyCode::S) {
+                let path = screenshot_path(SystemTime::now());
+                match screen.screenshot().save(&path) {
+                    Ok(()) => println!("Saved screenshot to {}", path.display()),
+                    Err(err) => println!("Unable to save screenshot to {}: {}", path.display(), err),
+                }
+            }
+
+            // copy control, putting the frame as it's shown on the clipboard for pasting elsewhere
+            if input.held_control() && input.key_pressed(VirtualKeyCode::C) {
+                match copy_image(&screen.screenshot()) {
+                    Ok(()) => println!("Copied the frame to the clipboard"),
+                    Err(err) => println!("Unable to copy the frame to the clipboard: {}", err),
+                }
+            }
+
+            // undo and redo controls, stepping through the snapshots taken before each edit
+            if metadata != before && drag.is_none() {
+                history.record(before);
+            }
+            let undo = input.held_control() && input.key_pressed(VirtualKeyCode::Z);
+            let redo = input.held_control() && input.key_pressed(VirtualKeyCode::Y);
+            if (undo || redo) && history.step(undo, &mut metadata) {
+                tween = None;
+                playback = None;
+                drag = None;
+                frame_stats.record(screen.render(&metadata, &render_opts));
+            }
+            // whatever the mouse and keyboard did to the scene
+            session::record(&mut recorder, None, &metadata);
+            // if any input happened request a redraw
+            window.request_redraw();
+        }
+
+        let mut title = frame_stats.title();
+        if let Some((point, field)) = probed {
+            title.push_str(&format!(" - field {:.4} at ({:.1}, {:.1}), threshold {}", field, point.x, point.y, metadata.threshold));
+        }
+        if title != shown_title {
+            window.set_title(&title);
+            shown_title = title;
+        }
+    });
+}
+
+
+/// Apply a command from the script, STDIN or the control panel to the scene, `initial` being the scene from the start
+fn apply_command(command: ControlCommand, metadata: &mut MetaballData, initial: &MetaballData, render_opts: &mut RenderOpts, animating: bool, generation: &Generation, rng: &mut StdRng) {
+    match command {
+        ControlCommand::Goo(goo) => {
+            match metadata.set_goo(goo) {
+                Ok(()) => println!("Set goo to {}", goo),
+                Err(err) => println!("Not setting goo to {}: {}", goo, err),
+            }
+        }
+        ControlCommand::Threshold(threshold) => {
+            match metadata.set_threshold(threshold) {
+                Ok(()) => println!("Set threshold to {}", threshold),
+                Err(err) => println!("Not setting threshold to {}: {}", threshold, err),
+            }
+        }
+        ControlCommand::Compare(comparison) => {
+            render_opts.compare = comparison;
+            match comparison {
+                Some(comparison) => println!("Comparing the scene on the left against {} on the right", comparison),
+                None => println!("Stopped comparing"),
+            }
+        }
+        ControlCommand::CompareCurrent => {
+            apply_command(ControlCommand::Compare(Some(Comparison::of(metadata))), metadata, initial, render_opts, animating, generation, rng)
+        }
+        ControlCommand::Falloff(falloff) => {
+            metadata.falloff = falloff;
+            println!("Set the falloff to {}", falloff);
+        }
+        ControlCommand::Pulse { index, pulse } => {
+            if index < metadata.metaballs.len() {
+                // the copies of a symmetry swing along with their original
+                let index = metadata.original_of(index);
+                metadata.metaballs[index].set_pulse(pulse);
+                metadata.symmetrize();
+                match pulse {
+                    Some(pulse) => println!("Pulsing metaball {} by {} at {} a second", index, pulse.amplitude, pulse.frequency),
+                    None => println!("Stopped metaball {} pulsing", index),
+                }
+            } else {
+                println!("There is no metaball {}, there are {} metaballs", index, metadata.metaballs.len());
+            }
+        }
+        ControlCommand::Motion { index, path, period } => {
+            if index < metadata.metaballs.len() {
+                // the copies of a symmetry follow their original around
+                let index = metadata.original_of(index);
+                let metaball = &mut metadata.metaballs[index];
+                metaball.motion = path.map(|path| Motion::starting_at(path, metaball.location, period));
+                metadata.symmetrize();
+                match path {
+                    Some(_) => println!("Sent metaball {} around a path of {} ticks", index, period),
+                    None => println!("Stopped metaball {} following a path", index),
+                }
+            } else {
+                println!("There is no metaball {}, there are {} metaballs", index, metadata.metaballs.len());
+            }
+        }
+        ControlCommand::Probe(point) => {
+            println!("The field at ({}, {}) is {}, against a threshold of {}", point.x, point.y, field_at(point.x, point.y, metadata), metadata.threshold);
+        }
+        ControlCommand::Noise(noise) => {
+            metadata.noise = noise;
+            match noise {
+                Some(noise) => println!("Set the noise to an amplitude of {} with a scale of {} pixels", noise.amplitude, noise.scale),
+                None => println!("Turned the noise off"),
+            }
+        }
+        ControlCommand::Symmetry(symmetry) => {
+            metadata.set_symmetry(symmetry);
+            println!("Set the symmetry to {}, making {} metaballs", symmetry, metadata.metaballs.len());
+        }
+        ControlCommand::LayerBlend { layer, blend } => match metadata.set_layer_blend(layer, blend) {
+            Ok(()) => println!("Layer {} is blended with {}", layer, blend),
+            Err(err) => println!("Unable to blend layer {}: {}", layer, err),
+        },
+        ControlCommand::ToggleGradient => {
+            render_opts.gradient = !render_opts.gradient;
+            println!("gradient toggled");
+        }
+        ControlCommand::Isolines(levels) => {
+            println!("Set isolines to {:?}", levels);
+            render_opts.isolines = levels;
+        }
+        ControlCommand::ExportField(path) => {
+            match export_field(&path, metadata) {
+                Ok(()) => println!("Exported field to {}", path.display()),
+                Err(err) => println!("Unable to export field to {}: {}", path.display(), err),
+            }
+        }
+        ControlCommand::ExportSvg(path) => {
+            match export_svg(&path, metadata, render_opts.fill_color()) {
+                Ok(()) => println!("Exported outline to {}", path.display()),
+                Err(err) => println!("Unable to export outline to {}: {}", path.display(), err),
+            }
+        }
+        ControlCommand::ExportSdf(path, spread) => {
+            match export_sdf(&path, metadata, spread) {
+                Ok(()) => println!("Exported signed distance field to {}", path.display()),
+                Err(err) => println!("Unable to export signed distance field to {}: {}", path.display(), err),
+            }
+        }
+        ControlCommand::ExportMesh(path, cell) => {
+            match export_mesh(&path, metadata, cell) {
+                Ok(()) => println!("Exported mesh to {}", path.display()),
+                Err(err) => println!("Unable to export mesh to {}: {}", path.display(), err),
+            }
+        }
+        ControlCommand::Add { location, size, radius } => {
+            let location = render_opts.snapped(location, metadata.width, metadata.height);
+            if !(0.0..metadata.width as f64).contains(&location.x) || !(0.0..metadata.height as f64).contains(&location.y) {
+                println!("({}, {}) is outside of the {}x{} image", location.x, location.y, metadata.width, metadata.height);
+            } else if !size.is_finite() {
+                // commands from OSC aren't checked until here
+                println!("Not adding a metaball of size {}: it needs to be a finite number", size);
+            } else if radius.is_some_and(|radius| radius.is_nan() || radius <= 0.0) {
+                println!("Not adding a metaball with a radius of influence of {}: it needs to be positive", radius.unwrap());
+            } else {
+                let index = metadata.add_metaball(Metaball { radius, ..Metaball::new(location, size) });
+                println!("Added metaball {} at ({}, {}) with size {}", index, location.x, location.y, size);
+            }
+        }
+        ControlCommand::Move { index, location } => {
+            let location = render_opts.snapped(location, metadata.width, metadata.height);
+            if index >= metadata.metaballs.len() {
+                println!("There is no metaball {}, there are {} metaballs", index, metadata.metaballs.len());
+            } else if !(0.0..metadata.width as f64).contains(&location.x) || !(0.0..metadata.height as f64).contains(&location.y) {
+                println!("({}, {}) is outside of the {}x{} image", location.x, location.y, metadata.width, metadata.height);
+            } else {
+                metadata.metaballs[index].move_to(location);
+                metadata.symmetrize_from(index);
+                println!("Moved metaball {} to ({}, {})", index, location.x, location.y);
+            }
+        }
+        ControlCommand::Size { index, size } => {
+            if !size.is_finite() {
+                println!("Not setting the size of metaball {} to {}: it needs to be a finite number", index, size);
+            } else if index < metadata.metaballs.len() {
+                metadata.metaballs[index].size = size;
+                metadata.symmetrize_from(index);
+                println!("Set the size of metaball {} to {}", index, size);
+            } else {
+                println!("There is no metaball {}, there are {} metaballs", index, metadata.metaballs.len());
+            }
+        }
+        ControlCommand::Line { index, end } => {
+            if index < metadata.metaballs.len() {
+                metadata.metaballs[index].primitive = end.map_or(Primitive::Point, |end| Primitive::Segment { end });
+                metadata.symmetrize_from(index);
+                match end {
+                    Some(end) => println!("Metaball {} is a line to ({}, {})", index, end.x, end.y),
+                    None => println!("Metaball {} is a point again", index),
+                }
+            } else {
+                println!("There is no metaball {}, there are {} metaballs", index, metadata.metaballs.len());
+            }
+        }
+        ControlCommand::Shape { index, primitive } => {
+            if index < metadata.metaballs.len() {
+                metadata.metaballs[index].primitive = primitive;
+                metadata.symmetrize_from(index);
+                match primitive {
+                    Primitive::RoundedBox { half, corner } => {
+                        println!("Metaball {} is a box reaching {} by {} with corners rounded by {}", index, half.0, half.1, corner)
+                    }
+                    Primitive::Ring { radius } => println!("Metaball {} is a ring of radius {}", index, radius),
+                    _ => println!("Metaball {} is a point again", index),
+                }
+            } else {
+                println!("There is no metaball {}, there are {} metaballs", index, metadata.metaballs.len());
+            }
+        }
+        ControlCommand::Layer { index, layer } => {
+            if index < metadata.metaballs.len() {
+                metadata.metaballs[index].layer = layer;
+                metadata.symmetrize_from(index);
+                println!("Metaball {} is on layer {}, which is blended with {}", index, layer, metadata.layer_blend(layer));
+            } else {
+                println!("There is no metaball {}, there are {} metaballs", index, metadata.metaballs.len());
+            }
+        }
+        ControlCommand::Stretch { index, axes, angle } => {
+            if index < metadata.metaballs.len() {
+                let metaball = &mut metadata.metaballs[index];
+                metaball.axes = axes;
+                metaball.angle = angle;
+                metadata.symmetrize_from(index);
+                println!("Stretched metaball {} to {} by {} turned {} degrees", index, axes.0, axes.1, angle);
+            } else {
+                println!("There is no metaball {}, there are {} metaballs", index, metadata.metaballs.len());
+            }
+        }
+        ControlCommand::Color { index, color } => {
+            if index < metadata.metaballs.len() {
+                metadata.metaballs[index].color = color;
+                metadata.symmetrize_from(index);
+                println!("Set the color of metaball {} to {:?}", index, color.0);
+                if !render_opts.ball_colors {
+                    println!("The metaballs' colors only show when each one is colored separately, with [B] or --ball-colors");
+                }
+            } else {
+                println!("There is no metaball {}, there are {} metaballs", index, metadata.metaballs.len());
+            }
+        }
+        ControlCommand::ExportGif(path, frames) => {
+            let sequence = if animating { Sequence::Animation } else { Sequence::Random };
+            export_recording(&path, &Recording::new(frames, sequence), metadata, render_opts, generation, rng);
+        }
+        ControlCommand::Export(path, frames, delay) => {
+            let sequence = if animating { Sequence::Animation } else { Sequence::Tween(TWEEN_FRAMES) };
+            let mut recording = Recording::new(frames, sequence);
+            if let Some(delay) = delay {
+                recording.delay = delay;
+            }
+            export_recording(&path, &recording, metadata, render_opts, generation, rng);
+        }
+        ControlCommand::Preset(name) => {
+            match preset(&name, metadata.width, metadata.height) {
+                Ok(scene) => {
+                    metadata.set_scene(scene);
+                    println!("Loaded preset {}", name);
+                }
+                Err(err) => println!("{}", err),
+            }
+        }
+        ControlCommand::Save(path) => {
+            match SceneConfig::from_data(metadata).save(&path) {
+                Ok(()) => println!("Saved the scene to {}", path.display()),
+                Err(err) => println!("Unable to save the scene to {}: {}", path.display(), err),
+            }
+        }
+        ControlCommand::Load(path) => {
+            match SceneConfig::load(&path).and_then(|config| config.into_data(metadata.width, metadata.height)) {
+                Ok(scene) => {
+                    metadata.set_scene(scene);
+                    println!("Loaded the scene from {}", path.display());
+                }
+                Err(err) => println!("Unable to load {}: {}", path.display(), err),
+            }
+        }
+        ControlCommand::State => {
+            let scene: String = scene_json(metadata).lines().map(str::trim).collect();
+            println!("{{\"state\": {}}}", scene);
+        }
+        ControlCommand::List => {
+            for command in metadata.commands() {
+                println!("{}", command);
+            }
+        }
+        ControlCommand::Remove(index) => {
+            if index < metadata.metaballs.len() {
+                metadata.remove_metaball(index);
+                println!("Removed metaball {}", index);
+            } else {
+                println!("There is no metaball {}, there are {} metaballs", index, metadata.metaballs.len());
+            }
+        }
+        ControlCommand::Clear => {
+            metadata.metaballs.clear();
+            println!("Removed all the metaballs");
+        }
+        ControlCommand::Bands(bands) => {
+            println!("Set bands to {:?}", bands.iter().map(|(level, _)| level).collect::<Vec<_>>());
+            render_opts.bands = bands;
+        }
+        ControlCommand::Foreground(color) => {
+            render_opts.on_color = color;
+            println!("Set the foreground color to {}", hex_color(color));
+        }
+        ControlCommand::Background(color) => {
+            render_opts.off_color = color;
+            println!("Set the background color to {}", hex_color(color));
+            if render_opts.background.is_some() {
+                println!("The background color only shows without --bg-image");
+            }
+        }
+        ControlCommand::Thresholds(levels) => {
+            println!("Set thresholds to {:?}", levels);
+            render_opts.bands = render_opts.threshold_bands(&levels);
+        }
+        ControlCommand::Snap(snap) => {
+            render_opts.snap = snap;
+            match snap {
+                Some(spacing) => println!("Snapping added metaballs to a {} pixel grid", spacing),
+                None => println!("Not snapping added metaballs"),
+            }
+        }
+        ControlCommand::Randomize => {
+            metadata.randomize_with(generation, rng);
+            println!("randomizing");
+        }
+        // the stick and triggers act every tick they're held, so only selecting says anything
+        ControlCommand::Gamepad(action) => match action {
+            Action::Nudge { selection, by } => {
+                if let Some(index) = Action::selected(selection, metadata.metaballs.len()) {
+                    let location = metadata.metaballs[index].location;
+                    let (width, height) = (metadata.width as f64, metadata.height as f64);
+                    metadata.metaballs[index].move_to(Pointf { x: (location.x + by.0).clamp(0.0, width - 1.0), y: (location.y + by.1).clamp(0.0, height - 1.0) });
+                    metadata.symmetrize_from(index);
+                }
+            }
+            Action::Select(selection) => match Action::selected(selection, metadata.metaballs.len()) {
+                Some(index) => println!("Selected metaball {}", index),
+                None => println!("There are no metaballs to select"),
+            },
+            Action::ScaleGoo(factor) => {
+                if let Err(err) = metadata.set_goo(metadata.goo * factor) {
+                    println!("Not changing the goo: {}", err);
+                }
+            }
+            Action::ScaleThreshold(factor) => {
+                if let Err(err) = metadata.set_threshold(metadata.threshold * factor) {
+                    println!("Not changing the threshold: {}", err);
+                }
+            }
+            Action::Randomize => apply_command(ControlCommand::Randomize, metadata, initial, render_opts, animating, generation, rng),
+        },
+        ControlCommand::Seed(seed) => {
+            // the same scene --seed starts with, and the same ones after it
+            *rng = StdRng::seed_from_u64(seed);
+            metadata.randomize_with(generation, rng);
+            println!("randomizing with seed {}", seed);
+        }
+        ControlCommand::RandomizeLocations => {
+            metadata.randomize_locations(generation, rng);
+            println!("randomized the metaball positions");
+        }
+        ControlCommand::RandomizeSizes => {
+            metadata.randomize_sizes(generation, rng);
+            println!("randomized the metaball sizes");
+        }
+        ControlCommand::Reset => {
+            let (width, height) = (metadata.width, metadata.height);
+            *metadata = initial.clone();
+            metadata.resize(width, height);
+            println!("Reset the scene");
+        }
+        ControlCommand::ToggleAnaglyph => {
+            render_opts.camera.anaglyph = !render_opts.camera.anaglyph;
+            println!("anaglyph {}", if render_opts.camera.anaglyph { "enabled" } else { "disabled" });
+            if render_opts.mode != RenderMode::Raymarch {
+                println!("The anaglyph only shows in the raymarched 3D view");
+            }
+        }
+        ControlCommand::ToggleHistogram => {
+            render_opts.histogram = !render_opts.histogram;
+            println!("histogram {}", if render_opts.histogram { "shown" } else { "hidden" });
+        }
+        ControlCommand::Colormap(colormap, scale) => {
+            render_opts.colormap = colormap;
+            render_opts.heat_scale = scale.unwrap_or(render_opts.heat_scale);
+            println!("colormap {} with the {} heat scale", render_opts.colormap, render_opts.heat_scale);
+            if render_opts.mode != RenderMode::Heatmap {
+                println!("The colormap only shows in the heatmap");
+            }
+        }
+        ControlCommand::TogglePhysics => {
+            metadata.physics.enabled = !metadata.physics.enabled;
+            println!("physics {}", if metadata.physics.enabled { "enabled" } else { "disabled" });
+        }
+        ControlCommand::Gravity(gravity) if gravity.is_finite() => {
+            metadata.physics.gravity = gravity;
+            println!("Set gravity to {}", gravity);
+        }
+        ControlCommand::Damping(damping) if (0.0..=1.0).contains(&damping) => {
+            metadata.physics.damping = damping;
+            println!("Set damping to {}", damping);
+        }
+        ControlCommand::Attraction(attraction) if attraction.is_finite() => {
+            metadata.physics.attraction = attraction;
+            println!("Set attraction to {}", attraction);
+        }
+        ControlCommand::Collision(collision) if collision.is_finite() && collision >= 0.0 => {
+            metadata.physics.collision = collision;
+            println!("Set collision to {}", collision);
+        }
+        ControlCommand::Gravity(value) | ControlCommand::Attraction(value) => {
+            println!("Not setting the physics to {}: it needs to be a finite number", value);
+        }
+        ControlCommand::Collision(collision) => {
+            println!("Not setting collision to {}: it needs to be a number of 0 or above", collision);
+        }
+        ControlCommand::Damping(damping) => {
+            println!("Not setting damping to {}: it needs to be within [0, 1]", damping);
+        }
+        // the callers stop reading commands at the end of the input, and keep the history and the recording
+        // themselves
+        ControlCommand::EndOfInput | ControlCommand::Undo | ControlCommand::Redo | ControlCommand::Record(_) => {}
+    }
+}
+
+/// Record frames of the scene to a file, reporting how it went
+fn export_recording(path: &Path, recording: &Recording, metadata: &MetaballData, render_opts: &RenderOpts, generation: &Generation, rng: &mut StdRng) {
+    println!("Recording {} frames to {}", recording.frames, path.display());
+    match record(path, recording, metadata, &render_opts.export_opts(), generation, rng) {
+        Ok(()) => println!("Exported recording to {}", path.display()),
+        Err(err) => println!("Unable to export recording to {}: {}", path.display(), err),
+    }
+}
+
+/// Renders metaball images on the backend that was picked, or the CPU if it couldn't be set up
+enum Renderer {
+    Cpu,
+    #[cfg(feature = "gpu")]
+    Gpu(Box<gpu::GpuRenderer>),
+}
+
+impl Renderer {
+    /// Set up a backend, saying so on STDERR when it falls back to the CPU
+    fn new(backend: Backend) -> Renderer {
+        match backend {
+            Backend::Cpu => Renderer::Cpu,
+            #[cfg(feature = "gpu")]
+            Backend::Gpu => match gpu::GpuRenderer::new() {
+                Some(gpu) => Renderer::Gpu(Box::new(gpu)),
+                None => {
+                    log::warn!("No GPU adapter found, rendering on the CPU");
+                    Renderer::Cpu
+                }
+            },
+            #[cfg(not(feature = "gpu"))]
+            Backend::Gpu => {
+                log::warn!("Built without the gpu feature, rendering on the CPU");
+                Renderer::Cpu
+            }
+        }
+    }
+
+    /// Render a metaball image, on the GPU when it's in use and can draw everything the options ask for
+    fn render_image(&mut self, width: u32, height: u32, metaballs: &MetaballData, opts: &RenderOpts) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        match self {
+            #[cfg(feature = "gpu")]
+            Renderer::Gpu(gpu) if gpu::GpuRenderer::supports(metaballs, opts) => gpu.render_image(width, height, metaballs, opts),
+            _ => render_image(width, height, metaballs, opts),
+        }
+    }
+
+    /// Render metaballs into an RGBA buffer of the given size, which the CPU fills directly, redrawing only
+    /// what's changed since the last frame it rendered into the buffer
+    fn render_into(&mut self, frame: &mut [u8], width: u32, height: u32, metaballs: &MetaballData, opts: &RenderOpts, incremental: &mut IncrementalRender) {
+        match self {
+            #[cfg(feature = "gpu")]
+            Renderer::Gpu(gpu) if gpu::GpuRenderer::supports(metaballs, opts) => {
+                incremental.invalidate();
+                frame.copy_from_slice(gpu.render_image(width, height, metaballs, opts).as_raw());
+            }
+            _ => {
+                incremental.render(frame, width, height, metaballs, opts);
+            }
+        }
+    }
+}
+
+/// The pixel buffer bound to the window and what renders into it
+struct Screen {
+    pub pix: pixels::Pixels,
+    /// The width and height of the pixel buffer
+    size: (u32, u32),
+    renderer: Renderer,
+    /// Paints the control panel, on the pixel buffer's device so it's rebuilt along with it
+    overlay: egui_wgpu_backend::RenderPass,
+    /// Eases the goo and threshold the metaballs are rendered with when they change
+    easing: Easing,
+    /// The audio the metaballs react to when rendering, with --audio
+    #[cfg(feature = "audio")]
+    audio: Option<capture::AudioInput>,
+    /// When to render at a lower resolution, with --progressive
+    progressive: Option<Progressive>,
+    /// What the frame in the pixel buffer was rendered from, so only what's changed is drawn again
+    incremental: IncrementalRender,
+}
+
+/// Renders coming in quicker than they can be rendered in full are rendered at a fraction of the
+/// resolution, and the last of them in full once they stop coming
+#[derive(Default)]
+struct Progressive {
+    /// When the last render was
+    last: Option<Instant>,
+    /// How long the last render in full took
+    cost: Duration,
+    /// When to render the coarse frame being shown in full, when it's coarse
+    refine_at: Option<Instant>,
+}
+
+impl Screen {
+    /// Get the window's texture and bind a pixel buffer of the given size to it
+    pub fn new(window: &Window, size: (u32, u32), vsync: bool, renderer: Renderer, ease: Duration) -> Result<Screen, pixels::Error> {
+        let pix = pixel_buffer(window, size, vsync)?;
+        let overlay = egui_wgpu_backend::RenderPass::new(pix.device(), SURFACE_FORMAT);
+        Ok(Screen {
+            pix,
+            size,
+            renderer,
+            overlay,
+            easing: Easing::new(ease),
+            #[cfg(feature = "audio")]
+            audio: None,
+            progressive: None,
+            incremental: IncrementalRender::default(),
+        })
+    }
+
+    /// Start reacting to the default audio input, saying so on STDERR when it can't
+    pub fn listen(&mut self) {
+        #[cfg(feature = "audio")]
+        match capture::AudioInput::open() {
+            Ok(audio) => self.audio = Some(audio),
+            Err(err) => log::warn!("Unable to listen to audio: {}", err),
+        }
+        #[cfg(not(feature = "audio"))]
+        log::warn!("Built without the audio feature, not reacting to audio");
+    }
+
+    /// Whether the metaballs change by themselves from one tick to the next, reacting to audio or easing
+    /// the goo and threshold, so they need rendering every tick
+    pub fn changing(&self) -> bool {
+        #[cfg(feature = "audio")]
+        let reacting = self.audio.is_some();
+        #[cfg(not(feature = "audio"))]
+        let reacting = false;
+        reacting || self.easing.easing()
+    }
+
+    /// Rebuild the pixel buffer with vsync turned on or off, since pixels only takes it when building
+    pub fn set_vsync(&mut self, window: &Window, vsync: bool) -> Result<(), pixels::Error> {
+        self.pix = pixel_buffer(window, self.size, vsync)?;
+        self.overlay = egui_wgpu_backend::RenderPass::new(self.pix.device(), SURFACE_FORMAT);
+        Ok(())
+    }
+
+    /// Render the metaballs into the pixel buffer for the next redraw. Returns how long rendering took.
+    pub fn render(&mut self, metaballs: &MetaballData, opts: &RenderOpts) -> Duration {
+        let start = Instant::now();
+        // coarse while renders come quicker than they settle and take longer than a tick, and in full otherwise
+        let coarse = self.progressive.as_mut().is_some_and(|progressive| {
+            let rushed = progressive.last.is_some_and(|last| start - last < SETTLE);
+            progressive.last = Some(start);
+            rushed && progressive.cost > ANIMATION_TICK && opts.background.is_none()
+        });
+        let (goo, threshold) = self.easing.shown(metaballs);
+        let eased = (goo, threshold) != (metaballs.goo, metaballs.threshold);
+        let eased = eased.then(|| MetaballData { goo, threshold, ..metaballs.clone() });
+        let metaballs = eased.as_ref().unwrap_or(metaballs);
+        #[cfg(feature = "audio")]
+        let reacting = self.audio.as_ref().map(|audio| metaballs::audio::react(metaballs, audio.levels()));
+        #[cfg(feature = "audio")]
+        let metaballs = reacting.as_ref().unwrap_or(metaballs);
+        if coarse {
+            self.render_coarse(metaballs, opts);
+        } else {
+            self.renderer.render_into(self.pix.get_frame(), self.size.0, self.size.1, metaballs, opts, &mut self.incremental);
+        }
+        if let Some(progressive) = &mut self.progressive {
+            progressive.refine_at = coarse.then(|| start + SETTLE);
+            if !coarse {
+                progressive.cost = start.elapsed();
+            }
+        }
+        start.elapsed()
+    }
+
+    /// Render the metaballs in full whatever came before, for animating and sharpening a coarse render
+    pub fn render_full(&mut self, metaballs: &MetaballData, opts: &RenderOpts) -> Duration {
+        if let Some(progressive) = &mut self.progressive {
+            progressive.last = None;
+        }
+        self.render(metaballs, opts)
+    }
+
+    /// When the coarse render being shown is due to be rendered in full, if it's coarse
+    pub fn refine_at(&self) -> Option<Instant> {
+        self.progressive.as_ref().and_then(|progressive| progressive.refine_at)
+    }
+
+    /// Render the metaballs at [PREVIEW_SCALE] times smaller and blow them back up to fill the buffer
+    fn render_coarse(&mut self, metaballs: &MetaballData, opts: &RenderOpts) {
+        let (width, height) = self.size;
+        let (small_width, small_height) = ((width / PREVIEW_SCALE).max(1), (height / PREVIEW_SCALE).max(1));
+        let mut small = metaballs.clone();
+        small.resize(small_width, small_height);
+        // the view's corner is in field units, which shrink along with the scene
+        let scale = small_width as f64 / width as f64;
+        let opts = RenderOpts { view: View { x: opts.view.x * scale, y: opts.view.y * scale, ..opts.view }, ..opts.clone() };
+        let image = self.renderer.render_image(small_width, small_height, &small, &opts);
+        self.incremental.invalidate();
+        for (index, pixel) in self.pix.get_frame().chunks_exact_mut(4).enumerate() {
+            let (x, y) = (index as u32 % width, index as u32 / width);
+            pixel.copy_from_slice(&image.get_pixel((x * small_width / width).min(small_width - 1), (y * small_height / height).min(small_height - 1)).0);
+        }
+    }
+
+    /// The last render, as it's shown in the window
+    pub fn screenshot(&mut self) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+        ImageBuffer::from_raw(self.size.0, self.size.1, self.pix.get_frame().to_vec()).unwrap()
+    }
+
+    /// Fit the pixel buffer to a resized window, with a new physical surface size and buffer size
+    pub fn resize(&mut self, surface: (u32, u32), size: (u32, u32)) {
+        self.pix.resize_surface(surface.0, surface.1);
+        self.pix.resize_buffer(size.0, size.1);
+        self.size = size;
+        self.incremental.invalidate();
+    }
+
+    /// Show the last render in the window, with the control panel over it if it's showing
+    pub fn present(&mut self, panel: Option<&PanelFrame>) -> Result<(), pixels::Error> {
+        let overlay = &mut self.overlay;
+        self.pix.render_with(|encoder, target, context| {
+            context.scaling_renderer.render(encoder, target);
+            if let Some(panel) = panel {
+                overlay.update_texture(&context.device, &context.queue, &panel.texture);
+                overlay.update_buffers(&context.device, &context.queue, &panel.meshes, &panel.screen);
+                overlay.execute(encoder, target, &panel.meshes, &panel.screen, None);
+            }
+        })
+    }
+}
+
+/// The size of the pixel buffer for a window surface of physical pixels. The buffer is only ever scaled up
+/// onto the surface by a whole number, so each of its pixels is a whole number of physical pixels: the
+/// downscale, times the scale factor rounded down unless it's at the native resolution. Rounding down
+/// keeps scale factors like 1.5 from leaving the buffer drawn at 1x in the middle of the window.
+fn buffer_size(surface: PhysicalSize<u32>, scale_factor: f64, downscale: u32, native_resolution: bool) -> (u32, u32) {
+    let factor = if native_resolution { downscale } else { (scale_factor.floor() as u32).max(1) * downscale };
+    ((surface.width / factor).max(1), (surface.height / factor).max(1))
+}
+
+/// A pixel buffer of the given size bound to the window's texture
+fn pixel_buffer(window: &Window, size: (u32, u32), vsync: bool) -> Result<pixels::Pixels, pixels::Error> {
+    let surface = window.inner_size();
+    let surface_texture = SurfaceTexture::new(surface.width, surface.height, window);
+    pixels::PixelsBuilder::new(size.0, size.1, surface_texture).enable_vsync(vsync).render_texture_format(SURFACE_FORMAT).build()
+}
+
+/// Give up on the window once it can't be drawn to any more, saying why
+fn exit_with(err: MetaballError) -> ! {
+    eprintln!("{}", err);
+    std::process::exit(1);
+}
+
+/// The clipboard tools an image can be copied with, tried in order: wl-copy on Wayland, then xclip on X11
+const CLIPBOARD_TOOLS: [(&str, &[&str]); 2] = [("wl-copy", &["--type", "image/png"]), ("xclip", &["-selection", "clipboard", "-target", "image/png"])];
+
+/// Put an image on the system clipboard as a PNG, handing it to the first clipboard tool that's installed.
+/// The tools keep serving the clipboard after the window closes, which the window itself couldn't.
+fn copy_image(image: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> Result<(), String> {
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png).encode(image.as_raw(), image.width(), image.height(), ColorType::Rgba8).map_err(|err| err.to_string())?;
+    for (tool, args) in CLIPBOARD_TOOLS {
+        let mut child = match process::Command::new(tool).args(args).stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::null()).spawn() {
+            Ok(child) => child,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(format!("{}: {}", tool, err)),
+        };
+        // the stdin is dropped once it's written so the tool sees the end of the image
+        child.stdin.take().map_or(Ok(()), |mut stdin| stdin.write_all(&png)).map_err(|err| format!("{}: {}", tool, err))?;
+        return match child.wait() {
+            Ok(status) if status.success() => Ok(()),
+            Ok(status) => Err(format!("{} failed with {}", tool, status)),
+            Err(err) => Err(format!("{}: {}", tool, err)),
+        };
+    }
+    Err("no clipboard tool found, install wl-clipboard or xclip".to_string())
+}
+
+/// A PNG in the working directory named after the time in UTC, such as metaballs-20210614-183005.png, with a
+/// number on the end when there's already a screenshot from that second
+fn screenshot_path(now: SystemTime) -> PathBuf {
+    let seconds = now.duration_since(UNIX_EPOCH).map_or(0, |since| since.as_secs());
+    let (days, time) = (seconds / 86400, seconds % 86400);
+    // the civil date from the days since 1970, counting years from March so the leap day comes last
+    let days = days + 719_468;
+    let (era, day_of_era) = (days / 146_097, days % 146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = era * 400 + year_of_era + if month <= 2 { 1 } else { 0 };
+    let stamp = format!("{}{:02}{:02}-{:02}{:02}{:02}", year, month, day, time / 3600, time / 60 % 60, time % 60);
+    let mut path = PathBuf::from(format!("metaballs-{}.png", stamp));
+    let mut count = 1;
+    while path.exists() {
+        count += 1;
+        path = PathBuf::from(format!("metaballs-{}-{}.png", stamp, count));
+    }
+    path
+}
+
+/// How much a pair of keys change a value by, `step` if the increase key was pressed
+/// and `-step` if the decrease key was pressed
+fn key_nudge(input: &WinitInputHelper, increase: VirtualKeyCode, decrease: VirtualKeyCode, step: f64) -> f64 {
+    let mut change = 0.0;
+    if input.key_pressed(increase) {
+        change += step;
+    }
+    if input.key_pressed(decrease) {
+        change -= step;
+    }
+    change
+}
+
+/// The scenes before each edit, so edits can be undone and redone. Only the most recent `depth` edits
+/// are kept.
+struct History {
+    undo: VecDeque<MetaballData>,
+    redo: Vec<MetaballData>,
+    depth: usize,
+}
+
+impl History {
+    pub fn new(depth: usize) -> History {
+        History { undo: VecDeque::new(), redo: Vec::new(), depth }
+    }
+
+    /// Remember the scene from before an edit, which forgets anything that was undone
+    pub fn record(&mut self, scene: MetaballData) {
+        self.redo.clear();
+        self.undo.push_back(scene);
+        while self.undo.len() > self.depth {
+            self.undo.pop_front();
+        }
+    }
+
+    /// Step back to the scene before the last edit, keeping `current` to redo
+    pub fn undo(&mut self, current: &MetaballData) -> Option<MetaballData> {
+        let scene = self.undo.pop_back()?;
+        self.redo.push(current.clone());
+        Some(scene)
+    }
+
+    /// Step forward to the scene the last undo left, keeping `current` to undo again
+    pub fn redo(&mut self, current: &MetaballData) -> Option<MetaballData> {
+        let scene = self.redo.pop()?;
+        self.undo.push_back(current.clone());
+        Some(scene)
+    }
+
+    /// Undo or redo on the scene, saying which. Returns whether there was anything to.
+    pub fn step(&mut self, undo: bool, metadata: &mut MetaballData) -> bool {
+        let restored = if undo { self.undo(metadata) } else { self.redo(metadata) };
+        match restored {
+            Some(scene) => {
+                // the scene may be from before the window was resized
+                let (width, height) = (metadata.width, metadata.height);
+                *metadata = scene;
+                metadata.resize(width, height);
+                println!("{}", if undo { "undone" } else { "redone" });
+                true
+            }
+            None => {
+                println!("Nothing to {}", if undo { "undo" } else { "redo" });
+                false
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The commands a line of text sends
+    fn sent(line: &str) -> Vec<ControlCommand> {
+        let (tx, rx) = std::sync::mpsc::channel();
+        run_line(line, &CommandSender::new(tx));
+        rx.try_iter().collect()
+    }
+
+    /// The commands a line of JSON is for
+    fn json_line(line: &str) -> Result<Vec<ControlCommand>, String> {
+        let command = json::parse(line)?;
+        json_commands(command.get("cmd").and_then(Json::as_str).unwrap_or(""), &command)
+    }
+
+    #[test]
+    fn lines_of_text_are_parsed_into_commands() {
+        assert!(matches!(sent("g1.8").as_slice(), [ControlCommand::Goo(goo)] if *goo == 1.8));
+        assert!(matches!(sent("t0.3,0.8").as_slice(), [ControlCommand::Thresholds(levels)] if *levels == [0.3, 0.8]));
+        assert!(matches!(sent("layer 2 1").as_slice(), [ControlCommand::Layer { index: 2, layer: 1 }]));
+        // anything that doesn't parse is reported and sends nothing
+        assert!(sent("t0.3,NaN").is_empty() && sent("layer 2 1000000").is_empty() && sent("gx").is_empty());
+        assert!(sent("a 10 10 nan").is_empty() && sent("a 10 10 inf 20").is_empty());
+        assert!(sent("line 0 nan nan").is_empty() && sent("line 0 20 inf").is_empty());
+        assert!(matches!(sent("line 0 20 30").as_slice(), [ControlCommand::Line { index: 0, end: Some(_) }]));
+    }
+
+    #[test]
+    fn json_commands_are_checked_before_they_are_acknowledged() {
+        let set = json_line(r#"{"cmd": "set", "goo": 1.8, "threshold": 0.4}"#);
+        assert!(matches!(set.as_deref(), Ok([ControlCommand::Goo(goo), ControlCommand::Threshold(threshold)]) if *goo == 1.8 && *threshold == 0.4));
+        assert!(matches!(json_line(r#"{"cmd": "move", "index": 2, "x": 10, "y": 20}"#).as_deref(), Ok([ControlCommand::Move { index: 2, .. }])));
+        assert!(json_line(r#"{"cmd": "set", "goo": 1e999}"#).is_err() && json_line(r#"{"cmd": "set", "threshold": -1}"#).is_err());
+        assert!(json_line(r#"{"cmd": "add", "x": 10, "y": 20, "radius": 0}"#).is_err());
+        assert!(matches!(json_line(r#"{"cmd": "seed", "seed": 42}"#).as_deref(), Ok([ControlCommand::Seed(42)])));
+        assert!(json_line(r#"{"cmd": "seed", "seed": -5}"#).is_err() && json_line(r#"{"cmd": "seed", "seed": 2.7}"#).is_err());
+        assert!(json_line(r#"{"cmd": "seed", "seed": 1e17}"#).is_err());
+        assert!(json_line(r#"{"cmd": "move", "index": 1.5, "x": 10, "y": 20}"#).is_err() && json_line(r#"{"cmd": "fly"}"#).is_err());
+    }
+
+    #[test]
+    fn thresholds_need_to_be_finite_and_above_0() {
+        assert_eq!(parse_thresholds("0.3, 0.8,"), Ok(vec![0.3, 0.8]));
+        assert!(parse_thresholds("0.3,NaN").is_err());
+        assert!(parse_thresholds("0.3,inf").is_err());
+        assert!(parse_thresholds("0.3,-1").is_err() && parse_thresholds("0,0.5").is_err());
+        assert!(parse_thresholds("0.3,hot").is_err());
+    }
+
+    #[test]
+    fn band_levels_need_to_be_finite() {
+        let bands = parse_bands("0.3:#ff0000, 0.8:00ff00").unwrap();
+        assert_eq!(bands.iter().map(|(level, _)| *level).collect::<Vec<_>>(), vec![0.3, 0.8]);
+        assert!(parse_bands("NaN:#ff0000").is_err() && parse_bands("0.3:#ff0000,inf:#00ff00").is_err());
+        assert!(parse_bands("0.3").is_err() && parse_bands("0.3:red").is_err());
+    }
+
+    #[test]
+    fn layers_stop_at_the_highest_one() {
+        assert_eq!(parse_layer("0"), Ok(0));
+        assert_eq!(parse_layer(&MAX_LAYER.to_string()), Ok(MAX_LAYER));
+        assert!(parse_layer(&(MAX_LAYER + 1).to_string()).is_err() && parse_layer("18446744073709551615").is_err());
+        assert!(parse_layer("-1").is_err());
+    }
+
+    #[test]
+    fn positive_numbers_are_finite() {
+        assert_eq!(parse_positive("1.6"), Ok(1.6));
+        assert!(parse_positive("inf").is_err() && parse_positive("NaN").is_err() && parse_positive("0").is_err());
+        // the text commands that take them
+        assert!(sent("box 0 inf 3").is_empty() && sent("ring 0 inf").is_empty() && sent("stretch 0 inf 1").is_empty());
+        assert!(sent("noise 0.5 inf").is_empty() && sent("compare inf 0.5").is_empty());
+        assert!(matches!(sent("ring 0 30").as_slice(), [ControlCommand::Shape { index: 0, .. }]));
+    }
+
+    #[test]
+    fn random_scenes_have_a_bounded_count() {
+        assert_eq!(parse_count("3"), Ok(3));
+        assert_eq!(parse_count(&MAX_METABALL_COUNT.to_string()), Ok(MAX_METABALL_COUNT));
+        assert!(parse_count("4294967295").is_err() && parse_count("-1").is_err());
+        assert_eq!(parse_count_factor("0.5"), Ok(0.5));
+        assert!(parse_count_factor("1e-300").is_err() && parse_count_factor("1e-6").is_err() && parse_count_factor("inf").is_err());
+    }
+
+    #[test]
+    fn frame_rates_are_slow_enough_to_time() {
+        assert_eq!(parse_fps("12.5"), Ok(12.5));
+        assert!(parse_fps("1e-300").is_err() && parse_fps("0").is_err() && parse_fps("inf").is_err());
+        assert_eq!(parse_fps(&MIN_FPS.to_string()), Ok(MIN_FPS));
+        assert_eq!(parse_frame_cap("0"), Ok(0.0));
+        assert!(parse_frame_cap("1e-300").is_err() && parse_frame_cap("-1").is_err());
+        assert_eq!(Pacing::new(Some(MIN_FPS)).interval, Duration::from_secs(1000));
     }
 }
@@ -0,0 +1,214 @@
+//! Marching cubes over the 3D metaball field, turning the surface of the shape into a triangle mesh for
+//! OBJ and STL files
+
+use crate::raymarch::{bounding_radius, field_at3, lifted, middle, Point3};
+use crate::MetaballData;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// The corners of a cube, by whether each is one step along x, y and z in the bits from lowest to highest
+const CORNERS: [(usize, usize, usize); 8] = [(0, 0, 0), (1, 0, 0), (0, 1, 0), (1, 1, 0), (0, 0, 1), (1, 0, 1), (0, 1, 1), (1, 1, 1)];
+
+/// Each cube split into six tetrahedra around its diagonal. Neighboring cubes split their shared faces the
+/// same way, so the surface has no cracks between them, and no case of a tetrahedron is ambiguous like some
+/// of a whole cube's are.
+const TETRAHEDRA: [[usize; 4]; 6] = [[0, 1, 3, 7], [0, 1, 5, 7], [0, 2, 3, 7], [0, 2, 6, 7], [0, 4, 5, 7], [0, 4, 6, 7]];
+
+/// The most points the grid the field is sampled on has along each side, so a tiny cell can't ask for more
+/// memory than there is
+pub const MAX_GRID_POINTS: usize = 512;
+
+/// A surface of triangles, facing out of the shape when their corners go counterclockwise
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Mesh {
+    pub vertices: Vec<Point3>,
+    /// The indices of each triangle's corners in the vertices
+    pub triangles: Vec<[usize; 3]>,
+}
+
+impl Mesh {
+    /// Write the mesh as a Wavefront OBJ. The image's down is -y, so the shape is the right way up in tools
+    /// where y is up.
+    pub fn write_obj(&self, mut out: impl Write) -> io::Result<()> {
+        for vertex in &self.vertices {
+            writeln!(out, "v {:.4} {:.4} {:.4}", vertex.x, -vertex.y, vertex.z)?;
+        }
+        // flipping y turns the triangles inside out, so they're wound the other way to keep facing out.
+        // OBJ counts the vertices from 1.
+        for [a, b, c] in &self.triangles {
+            writeln!(out, "f {} {} {}", a + 1, c + 1, b + 1)?;
+        }
+        out.flush()
+    }
+
+    /// Write the mesh as a binary STL, laid out the same way as [write_obj](Mesh::write_obj)
+    pub fn write_stl(&self, mut out: impl Write) -> io::Result<()> {
+        let mut header = [0u8; 80];
+        header[..9].copy_from_slice(b"metaballs");
+        out.write_all(&header)?;
+        out.write_all(&(self.triangles.len() as u32).to_le_bytes())?;
+        for &[a, b, c] in &self.triangles {
+            let flipped = |index: usize| {
+                let vertex = self.vertices[index];
+                Point3::new(vertex.x, -vertex.y, vertex.z)
+            };
+            let (a, b, c) = (flipped(a), flipped(c), flipped(b));
+            let normal = (b - a).cross(c - a).normalized();
+            for point in &[normal, a, b, c] {
+                for value in &[point.x, point.y, point.z] {
+                    // a degenerate triangle has no normal, which is written as 0
+                    let value = if value.is_finite() { *value as f32 } else { 0.0 };
+                    out.write_all(&value.to_le_bytes())?;
+                }
+            }
+            out.write_all(&[0, 0])?;
+        }
+        out.flush()
+    }
+}
+
+/// The surface of a scene's shape in 3D at its threshold, with the metaballs lifted into balls the same way
+/// as the raymarched view and the field sampled on a grid of cubes `cell` pixels wide, or wider when that
+/// would take more than [MAX_GRID_POINTS] along a side
+pub fn surface(metaball_data: &MetaballData, cell: f64) -> Mesh {
+    let metaballs = lifted(metaball_data);
+    let center = middle(metaball_data);
+    let bound = match bounding_radius(center, &metaballs, metaball_data) {
+        Some(bound) if bound.is_finite() => bound,
+        _ => return Mesh::default(),
+    };
+    let (count, cell) = grid(bound, cell);
+    let origin = center - Point3::new(1.0, 1.0, 1.0) * (count / 2) as f64 * cell;
+    let index = |x: usize, y: usize, z: usize| (z * count + y) * count + x;
+
+    let mut field = vec![0.0; count * count * count];
+    field.par_chunks_mut(count * count).enumerate().for_each(|(z, slice)| {
+        for (i, value) in slice.iter_mut().enumerate() {
+            *value = field_at3(point_of(z * count * count + i, count, origin, cell), &metaballs, metaball_data);
+        }
+    });
+    let level = metaball_data.threshold;
+    let inside = |corner: usize| field[corner] > level;
+
+    let mut mesh = Mesh::default();
+    // the crossing on each edge between two grid points, shared by every triangle meeting there
+    let mut crossings: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut crossing = |from: usize, to: usize, mesh: &mut Mesh| {
+        let key = (from.min(to), from.max(to));
+        *crossings.entry(key).or_insert_with(|| {
+            let (from_value, to_value) = (field[from], field[to]);
+            // the field is infinite right on a metaball's center, which the level is right next to
+            let t = if from_value.is_infinite() {
+                1.0
+            } else if to_value.is_infinite() {
+                0.0
+            } else {
+                (level - from_value) / (to_value - from_value)
+            };
+            let (from, to) = (point_of(from, count, origin, cell), point_of(to, count, origin, cell));
+            mesh.vertices.push(from + (to - from) * t);
+            mesh.vertices.len() - 1
+        })
+    };
+
+    for z in 0..count - 1 {
+        for y in 0..count - 1 {
+            for x in 0..count - 1 {
+                let corners = CORNERS.map(|(dx, dy, dz)| index(x + dx, y + dy, z + dz));
+                for tetrahedron in &TETRAHEDRA {
+                    let corners = tetrahedron.map(|corner| corners[corner]);
+                    let (within, without): (Vec<usize>, Vec<usize>) = corners.iter().partition(|&&corner| inside(corner));
+                    let triangles = match (within.as_slice(), without.as_slice()) {
+                        // one corner cut off from the other three
+                        ([a], [b, c, d]) | ([b, c, d], [a]) => vec![[(*a, *b), (*a, *c), (*a, *d)]],
+                        // a quad between two pairs of corners
+                        ([a, b], [c, d]) => vec![[(*a, *c), (*a, *d), (*b, *d)], [(*a, *c), (*b, *d), (*b, *c)]],
+                        _ => continue,
+                    };
+                    // out is from the middle of the corners inside towards the middle of the ones outside
+                    let centroid = |corners: &[usize]| {
+                        let sum = corners.iter().fold(Point3::default(), |sum, &corner| sum + point_of(corner, count, origin, cell));
+                        sum * (1.0 / corners.len() as f64)
+                    };
+                    let out_of = centroid(&without) - centroid(&within);
+                    for edges in triangles {
+                        let [a, b, c] = edges.map(|(from, to)| crossing(from, to, &mut mesh));
+                        let (pa, pb, pc) = (mesh.vertices[a], mesh.vertices[b], mesh.vertices[c]);
+                        let facing = (pb - pa).cross(pc - pa).dot(out_of);
+                        mesh.triangles.push(if facing < 0.0 { [a, c, b] } else { [a, b, c] });
+                    }
+                }
+            }
+        }
+    }
+    mesh
+}
+
+/// How many points along each side the grid reaching `bound` from the middle has, and how wide its cubes are.
+/// It reaches a cell past the bound on every side, so the surface is closed all the way around.
+fn grid(bound: f64, cell: f64) -> (usize, f64) {
+    let steps = (bound / cell).ceil();
+    let most = ((MAX_GRID_POINTS - 3) / 2) as f64;
+    let (steps, cell) = if steps > most {
+        let wider = bound / most;
+        log::warn!("{:e} pixel cells are too small for a grid of at most {} points a side, using {:.3} pixel cells", cell, MAX_GRID_POINTS, wider);
+        (most, wider)
+    } else {
+        (steps, cell)
+    };
+    ((steps as usize + 1) * 2 + 1, cell)
+}
+
+/// Where a grid point is, by its index
+fn point_of(index: usize, count: usize, origin: Point3, cell: f64) -> Point3 {
+    origin + Point3::new((index % count) as f64, (index / count % count) as f64, (index / (count * count)) as f64) * cell
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metaball, Pointf};
+
+    #[test]
+    fn a_lone_metaball_makes_a_closed_ball() {
+        let metadata = MetaballData::new(1.6, 0.5, 64, 64, vec![Metaball::new(Pointf { x: 32.0, y: 32.0 }, 60.0)]);
+        let mesh = surface(&metadata, 2.0);
+        assert!(!mesh.triangles.is_empty());
+
+        let radius = (60.0f64 / 0.5).powf(1.0 / 1.6);
+        for vertex in &mesh.vertices {
+            let distance = (*vertex - Point3::new(32.0, 32.0, 0.0)).length();
+            assert!((distance - radius).abs() < 0.5, "{:?} is {} from the center", vertex, distance);
+        }
+        // every edge of a closed surface is shared by two triangles, going opposite ways along it
+        let mut edges = HashMap::new();
+        for &[a, b, c] in &mesh.triangles {
+            for edge in &[(a, b), (b, c), (c, a)] {
+                *edges.entry(*edge).or_insert(0) += 1;
+            }
+        }
+        for (&(from, to), &uses) in &edges {
+            assert_eq!((uses, edges.get(&(to, from))), (1, Some(&1)), "edge {} to {}", from, to);
+        }
+    }
+
+    #[test]
+    fn tiny_cells_are_widened_to_fit_the_grid() {
+        assert_eq!(grid(10.0, 2.0), (13, 2.0));
+        for cell in [0.001, 1e-300] {
+            let (count, cell) = grid(100.0, cell);
+            assert!(count <= MAX_GRID_POINTS, "{} points a side", count);
+            assert!(cell * ((count / 2) as f64 - 1.0) >= 100.0);
+        }
+    }
+
+    #[test]
+    fn stl_files_hold_every_triangle() {
+        let metadata = MetaballData::new(1.6, 0.5, 64, 64, vec![Metaball::new(Pointf { x: 32.0, y: 32.0 }, 60.0)]);
+        let mesh = surface(&metadata, 4.0);
+        let mut stl = vec![];
+        mesh.write_stl(&mut stl).unwrap();
+        assert_eq!(stl.len(), 84 + 50 * mesh.triangles.len());
+    }
+}
@@ -0,0 +1,179 @@
+//! Driving the scene from the knobs of a MIDI controller, read as the raw bytes of a MIDI device such as the
+//! ALSA `/dev/snd/midiC1D0` or OSS `/dev/midi1`. Each control change is mapped onto the goo, the threshold
+//! or the size of a metaball by a mapping file, or by the default mapping without one.
+
+use crate::BASE_METABALL_SIZE;
+use serde::Deserialize;
+use std::path::Path;
+
+/// A knob or slider moving, with the channel from 0 to 15 and the number and value from 0 to 127
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ControlChange {
+    pub channel: u8,
+    pub number: u8,
+    pub value: u8,
+}
+
+/// Takes in the bytes of a MIDI stream one at a time, keeping the status of the message they're part of
+/// since controllers leave it out of messages with the same status as the last one
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Parser {
+    status: Option<u8>,
+    data: Vec<u8>,
+}
+
+impl Parser {
+    /// Take in the next byte, the control change it finishes if any. The other messages are skipped over.
+    pub fn push(&mut self, byte: u8) -> Option<ControlChange> {
+        match byte {
+            // clock and the other real time messages can come between any two bytes, without changing anything
+            0xf8..=0xff => None,
+            // system exclusive and the other system messages end the running status, and their data's skipped
+            0xf0..=0xf7 => {
+                self.status = None;
+                self.data.clear();
+                None
+            }
+            0x80..=0xef => {
+                self.status = Some(byte);
+                self.data.clear();
+                None
+            }
+            _ => {
+                let status = self.status?;
+                self.data.push(byte);
+                // program changes and channel pressure have one data byte, the others two
+                let length = if matches!(status & 0xf0, 0xc0 | 0xd0) { 1 } else { 2 };
+                if self.data.len() < length {
+                    return None;
+                }
+                let data = std::mem::take(&mut self.data);
+                (status & 0xf0 == 0xb0).then(|| ControlChange { channel: status & 0x0f, number: data[0], value: data[1] })
+            }
+        }
+    }
+}
+
+/// What a control change is mapped onto
+#[derive(Copy, Clone, PartialEq, Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Target {
+    Goo,
+    Threshold,
+    /// The size of the metaball at the mapping's index
+    Size,
+}
+
+/// A control change mapped onto a parameter, moving it from `min` with the control at 0 to `max` at 127
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct Mapping {
+    /// The control change number
+    pub number: u8,
+    /// The channel from 1 to 16, the way controllers label them, or any channel when left out
+    #[serde(default)]
+    pub channel: Option<u8>,
+    pub target: Target,
+    /// Which metaball a size mapping changes
+    #[serde(default)]
+    pub index: Option<usize>,
+    pub min: f64,
+    pub max: f64,
+}
+
+/// A parameter set by a control change
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Change {
+    Goo(f64),
+    Threshold(f64),
+    Size { index: usize, size: f64 },
+}
+
+/// The mappings from control changes to parameters, loaded from the `[[cc]]` tables of a TOML file
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct MidiMap {
+    #[serde(default)]
+    pub cc: Vec<Mapping>,
+}
+
+impl Default for MidiMap {
+    /// Controls 1 and 2 for the goo and threshold, and 3 to 10 for the sizes of the first eight metaballs
+    fn default() -> MidiMap {
+        let mapping = |number, target, index, min, max| Mapping { number, channel: None, target, index, min, max };
+        let mut cc = vec![mapping(1, Target::Goo, None, 0.5, 4.0), mapping(2, Target::Threshold, None, 0.05, 2.0)];
+        cc.extend((0..8).map(|index| mapping(3 + index as u8, Target::Size, Some(index), 0.0, BASE_METABALL_SIZE * 2.0)));
+        MidiMap { cc }
+    }
+}
+
+impl MidiMap {
+    pub fn load(path: &Path) -> Result<MidiMap, String> {
+        let text = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+        MidiMap::parse(&text)
+    }
+
+    /// Parse a TOML mapping, checking the numbers, channels and ranges are ones that can be used
+    pub fn parse(text: &str) -> Result<MidiMap, String> {
+        let map: MidiMap = toml::from_str(text).map_err(|err| err.to_string())?;
+        for mapping in &map.cc {
+            if mapping.number > 127 {
+                return Err(format!("control change {} is past 127", mapping.number));
+            }
+            if mapping.channel.is_some_and(|channel| !(1..=16).contains(&channel)) {
+                return Err(format!("control change {} has a channel that isn't from 1 to 16", mapping.number));
+            }
+            if !mapping.min.is_finite() || !mapping.max.is_finite() {
+                return Err(format!("control change {} needs a finite min and max", mapping.number));
+            }
+            match mapping.target {
+                Target::Size if mapping.index.is_none() => return Err(format!("control change {} needs the index of a metaball to size", mapping.number)),
+                Target::Goo | Target::Threshold if mapping.min <= 0.0 || mapping.max <= 0.0 => {
+                    return Err(format!("control change {} needs a positive min and max", mapping.number));
+                }
+                _ => {}
+            }
+        }
+        Ok(map)
+    }
+
+    /// The parameters a control change sets, through each mapping of it
+    pub fn changes(&self, control: ControlChange) -> Vec<Change> {
+        let fraction = control.value as f64 / 127.0;
+        self.cc
+            .iter()
+            .filter(|mapping| mapping.number == control.number && mapping.channel.is_none_or(|channel| channel == control.channel + 1))
+            .map(|mapping| {
+                let value = mapping.min + (mapping.max - mapping.min) * fraction;
+                match mapping.target {
+                    Target::Goo => Change::Goo(value),
+                    Target::Threshold => Change::Threshold(value),
+                    Target::Size => Change::Size { index: mapping.index.unwrap_or(0), size: value },
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn control_changes_are_parsed_and_mapped() {
+        let mut parser = Parser::default();
+        // a control change, another with the running status and a clock between its bytes, then a note on
+        let bytes = [0xb1, 20, 127, 21, 0xf8, 0, 0x90, 20, 127];
+        let controls: Vec<_> = bytes.iter().filter_map(|&byte| parser.push(byte)).collect();
+        assert_eq!(controls, vec![ControlChange { channel: 1, number: 20, value: 127 }, ControlChange { channel: 1, number: 21, value: 0 }]);
+
+        let map = MidiMap::parse(
+            "[[cc]]\nnumber = 20\nchannel = 2\ntarget = \"goo\"\nmin = 1.0\nmax = 3.0\n\n\
+             [[cc]]\nnumber = 21\ntarget = \"size\"\nindex = 4\nmin = 10.0\nmax = 50.0\n",
+        )
+        .unwrap();
+        assert_eq!(map.changes(controls[0]), vec![Change::Goo(3.0)]);
+        assert_eq!(map.changes(controls[1]), vec![Change::Size { index: 4, size: 10.0 }]);
+        assert_eq!(map.changes(ControlChange { channel: 0, number: 20, value: 0 }), vec![]);
+        assert!(MidiMap::parse("[[cc]]\nnumber = 3\ntarget = \"size\"\nmin = 0.0\nmax = 1.0\n").is_err());
+        assert_eq!(MidiMap::default().changes(ControlChange { channel: 9, number: 2, value: 127 }), vec![Change::Threshold(2.0)]);
+    }
+}
@@ -0,0 +1,212 @@
+//! Looping paths for metaballs to follow when animating, in place of bouncing around at a velocity, for the
+//! classic metaball demos where the blobs circle and weave through each other forever, and pulses swinging
+//! their sizes so even still scenes breathe
+
+use crate::{Pointf, ANIMATION_TICK};
+use rand::Rng;
+use std::f64::consts::TAU;
+use std::fmt;
+use std::str::FromStr;
+
+/// How many animation ticks a path takes to come back around when none is given
+pub const MOTION_PERIOD: f64 = 120.0;
+
+/// The shape of a path, around its middle
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Path {
+    /// A circle of this radius
+    Orbit { radius: f64 },
+    /// A Lissajous curve, swinging this far each way across and up and down, this many times across and
+    /// up and down per loop
+    Lissajous { amplitude: (f64, f64), frequencies: (u32, u32) },
+}
+
+/// A metaball going around a path over and over, see [Metaball::motion](crate::Metaball::motion)
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Motion {
+    pub path: Path,
+    /// The middle of the path
+    pub center: Pointf,
+    /// How many animation ticks one loop takes
+    pub period: f64,
+    /// How far around the loop the metaball is, within [0, 1)
+    pub phase: f64,
+}
+
+impl Motion {
+    /// A path around whatever middle puts its start on a location, so a metaball picks it up from where it is
+    pub fn starting_at(path: Path, location: Pointf, period: f64) -> Motion {
+        let offset = path.offset(0.0);
+        Motion { path, center: Pointf { x: location.x - offset.x, y: location.y - offset.y }, period, phase: 0.0 }
+    }
+
+    /// Where the metaball is
+    pub fn location(&self) -> Pointf {
+        let offset = self.path.offset(self.phase);
+        Pointf { x: self.center.x + offset.x, y: self.center.y + offset.y }
+    }
+
+    /// Where the metaball was when the path was started
+    pub fn start(&self) -> Pointf {
+        Motion { phase: 0.0, ..*self }.location()
+    }
+
+    /// Go one animation tick further around the path
+    pub fn advance(&mut self) {
+        self.phase = (self.phase + 1.0 / self.period).fract();
+    }
+}
+
+impl Path {
+    /// How far from the middle the path is a fraction of the way around it
+    fn offset(&self, phase: f64) -> Pointf {
+        let angle = phase * TAU;
+        match *self {
+            Path::Orbit { radius } => Pointf { x: radius * angle.cos(), y: radius * angle.sin() },
+            Path::Lissajous { amplitude: (x, y), frequencies: (a, b) } => {
+                Pointf { x: x * (a as f64 * angle).cos(), y: y * (b as f64 * angle).sin() }
+            }
+        }
+    }
+}
+
+/// A metaball's size swinging either way around its resting size while animating, see
+/// [Metaball::pulse](crate::Metaball::pulse)
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Pulse {
+    /// How far the size swings either way, as a fraction of the resting size within [0, 1)
+    pub amplitude: f64,
+    /// How many times a second the size swings back and forth
+    pub frequency: f64,
+    /// How far into a swing the pulse starts, as a fraction of one
+    pub phase: f64,
+    /// The seconds of animation since the pulse started
+    pub elapsed: f64,
+}
+
+impl Pulse {
+    pub fn new(amplitude: f64, frequency: f64, phase: f64) -> Pulse {
+        Pulse { amplitude, frequency, phase, elapsed: 0.0 }
+    }
+
+    /// Check the amplitude is within [0, 1), so the size never reaches 0, the frequency is positive and the
+    /// phase is a number
+    pub fn check(amplitude: f64, frequency: f64, phase: f64) -> Result<(), String> {
+        if !(0.0..1.0).contains(&amplitude) {
+            return Err(format!("the amplitude {} isn't within [0, 1)", amplitude));
+        }
+        if !frequency.is_finite() || frequency <= 0.0 {
+            return Err(format!("the frequency {} isn't a positive number", frequency));
+        }
+        if !phase.is_finite() {
+            return Err(format!("the phase {} isn't a number", phase));
+        }
+        Ok(())
+    }
+
+    /// How many times its resting size the metaball is now
+    pub fn scale(&self) -> f64 {
+        1.0 + self.amplitude * ((self.frequency * self.elapsed + self.phase) * TAU).sin()
+    }
+
+    /// Go one animation tick further, returning what the metaball's size is multiplied by
+    pub fn advance(&mut self) -> f64 {
+        let before = self.scale();
+        self.elapsed += ANIMATION_TICK.as_secs_f64();
+        self.scale() / before
+    }
+}
+
+/// The amplitude, frequency and phase, as the pulse command takes them
+impl fmt::Display for Pulse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {}", self.amplitude, self.frequency, self.phase)
+    }
+}
+
+/// How the path is given to the motion command
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Path::Orbit { radius } => write!(f, "orbit {}", radius),
+            Path::Lissajous { amplitude: (x, y), frequencies: (a, b) } => write!(f, "lissajous {} {} {} {}", x, y, a, b),
+        }
+    }
+}
+
+/// The path and period, as the motion command takes them
+impl fmt::Display for Motion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.path, self.period)
+    }
+}
+
+/// The kinds of path that random ones can be drawn from, for `--motion`
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum PathKind {
+    Orbit,
+    Lissajous,
+}
+
+impl PathKind {
+    /// A random path of this kind, about as big as a metaball
+    pub fn random(&self, rng: &mut impl Rng) -> Path {
+        match self {
+            PathKind::Orbit => Path::Orbit { radius: rng.gen_range(20.0..60.0) },
+            PathKind::Lissajous => Path::Lissajous {
+                amplitude: (rng.gen_range(30.0..80.0), rng.gen_range(30.0..80.0)),
+                frequencies: (rng.gen_range(1..=3), rng.gen_range(1..=3)),
+            },
+        }
+    }
+}
+
+impl FromStr for PathKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "orbit" => Ok(PathKind::Orbit),
+            "lissajous" => Ok(PathKind::Lissajous),
+            _ => Err(format!("Unknown motion \"{}\", expected orbit or lissajous", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_loop_back_to_where_they_started() {
+        let location = Pointf { x: 100.0, y: 80.0 };
+        for path in [Path::Orbit { radius: 30.0 }, Path::Lissajous { amplitude: (40.0, 20.0), frequencies: (3, 2) }] {
+            let mut motion = Motion::starting_at(path, location, 8.0);
+            assert_eq!(motion.location(), location);
+            let mut furthest: f64 = 0.0;
+            for _ in 0..8 {
+                motion.advance();
+                furthest = furthest.max(motion.location().distance_to(location.x, location.y, 1.0));
+            }
+            let back = motion.location();
+            assert!((back.x - location.x).abs() < 1e-9 && (back.y - location.y).abs() < 1e-9, "{:?}", back);
+            assert!(furthest > 30.0);
+        }
+    }
+
+    #[test]
+    fn pulses_swing_the_size_around_where_it_rests() {
+        let mut pulse = Pulse::new(0.25, 1.0, 0.25);
+        assert_eq!(pulse.scale(), 1.25);
+        let mut size = 100.0 * pulse.scale();
+        let (mut smallest, mut biggest) = (size, size);
+        let ticks = (1.0 / ANIMATION_TICK.as_secs_f64()).round() as usize;
+        for _ in 0..ticks {
+            size *= pulse.advance();
+            smallest = f64::min(smallest, size);
+            biggest = f64::max(biggest, size);
+        }
+        assert!((size / pulse.scale() - 100.0).abs() < 1e-9);
+        assert!(smallest < 76.0 && biggest == 125.0, "{} to {}", smallest, biggest);
+    }
+}
@@ -0,0 +1,90 @@
+//! Perlin noise over the image that the field is modulated with, giving the edges of the shape an organic
+//! wobble instead of perfect circles
+
+use std::fmt;
+
+/// The noise scale when none is given, in pixels
+pub const NOISE_SCALE: f64 = 32.0;
+
+/// How strongly and how finely the field is perturbed
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Noise {
+    /// How far the field is scaled up or down by the noise at most, as a fraction of it within [0, 1]
+    pub amplitude: f64,
+    /// About how many pixels apart the bumps of the noise are
+    pub scale: f64,
+}
+
+impl Noise {
+    /// What the field at a point is multiplied by, which is 1 on average. The noise never reaches ±1,
+    /// so the factor stays positive and the field keeps its sign.
+    pub fn factor(&self, x: f64, y: f64) -> f64 {
+        1.0 + self.amplitude * perlin(x / self.scale, y / self.scale)
+    }
+}
+
+impl fmt::Display for Noise {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.amplitude, self.scale)
+    }
+}
+
+/// Two dimensional Perlin noise, 0 on every integer lattice point and within about ±0.71 in between. A
+/// tiny noise scale puts the points far out, past where the lattice points fit in an `i64`, where the
+/// neighbors of the last one wrap around, or even past where an `f64` is finite, where the noise is 0.
+fn perlin(x: f64, y: f64) -> f64 {
+    if !x.is_finite() || !y.is_finite() {
+        return 0.0;
+    }
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let (i, j) = (x0 as i64, y0 as i64);
+    // how far along the corner at (di, dj) points its gradient
+    let corner = |di: i64, dj: i64| {
+        let (gx, gy) = gradient(i.wrapping_add(di), j.wrapping_add(dj));
+        gx * (fx - di as f64) + gy * (fy - dj as f64)
+    };
+    let (sx, sy) = (fade(fx), fade(fy));
+    let top = lerp(corner(0, 0), corner(1, 0), sx);
+    let bottom = lerp(corner(0, 1), corner(1, 1), sx);
+    lerp(top, bottom, sy)
+}
+
+/// One of eight unit gradients for a lattice point, picked by hashing it
+fn gradient(i: i64, j: i64) -> (f64, f64) {
+    let mut hash = (i as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ (j as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    hash ^= hash >> 29;
+    hash = hash.wrapping_mul(0x1656_67B1_9E37_79F9);
+    hash ^= hash >> 32;
+    let angle = (hash % 8) as f64 * std::f64::consts::FRAC_PI_4;
+    (angle.cos(), angle.sin())
+}
+
+/// Perlin's quintic ease, so the noise is smooth across the lattice cells
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_noise_wobbles_the_field_by_at_most_the_amplitude() {
+        let noise = Noise { amplitude: 0.5, scale: 16.0 };
+        let factors: Vec<f64> = (0..64 * 64).map(|i| noise.factor((i % 64) as f64 * 1.3, (i / 64) as f64 * 0.7)).collect();
+        assert!(factors.iter().all(|factor| (0.5..=1.5).contains(factor)));
+        assert!(factors.iter().any(|&factor| factor > 1.1) && factors.iter().any(|&factor| factor < 0.9));
+        assert_eq!(noise.factor(32.0, 48.0), 1.0);
+        // smooth, so neighboring pixels are close
+        assert!((noise.factor(10.0, 10.0) - noise.factor(10.5, 10.0)).abs() < 0.1);
+        for scale in [1e-300, 1e-320] {
+            let tiny = Noise { amplitude: 0.5, scale };
+            assert!((0..64).map(|i| tiny.factor(i as f64 * 0.7, 3.0)).all(|factor| (0.5..=1.5).contains(&factor)));
+        }
+    }
+}
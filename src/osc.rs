@@ -0,0 +1,182 @@
+//! Decoding Open Sound Control packets, for driving the scene from TouchOSC, SuperCollider or a DAW
+
+use std::convert::{TryFrom, TryInto};
+
+/// An argument of an OSC message, by its type tag
+#[derive(Clone, PartialEq, Debug)]
+pub enum Arg {
+    /// `i`
+    Int(i32),
+    /// `f`
+    Float(f32),
+    /// `h`
+    Long(i64),
+    /// `d`
+    Double(f64),
+    /// `s`, or `S` for a symbol
+    String(String),
+    /// `b`, raw bytes
+    Blob(Vec<u8>),
+    /// `T` and `F`
+    Bool(bool),
+    /// `N`, or `I` for an impulse, which have no value
+    Nil,
+}
+
+impl Arg {
+    /// The argument as a number, if it's any kind of number. True is 1 and false is 0, the way buttons send
+    /// them.
+    pub fn number(&self) -> Option<f64> {
+        match *self {
+            Arg::Int(value) => Some(value as f64),
+            Arg::Float(value) => Some(value as f64),
+            Arg::Long(value) => Some(value as f64),
+            Arg::Double(value) => Some(value),
+            Arg::Bool(value) => Some(if value { 1.0 } else { 0.0 }),
+            _ => None,
+        }
+    }
+}
+
+/// A message to an address such as `/metaballs/goo`
+#[derive(Clone, PartialEq, Debug)]
+pub struct Message {
+    pub address: String,
+    pub args: Vec<Arg>,
+}
+
+/// The messages in a packet, in order, with any bundles unpacked. The bundles' time tags are ignored, so
+/// everything happens as soon as it arrives.
+pub fn decode(packet: &[u8]) -> Result<Vec<Message>, String> {
+    let mut messages = vec![];
+    decode_into(packet, &mut messages)?;
+    Ok(messages)
+}
+
+fn decode_into(packet: &[u8], messages: &mut Vec<Message>) -> Result<(), String> {
+    let mut reader = Reader { bytes: packet };
+    let address = reader.string()?;
+    if address == "#bundle" {
+        reader.take(8)?;
+        while !reader.bytes.is_empty() {
+            let size = reader.int()?;
+            let element = usize::try_from(size).map_err(|_| format!("bundle element of {} bytes", size))?;
+            decode_into(reader.take(element)?, messages)?;
+        }
+        return Ok(());
+    }
+    if !address.starts_with('/') {
+        return Err(format!("\"{}\" isn't an address", address));
+    }
+    // old senders leave out the type tags when there are no arguments
+    let tags = if reader.bytes.is_empty() { ",".to_string() } else { reader.string()? };
+    let tags = tags.strip_prefix(',').ok_or_else(|| format!("\"{}\" aren't type tags", tags))?;
+    let args = tags
+        .chars()
+        .map(|tag| match tag {
+            'i' => reader.int().map(Arg::Int),
+            'f' => reader.take(4).map(|bytes| Arg::Float(f32::from_be_bytes(bytes.try_into().unwrap()))),
+            'h' => reader.take(8).map(|bytes| Arg::Long(i64::from_be_bytes(bytes.try_into().unwrap()))),
+            'd' => reader.take(8).map(|bytes| Arg::Double(f64::from_be_bytes(bytes.try_into().unwrap()))),
+            's' | 'S' => reader.string().map(Arg::String),
+            'b' => reader.blob().map(Arg::Blob),
+            'T' => Ok(Arg::Bool(true)),
+            'F' => Ok(Arg::Bool(false)),
+            'N' | 'I' => Ok(Arg::Nil),
+            tag => Err(format!("unsupported type tag '{}'", tag)),
+        })
+        .collect::<Result<_, _>>()?;
+    messages.push(Message { address, args });
+    Ok(())
+}
+
+/// Reads the parts of a packet from the front, each padded out to a multiple of four bytes
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        if count > self.bytes.len() {
+            return Err("the packet ends partway through".to_string());
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn int(&mut self) -> Result<i32, String> {
+        self.take(4).map(|bytes| i32::from_be_bytes(bytes.try_into().unwrap()))
+    }
+
+    /// A string ends at a zero byte, with more zeros up to the padding
+    fn string(&mut self) -> Result<String, String> {
+        let end = self.bytes.iter().position(|&byte| byte == 0).ok_or("a string runs off the end of the packet")?;
+        let text = String::from_utf8(self.bytes[..end].to_vec()).map_err(|err| err.to_string())?;
+        self.take((end + 4) / 4 * 4)?;
+        Ok(text)
+    }
+
+    /// A blob is its size followed by the bytes, padded
+    fn blob(&mut self) -> Result<Vec<u8>, String> {
+        let size = self.int()?;
+        let size = usize::try_from(size).map_err(|_| format!("blob of {} bytes", size))?;
+        let blob = self.take(size)?.to_vec();
+        self.take((4 - size % 4) % 4)?;
+        Ok(blob)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A string padded the way OSC pads them
+    fn padded(text: &str) -> Vec<u8> {
+        let mut bytes = text.as_bytes().to_vec();
+        bytes.resize((text.len() + 4) / 4 * 4, 0);
+        bytes
+    }
+
+    fn message(address: &str, tags: &str, args: &[u8]) -> Vec<u8> {
+        [padded(address), padded(tags), args.to_vec()].concat()
+    }
+
+    #[test]
+    fn messages_decode_their_arguments() {
+        let args = [2i32.to_be_bytes(), 10.5f32.to_be_bytes(), 20.0f32.to_be_bytes()].concat();
+        let packet = message("/metaballs/ball/2/pos", ",iffT", &args);
+        assert_eq!(packet.len() % 4, 0);
+        let messages = decode(&packet).unwrap();
+        assert_eq!(
+            messages,
+            vec![Message {
+                address: "/metaballs/ball/2/pos".to_string(),
+                args: vec![Arg::Int(2), Arg::Float(10.5), Arg::Float(20.0), Arg::Bool(true)],
+            }]
+        );
+        assert_eq!(messages[0].args[1].number(), Some(10.5));
+    }
+
+    #[test]
+    fn bundles_unpack_in_order() {
+        let goo = message("/metaballs/goo", ",d", &1.8f64.to_be_bytes());
+        let reset = message("/metaballs/reset", ",", &[]);
+        let mut packet = padded("#bundle");
+        packet.extend_from_slice(&1u64.to_be_bytes());
+        for element in &[&goo, &reset] {
+            packet.extend_from_slice(&(element.len() as i32).to_be_bytes());
+            packet.extend_from_slice(element);
+        }
+        let addresses: Vec<String> = decode(&packet).unwrap().into_iter().map(|message| message.address).collect();
+        assert_eq!(addresses, ["/metaballs/goo", "/metaballs/reset"]);
+    }
+
+    #[test]
+    fn truncated_packets_are_errors() {
+        let packet = message("/metaballs/goo", ",f", &[0, 0]);
+        assert!(decode(&packet).is_err());
+        assert!(decode(b"/metab").is_err());
+        assert!(decode(&message("/metaballs/goo", ",x", &[])).is_err());
+    }
+}
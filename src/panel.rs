@@ -0,0 +1,197 @@
+//! The control panel drawn over the window, for tuning the scene with the mouse instead of typing commands
+
+use crate::ControlCommand;
+use egui::paint::ClippedMesh;
+use egui_wgpu_backend::ScreenDescriptor;
+use egui_winit_platform::{Platform, PlatformDescriptor};
+use metaballs::{MetaballData, MESH_CELL};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Instant;
+use winit::event::{ElementState, Event, WindowEvent};
+use winit::window::Window;
+
+/// How many frames the export button records to GIFs and animated PNGs unless it's changed
+const DEFAULT_EXPORT_FRAMES: u32 = 60;
+
+/// The panel's widgets and what was drawn last, kept between frames
+pub struct Panel {
+    platform: Platform,
+    /// When the panel was made, which egui counts its animations from
+    start: Instant,
+    /// Whether the panel is showing, and taking the input over it
+    pub visible: bool,
+    /// The index of the metaball the panel edits
+    selected: usize,
+    export_path: String,
+    export_frames: u32,
+    /// Whether egui asked to lay out the panel again, for finishing an animation
+    repaint: bool,
+    /// What the last update drew, if the panel is showing
+    frame: Option<PanelFrame>,
+}
+
+/// The panel as drawn by an update, for the screen to paint over the metaballs
+pub struct PanelFrame {
+    pub meshes: Vec<ClippedMesh>,
+    pub texture: Arc<egui::Texture>,
+    pub screen: ScreenDescriptor,
+}
+
+impl Panel {
+    /// A hidden panel for a window
+    pub fn new(window: &Window) -> Panel {
+        let size = window.inner_size();
+        let platform = Platform::new(PlatformDescriptor {
+            physical_width: size.width,
+            physical_height: size.height,
+            scale_factor: window.scale_factor(),
+            font_definitions: egui::FontDefinitions::default(),
+            style: egui::Style::default(),
+        });
+        Panel {
+            platform,
+            start: Instant::now(),
+            visible: false,
+            selected: 0,
+            export_path: "metaballs.svg".to_string(),
+            export_frames: DEFAULT_EXPORT_FRAMES,
+            repaint: false,
+            frame: None,
+        }
+    }
+
+    /// Pass an event on to the panel. Returns whether the panel took it, in which case the window
+    /// controls should ignore it.
+    pub fn handle_event<T>(&mut self, event: &Event<T>) -> bool {
+        // the size still has to be kept up with while hidden, but nothing else is queued up for later
+        let resized = matches!(event, Event::WindowEvent { event: WindowEvent::Resized(_) | WindowEvent::ScaleFactorChanged { .. }, .. });
+        if !self.visible && !resized {
+            return false;
+        }
+        self.platform.handle_event(event);
+        // letting go of a button always reaches the window controls, so dragging a metaball over the panel
+        // doesn't leave it stuck to the cursor
+        let released = matches!(event, Event::WindowEvent { event: WindowEvent::MouseInput { state: ElementState::Released, .. }, .. });
+        self.visible && !released && self.platform.captures_event(event)
+    }
+
+    /// Lay out the panel for the scene, returning the commands for whatever was changed in it. Nothing is
+    /// drawn while it's hidden.
+    pub fn update(&mut self, window: &Window, scene: &MetaballData) -> Vec<ControlCommand> {
+        let mut commands = Vec::new();
+        if !self.visible {
+            self.frame = None;
+            self.repaint = false;
+            return commands;
+        }
+        self.platform.update_time(self.start.elapsed().as_secs_f64());
+        self.platform.begin_frame();
+        let ctx = self.platform.context();
+        egui::Window::new("Metaballs")
+            .default_pos((8.0, 8.0))
+            .resizable(false)
+            .show(&ctx, |ui| self.controls(ui, scene, &mut commands));
+        let (output, shapes) = self.platform.end_frame();
+        self.repaint = output.needs_repaint;
+
+        let size = window.inner_size();
+        self.frame = Some(PanelFrame {
+            meshes: ctx.tessellate(shapes),
+            texture: ctx.texture(),
+            screen: ScreenDescriptor {
+                physical_width: size.width,
+                physical_height: size.height,
+                scale_factor: window.scale_factor() as f32,
+            },
+        });
+        commands
+    }
+
+    /// What the last update drew, or `None` while the panel is hidden
+    pub fn frame(&self) -> Option<&PanelFrame> {
+        self.frame.as_ref()
+    }
+
+    /// Whether the panel should be laid out again even without any new input
+    pub fn needs_repaint(&self) -> bool {
+        self.repaint
+    }
+
+    /// Whether a widget is being dragged, so the changes so far are part of one edit
+    pub fn is_editing(&self) -> bool {
+        self.visible && self.platform.context().is_using_pointer()
+    }
+
+    fn controls(&mut self, ui: &mut egui::Ui, scene: &MetaballData, commands: &mut Vec<ControlCommand>) {
+        // values typed past the ends of the sliders are kept, the scene checks them like any other
+        let mut goo = scene.goo;
+        if ui.add(egui::Slider::new(&mut goo, 0.1..=5.0).clamp_to_range(false).text("goo")).changed() {
+            commands.push(ControlCommand::Goo(goo));
+        }
+        let mut threshold = scene.threshold;
+        if ui.add(egui::Slider::new(&mut threshold, 0.05..=2.0).clamp_to_range(false).text("threshold")).changed() {
+            commands.push(ControlCommand::Threshold(threshold));
+        }
+
+        ui.separator();
+        if scene.metaballs.is_empty() {
+            ui.label("No metaballs to edit");
+        } else {
+            // the metaballs may have been removed since the selected one was picked
+            let last = scene.metaballs.len() - 1;
+            self.selected = self.selected.min(last);
+            ui.add(egui::Slider::new(&mut self.selected, 0..=last).text("metaball"));
+            let index = self.selected;
+            let metaball = &scene.metaballs[index];
+            let mut location = metaball.location;
+            let moved_x = ui.add(egui::Slider::new(&mut location.x, 0.0..=(scene.width - 1) as f64).text("x")).changed();
+            let moved_y = ui.add(egui::Slider::new(&mut location.y, 0.0..=(scene.height - 1) as f64).text("y")).changed();
+            if moved_x || moved_y {
+                commands.push(ControlCommand::Move { index, location });
+            }
+            let mut size = metaball.size;
+            let resized = ui.horizontal(|ui| {
+                let resized = ui.add(egui::DragValue::new(&mut size).speed(0.5)).changed();
+                ui.label("size");
+                resized
+            });
+            if resized.inner {
+                commands.push(ControlCommand::Size { index, size });
+            }
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("Randomize").clicked() {
+                commands.push(ControlCommand::Randomize);
+            }
+            if ui.button("Reset").clicked() {
+                commands.push(ControlCommand::Reset);
+            }
+        });
+
+        ui.separator();
+        ui.add(egui::TextEdit::singleline(&mut self.export_path).hint_text("out.svg, .gif, .png, .csv or .obj"));
+        ui.add(egui::Slider::new(&mut self.export_frames, 1..=300).text("frames"));
+        if ui.button("Export").clicked() {
+            match export_command(PathBuf::from(self.export_path.trim()), self.export_frames) {
+                Ok(command) => commands.push(command),
+                Err(err) => println!("Not exporting to {}: {}", self.export_path.trim(), err),
+            }
+        }
+    }
+}
+
+/// The command exporting to a path, picked by its extension: the outline for .svg, a recording of this many
+/// frames for .gif, .png and .apng, the field values for .csv, .f32 and .f64, and the 3D surface for .obj and .stl
+fn export_command(path: PathBuf, frames: u32) -> Result<ControlCommand, String> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("svg") => Ok(ControlCommand::ExportSvg(path)),
+        Some("gif") | Some("png") | Some("apng") if frames == 0 => Err("there needs to be at least 1 frame to record".to_string()),
+        Some("gif") | Some("png") | Some("apng") => Ok(ControlCommand::Export(path, frames, None)),
+        Some("csv") | Some("f32") | Some("f64") => Ok(ControlCommand::ExportField(path)),
+        Some("obj") | Some("stl") => Ok(ControlCommand::ExportMesh(path, MESH_CELL)),
+        _ => Err("it needs to end in .svg, .gif, .png, .apng, .csv, .f32, .f64, .obj or .stl".to_string()),
+    }
+}
@@ -0,0 +1,208 @@
+//! Evaluating the field only near the edge of the shape, by splitting the image into quads and bounding the
+//! field over each quad with interval arithmetic on the falloff. A quad the shape's edge can't pass through
+//! is filled with one value, and only the quads it might cross are split further, down to a few pixels.
+
+use crate::spatial::{MetaballGrid, CELL_SIZE};
+use crate::{field_distance, field_grid, field_of, field_point, milliseconds_since, shade_field, BlendMode, Metaball, MetaballData, RenderOpts, BAND_ROWS};
+use image::{ImageBuffer, Rgba};
+use rayon::prelude::*;
+use std::time::Instant;
+
+/// How many pixels wide the smallest quads are, whose pixels are all evaluated if the edge might cross them
+const MIN_QUAD: u32 = 4;
+
+/// A square of pixels, and the field over it if it's filled with one value
+struct Quad {
+    x: u32,
+    y: u32,
+    size: u32,
+    fill: Option<f64>,
+}
+
+/// Render the shape like [naive_impl](crate::naive_impl), evaluating the field only in the quads that the
+/// threshold, a band or an isoline might pass through, and inside the shape too for the gradient. All the
+/// pixels of any other quad are on the same side of each of those levels, so they're drawn the same from
+/// one value. Scenes blended or layered other than by summing are evaluated at every pixel.
+pub fn quadtree_impl(width: u32, height: u32, scale: u32, metaball_data: &MetaballData, opts: &RenderOpts) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let mut image = ImageBuffer::new(width, height);
+    let field = if metaball_data.blend == BlendMode::Additive && !metaball_data.is_layered() {
+        quadtree_field(width, height, scale, metaball_data, opts)
+    } else {
+        field_grid(width, height, scale, &opts.view, metaball_data, opts.precision)
+    };
+    shade_field(&mut image, &field, scale, metaball_data, opts, None);
+    image
+}
+
+/// What splitting quads needs to know about the image and the scene
+struct Tree<'a> {
+    width: u32,
+    height: u32,
+    scale: u32,
+    metaball_data: &'a MetaballData,
+    opts: &'a RenderOpts,
+    /// The field levels that pixels are drawn differently on either side of
+    levels: Vec<f64>,
+    grid: Option<MetaballGrid>,
+    /// The layers there are metaballs on, see [layers_in_use](MetaballData::layers_in_use)
+    layers: Vec<usize>,
+}
+
+/// The field at every pixel through the quadtree, exact in the quads that are split all the way down and
+/// one value in between the bounds of each quad that's filled
+fn quadtree_field(width: u32, height: u32, scale: u32, metaball_data: &MetaballData, opts: &RenderOpts) -> Vec<f64> {
+    let mut field = vec![0.0; width as usize * height as usize];
+    if width == 0 || height == 0 {
+        return field;
+    }
+    let start = Instant::now();
+    let mut levels = vec![metaball_data.threshold];
+    levels.extend(opts.bands.iter().map(|(level, _)| *level));
+    levels.extend(&opts.isolines);
+    let grid = MetaballGrid::new(width, height, scale, &opts.view, metaball_data);
+    let tree = Tree { width, height, scale, metaball_data, opts, levels, grid, layers: metaball_data.layers_in_use() };
+    let mut quads = vec![];
+    let size = width.max(height).next_power_of_two().max(MIN_QUAD);
+    tree.split(&mut quads, Quad { x: 0, y: 0, size, fill: None });
+
+    // the quads lined up with the bands of rows they cover, for filling the bands in parallel
+    let mut banded: Vec<Vec<&Quad>> = vec![vec![]; height.div_ceil(BAND_ROWS) as usize];
+    for quad in &quads {
+        for band in quad.y / BAND_ROWS..(quad.y + quad.size).min(height).div_ceil(BAND_ROWS) {
+            banded[band as usize].push(quad);
+        }
+    }
+    field.par_chunks_mut((width * BAND_ROWS) as usize).zip(banded).enumerate().for_each(|(band, (values, quads))| {
+        let first_row = band as u32 * BAND_ROWS;
+        let rows = values.len() as u32 / width;
+        for quad in quads {
+            let (left, right) = (quad.x, (quad.x + quad.size).min(width));
+            for y in quad.y.max(first_row)..(quad.y + quad.size).min(first_row + rows) {
+                let row = &mut values[((y - first_row) * width) as usize..][..width as usize];
+                match quad.fill {
+                    Some(value) => row[left as usize..right as usize].fill(value),
+                    None => {
+                        for x in left..right {
+                            row[x as usize] = tree.field_at(x, y);
+                        }
+                    }
+                }
+            }
+        }
+    });
+    let evaluated: u64 = quads.iter().filter(|quad| quad.fill.is_none()).map(|quad| clipped_area(quad, width, height)).sum();
+    log::trace!(
+        "The quadtree field at {}x{} took {:.2}ms with {} quads, evaluating {:.1}% of the pixels",
+        width,
+        height,
+        milliseconds_since(start),
+        quads.len(),
+        evaluated as f64 * 100.0 / (width as f64 * height as f64)
+    );
+    field
+}
+
+impl Tree<'_> {
+    /// Fill a quad if none of the levels can be in the field over it, and otherwise split it into four,
+    /// until it's as small as a quad gets and is left to be evaluated at each pixel
+    fn split(&self, quads: &mut Vec<Quad>, quad: Quad) {
+        // quads hanging entirely off of the image have no pixels
+        if quad.x >= self.width || quad.y >= self.height {
+            return;
+        }
+        let (low, high) = self.bounds(&quad);
+        // bounds that came out undefined, from infinitely positive and negative metaballs, could be anything
+        let crossed = low.is_nan() || high.is_nan() || self.levels.iter().any(|level| (low..=high).contains(level));
+        if !crossed {
+            // the gradient shades the inside by the field itself, which has to be evaluated all over
+            if self.opts.gradient && low > self.metaball_data.threshold {
+                quads.push(quad);
+            } else {
+                quads.push(Quad { fill: Some(if low.is_finite() { low } else { high }), ..quad });
+            }
+        } else if quad.size <= MIN_QUAD {
+            quads.push(quad);
+        } else {
+            let half = quad.size / 2;
+            for (dx, dy) in [(0, 0), (half, 0), (0, half), (half, half)] {
+                self.split(quads, Quad { x: quad.x + dx, y: quad.y + dy, size: half, fill: None });
+            }
+        }
+    }
+
+    /// The metaballs counted over a quad, which are only those of its cell of the grid once it's no bigger
+    fn metaballs(&self, x: u32, y: u32, size: u32) -> &[Metaball] {
+        self.grid.as_ref().filter(|_| size <= CELL_SIZE).map_or(&self.metaball_data.metaballs[..], |grid| grid.metaballs_at(x, y))
+    }
+
+    /// The field at a pixel, just as [field_grid] evaluates it in full precision
+    fn field_at(&self, x: u32, y: u32) -> f64 {
+        let (px, py) = field_point(x, y, self.scale, &self.opts.view);
+        let noise = self.metaball_data.noise.map_or(1.0, |noise| noise.factor(px, py));
+        field_of(px, py, self.metaballs(x, y, 1), &self.layers, self.metaball_data) * noise
+    }
+
+    /// The least and most the field can be over the pixels of a quad. Every distance the field measures is
+    /// no more than its stretch times as far apart as the points it's measured at, so from the center of
+    /// the quad out to its corners it changes by at most that much, and each metaball adds less the further
+    /// away it is.
+    fn bounds(&self, quad: &Quad) -> (f64, f64) {
+        let (metaball_data, view) = (self.metaball_data, &self.opts.view);
+        let (left, top) = field_point(quad.x, quad.y, self.scale, view);
+        let (right, bottom) = field_point((quad.x + quad.size).min(self.width) - 1, (quad.y + quad.size).min(self.height) - 1, self.scale, view);
+        let (x, y) = ((left + right) / 2.0, (top + bottom) / 2.0);
+        let reach = ((right - left) / 2.0 * metaball_data.pixel_aspect).hypot((bottom - top) / 2.0);
+        let falloff = metaball_data.falloff.function();
+        let (mut low, mut high) = (0.0, 0.0);
+        for metaball in self.metaballs(quad.x, quad.y, quad.size) {
+            let distance = field_distance(metaball, x, y, metaball_data);
+            let stretch = if metaball.is_round() { 1.0 } else { 1.0 / metaball.axes.0.min(metaball.axes.1) };
+            let (nearest, farthest) = ((distance - reach * stretch).max(0.0), distance + reach * stretch);
+            if !metaball.reaches(nearest) {
+                continue;
+            }
+            let goo = metaball.goo_or(metaball_data.goo);
+            let most = falloff.value(metaball.size, nearest, goo);
+            let least = if metaball.reaches(farthest) { falloff.value(metaball.size, farthest, goo) } else { 0.0 };
+            low += most.min(least);
+            high += most.max(least);
+        }
+        // the noise scales the field up or down by at most its amplitude
+        if let Some(noise) = metaball_data.noise {
+            let (least, most) = (1.0 - noise.amplitude, 1.0 + noise.amplitude);
+            (low, high) = ((low * least).min(low * most), (high * least).max(high * most));
+        }
+        // a hair of room for rounding, so no pixel is on the other side of a level than the bounds are
+        (low - low.abs() * 1e-9, high + high.abs() * 1e-9)
+    }
+}
+
+/// How many of a quad's pixels are on the image
+fn clipped_area(quad: &Quad, width: u32, height: u32) -> u64 {
+    (((quad.x + quad.size).min(width) - quad.x) as u64) * (((quad.y + quad.size).min(height) - quad.y) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{naive_impl, Pointf};
+
+    #[test]
+    fn quadtree_matches_evaluating_every_pixel() {
+        let mut stretched = Metaball { radius: Some(30.0), ..Metaball::new(Pointf { x: 60.0, y: 30.0 }, 25.0) };
+        stretched.axes = (2.0, 0.5);
+        stretched.angle = 30.0;
+        let metadata = MetaballData::new(1.6, 0.5, 100, 75, vec![
+            Metaball { radius: Some(40.0), ..Metaball::new(Pointf { x: 25.0, y: 40.0 }, 40.0) },
+            Metaball { radius: Some(20.0), ..Metaball::new(Pointf { x: 35.0, y: 40.0 }, -10.0) },
+            stretched,
+        ]);
+        for opts in [
+            RenderOpts::default(),
+            RenderOpts { gradient: true, isolines: vec![0.2, 0.9], ..RenderOpts::default() },
+            RenderOpts { bands: vec![(0.3, Rgba([0, 0, 255, 255])), (1.0, Rgba([0, 255, 0, 255]))], ..RenderOpts::default() },
+        ] {
+            assert_eq!(quadtree_impl(100, 75, 1, &metadata, &opts), naive_impl(100, 75, 1, &metadata, &opts));
+        }
+    }
+}
@@ -0,0 +1,335 @@
+//! A 3D view of the metaballs, raymarching the field with each metaball as a ball instead of a circle
+
+use crate::{background_at, blend_field, blend_over, BlendMode, Metaball, MetaballData, RenderOpts};
+use image::{ImageBuffer, Rgba};
+use rayon::prelude::*;
+use std::f64::consts::{FRAC_PI_2, TAU};
+use std::ops::{Add, Mul, Sub};
+
+/// The camera's vertical field of view in radians
+const FIELD_OF_VIEW: f64 = 0.9;
+
+/// How far the camera can tilt above or below the image, short of looking straight down on it
+const MAX_PITCH: f64 = FRAC_PI_2 - 0.05;
+
+/// How many steps each ray takes across the furthest the shape could reach, before homing in on the surface
+const RAY_STEPS: u32 = 96;
+
+/// How many times the step a ray crossed the surface in is halved
+const REFINE_STEPS: u32 = 10;
+
+/// How much of the inside color the surfaces facing away from the light still get
+const AMBIENT: f64 = 0.15;
+
+/// How far apart the eyes of an anaglyph are, as a fraction of how far they are from the image. The image
+/// plane is where they converge, so the metaballs bulge out of the screen towards the viewer.
+const EYE_SEPARATION: f64 = 0.04;
+
+/// A point in 3D, with x and y along the image as usual and z coming out of it towards the viewer
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct Point3 {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Point3 {
+    pub fn new(x: f64, y: f64, z: f64) -> Point3 {
+        Point3 { x, y, z }
+    }
+
+    pub fn dot(self, other: Point3) -> f64 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    pub fn cross(self, other: Point3) -> Point3 {
+        Point3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    pub fn length(self) -> f64 {
+        self.dot(self).sqrt()
+    }
+
+    /// The point scaled to a length of 1
+    pub fn normalized(self) -> Point3 {
+        self * (1.0 / self.length())
+    }
+}
+
+impl Add for Point3 {
+    type Output = Point3;
+
+    fn add(self, other: Point3) -> Point3 {
+        Point3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl Sub for Point3 {
+    type Output = Point3;
+
+    fn sub(self, other: Point3) -> Point3 {
+        Point3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl Mul<f64> for Point3 {
+    type Output = Point3;
+
+    fn mul(self, scale: f64) -> Point3 {
+        Point3::new(self.x * scale, self.y * scale, self.z * scale)
+    }
+}
+
+/// A metaball in 3D
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Metaball3 {
+    pub location: Point3,
+    pub size: f64,
+    /// How far the metaball reaches, it adds nothing to the field any further away
+    pub radius: Option<f64>,
+    /// The goo of this metaball, in place of the scene's
+    pub goo: Option<f64>,
+}
+
+impl Metaball3 {
+    /// A metaball lifted off the image to a depth, the image being the slice of the 3D field at a depth of 0
+    pub fn lift(metaball: &Metaball, z: f64) -> Metaball3 {
+        Metaball3 {
+            location: Point3::new(metaball.location.x, metaball.location.y, z),
+            size: metaball.size,
+            radius: metaball.radius,
+            goo: metaball.goo,
+        }
+    }
+}
+
+/// Where the 3D view is seen from, orbiting the middle of the image. With no yaw or pitch it looks straight
+/// at the image, the same way up as the 2D view.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct Camera {
+    /// Radians around the image's vertical axis
+    pub yaw: f64,
+    /// Radians above the image, or below it when negative
+    pub pitch: f64,
+    /// Whether the view is a red-cyan anaglyph for 3D glasses, in grey with what a left eye sees in the red
+    /// channel and what a right eye sees in the green and blue ones
+    pub anaglyph: bool,
+}
+
+impl Camera {
+    /// Move around the image by some radians, stopping short of going over the top or bottom
+    pub fn orbit(&mut self, yaw: f64, pitch: f64) {
+        self.yaw = (self.yaw + yaw).rem_euclid(TAU);
+        self.pitch = (self.pitch + pitch).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+}
+
+/// The 3D field at a point, blended the same way as the 2D one. Negative sizes carve into the shape.
+pub fn field_at3(point: Point3, metaballs: &[Metaball3], metaball_data: &MetaballData) -> f64 {
+    blend_field(
+        metaball_data,
+        metaballs.iter().filter_map(|metaball| {
+            let distance = (metaball.location - point).length();
+            let reaches = metaball.radius.is_none_or(|radius| distance <= radius);
+            Some((metaball.size, metaball.goo.unwrap_or(metaball_data.goo), distance)).filter(|_| reaches)
+        }),
+    )
+}
+
+/// Raymarch the metaballs, lifted into 3D on the plane of the image, as seen by the render options' camera. The image is `width × height` however big the scene is, seeing the whole scene
+/// at the image plane. Tiling, pixel aspects, the view and everything coloring the inside by the field are
+/// for the 2D view, the surface is shaded from the inside color alone.
+pub fn raymarch_impl(width: u32, height: u32, metaball_data: &MetaballData, opts: &RenderOpts) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    if !opts.camera.anaglyph {
+        return render_eye(width, height, metaball_data, opts, 0.0);
+    }
+    // each eye only sees through its own channels, so they're both in grey for any inside color to show to both
+    let grey = |pixel: &Rgba<u8>| (0.299 * pixel.0[0] as f64 + 0.587 * pixel.0[1] as f64 + 0.114 * pixel.0[2] as f64).round() as u8;
+    let mut image = render_eye(width, height, metaball_data, opts, -EYE_SEPARATION / 2.0);
+    let right = render_eye(width, height, metaball_data, opts, EYE_SEPARATION / 2.0);
+    for (left, right) in image.pixels_mut().zip(right.pixels()) {
+        let cyan = grey(right);
+        left.0 = [grey(left), cyan, cyan, left.0[3]];
+    }
+    image
+}
+
+/// Raymarch the view from an eye moved to the right by a fraction of its distance from the image, still
+/// looking at the middle of it
+fn render_eye(width: u32, height: u32, metaball_data: &MetaballData, opts: &RenderOpts, offset: f64) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let metaballs = lifted(metaball_data);
+    let target = middle(metaball_data);
+    let bound = bounding_radius(target, &metaballs, metaball_data);
+
+    // far enough back that the image fills the view when looking straight at it
+    let half_view = (FIELD_OF_VIEW / 2.0).tan();
+    let distance = metaball_data.height as f64 / 2.0 / half_view;
+    let Camera { yaw, pitch, .. } = opts.camera;
+    let center = target + Point3::new(pitch.cos() * yaw.sin(), -pitch.sin(), pitch.cos() * yaw.cos()) * distance;
+    let basis = |eye: Point3| {
+        let forward = (target - eye).normalized();
+        let right = Point3::new(0.0, -1.0, 0.0).cross(forward).normalized();
+        (forward, right, right.cross(forward))
+    };
+    // the light moves with the camera, so whichever way the scene is seen from it's lit from the top left,
+    // and both eyes of an anaglyph see it lit the same way
+    let (forward, right, down) = basis(center);
+    let light = (right * -1.0 + down * -1.0 + forward * -1.5).normalized();
+    let eye = center + right * (offset * distance);
+    let (forward, right, down) = basis(eye);
+    let color = opts.fill_color();
+    // each pixel's ray goes through the point of the image plane the 2D view samples, see field_point
+    let scale = height as f64 / metaball_data.height as f64;
+    let plane = |pixel: usize| (pixel as f64 + 0.5) / scale - 0.5;
+
+    let mut image = ImageBuffer::new(width, height);
+    image.as_mut().par_chunks_mut(width as usize * 4).enumerate().for_each(|(y, row)| {
+        for (x, out) in row.chunks_mut(4).enumerate() {
+            let (u, v) = ((plane(x) - target.x) / distance, (plane(y) - target.y) / distance);
+            let ray = (forward + right * u + down * v).normalized();
+            let background = background_at(x as u32, y as u32, width, height, opts);
+            let shaded = bound.and_then(|bound| march(eye, ray, target, bound, &metaballs, metaball_data)).map(|hit| {
+                let normal = surface_normal(hit, ray, &metaballs, metaball_data);
+                let shade = AMBIENT + (1.0 - AMBIENT) * normal.dot(light).max(0.0);
+                let mut lit = color;
+                for channel in &mut lit.0[..3] {
+                    *channel = (*channel as f64 * shade).round() as u8;
+                }
+                blend_over(lit, background)
+            });
+            out.copy_from_slice(&shaded.unwrap_or(background).0);
+        }
+    });
+    image
+}
+
+/// A scene's metaballs lifted into 3D, all on the plane of the image
+pub(crate) fn lifted(metaball_data: &MetaballData) -> Vec<Metaball3> {
+    metaball_data.metaballs.iter().map(|metaball| Metaball3::lift(metaball, 0.0)).collect()
+}
+
+/// The middle of a scene's image, which the camera orbits
+pub(crate) fn middle(metaball_data: &MetaballData) -> Point3 {
+    Point3::new(metaball_data.width as f64 / 2.0, metaball_data.height as f64 / 2.0, 0.0)
+}
+
+/// How far from the middle of the image the surface could be, or `None` if there are no metaballs adding to
+/// the field. In the additive field a point is only on the surface if some metaball adds at least its share
+/// of the threshold there, and each metaball the smooth union merges grows it by at most a quarter of `k`.
+pub(crate) fn bounding_radius(target: Point3, metaballs: &[Metaball3], metaball_data: &MetaballData) -> Option<f64> {
+    let falloff = metaball_data.falloff.function();
+    let positive = metaballs.iter().filter(|metaball| metaball.size > 0.0).count();
+    metaballs
+        .iter()
+        .filter(|metaball| metaball.size > 0.0)
+        .map(|metaball| {
+            let goo = metaball.goo.unwrap_or(metaball_data.goo);
+            let reach = match metaball_data.blend {
+                BlendMode::Additive => falloff.edge(metaball.size, metaball_data.threshold / positive as f64, goo),
+                BlendMode::SmoothUnion { k } => falloff.edge(metaball.size, metaball_data.threshold, goo) + k * positive as f64 / 4.0,
+            };
+            (metaball.location - target).length() + metaball.radius.map_or(reach, |radius| radius.min(reach))
+        })
+        .reduce(f64::max)
+}
+
+/// Where a ray from a point first enters the shape, if it does within the sphere it's bound to
+fn march(eye: Point3, ray: Point3, target: Point3, bound: f64, metaballs: &[Metaball3], metaball_data: &MetaballData) -> Option<Point3> {
+    // the stretch of the ray inside the bounding sphere
+    let to_eye = eye - target;
+    let along = -to_eye.dot(ray);
+    let squared = along * along - (to_eye.dot(to_eye) - bound * bound);
+    if squared < 0.0 {
+        return None;
+    }
+    let (near, far) = ((along - squared.sqrt()).max(0.0), along + squared.sqrt());
+    let inside = |t: f64| field_at3(eye + ray * t, metaballs, metaball_data) >= metaball_data.threshold;
+
+    let step = 2.0 * bound / RAY_STEPS as f64;
+    let mut outside = near;
+    if inside(outside) {
+        return Some(eye + ray * near);
+    }
+    while outside < far {
+        let next = outside + step;
+        if inside(next) {
+            // narrow down the step the surface is in
+            let mut into = next;
+            for _ in 0..REFINE_STEPS {
+                let middle = (outside + into) / 2.0;
+                if inside(middle) {
+                    into = middle;
+                } else {
+                    outside = middle;
+                }
+            }
+            return Some(eye + ray * into);
+        }
+        outside = next;
+    }
+    None
+}
+
+/// The way out of the shape at a point on its surface, which is where the field falls off fastest. The
+/// ray's way back is used where the field is flat.
+fn surface_normal(point: Point3, ray: Point3, metaballs: &[Metaball3], metaball_data: &MetaballData) -> Point3 {
+    let field = |offset: Point3| field_at3(point + offset, metaballs, metaball_data);
+    let h = 1e-3;
+    let gradient = Point3::new(
+        field(Point3::new(h, 0.0, 0.0)) - field(Point3::new(-h, 0.0, 0.0)),
+        field(Point3::new(0.0, h, 0.0)) - field(Point3::new(0.0, -h, 0.0)),
+        field(Point3::new(0.0, 0.0, h)) - field(Point3::new(0.0, 0.0, -h)),
+    );
+    let normal = (gradient * -1.0).normalized();
+    if normal.length().is_finite() { normal } else { ray * -1.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{field_at, Pointf, OFF_PIXEL};
+
+    #[test]
+    fn the_image_is_the_slice_of_the_field_through_the_middle() {
+        let metadata = MetaballData::new(1.6, 0.5, 64, 64, vec![
+            Metaball::new(Pointf { x: 20.0, y: 30.0 }, 40.0),
+            Metaball::new(Pointf { x: 44.0, y: 30.0 }, -20.0),
+        ]);
+        let lifted = lifted(&metadata);
+        for &(x, y) in &[(10.0, 10.0), (32.0, 30.0), (50.5, 41.0)] {
+            assert_eq!(field_at3(Point3::new(x, y, 0.0), &lifted, &metadata), field_at(x, y, &metadata));
+        }
+    }
+
+    #[test]
+    fn a_ball_in_the_middle_looks_the_same_from_every_side() {
+        let metadata = MetaballData::new(1.6, 0.5, 64, 64, vec![Metaball::new(Pointf { x: 32.0, y: 32.0 }, 60.0)]);
+        let mut opts = RenderOpts::default();
+        let front = raymarch_impl(64, 64, &metadata, &opts);
+        assert_ne!(*front.get_pixel(32, 32), OFF_PIXEL);
+        assert_eq!(*front.get_pixel(0, 0), OFF_PIXEL);
+        opts.camera.orbit(1.0, 0.0);
+        let side = raymarch_impl(64, 64, &metadata, &opts);
+        // the outline matches, only the pixels right on the edge could go either way
+        let different = front.pixels().zip(side.pixels()).filter(|(front, side)| (**front == OFF_PIXEL) != (**side == OFF_PIXEL)).count();
+        assert!(different <= 4, "{} pixels of the outline moved", different);
+    }
+
+    #[test]
+    fn anaglyph_eyes_see_the_balls_from_either_side() {
+        let metadata = MetaballData::new(1.6, 0.5, 64, 64, vec![Metaball::new(Pointf { x: 32.0, y: 32.0 }, 60.0)]);
+        let opts = RenderOpts { camera: Camera { anaglyph: true, ..Camera::default() }, ..RenderOpts::default() };
+        let image = raymarch_impl(64, 64, &metadata, &opts);
+        // both eyes see the ball on the image plane in the same place, but the bulge of it towards them from
+        // either side
+        let outline = |channel: usize| image.pixels().map(|pixel| pixel.0[channel] > 0).collect::<Vec<_>>();
+        assert_eq!(outline(0), outline(2));
+        assert!(image.pixels().any(|pixel| pixel.0[0] != pixel.0[2]));
+        assert!(image.pixels().all(|pixel| pixel.0[1] == pixel.0[2]));
+        assert_ne!(image, raymarch_impl(64, 64, &metadata, &RenderOpts::default()));
+    }
+}
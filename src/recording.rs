@@ -0,0 +1,442 @@
+//! Recording a run of frames from a scene to an animated GIF or PNG, to a video as raw Y4M frames or
+//! encoded by ffmpeg, or to a sprite sheet
+
+use crate::keyframes::Keyframes;
+use crate::{render_image, Generation, MetaballData, RenderOpts, Tween, ANIMATION_TICK};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::error::{EncodingError, ImageFormatHint, ParameterError, ParameterErrorKind};
+use image::imageops::{self, FilterType};
+use image::{Delay, Frame, ImageBuffer, ImageError, ImageFormat, ImageResult, Rgba};
+use rand::Rng;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// How long each random scene is shown for when jumping between them
+pub const RANDOM_FRAME_DELAY: Duration = Duration::from_millis(500);
+
+/// How many frames morphing into each new random scene takes, the same as the T key by default
+pub const TWEEN_FRAMES: u32 = 30;
+
+/// The extensions of videos that ffmpeg encodes the frames to
+pub const FFMPEG_EXTENSIONS: [&str; 4] = ["mp4", "webm", "mkv", "mov"];
+
+/// What the frames after the first one show
+#[derive(Clone, PartialEq, Debug)]
+pub enum Sequence {
+    /// Successive steps of the animation
+    Animation,
+    /// A new random scene with the same parameters each frame
+    Random,
+    /// Morphing into a new random scene, taking this many frames for each one
+    Tween(u32),
+    /// Playing keyframes in real time, each frame being the delay further along
+    Keyframes(Keyframes),
+}
+
+/// How many frames to record of what, and how long each is shown for
+#[derive(Clone, PartialEq, Debug)]
+pub struct Recording {
+    pub frames: u32,
+    pub delay: Duration,
+    pub sequence: Sequence,
+}
+
+impl Recording {
+    /// A recording showing each frame for as long as the sequence usually takes between them, an animation
+    /// tick for moving metaballs and half a second for jumps between random scenes
+    pub fn new(frames: u32, sequence: Sequence) -> Recording {
+        let delay = match sequence {
+            Sequence::Animation | Sequence::Tween(_) | Sequence::Keyframes(_) => ANIMATION_TICK,
+            Sequence::Random => RANDOM_FRAME_DELAY,
+        };
+        Recording { frames, delay, sequence }
+    }
+}
+
+/// Record frames to an animated GIF, or an animated PNG if the path ends in `.png` or `.apng`. Frames are
+/// encoded as they are rendered, so only one is held in memory at a time.
+pub fn record(path: &Path, recording: &Recording, metaballs: &MetaballData, opts: &RenderOpts, generation: &Generation, rng: &mut impl Rng) -> ImageResult<()> {
+    check_frames(recording)?;
+    if is_video(path) {
+        let mut video = VideoWriter::create(path, metaballs.width, metaballs.height, recording.delay)?;
+        each_frame(recording, metaballs, opts, generation, rng, |image| Ok(video.write_frame(&image)?))?;
+        return Ok(video.finish()?);
+    }
+    let out = BufWriter::new(File::create(path)?);
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("png") | Some("apng") => {
+            let mut encoder = ApngEncoder::new(out, metaballs.width, metaballs.height, recording)?;
+            each_frame(recording, metaballs, opts, generation, rng, |image| encoder.encode_frame(&image))
+        }
+        _ => {
+            let mut encoder = GifEncoder::new(out);
+            encoder.set_repeat(Repeat::Infinite)?;
+            let delay = Delay::from_saturating_duration(recording.delay);
+            each_frame(recording, metaballs, opts, generation, rng, |image| encoder.encode_frame(Frame::from_parts(image, 0, 0, delay)))
+        }
+    }
+}
+
+/// Record frames in a grid on one PNG, a sprite sheet for game engines, filling each row from the left and
+/// the rows from the top with as many columns as rows or one more. Where each frame is and how long it's
+/// shown for goes in a JSON frame map next to it, with the same name ending in `.json`.
+pub fn record_sheet(path: &Path, recording: &Recording, metaballs: &MetaballData, opts: &RenderOpts, generation: &Generation, rng: &mut impl Rng) -> ImageResult<()> {
+    check_frames(recording)?;
+    let (width, height) = (metaballs.width, metaballs.height);
+    let columns = (recording.frames as f64).sqrt().ceil().max(1.0) as u32;
+    let rows = recording.frames.div_ceil(columns).max(1);
+    let mut sheet = ImageBuffer::new(width * columns, height * rows);
+    let mut frame = 0;
+    each_frame(recording, metaballs, opts, generation, rng, |image| {
+        imageops::replace(&mut sheet, &image, frame % columns * width, frame / columns * height);
+        frame += 1;
+        Ok(())
+    })?;
+    sheet.save(path)?;
+
+    let duration = recording.delay.as_millis();
+    let frames: Vec<String> = (0..recording.frames)
+        .map(|frame| {
+            format!(
+                "    {{\"x\": {}, \"y\": {}, \"w\": {}, \"h\": {}, \"duration\": {}}}",
+                frame % columns * width,
+                frame / columns * height,
+                width,
+                height,
+                duration
+            )
+        })
+        .collect();
+    let image = path.file_name().map_or(String::new(), |name| name.to_string_lossy().replace('\\', "\\\\").replace('"', "\\\""));
+    let map = format!(
+        "{{\n  \"frames\": [\n{}\n  ],\n  \"meta\": {{\"image\": \"{}\", \"size\": {{\"w\": {}, \"h\": {}}}, \"columns\": {}, \"rows\": {}}}\n}}\n",
+        frames.join(",\n"),
+        image,
+        sheet.width(),
+        sheet.height(),
+        columns,
+        rows
+    );
+    std::fs::write(path.with_extension("json"), map)?;
+    Ok(())
+}
+
+/// Render every frame of a recording in order, handing each to `encode`
+fn each_frame(
+    recording: &Recording,
+    metaballs: &MetaballData,
+    opts: &RenderOpts,
+    generation: &Generation,
+    rng: &mut impl Rng,
+    mut encode: impl FnMut(ImageBuffer<Rgba<u8>, Vec<u8>>) -> ImageResult<()>,
+) -> ImageResult<()> {
+    let mut scene = metaballs.clone();
+    let mut tween: Option<Tween> = None;
+    for frame in 0..recording.frames {
+        if let Sequence::Keyframes(keyframes) = &recording.sequence {
+            scene.set_scene(keyframes.scene_at(frame as f64 * recording.delay.as_secs_f64()));
+        }
+        encode(render_image(scene.width, scene.height, &scene, opts))?;
+        match recording.sequence {
+            Sequence::Animation => {
+                scene.step();
+            }
+            Sequence::Random => scene.randomize_with(generation, rng),
+            Sequence::Tween(frames) => {
+                let transition = tween.get_or_insert_with(|| {
+                    let mut target = scene.clone();
+                    target.randomize_with(generation, rng);
+                    Tween::new(scene.clone(), target, frames)
+                });
+                scene = transition.step();
+                if transition.finished() {
+                    tween = None;
+                }
+            }
+            Sequence::Keyframes(_) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Whether frames recorded to a path go to a video, which they do for `-` as Y4M on stdout, a `.y4m` file
+/// and any of the [FFMPEG_EXTENSIONS]
+pub fn is_video(path: &Path) -> bool {
+    path == Path::new("-")
+        || path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| ext == "y4m" || FFMPEG_EXTENSIONS.contains(&ext))
+}
+
+/// Writes frames as an uncompressed YUV4MPEG2 stream, to stdout, a file or an ffmpeg process encoding them
+/// to a video. The colors are converted to BT.601 Y'CbCr without any chroma subsampling, and transparent
+/// pixels are written as their color.
+pub struct VideoWriter {
+    out: Box<dyn Write>,
+    /// The ffmpeg process the frames are piped into, for videos it encodes
+    ffmpeg: Option<Child>,
+    width: u32,
+    height: u32,
+}
+
+impl VideoWriter {
+    /// Start a video of frames of the given size, each shown for the delay
+    pub fn create(path: &Path, width: u32, height: u32, delay: Duration) -> io::Result<VideoWriter> {
+        let (out, ffmpeg): (Box<dyn Write>, _) = if path == Path::new("-") {
+            (Box::new(BufWriter::new(io::stdout())), None)
+        } else if path.extension().is_some_and(|ext| ext == "y4m") {
+            (Box::new(BufWriter::new(File::create(path)?)), None)
+        } else {
+            let mut ffmpeg = Command::new("ffmpeg")
+                .args(["-y", "-loglevel", "error", "-f", "yuv4mpegpipe", "-i", "-"])
+                // the common video codecs need whole chroma samples, so odd sizes are padded by a pixel
+                .args(["-vf", "pad=ceil(iw/2)*2:ceil(ih/2)*2", "-pix_fmt", "yuv420p"])
+                .arg(path)
+                .stdin(Stdio::piped())
+                .spawn()
+                .map_err(|err| match err.kind() {
+                    io::ErrorKind::NotFound => io::Error::new(err.kind(), "encoding a video needs ffmpeg installed"),
+                    _ => err,
+                })?;
+            let stdin = ffmpeg.stdin.take().expect("ffmpeg's stdin is piped");
+            (Box::new(BufWriter::new(stdin)), Some(ffmpeg))
+        };
+        let mut video = VideoWriter { out, ffmpeg, width, height };
+        let (numerator, denominator) = frame_rate(delay);
+        writeln!(video.out, "YUV4MPEG2 W{} H{} F{}:{} Ip A1:1 C444", width, height, numerator, denominator)?;
+        Ok(video)
+    }
+
+    /// Add a frame, the same size as the video
+    pub fn write_frame(&mut self, image: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> io::Result<()> {
+        debug_assert_eq!(image.dimensions(), (self.width, self.height));
+        let pixels: Vec<[u8; 3]> = image.pixels().map(|&pixel| y_cb_cr(pixel)).collect();
+        self.out.write_all(b"FRAME\n")?;
+        // one whole plane for each component
+        for component in 0..3 {
+            let plane: Vec<u8> = pixels.iter().map(|pixel| pixel[component]).collect();
+            self.out.write_all(&plane)?;
+        }
+        Ok(())
+    }
+
+    /// Finish the video, waiting for ffmpeg to finish encoding it
+    pub fn finish(mut self) -> io::Result<()> {
+        self.out.flush()?;
+        // closing the pipe ends ffmpeg's input
+        drop(self.out);
+        match self.ffmpeg.take().map(|mut ffmpeg| ffmpeg.wait()).transpose()? {
+            Some(status) if !status.success() => Err(io::Error::other(format!("ffmpeg failed with {}", status))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A video of whatever is being shown live, such as in the window, with each frame held until the next one
+/// so it plays back in real time however irregularly the frames come
+pub struct LiveRecording {
+    video: VideoWriter,
+    delay: Duration,
+    start: Instant,
+    /// How many frames of the video have been written
+    written: u32,
+    /// The frame being shown, which is written out for as long as it was shown once it's replaced
+    shown: Option<ImageBuffer<Rgba<u8>, Vec<u8>>>,
+}
+
+impl LiveRecording {
+    /// Record to a video with a frame every delay, starting now
+    pub fn new(video: VideoWriter, delay: Duration) -> LiveRecording {
+        LiveRecording { video, delay, start: Instant::now(), written: 0, shown: None }
+    }
+
+    /// Show a new frame from a moment on, scaled to the size of the video if it's another size
+    pub fn show(&mut self, image: ImageBuffer<Rgba<u8>, Vec<u8>>, now: Instant) -> io::Result<()> {
+        self.catch_up(self.frames_by(now))?;
+        self.shown = Some(if image.dimensions() == (self.video.width, self.video.height) {
+            image
+        } else {
+            imageops::resize(&image, self.video.width, self.video.height, FilterType::Triangle)
+        });
+        Ok(())
+    }
+
+    /// Finish the video with the frame being shown at a moment, holding it until then
+    pub fn finish(mut self, now: Instant) -> io::Result<()> {
+        self.catch_up(self.frames_by(now).max(self.written + 1))?;
+        self.video.finish()
+    }
+
+    /// How many frames the video should have up to a moment
+    fn frames_by(&self, now: Instant) -> u32 {
+        (now.saturating_duration_since(self.start).as_secs_f64() / self.delay.as_secs_f64()) as u32
+    }
+
+    /// Write the frame being shown until the video has a number of frames
+    fn catch_up(&mut self, frames: u32) -> io::Result<()> {
+        if let Some(shown) = &self.shown {
+            while self.written < frames {
+                self.video.write_frame(shown)?;
+                self.written += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The frames per second a delay makes as a fraction in lowest terms, a whole number when it's close to one
+fn frame_rate(delay: Duration) -> (u32, u32) {
+    let micros = delay.as_micros().clamp(1, u32::MAX as u128) as u32;
+    let rate = 1_000_000.0 / micros as f64;
+    if (rate - rate.round()).abs() < 1e-3 {
+        return (rate.round() as u32, 1);
+    }
+    let (mut a, mut b) = (1_000_000, micros);
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    (1_000_000 / a, micros / a)
+}
+
+/// A color in studio swing BT.601 Y'CbCr, as video players expect
+fn y_cb_cr(color: Rgba<u8>) -> [u8; 3] {
+    let [r, g, b, _] = color.0.map(f64::from);
+    let y = 16.0 + (65.481 * r + 128.553 * g + 24.966 * b) / 255.0;
+    let cb = 128.0 + (-37.797 * r - 74.203 * g + 112.0 * b) / 255.0;
+    let cr = 128.0 + (112.0 * r - 93.786 * g - 18.214 * b) / 255.0;
+    [y.round() as u8, cb.round() as u8, cr.round() as u8]
+}
+
+/// Writes an animated PNG a frame at a time. The png crate only writes still images, so the animation
+/// chunks are written around the first frame's image data and the rest go in frame data chunks.
+struct ApngEncoder<W: Write> {
+    writer: png::Writer<W>,
+    width: u32,
+    height: u32,
+    /// The delay as the numerator and denominator of a fraction of a second
+    delay: (u16, u16),
+    /// The number of the next animation chunk, which the frame control and data chunks share
+    sequence: u32,
+}
+
+impl<W: Write> ApngEncoder<W> {
+    fn new(out: W, width: u32, height: u32, recording: &Recording) -> ImageResult<ApngEncoder<W>> {
+        let mut encoder = png::Encoder::new(out, width, height);
+        encoder.set_color(png::ColorType::RGBA);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(png_error)?;
+        // the frame count and 0 to loop forever
+        let mut control = recording.frames.to_be_bytes().to_vec();
+        control.extend_from_slice(&0u32.to_be_bytes());
+        writer.write_chunk(*b"acTL", &control).map_err(png_error)?;
+        let delay = (recording.delay.as_millis().min(u16::MAX as u128) as u16, 1000);
+        Ok(ApngEncoder { writer, width, height, delay, sequence: 0 })
+    }
+
+    fn encode_frame(&mut self, image: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> ImageResult<()> {
+        let mut control = self.next_sequence().to_be_bytes().to_vec();
+        for value in &[self.width, self.height, 0, 0] {
+            control.extend_from_slice(&value.to_be_bytes());
+        }
+        control.extend_from_slice(&self.delay.0.to_be_bytes());
+        control.extend_from_slice(&self.delay.1.to_be_bytes());
+        // nothing is disposed of or blended, every frame replaces the whole image
+        control.extend_from_slice(&[0, 0]);
+        self.writer.write_chunk(*b"fcTL", &control).map_err(png_error)?;
+
+        if self.sequence == 1 {
+            // the first frame is also the still image shown by viewers without animation support
+            return self.writer.write_image_data(image.as_raw()).map_err(png_error);
+        }
+        // each row starts with the filter type, which is none
+        let mut rows = Vec::with_capacity(image.as_raw().len() + image.height() as usize);
+        for row in image.as_raw().chunks(self.width as usize * 4) {
+            rows.push(0);
+            rows.extend_from_slice(row);
+        }
+        let mut data = self.next_sequence().to_be_bytes().to_vec();
+        data.extend(deflate::deflate_bytes_zlib(&rows));
+        self.writer.write_chunk(*b"fdAT", &data).map_err(png_error)
+    }
+
+    fn next_sequence(&mut self) -> u32 {
+        self.sequence += 1;
+        self.sequence - 1
+    }
+}
+
+/// Check there's a frame to record before anything's written, since an animated PNG or sprite sheet of no
+/// frames has no image data and isn't a valid PNG
+fn check_frames(recording: &Recording) -> ImageResult<()> {
+    if recording.frames == 0 {
+        return Err(ImageError::Parameter(ParameterError::from_kind(ParameterErrorKind::Generic("there are no frames to record".to_string()))));
+    }
+    Ok(())
+}
+
+fn png_error(err: png::EncodingError) -> ImageError {
+    ImageError::Encoding(EncodingError::new(ImageFormatHint::Exact(ImageFormat::Png), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metaball, Pointf};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn animated_pngs_start_with_the_scene_as_a_still_image() {
+        let scene = MetaballData::new(1.6, 0.5, 32, 24, vec![Metaball::new(Pointf { x: 16.0, y: 12.0 }, 30.0)]);
+        let opts = RenderOpts::default();
+        let path = std::env::temp_dir().join(format!("metaballs-recording-{}.png", std::process::id()));
+        let recording = Recording::new(3, Sequence::Tween(2));
+        record(&path, &recording, &scene, &opts, &Generation::default(), &mut StdRng::seed_from_u64(3)).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let frame_controls = bytes.windows(4).filter(|name| *name == b"fcTL").count();
+        assert_eq!(frame_controls, 3);
+        let still = image::load_from_memory(&bytes).unwrap().into_rgba8();
+        assert_eq!(still, render_image(32, 24, &scene, &opts));
+        // nothing is written without a frame
+        assert!(record(&path, &Recording::new(0, Sequence::Animation), &scene, &opts, &Generation::default(), &mut StdRng::seed_from_u64(3)).is_err());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn videos_are_a_header_then_a_plane_of_each_component_per_frame() {
+        let scene = MetaballData::new(1.6, 0.5, 4, 2, vec![]);
+        let path = std::env::temp_dir().join(format!("metaballs-recording-{}.y4m", std::process::id()));
+        let recording = Recording { frames: 2, delay: Duration::from_micros(33_333), sequence: Sequence::Animation };
+        assert!(is_video(&path) && is_video(Path::new("-")) && is_video(Path::new("out.webm")) && !is_video(Path::new("out.gif")));
+        record(&path, &recording, &scene, &RenderOpts::default(), &Generation::default(), &mut StdRng::seed_from_u64(3)).unwrap();
+
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let header = b"YUV4MPEG2 W4 H2 F30:1 Ip A1:1 C444\n";
+        assert!(bytes.starts_with(header));
+        assert_eq!(bytes.len(), header.len() + 2 * (b"FRAME\n".len() + 3 * 4 * 2));
+        assert_eq!(frame_rate(Duration::from_millis(16)), (125, 2));
+        assert_eq!(y_cb_cr(Rgba([255, 255, 255, 255])), [235, 128, 128]);
+        assert_eq!(y_cb_cr(Rgba([0, 0, 0, 255])), [16, 128, 128]);
+    }
+
+    #[test]
+    fn sprite_sheets_fill_rows_of_frames() {
+        let scene = MetaballData::new(1.6, 0.5, 8, 6, vec![Metaball::new(Pointf { x: 4.0, y: 3.0 }, 10.0)]);
+        let path = std::env::temp_dir().join(format!("metaballs-sheet-{}.png", std::process::id()));
+        let recording = Recording::new(5, Sequence::Animation);
+        record_sheet(&path, &recording, &scene, &RenderOpts::default(), &Generation::default(), &mut StdRng::seed_from_u64(3)).unwrap();
+
+        let sheet = image::open(&path).unwrap().into_rgba8();
+        let map = std::fs::read_to_string(path.with_extension("json")).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(path.with_extension("json")).unwrap();
+        assert_eq!(sheet.dimensions(), (24, 12));
+        assert_eq!(imageops::crop_imm(&sheet, 0, 0, 8, 6).to_image(), render_image(8, 6, &scene, &RenderOpts::default()));
+        assert!(map.contains("{\"x\": 8, \"y\": 6, \"w\": 8, \"h\": 6, \"duration\": 16}\n  ]"));
+        assert!(map.contains("\"size\": {\"w\": 24, \"h\": 12}, \"columns\": 3, \"rows\": 2}"));
+    }
+}
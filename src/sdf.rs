@@ -0,0 +1,104 @@
+//! An approximate signed distance field of the shape, for feeding metaball shapes into SDF-based shaders.
+//! Pixels are split into inside and outside by the threshold, and the exact distance transform of
+//! Felzenszwalb and Huttenlocher finds how far each one is from the nearest pixel on the other side.
+
+use crate::{field_grid, MetaballData, Precision, View};
+
+/// Stands in for infinity as the squared distance to nothing, so the parabolas can still be intersected
+const FAR: f64 = 1e20;
+
+/// How far each pixel's center is from the edge of the shape in pixels, negative inside and row by row.
+/// The edge is taken to be halfway between pixels on either side of it, so the distances are within half
+/// a pixel of the real ones. They're infinite when the shape is everywhere or nowhere.
+pub fn signed_distances(metaball_data: &MetaballData) -> Vec<f64> {
+    let (width, height) = (metaball_data.width as usize, metaball_data.height as usize);
+    let field = field_grid(metaball_data.width, metaball_data.height, 1, &View::default(), metaball_data, Precision::F64);
+    let inside: Vec<bool> = field.iter().map(|&sum| sum > metaball_data.threshold).collect();
+    let to_inside = squared_distances(&inside, true, width, height);
+    let to_outside = squared_distances(&inside, false, width, height);
+    let distance = |squared: f64| if squared >= FAR { f64::INFINITY } else { squared.sqrt() - 0.5 };
+    inside
+        .iter()
+        .zip(to_inside.iter().zip(&to_outside))
+        .map(|(&inside, (&to_inside, &to_outside))| if inside { -distance(to_outside) } else { distance(to_inside) })
+        .collect()
+}
+
+/// The squared distance from each pixel to the nearest one that's inside the shape, or outside with
+/// `inside` false, transforming the columns and then the rows
+fn squared_distances(mask: &[bool], inside: bool, width: usize, height: usize) -> Vec<f64> {
+    let mut squared: Vec<f64> = mask.iter().map(|&pixel| if pixel == inside { 0.0 } else { FAR }).collect();
+    let mut line = vec![0.0; height.max(width)];
+    for x in 0..width {
+        let column: Vec<f64> = (0..height).map(|y| squared[y * width + x]).collect();
+        transform(&column, &mut line[..height]);
+        for (y, &value) in line[..height].iter().enumerate() {
+            squared[y * width + x] = value;
+        }
+    }
+    for row in squared.chunks_mut(width.max(1)) {
+        let original = row.to_vec();
+        transform(&original, row);
+    }
+    squared
+}
+
+/// The one dimensional distance transform, the lowest of the parabolas `(q - p)² + f(p)` at each q
+fn transform(f: &[f64], out: &mut [f64]) {
+    if f.is_empty() {
+        return;
+    }
+    // the parabolas in the lower envelope, and where each one starts being the lowest
+    let mut parabolas = vec![0usize; f.len()];
+    let mut starts = vec![0.0; f.len() + 1];
+    let intersection = |q: usize, p: usize| ((f[q] + (q * q) as f64) - (f[p] + (p * p) as f64)) / (2 * q - 2 * p) as f64;
+    let mut k = 0;
+    starts[0] = f64::NEG_INFINITY;
+    starts[1] = f64::INFINITY;
+    for q in 1..f.len() {
+        let mut start = intersection(q, parabolas[k]);
+        while start <= starts[k] {
+            k -= 1;
+            start = intersection(q, parabolas[k]);
+        }
+        k += 1;
+        parabolas[k] = q;
+        starts[k] = start;
+        starts[k + 1] = f64::INFINITY;
+    }
+    k = 0;
+    for (q, value) in out.iter_mut().enumerate() {
+        while starts[k + 1] < q as f64 {
+            k += 1;
+        }
+        let offset = q as f64 - parabolas[k] as f64;
+        *value = offset * offset + f[parabolas[k]];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metaball, Pointf};
+
+    #[test]
+    fn distances_are_measured_from_the_edge() {
+        // a lone metaball's shape is a circle, 40 pixels across with these
+        let metadata = MetaballData::new(1.0, 1.0, 96, 64, vec![Metaball::new(Pointf { x: 48.0, y: 32.0 }, 20.0)]);
+        let distances = signed_distances(&metadata);
+        let at = |x: usize, y: usize| distances[y * 96 + x];
+        assert!(at(48, 32) < -19.0 && at(48, 32) > -21.0);
+        assert!(at(90, 32) > 21.0 && at(90, 32) < 23.0);
+        assert!(at(68, 32).abs() <= 1.0);
+        assert!(at(48, 32) < at(58, 32) && at(58, 32) < at(68, 32) && at(68, 32) < at(78, 32));
+    }
+
+    #[test]
+    fn the_transform_finds_the_nearest_feature() {
+        let mask = [false, false, true, false, false, false, true];
+        let squared = squared_distances(&mask, true, 7, 1);
+        assert_eq!(squared, vec![4.0, 1.0, 0.0, 1.0, 4.0, 1.0, 0.0]);
+        let nothing = squared_distances(&[], true, 0, 0);
+        assert!(nothing.is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+//! Recording a session as a script of stdin commands, for `record` and `replay`
+
+use crate::ControlCommand;
+use metaballs::{hex_color, MetaballData, Primitive};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Writes what happens to the scene to a file as the commands that would do it again, with a sleep before
+/// each batch of them for the time that went by. Randomizing, loading, resetting and editing with the mouse
+/// and keyboard are written as the changes they made to the scene, so replaying makes the same ones,
+/// while the view and the keys that only change how it's drawn aren't recorded.
+pub struct Recorder {
+    file: BufWriter<File>,
+    /// When the commands were last written
+    written: Instant,
+    /// The scene as the recording has it so far
+    scene: MetaballData,
+}
+
+impl Recorder {
+    /// Start recording to a file, beginning with the commands that recreate the scene as it is
+    pub fn start(path: &Path, metadata: &MetaballData) -> Result<Recorder, String> {
+        let file = File::create(path).map_err(|err| err.to_string())?;
+        let mut recorder = Recorder { file: BufWriter::new(file), written: Instant::now(), scene: metadata.clone() };
+        let mut commands = vec!["# a metaballs session, play it again with replay <path> or --script".to_string(), "clear".to_string()];
+        commands.extend(metadata.commands());
+        recorder.write(&commands, false)?;
+        Ok(recorder)
+    }
+
+    /// Record a command that was just applied, as the command itself when running it again does the same
+    /// thing, or otherwise as how it changed the scene. No command records any changes made some other way.
+    pub fn record(&mut self, command: Option<String>, metadata: &MetaballData) -> Result<(), String> {
+        let commands = match command {
+            Some(command) => vec![command],
+            None => metadata.commands_from(&self.scene),
+        };
+        self.scene = metadata.clone();
+        self.write(&commands, true)
+    }
+
+    fn write(&mut self, commands: &[String], wait: bool) -> Result<(), String> {
+        if commands.is_empty() {
+            return Ok(());
+        }
+        let waited = self.written.elapsed().as_millis();
+        self.written = Instant::now();
+        if wait && waited > 0 {
+            writeln!(self.file, "sleep {}", waited).map_err(|err| err.to_string())?;
+        }
+        for command in commands {
+            writeln!(self.file, "{}", command).map_err(|err| err.to_string())?;
+        }
+        // anything recorded is kept, however the program ends
+        self.file.flush().map_err(|err| err.to_string())
+    }
+}
+
+/// Start recording to a file, or stop recording with no file
+pub fn set_recording(recorder: &mut Option<Recorder>, path: Option<PathBuf>, metadata: &MetaballData) {
+    *recorder = None;
+    match path {
+        Some(path) => match Recorder::start(&path, metadata) {
+            Ok(started) => {
+                *recorder = Some(started);
+                println!("Recording the session to {}", path.display());
+            }
+            Err(err) => println!("Unable to record to {}: {}", path.display(), err),
+        },
+        None => println!("Stopped recording"),
+    }
+}
+
+/// Record a command that was just applied or the changes since the last one, see [Recorder::record],
+/// stopping if the recording can't be written
+pub fn record(recorder: &mut Option<Recorder>, command: Option<String>, metadata: &MetaballData) {
+    if let Some(Err(err)) = recorder.as_mut().map(|recorder| recorder.record(command, metadata)) {
+        println!("Stopped recording: {}", err);
+        *recorder = None;
+    }
+}
+
+/// The stdin command for a control command, if running it again always does the same thing
+pub fn command_line(command: &ControlCommand) -> Option<String> {
+    Some(match command {
+        ControlCommand::Goo(goo) => format!("g{}", goo),
+        ControlCommand::Threshold(threshold) => format!("t{}", threshold),
+        ControlCommand::Falloff(falloff) => format!("falloff {}", falloff),
+        ControlCommand::Compare(Some(comparison)) => format!("compare {} {} {}", comparison.goo, comparison.threshold, comparison.falloff),
+        ControlCommand::Compare(None) => "compare off".to_string(),
+        ControlCommand::CompareCurrent => "compare".to_string(),
+        ControlCommand::Symmetry(symmetry) => format!("symmetry {}", symmetry),
+        ControlCommand::LayerBlend { layer, blend } => format!("layer-blend {} {}", layer, blend),
+        ControlCommand::Noise(Some(noise)) => format!("noise {}", noise),
+        ControlCommand::Noise(None) => "noise off".to_string(),
+        ControlCommand::Motion { index, path: None, .. } => format!("motion {} static", index),
+        ControlCommand::Motion { index, path: Some(path), period } => format!("motion {} {} {}", index, path, period),
+        ControlCommand::Pulse { index, pulse: None } => format!("pulse {} off", index),
+        ControlCommand::Pulse { index, pulse: Some(pulse) } => format!("pulse {} {}", index, pulse),
+        ControlCommand::ToggleGradient => "i".to_string(),
+        ControlCommand::Isolines(levels) => format!("c{}", levels.iter().map(f64::to_string).collect::<Vec<_>>().join(",")),
+        ControlCommand::Bands(bands) => {
+            let bands: Vec<String> = bands.iter().map(|(level, color)| format!("{}:{}", level, hex_color(*color))).collect();
+            format!("bands {}", bands.join(","))
+        }
+        ControlCommand::Foreground(color) => format!("fg {}", hex_color(*color)),
+        ControlCommand::Background(color) => format!("bg {}", hex_color(*color)),
+        ControlCommand::Thresholds(levels) => format!("t{}", levels.iter().map(f64::to_string).collect::<Vec<_>>().join(",")),
+        ControlCommand::Snap(None) => "snap off".to_string(),
+        ControlCommand::Snap(Some(spacing)) => format!("snap {}", spacing),
+        ControlCommand::ExportField(path) => format!("f {}", path.display()),
+        ControlCommand::ExportSvg(path) => format!("svg {}", path.display()),
+        ControlCommand::ExportMesh(path, cell) => format!("mesh {} {}", path.display(), cell),
+        ControlCommand::ExportSdf(path, spread) => format!("sdf {} {}", path.display(), spread),
+        ControlCommand::Add { location, size, radius: None } => format!("a {} {} {}", location.x, location.y, size),
+        ControlCommand::Add { location, size, radius: Some(radius) } => format!("a {} {} {} {}", location.x, location.y, size, radius),
+        ControlCommand::Remove(index) => format!("d {}", index),
+        ControlCommand::Move { index, location } => format!("m {} {} {}", index, location.x, location.y),
+        ControlCommand::Color { index, color } => format!("color {} {}", index, hex_color(*color)),
+        ControlCommand::Line { index, end: Some(end) } => format!("line {} {} {}", index, end.x, end.y),
+        ControlCommand::Line { index, end: None } => format!("line {} off", index),
+        ControlCommand::Shape { index, primitive: Primitive::RoundedBox { half, corner } } => format!("box {} {} {} {}", index, half.0, half.1, corner),
+        ControlCommand::Shape { index, primitive: Primitive::Ring { radius } } => format!("ring {} {}", index, radius),
+        ControlCommand::Shape { index, .. } => format!("line {} off", index),
+        ControlCommand::Layer { index, layer } => format!("layer {} {}", index, layer),
+        ControlCommand::Stretch { index, axes, angle } => format!("stretch {} {} {} {}", index, axes.0, axes.1, angle),
+        ControlCommand::Clear => "clear".to_string(),
+        ControlCommand::Save(path) => format!("save {}", path.display()),
+        ControlCommand::List => "ls".to_string(),
+        ControlCommand::State => "{\"cmd\": \"state\"}".to_string(),
+        ControlCommand::Probe(point) => format!("p {} {}", point.x, point.y),
+        ControlCommand::ExportGif(path, frames) => format!("gif {} {}", path.display(), frames),
+        ControlCommand::Export(path, frames, None) => format!("e {} {}", path.display(), frames),
+        ControlCommand::Export(path, frames, Some(delay)) => format!("e {} {} {}", path.display(), frames, delay.as_millis()),
+        ControlCommand::TogglePhysics => "physics".to_string(),
+        ControlCommand::ToggleAnaglyph => "anaglyph".to_string(),
+        ControlCommand::ToggleHistogram => "histogram".to_string(),
+        ControlCommand::Colormap(colormap, None) => format!("colormap {}", colormap),
+        ControlCommand::Colormap(colormap, Some(scale)) => format!("colormap {} {}", colormap, scale),
+        ControlCommand::Gravity(gravity) => format!("gravity {}", gravity),
+        ControlCommand::Damping(damping) => format!("damping {}", damping),
+        ControlCommand::Attraction(attraction) => format!("attraction {}", attraction),
+        ControlCommand::Collision(collision) => format!("collision {}", collision),
+        // these depend on the random numbers, files, the window size, the history or the scene from the
+        // start, or have no stdin command
+        ControlCommand::Size { .. }
+        | ControlCommand::Preset(_)
+        | ControlCommand::Load(_)
+        | ControlCommand::Reset
+        | ControlCommand::Randomize
+        | ControlCommand::Seed(_)
+        | ControlCommand::RandomizeLocations
+        | ControlCommand::RandomizeSizes
+        | ControlCommand::Undo
+        | ControlCommand::Redo
+        | ControlCommand::Record(_)
+        | ControlCommand::Gamepad(_)
+        | ControlCommand::EndOfInput => return None,
+    })
+}
@@ -0,0 +1,75 @@
+//! Favorite scenes kept in numbered slots, for flipping between them with the number keys. The slots are
+//! saved to a TOML file of scenes such as
+//!
+//! ```toml
+//! [slots.1]
+//! goo = 1.6
+//! threshold = 0.5
+//!
+//! [[slots.1.metaballs]]
+//! x = 100
+//! y = 128
+//! size = 40.0
+//! ```
+
+use crate::{MetaballData, SceneConfig};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::ErrorKind;
+use std::path::Path;
+
+/// The slots there are, numbered like the keys
+pub const SLOTS: std::ops::RangeInclusive<u8> = 1..=9;
+
+/// The scenes in the slots that have been filled
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Slots {
+    /// By slot number, which TOML only has as strings
+    #[serde(default)]
+    slots: BTreeMap<String, SceneConfig>,
+}
+
+impl Slots {
+    /// Read the slots from a file, which are all empty if it doesn't exist yet
+    pub fn load(path: &Path) -> Result<Slots, String> {
+        match std::fs::read_to_string(path) {
+            Ok(text) => toml::from_str(&text).map_err(|err| err.to_string()),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Slots::default()),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+
+    /// Write the slots to a file
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = toml::to_string(self).map_err(|err| err.to_string())?;
+        std::fs::write(path, text).map_err(|err| err.to_string())
+    }
+
+    /// Keep a scene's goo, threshold and metaballs in a slot, replacing whatever was in it
+    pub fn store(&mut self, slot: u8, metadata: &MetaballData) {
+        self.slots.insert(slot.to_string(), SceneConfig::from_data(metadata));
+    }
+
+    /// The scene in a slot laid out on an image, if the slot has been filled
+    pub fn recall(&self, slot: u8, width: u32, height: u32) -> Option<Result<MetaballData, String>> {
+        self.slots.get(&slot.to_string()).map(|scene| scene.clone().into_data(width, height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Metaball, Pointf};
+
+    #[test]
+    fn slots_keep_their_scenes_through_the_file() {
+        let scene = MetaballData::new(2.0, 0.8, 64, 64, vec![Metaball::new(Pointf { x: 10.0, y: 20.0 }, 30.0)]);
+        let mut slots = Slots::default();
+        slots.store(4, &scene);
+        let slots: Slots = toml::from_str(&toml::to_string(&slots).unwrap()).unwrap();
+        assert_eq!(slots.recall(4, 64, 64), Some(Ok(scene)));
+        assert_eq!(slots.recall(5, 64, 64), None);
+        assert!(slots.recall(4, 8, 8).unwrap().is_err());
+    }
+}
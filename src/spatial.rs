@@ -0,0 +1,142 @@
+//! A uniform grid over the image, bucketing the metaballs by the cells of pixels they reach so each pixel
+//! only evaluates the metaballs near it
+
+use crate::{field_point, BlendMode, Metaball, MetaballData, Pointf, View, FIELD_EPSILON};
+
+/// How many pixels wide and tall each cell of the grid is
+pub(crate) const CELL_SIZE: u32 = 16;
+
+/// The metaballs that reach each cell of pixels of an image, in the scene's order
+pub struct MetaballGrid {
+    columns: u32,
+    cells: Vec<Vec<Metaball>>,
+}
+
+impl MetaballGrid {
+    /// Bucket the metaballs for an image rendered through a view, see [field_grid](crate::field_grid). `None`
+    /// if every metaball counts everywhere: distances in the smooth union matter however far away they are,
+    /// tiling puts copies of each metaball all around the image, and even a faint field matters to the
+    /// layers it's multiplied with or the least of.
+    pub fn new(width: u32, height: u32, scale: u32, view: &View, metaball_data: &MetaballData) -> Option<MetaballGrid> {
+        if !bucketed(metaball_data) || width == 0 || height == 0 {
+            return None;
+        }
+        let columns = width.div_ceil(CELL_SIZE);
+        let mut cells = vec![Vec::new(); (columns * height.div_ceil(CELL_SIZE)) as usize];
+        for metaball in &metaball_data.metaballs {
+            cells_reached(metaball, width, height, scale, view, metaball_data, |cell| cells[cell].push(*metaball));
+        }
+        Some(MetaballGrid { columns, cells })
+    }
+
+    /// The metaballs reaching the cell a pixel is in
+    pub fn metaballs_at(&self, x: u32, y: u32) -> &[Metaball] {
+        &self.cells[((y / CELL_SIZE) * self.columns + x / CELL_SIZE) as usize]
+    }
+}
+
+/// Whether a scene's metaballs can be bucketed into cells at all, see [MetaballGrid::new]
+pub(crate) fn bucketed(metaball_data: &MetaballData) -> bool {
+    metaball_data.blend == BlendMode::Additive && !metaball_data.tile && !metaball_data.is_layered()
+}
+
+/// Visit the index of each cell of the grid a metaball reaches, see [MetaballGrid::new]
+pub(crate) fn cells_reached(metaball: &Metaball, width: u32, height: u32, scale: u32, view: &View, metaball_data: &MetaballData, mut visit: impl FnMut(usize)) {
+    let (columns, rows) = (width.div_ceil(CELL_SIZE), height.div_ceil(CELL_SIZE));
+    let aspect = metaball_data.pixel_aspect;
+    // how far from its center the metaball adds at least the epsilon
+    let level = metaball_data.threshold * FIELD_EPSILON;
+    let cutoff = metaball_data.falloff.function().edge(metaball.size, level, metaball.goo_or(metaball_data.goo));
+    // a stretched metaball reaches no further than a round one would times its longer axis, and a
+    // metaline no further than that from anywhere along it
+    let radius = metaball.radius.map_or(cutoff, |radius| radius.min(cutoff)) * metaball.axes.0.max(metaball.axes.1) + metaball.extent();
+    let Pointf { x, y } = metaball.location;
+    // the cells under the square around the reach, which are then checked against the circle itself
+    let (first_column, last_column) = cell_span(x - radius / aspect, x + radius / aspect, view.x, view.zoom, scale, columns);
+    let (first_row, last_row) = cell_span(y - radius, y + radius, view.y, view.zoom, scale, rows);
+    for row in first_row..last_row {
+        for column in first_column..last_column {
+            let (left, top) = field_point(column * CELL_SIZE, row * CELL_SIZE, scale, view);
+            let (right, bottom) = field_point(((column + 1) * CELL_SIZE).min(width) - 1, ((row + 1) * CELL_SIZE).min(height) - 1, scale, view);
+            // how far the center is from the closest pixel of the cell
+            let dx = (left - x).max(x - right).max(0.0) * aspect;
+            let dy = (top - y).max(y - bottom).max(0.0);
+            if dx.hypot(dy) <= radius {
+                visit((row * columns + column) as usize);
+            }
+        }
+    }
+}
+
+/// Which cells of the grid over an image need to be drawn again, the rest being left as they were
+pub(crate) struct DirtyCells {
+    columns: u32,
+    dirty: Vec<bool>,
+}
+
+impl DirtyCells {
+    /// No cells of an image dirty yet
+    pub fn new(width: u32, height: u32) -> DirtyCells {
+        let columns = width.div_ceil(CELL_SIZE);
+        DirtyCells { columns, dirty: vec![false; (columns * height.div_ceil(CELL_SIZE)) as usize] }
+    }
+
+    /// Mark a cell by its index, see [cells_reached]
+    pub fn mark(&mut self, cell: usize) {
+        self.dirty[cell] = true;
+    }
+
+    /// Mark every cell under a rectangle of pixels, which may hang off of the image
+    pub fn mark_pixels(&mut self, left: i64, top: i64, right: i64, bottom: i64) {
+        let rows = (self.dirty.len() as u32 / self.columns.max(1)) as i64;
+        let cell = |pixel: i64, count: i64| (pixel.max(0) / CELL_SIZE as i64).min(count - 1);
+        if right < 0 || bottom < 0 || left >= self.columns as i64 * CELL_SIZE as i64 || top >= rows * CELL_SIZE as i64 {
+            return;
+        }
+        for row in cell(top, rows)..=cell(bottom, rows) {
+            for column in cell(left, self.columns as i64)..=cell(right, self.columns as i64) {
+                self.dirty[(row * self.columns as i64 + column) as usize] = true;
+            }
+        }
+    }
+
+    /// Whether the cell a pixel is in is dirty
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        self.dirty[((y / CELL_SIZE) * self.columns + x / CELL_SIZE) as usize]
+    }
+
+    /// How many of the cells are dirty
+    pub fn count(&self) -> usize {
+        self.dirty.iter().filter(|&&dirty| dirty).count()
+    }
+}
+
+/// The cells along one axis with pixels sampling the field between two points, as a range of indices to
+/// the `count` cells. Pixels sample at `origin + ((pixel + 0.5) / scale - 0.5) / zoom`, see [field_point].
+fn cell_span(from: f64, to: f64, origin: f64, zoom: f64, scale: u32, count: u32) -> (u32, u32) {
+    let pixel = |point: f64| ((point - origin) * zoom + 0.5) * scale as f64 - 0.5;
+    let last_pixel = (count * CELL_SIZE) as f64 - 1.0;
+    let (from, to) = (pixel(from), pixel(to));
+    // a span entirely off one side of the image, or of a metaball that doesn't reach anywhere, covers nothing
+    if from.is_nan() || to.is_nan() || to < 0.0 || from > last_pixel {
+        return (0, 0);
+    }
+    (from.floor().max(0.0) as u32 / CELL_SIZE, to.ceil().min(last_pixel) as u32 / CELL_SIZE + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metaballs_only_go_in_the_cells_they_reach() {
+        let mut near = Metaball::new(Pointf { x: 8.0, y: 8.0 }, 1.0);
+        near.radius = Some(4.0);
+        let everywhere = Metaball::new(Pointf { x: 60.0, y: 60.0 }, 90.0);
+        let metadata = MetaballData::new(1.6, 0.5, 64, 64, vec![near, everywhere]);
+        let grid = MetaballGrid::new(64, 64, 1, &View::default(), &metadata).unwrap();
+        assert_eq!(grid.metaballs_at(3, 3), &[near, everywhere]);
+        assert_eq!(grid.metaballs_at(40, 3), &[everywhere]);
+        assert_eq!(grid.metaballs_at(3, 40), &[everywhere]);
+    }
+}
@@ -0,0 +1,239 @@
+//! Symmetry modes that mirror each metaball across the middle of the image or repeat it around the middle,
+//! for kaleidoscopic patterns. With a symmetry the metaballs come in groups of copies one after another,
+//! the first of each group being the original that the rest are images of, so the field needs nothing
+//! special and editing any copy moves the whole group.
+
+use crate::{Metaball, MetaballData, Pointf, Primitive, RelPoint};
+use std::f64::consts::TAU;
+use std::fmt;
+use std::iter::repeat_n;
+use std::str::FromStr;
+
+/// How each metaball is repeated over the image
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum Symmetry {
+    #[default]
+    None,
+    /// Mirrored from left to right
+    Horizontal,
+    /// Mirrored from top to bottom
+    Vertical,
+    /// Mirrored both ways, making four copies
+    Both,
+    /// Repeated this many times around the middle
+    Radial(u32),
+}
+
+impl Symmetry {
+    /// How many copies of each metaball there are, the original included
+    pub fn copies(&self) -> usize {
+        match self {
+            Symmetry::None => 1,
+            Symmetry::Horizontal | Symmetry::Vertical => 2,
+            Symmetry::Both => 4,
+            Symmetry::Radial(count) => *count as usize,
+        }
+    }
+
+    /// Where a copy of a point goes on an image, or with `inverse` where the original of a copy's point is
+    fn image(&self, copy: usize, point: Pointf, width: u32, height: u32, inverse: bool) -> Pointf {
+        let (middle_x, middle_y) = ((width - 1) as f64 / 2.0, (height - 1) as f64 / 2.0);
+        let (flip_x, flip_y) = match self {
+            Symmetry::None | Symmetry::Radial(_) => (false, false),
+            Symmetry::Horizontal => (copy == 1, false),
+            Symmetry::Vertical => (false, copy == 1),
+            Symmetry::Both => (copy & 1 == 1, copy & 2 == 2),
+        };
+        if let Symmetry::Radial(count) = self {
+            let angle = copy as f64 * TAU / *count as f64 * if inverse { -1.0 } else { 1.0 };
+            let (x, y) = (point.x - middle_x, point.y - middle_y);
+            return Pointf { x: middle_x + x * angle.cos() - y * angle.sin(), y: middle_y + x * angle.sin() + y * angle.cos() };
+        }
+        Pointf {
+            x: if flip_x { 2.0 * middle_x - point.x } else { point.x },
+            y: if flip_y { 2.0 * middle_y - point.y } else { point.y },
+        }
+    }
+
+    /// How far a copy of a metaball is turned when the original is turned by an angle, or with `inverse` how
+    /// far the original is turned when a copy is. Mirroring it one way turns it the other way.
+    fn angle(&self, copy: usize, angle: f64, inverse: bool) -> f64 {
+        match self {
+            Symmetry::None => angle,
+            Symmetry::Horizontal | Symmetry::Vertical => if copy == 1 { -angle } else { angle },
+            Symmetry::Both => if copy == 1 || copy == 2 { -angle } else { angle },
+            Symmetry::Radial(count) => angle + copy as f64 * 360.0 / *count as f64 * if inverse { -1.0 } else { 1.0 },
+        }
+    }
+
+    /// Which way a copy of a metaball moves when the original moves by a velocity
+    fn velocity(&self, copy: usize, velocity: RelPoint) -> RelPoint {
+        let image = self.image(copy, Pointf { x: velocity.x as f64, y: velocity.y as f64 }, 1, 1, false);
+        RelPoint { x: image.x.round() as i64, y: image.y.round() as i64 }
+    }
+}
+
+impl FromStr for Symmetry {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(Symmetry::None),
+            "horizontal" => Ok(Symmetry::Horizontal),
+            "vertical" => Ok(Symmetry::Vertical),
+            "both" => Ok(Symmetry::Both),
+            _ => match s.strip_prefix("radial:").map(u32::from_str) {
+                Some(Ok(count)) if count >= 2 => Ok(Symmetry::Radial(count)),
+                Some(_) => Err(format!("A radial symmetry needs at least 2 copies, like radial:6, not \"{}\"", s)),
+                None => Err(format!("Unknown symmetry \"{}\", expected none, horizontal, vertical, both or radial:<copies>", s)),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Symmetry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Symmetry::None => f.write_str("none"),
+            Symmetry::Horizontal => f.write_str("horizontal"),
+            Symmetry::Vertical => f.write_str("vertical"),
+            Symmetry::Both => f.write_str("both"),
+            Symmetry::Radial(count) => write!(f, "radial:{}", count),
+        }
+    }
+}
+
+impl MetaballData {
+    /// Switch to another symmetry, keeping the original of each group and giving it the new symmetry's
+    /// copies. Every metaball is an original without a symmetry.
+    pub fn set_symmetry(&mut self, symmetry: Symmetry) {
+        let copies = symmetry.copies();
+        self.metaballs = self.originals().flat_map(|metaball| repeat_n(*metaball, copies)).collect();
+        self.symmetry = symmetry;
+        self.symmetrize();
+    }
+
+    /// The original of each group of copies
+    pub fn originals(&self) -> impl Iterator<Item = &Metaball> {
+        self.metaballs.iter().step_by(self.symmetry.copies())
+    }
+
+    /// Lay the copies in each group out around their original again, after the originals have changed.
+    /// Metaballs past the last whole group, such as from a scene loaded without the symmetry, are left as
+    /// they are.
+    pub fn symmetrize(&mut self) {
+        let (symmetry, width, height) = (self.symmetry, self.width, self.height);
+        if symmetry == Symmetry::None {
+            return;
+        }
+        for group in self.metaballs.chunks_exact_mut(symmetry.copies()) {
+            let original = group[0];
+            for (copy, metaball) in group.iter_mut().enumerate().skip(1) {
+                *metaball = Metaball {
+                    location: symmetry.image(copy, original.location, width, height, false),
+                    velocity: original.velocity.map(|velocity| symmetry.velocity(copy, velocity)),
+                    angle: symmetry.angle(copy, original.angle, false),
+                    primitive: match original.primitive {
+                        Primitive::Segment { end } => Primitive::Segment { end: symmetry.image(copy, end, width, height, false) },
+                        // the rest are laid out around the location, and turned along with the angle
+                        primitive => primitive,
+                    },
+                    // the copies are laid out around the original as it goes around its path
+                    motion: None,
+                    pulse: None,
+                    ..original
+                };
+            }
+        }
+    }
+
+    /// The index of the original of a metaball's group, which is the metaball itself past the last whole
+    /// group
+    pub fn original_of(&self, index: usize) -> usize {
+        let copies = self.symmetry.copies();
+        if index < self.metaballs.len() / copies * copies {
+            index - index % copies
+        } else {
+            index
+        }
+    }
+
+    /// Make the rest of a metaball's group images of it, after it's been moved or changed. The original
+    /// keeps its own velocity and path, which it takes along to where it's moved.
+    pub fn symmetrize_from(&mut self, index: usize) {
+        let start = self.original_of(index);
+        if start == index {
+            self.symmetrize();
+            return;
+        }
+        let edited = self.metaballs[index];
+        let location = self.symmetry.image(index - start, edited.location, self.width, self.height, true);
+        let angle = self.symmetry.angle(index - start, edited.angle, true);
+        let primitive = match edited.primitive {
+            Primitive::Segment { end } => Primitive::Segment { end: self.symmetry.image(index - start, end, self.width, self.height, true) },
+            primitive => primitive,
+        };
+        let original = &mut self.metaballs[start];
+        original.move_to(location);
+        *original = Metaball { location, velocity: original.velocity, motion: original.motion, pulse: original.pulse, angle, primitive, ..edited };
+        self.symmetrize();
+    }
+
+    /// Add a metaball along with its copies. Returns the index of the metaball itself.
+    pub fn add_metaball(&mut self, metaball: Metaball) -> usize {
+        let index = self.metaballs.len();
+        self.metaballs.extend(repeat_n(metaball, self.symmetry.copies()));
+        self.symmetrize();
+        index
+    }
+
+    /// Remove a metaball along with the rest of its group
+    pub fn remove_metaball(&mut self, index: usize) {
+        let copies = self.symmetry.copies();
+        if index < self.metaballs.len() / copies * copies {
+            let start = index - index % copies;
+            self.metaballs.drain(start..start + copies);
+        } else {
+            self.metaballs.remove(index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_follow_whichever_one_is_moved() {
+        let mut metadata = MetaballData::new(1.6, 0.5, 101, 51, vec![]);
+        metadata.set_symmetry(Symmetry::Both);
+        metadata.add_metaball(Metaball::new(Pointf { x: 10.0, y: 5.0 }, 30.0));
+        let locations = |metadata: &MetaballData| metadata.metaballs.iter().map(|metaball| metaball.location).collect::<Vec<_>>();
+        assert_eq!(locations(&metadata), vec![
+            Pointf { x: 10.0, y: 5.0 },
+            Pointf { x: 90.0, y: 5.0 },
+            Pointf { x: 10.0, y: 45.0 },
+            Pointf { x: 90.0, y: 45.0 },
+        ]);
+        metadata.metaballs[3].location = Pointf { x: 80.0, y: 40.0 };
+        metadata.symmetrize_from(3);
+        assert_eq!(metadata.metaballs[0].location, Pointf { x: 20.0, y: 10.0 });
+        assert_eq!(metadata.metaballs[1].location, Pointf { x: 80.0, y: 10.0 });
+        metadata.remove_metaball(2);
+        assert!(metadata.metaballs.is_empty());
+    }
+
+    #[test]
+    fn radial_copies_go_around_the_middle() {
+        let mut metadata = MetaballData::new(1.6, 0.5, 65, 65, vec![Metaball::new(Pointf { x: 52.0, y: 32.0 }, 30.0)]);
+        metadata.set_symmetry(Symmetry::from_str("radial:4").unwrap());
+        let expected = [(52.0, 32.0), (32.0, 52.0), (12.0, 32.0), (32.0, 12.0)];
+        for (metaball, (x, y)) in metadata.metaballs.iter().zip(expected) {
+            assert!((metaball.location.x - x).abs() < 1e-9 && (metaball.location.y - y).abs() < 1e-9, "{:?}", metaball.location);
+        }
+        metadata.set_symmetry(Symmetry::None);
+        assert_eq!(metadata.metaballs.len(), 1);
+        assert_eq!(Symmetry::from_str(&Symmetry::Radial(6).to_string()), Ok(Symmetry::Radial(6)));
+        assert!(Symmetry::from_str("radial:1").is_err());
+    }
+}
@@ -0,0 +1,99 @@
+//! Named sets of colors that go together, for `--theme` and cycling through them with the N key
+
+use crate::{RenderOpts, CROSS_PIXEL, GRADIENT_LOW_PIXEL, ISOLINE_PIXEL, OFF_PIXEL, ON_PIXEL};
+use image::Rgba;
+use std::str::FromStr;
+
+/// The colors of everything that's drawn, which a theme sets all at once
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Theme {
+    pub name: &'static str,
+    /// The inside of the shape
+    pub foreground: Rgba<u8>,
+    pub background: Rgba<u8>,
+    /// The center point indicators
+    pub marker: Rgba<u8>,
+    /// What the intensity gradient fades to towards the centers
+    pub gradient: Rgba<u8>,
+    /// The contour lines
+    pub isoline: Rgba<u8>,
+}
+
+/// The themes in the order the N key cycles through them, starting from the default colors
+pub const THEMES: [Theme; 4] = [
+    Theme { name: "classic", foreground: ON_PIXEL, background: OFF_PIXEL, marker: CROSS_PIXEL, gradient: GRADIENT_LOW_PIXEL, isoline: ISOLINE_PIXEL },
+    Theme {
+        name: "neon",
+        foreground: Rgba([57, 255, 20, 255]),
+        background: Rgba([10, 0, 20, 255]),
+        marker: Rgba([255, 46, 240, 255]),
+        gradient: Rgba([0, 229, 255, 255]),
+        isoline: Rgba([255, 240, 31, 255]),
+    },
+    Theme {
+        name: "mono",
+        foreground: Rgba([255, 255, 255, 255]),
+        background: Rgba([0, 0, 0, 255]),
+        marker: Rgba([128, 128, 128, 255]),
+        gradient: Rgba([64, 64, 64, 255]),
+        isoline: Rgba([192, 192, 192, 255]),
+    },
+    Theme {
+        name: "pastel",
+        foreground: Rgba([255, 179, 186, 255]),
+        background: Rgba([253, 246, 227, 255]),
+        marker: Rgba([106, 140, 175, 255]),
+        gradient: Rgba([186, 225, 255, 255]),
+        isoline: Rgba([158, 158, 158, 255]),
+    },
+];
+
+impl Theme {
+    /// Use the theme's colors for rendering
+    pub fn apply(&self, opts: &mut RenderOpts) {
+        opts.on_color = self.foreground;
+        opts.off_color = self.background;
+        opts.cross_color = self.marker;
+        opts.gradient_color = self.gradient;
+        opts.isoline_color = self.isoline;
+    }
+
+    /// The theme after this one, going back to the first after the last
+    pub fn next(&self) -> Theme {
+        let index = THEMES.iter().position(|theme| theme.name == self.name).map_or(0, |index| index + 1);
+        THEMES[index % THEMES.len()]
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        THEMES.iter().find(|theme| theme.name == s).copied().ok_or_else(|| {
+            let names: Vec<&str> = THEMES.iter().map(|theme| theme.name).collect();
+            format!("Unknown theme \"{}\", expected one of {}", s, names.join(", "))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn themes_cycle_back_to_the_default_colors() {
+        let mut theme = Theme::from_str("classic").unwrap();
+        for _ in 0..THEMES.len() {
+            theme = theme.next();
+        }
+        assert_eq!(theme, THEMES[0]);
+        let mut opts = RenderOpts::default();
+        Theme::from_str("neon").unwrap().apply(&mut opts);
+        assert_eq!(opts.off_color, THEMES[1].background);
+        THEMES[0].apply(&mut opts);
+        let defaults = RenderOpts::default();
+        assert_eq!((opts.on_color, opts.off_color, opts.cross_color), (defaults.on_color, defaults.off_color, defaults.cross_color));
+        assert_eq!((opts.gradient_color, opts.isoline_color), (defaults.gradient_color, defaults.isoline_color));
+        assert!(Theme::from_str("plaid").is_err());
+    }
+}
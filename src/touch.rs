@@ -0,0 +1,131 @@
+//! Turning the fingers on a touchscreen into gestures: a tap, one finger dragging, or two fingers pinching.
+//! Fingers are tracked by the ids the touchscreen gives them, at points in whatever units the caller uses.
+
+use crate::Pointf;
+
+/// How far a finger can wander before lifting and still count as a tap
+pub const TAP_SLOP: f64 = 6.0;
+
+/// Where a finger is in touching the screen
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Phase {
+    Started,
+    Moved,
+    Ended,
+    /// The touch was taken away by the system, which is no tap
+    Cancelled,
+}
+
+/// What the fingers are doing
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Gesture {
+    /// One finger touched and lifted again without moving
+    Tap(Pointf),
+    /// One finger moved from where it touched down to a point
+    Drag { start: Pointf, to: Pointf },
+    /// Two fingers are this many times as far apart as when the second touched down
+    Pinch(f64),
+    /// The fingers dragging or pinching lifted, ending the gesture
+    Done,
+}
+
+/// A finger down on the screen
+#[derive(Copy, Clone, PartialEq, Debug)]
+struct Finger {
+    id: u64,
+    start: Pointf,
+    at: Pointf,
+}
+
+/// The fingers on the screen, see [touch](Touches::touch)
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Touches {
+    fingers: Vec<Finger>,
+    /// Whether the fingers down now have moved, or there have been two of them, so lifting them isn't a tap
+    gesturing: bool,
+    /// Whether there have been two fingers down since the screen was last let go of
+    pinched: bool,
+    /// How far apart two fingers were when the pinch started
+    pinch_start: Option<f64>,
+}
+
+impl Touches {
+    /// Take in a finger touching, moving or lifting, the gesture that makes if any
+    pub fn touch(&mut self, id: u64, phase: Phase, point: Pointf) -> Option<Gesture> {
+        match phase {
+            Phase::Started => {
+                self.fingers.push(Finger { id, start: point, at: point });
+                if self.fingers.len() == 2 {
+                    self.gesturing = true;
+                    self.pinched = true;
+                    self.pinch_start = Some(self.spread());
+                }
+                None
+            }
+            Phase::Moved => {
+                let finger = self.fingers.iter_mut().find(|finger| finger.id == id)?;
+                finger.at = point;
+                let finger = *finger;
+                match (self.fingers.len(), self.pinch_start) {
+                    (2, Some(start)) if start > 0.0 => Some(Gesture::Pinch(self.spread() / start)),
+                    (1, _) if !self.pinched && (self.gesturing || finger.start.distance_to(point.x, point.y, 1.0) > TAP_SLOP) => {
+                        self.gesturing = true;
+                        Some(Gesture::Drag { start: finger.start, to: point })
+                    }
+                    _ => None,
+                }
+            }
+            Phase::Ended | Phase::Cancelled => {
+                let position = self.fingers.iter().position(|finger| finger.id == id)?;
+                let finger = self.fingers.remove(position);
+                // a pinch is over once either finger lifts, and the other doesn't start dragging from there
+                if self.pinch_start.take().is_some() {
+                    return Some(Gesture::Done);
+                }
+                if !self.fingers.is_empty() {
+                    return None;
+                }
+                self.pinched = false;
+                let gesturing = std::mem::take(&mut self.gesturing);
+                match phase {
+                    _ if gesturing => Some(Gesture::Done),
+                    Phase::Ended => Some(Gesture::Tap(finger.start)),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// How far apart the first two fingers are
+    fn spread(&self) -> f64 {
+        let (a, b) = (self.fingers[0].at, self.fingers[1].at);
+        a.distance_to(b.x, b.y, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingers_tap_drag_and_pinch() {
+        let mut touches = Touches::default();
+        let point = |x: f64, y: f64| Pointf { x, y };
+        touches.touch(1, Phase::Started, point(10.0, 10.0));
+        assert_eq!(touches.touch(1, Phase::Moved, point(12.0, 11.0)), None);
+        assert_eq!(touches.touch(1, Phase::Ended, point(12.0, 11.0)), Some(Gesture::Tap(point(10.0, 10.0))));
+
+        touches.touch(2, Phase::Started, point(10.0, 10.0));
+        assert_eq!(touches.touch(2, Phase::Moved, point(30.0, 10.0)), Some(Gesture::Drag { start: point(10.0, 10.0), to: point(30.0, 10.0) }));
+        // once it's dragging, it keeps dragging back within the slop
+        assert_eq!(touches.touch(2, Phase::Moved, point(11.0, 10.0)), Some(Gesture::Drag { start: point(10.0, 10.0), to: point(11.0, 10.0) }));
+        assert_eq!(touches.touch(2, Phase::Ended, point(11.0, 10.0)), Some(Gesture::Done));
+
+        touches.touch(3, Phase::Started, point(0.0, 0.0));
+        touches.touch(4, Phase::Started, point(10.0, 0.0));
+        assert_eq!(touches.touch(4, Phase::Moved, point(20.0, 0.0)), Some(Gesture::Pinch(2.0)));
+        assert_eq!(touches.touch(3, Phase::Ended, point(0.0, 0.0)), Some(Gesture::Done));
+        assert_eq!(touches.touch(4, Phase::Moved, point(40.0, 0.0)), None);
+        assert_eq!(touches.touch(4, Phase::Ended, point(40.0, 0.0)), Some(Gesture::Done));
+    }
+}
@@ -0,0 +1,416 @@
+//! Drawing the scene in the terminal instead of a window, out of half block characters that each show two
+//! pixels or Braille characters that each show eight dots, or as an image with the Kitty graphics protocol
+//! or sixels, and reading the keys pressed in it. The bottom rows are left to scroll on their own, so
+//! whatever the commands print shows up under the scene.
+
+use image::png::PngEncoder;
+use image::{ColorType, ImageBuffer, Rgba};
+use std::collections::BTreeSet;
+use std::fmt::{self, Write as _};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver};
+
+/// How many rows at the bottom of the terminal are kept for the status line and what the commands print
+pub const MESSAGE_ROWS: u16 = 5;
+
+/// The size the terminal is taken to be when it can't be asked
+const DEFAULT_SIZE: (u16, u16) = (80, 24);
+
+/// How many pixels across and down a character is taken to be when the terminal doesn't say
+const DEFAULT_CELL_PIXELS: (u32, u32) = (8, 16);
+
+/// The most base64 the Kitty graphics protocol takes in one escape
+const KITTY_CHUNK: usize = 4096;
+
+/// How the scene is drawn with characters
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Glyphs {
+    /// Upper half blocks colored with the top pixel in front and the bottom one behind, two pixels a cell
+    HalfBlocks,
+    /// Braille patterns with a dot for each pixel that isn't the background, two across and four down a
+    /// cell, colored with the average of the dots
+    Braille,
+}
+
+impl Glyphs {
+    /// How many pixels across and down each character shows
+    pub fn cell(&self) -> (u32, u32) {
+        match self {
+            Glyphs::HalfBlocks => (1, 2),
+            Glyphs::Braille => (2, 4),
+        }
+    }
+
+    /// An image as one line of characters for each row of cells, colored with 24 bit color escapes
+    pub fn lines(&self, image: &ImageBuffer<Rgba<u8>, Vec<u8>>, background: Rgba<u8>) -> Vec<String> {
+        let (cell_width, cell_height) = self.cell();
+        let (columns, rows) = (image.width() / cell_width, image.height() / cell_height);
+        (0..rows)
+            .map(|row| {
+                let mut line = String::new();
+                let mut colors = None;
+                for column in 0..columns {
+                    let (x, y) = (column * cell_width, row * cell_height);
+                    let (glyph, front, back) = match self {
+                        Glyphs::HalfBlocks => ('▀', *image.get_pixel(x, y), *image.get_pixel(x, y + 1)),
+                        Glyphs::Braille => {
+                            let (mut pattern, mut sum, mut dots) = (0, [0u32; 3], 0);
+                            for (bit, (dx, dy)) in BRAILLE_DOTS.iter().enumerate() {
+                                let pixel = image.get_pixel(x + dx, y + dy);
+                                if *pixel != background {
+                                    pattern |= 1 << bit;
+                                    for (total, channel) in sum.iter_mut().zip(pixel.0) {
+                                        *total += channel as u32;
+                                    }
+                                    dots += 1;
+                                }
+                            }
+                            let front = match dots {
+                                0 => background,
+                                _ => Rgba([(sum[0] / dots) as u8, (sum[1] / dots) as u8, (sum[2] / dots) as u8, 255]),
+                            };
+                            (std::char::from_u32(0x2800 + pattern).unwrap(), front, background)
+                        }
+                    };
+                    // only change colors where they change, which is seldom inside and outside the shape
+                    if colors != Some((front, back)) {
+                        let _ = write!(line, "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m", front[0], front[1], front[2], back[0], back[1], back[2]);
+                        colors = Some((front, back));
+                    }
+                    line.push(glyph);
+                }
+                line.push_str("\x1b[0m");
+                line
+            })
+            .collect()
+    }
+}
+
+/// The image protocols the scene can be drawn with, in terminals that show images
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Graphics {
+    /// The Kitty graphics protocol, sending each frame as a PNG scaled to fill the rows above the messages
+    Kitty,
+    /// Sixels, in 216 colors at the size it's rendered at
+    Sixel,
+}
+
+impl Graphics {
+    /// An image as the escapes that draw it from the cursor on, filling a number of columns and rows
+    pub fn escapes(&self, image: &ImageBuffer<Rgba<u8>, Vec<u8>>, columns: u16, rows: u16) -> String {
+        match self {
+            Graphics::Kitty => kitty(image, columns, rows),
+            Graphics::Sixel => sixel(image),
+        }
+    }
+}
+
+impl FromStr for Graphics {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "kitty" => Ok(Graphics::Kitty),
+            "sixel" => Ok(Graphics::Sixel),
+            _ => Err(format!("Unknown graphics protocol \"{}\", expected kitty or sixel", s)),
+        }
+    }
+}
+
+impl fmt::Display for Graphics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Graphics::Kitty => "kitty",
+            Graphics::Sixel => "sixel",
+        })
+    }
+}
+
+/// An image as a PNG in Kitty graphics escapes, replacing the last one sent and leaving the cursor where
+/// it was
+fn kitty(image: &ImageBuffer<Rgba<u8>, Vec<u8>>, columns: u16, rows: u16) -> String {
+    let mut png = Vec::new();
+    if PngEncoder::new(&mut png).encode(image.as_raw(), image.width(), image.height(), ColorType::Rgba8).is_err() {
+        return String::new();
+    }
+    let data = data_encoding::BASE64.encode(&png);
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(KITTY_CHUNK).collect();
+    let mut escapes = String::from("\x1b_Ga=d,d=I,i=1,q=2\x1b\\");
+    for (index, chunk) in chunks.iter().enumerate() {
+        let more = (index + 1 < chunks.len()) as u8;
+        let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+        if index == 0 {
+            let _ = write!(escapes, "\x1b_Ga=T,f=100,i=1,q=2,C=1,c={},r={},m={};{}\x1b\\", columns, rows, more, chunk);
+        } else {
+            let _ = write!(escapes, "\x1b_Gm={};{}\x1b\\", more, chunk);
+        }
+    }
+    escapes
+}
+
+/// An image as sixels, with its colors rounded to the nearest of six levels of red, green and blue
+fn sixel(image: &ImageBuffer<Rgba<u8>, Vec<u8>>) -> String {
+    let level = |channel: u8| (channel as usize * 5 + 127) / 255;
+    let palette_index = |pixel: &Rgba<u8>| level(pixel[0]) * 36 + level(pixel[1]) * 6 + level(pixel[2]);
+    let indices: Vec<usize> = image.pixels().map(palette_index).collect();
+    let (width, height) = (image.width() as usize, image.height() as usize);
+
+    let mut escapes = format!("\x1bPq\"1;1;{};{}", width, height);
+    for index in indices.iter().collect::<BTreeSet<_>>() {
+        let _ = write!(escapes, "#{};2;{};{};{}", index, index / 36 * 20, index / 6 % 6 * 20, index % 6 * 20);
+    }
+    // each band of six rows is drawn over once for each color in it
+    for band in (0..height).step_by(6) {
+        let rows = band..(band + 6).min(height);
+        let colors: BTreeSet<usize> = rows.clone().flat_map(|y| indices[y * width..(y + 1) * width].iter().copied()).collect();
+        for color in colors {
+            let _ = write!(escapes, "#{}", color);
+            let mut run: Option<(char, usize)> = None;
+            for x in 0..width {
+                let bits = rows.clone().enumerate().filter(|(_, y)| indices[y * width + x] == color).fold(0, |bits, (dy, _)| bits | 1 << dy);
+                let sixel = (63 + bits) as u8 as char;
+                run = match run {
+                    Some((last, count)) if last == sixel => Some((last, count + 1)),
+                    Some(run) => {
+                        push_run(&mut escapes, run);
+                        Some((sixel, 1))
+                    }
+                    None => Some((sixel, 1)),
+                };
+            }
+            if let Some(run) = run {
+                push_run(&mut escapes, run);
+            }
+            escapes.push('$');
+        }
+        escapes.push('-');
+    }
+    escapes.push_str("\x1b\\");
+    escapes
+}
+
+/// A run of the same sixel, repeated with a count once that's shorter
+fn push_run(escapes: &mut String, (sixel, count): (char, usize)) {
+    if count > 3 {
+        let _ = write!(escapes, "!{}{}", count, sixel);
+    } else {
+        escapes.extend(std::iter::repeat_n(sixel, count));
+    }
+}
+
+/// Where each of the eight dots of a Braille cell is, in the order of their bits
+const BRAILLE_DOTS: [(u32, u32); 8] = [(0, 0), (0, 1), (0, 2), (1, 0), (1, 1), (1, 2), (0, 3), (1, 3)];
+
+/// A key pressed in the terminal
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum Key {
+    Char(char),
+    /// A letter with Control held
+    Control(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Enter,
+    Backspace,
+    Escape,
+}
+
+/// Parse the keys in what the terminal sent in one go. An escape on its own is the escape key, and the
+/// arrow keys come as escape sequences, with or without modifiers.
+fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = vec![];
+    let text = String::from_utf8_lossy(bytes);
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        keys.push(match c {
+            '\x1b' if chars.peek() == Some(&'[') || chars.peek() == Some(&'O') => {
+                chars.next();
+                // skip the modifiers, such as the 1;2 of Shift
+                let mut last = None;
+                for c in chars.by_ref() {
+                    if !c.is_ascii_digit() && c != ';' {
+                        last = Some(c);
+                        break;
+                    }
+                }
+                match last {
+                    Some('A') => Key::Up,
+                    Some('B') => Key::Down,
+                    Some('C') => Key::Right,
+                    Some('D') => Key::Left,
+                    _ => continue,
+                }
+            }
+            '\x1b' => Key::Escape,
+            '\r' | '\n' => Key::Enter,
+            '\x7f' | '\x08' => Key::Backspace,
+            '\x01'..='\x1a' => Key::Control((c as u8 - 1 + b'a') as char),
+            c => Key::Char(c),
+        });
+    }
+    keys
+}
+
+/// The terminal the scene is drawn in, switched to its alternate screen with the keys read one at a time
+/// for as long as this is around
+pub struct Terminal {
+    tty: Option<File>,
+    #[cfg(unix)]
+    original: Option<libc::termios>,
+    out: io::Stdout,
+}
+
+impl Terminal {
+    /// Take over the terminal. Keys are read from it even when commands are piped into STDIN, though only
+    /// where there's a terminal to read them from.
+    pub fn open() -> io::Result<Terminal> {
+        let tty = File::options().read(true).write(true).open("/dev/tty").ok();
+        let mut terminal = Terminal {
+            #[cfg(unix)]
+            original: match &tty {
+                Some(tty) => Some(raw_mode(tty)?),
+                None => None,
+            },
+            tty,
+            out: io::stdout(),
+        };
+        let rows = terminal.size().1;
+        // everything under the scene scrolls by itself, with the cursor left at the bottom for printing
+        write!(terminal.out, "\x1b[?1049h\x1b[?25l\x1b[2J\x1b[{};{}r\x1b[{};1H", rows - MESSAGE_ROWS + 2, rows, rows)?;
+        terminal.out.flush()?;
+        Ok(terminal)
+    }
+
+    /// The keys pressed from now on, when there's a terminal to read them from
+    pub fn keys(&self) -> io::Result<Option<Receiver<Key>>> {
+        let mut tty = match &self.tty {
+            Some(tty) => tty.try_clone()?,
+            None => return Ok(None),
+        };
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut buffer = [0; 64];
+            while let Ok(read @ 1..) = tty.read(&mut buffer) {
+                for key in parse_keys(&buffer[..read]) {
+                    if tx.send(key).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(Some(rx))
+    }
+
+    /// How many columns and rows of characters the terminal has
+    pub fn size(&self) -> (u16, u16) {
+        #[cfg(unix)]
+        if let Some(tty) = &self.tty {
+            use std::os::unix::io::AsRawFd;
+            let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+            if unsafe { libc::ioctl(tty.as_raw_fd(), libc::TIOCGWINSZ, &mut size) } == 0 && size.ws_col > 0 && size.ws_row > 0 {
+                return (size.ws_col, size.ws_row.max(MESSAGE_ROWS + 1));
+            }
+        }
+        DEFAULT_SIZE
+    }
+
+    /// How many pixels across and down each character is
+    fn cell_pixels(&self) -> (u32, u32) {
+        #[cfg(unix)]
+        if let Some(tty) = &self.tty {
+            use std::os::unix::io::AsRawFd;
+            let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+            if unsafe { libc::ioctl(tty.as_raw_fd(), libc::TIOCGWINSZ, &mut size) } == 0 && size.ws_xpixel > 0 && size.ws_ypixel > 0 {
+                return ((size.ws_xpixel / size.ws_col) as u32, (size.ws_ypixel / size.ws_row) as u32);
+            }
+        }
+        DEFAULT_CELL_PIXELS
+    }
+
+    /// The size of image that fills the rows above the messages, drawn with some glyphs or as an image a
+    /// fraction of the terminal's resolution. Sixels come in bands of six rows, so they're kept whole.
+    pub fn image_size(&self, glyphs: Glyphs, graphics: Option<Graphics>, downscale: u32) -> (u32, u32) {
+        let (columns, rows) = self.size();
+        let (cell_width, cell_height) = match graphics {
+            Some(_) => self.cell_pixels(),
+            None => glyphs.cell(),
+        };
+        let (width, height) = (columns as u32 * cell_width, (rows - MESSAGE_ROWS) as u32 * cell_height);
+        match graphics {
+            Some(graphics) => {
+                let height = (height / downscale).max(1);
+                ((width / downscale).max(1), if graphics == Graphics::Sixel { (height / 6 * 6).max(6) } else { height })
+            }
+            None => (width, height),
+        }
+    }
+
+    /// Draw lines of characters from the top and the status line under them, putting the cursor back where
+    /// the messages are printed
+    pub fn draw(&mut self, lines: &[String], status: &str) -> io::Result<()> {
+        let mut frame = String::from("\x1b7");
+        for (row, line) in lines.iter().enumerate() {
+            let _ = write!(frame, "\x1b[{};1H{}", row + 1, line);
+        }
+        self.finish_frame(frame, status)
+    }
+
+    /// Draw an image over the rows above the messages with an image protocol, and the status line under it
+    pub fn draw_image(&mut self, image: &ImageBuffer<Rgba<u8>, Vec<u8>>, graphics: Graphics, status: &str) -> io::Result<()> {
+        let (columns, rows) = self.size();
+        let frame = format!("\x1b7\x1b[1;1H{}", graphics.escapes(image, columns, rows - MESSAGE_ROWS));
+        self.finish_frame(frame, status)
+    }
+
+    /// Write a frame with the status line on the row under the scene, putting the cursor back
+    fn finish_frame(&mut self, mut frame: String, status: &str) -> io::Result<()> {
+        let (columns, rows) = self.size();
+        let status: String = status.chars().take(columns as usize).collect();
+        let _ = write!(frame, "\x1b[{};1H\x1b[7m{:<columns$}\x1b[0m\x1b8", rows - MESSAGE_ROWS + 1, status, columns = columns as usize);
+        self.out.write_all(frame.as_bytes())?;
+        self.out.flush()
+    }
+
+    /// Start the scrolling rows below the scene again, after the terminal's been resized
+    pub fn resized(&mut self) -> io::Result<()> {
+        let rows = self.size().1;
+        write!(self.out, "\x1b[2J\x1b[{};{}r\x1b[{};1H", rows - MESSAGE_ROWS + 2, rows, rows)?;
+        self.out.flush()
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = write!(self.out, "\x1b[r\x1b[0m\x1b[?25h\x1b[?1049l");
+        let _ = self.out.flush();
+        #[cfg(unix)]
+        if let (Some(tty), Some(original)) = (&self.tty, &self.original) {
+            use std::os::unix::io::AsRawFd;
+            unsafe { libc::tcsetattr(tty.as_raw_fd(), libc::TCSANOW, original) };
+        }
+    }
+}
+
+/// Read the terminal's keys as they're pressed, without echoing them or Control+C stopping everything,
+/// returning how it was set before. Output is left as it was so printed lines still start at the left.
+#[cfg(unix)]
+fn raw_mode(tty: &File) -> io::Result<libc::termios> {
+    use std::os::unix::io::AsRawFd;
+    let fd = tty.as_raw_fd();
+    let mut settings: libc::termios = unsafe { std::mem::zeroed() };
+    if unsafe { libc::tcgetattr(fd, &mut settings) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let original = settings;
+    settings.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+    settings.c_iflag &= !(libc::IXON | libc::ICRNL);
+    settings.c_cc[libc::VMIN] = 1;
+    settings.c_cc[libc::VTIME] = 0;
+    if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &settings) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(original)
+}